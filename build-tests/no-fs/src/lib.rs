@@ -0,0 +1,26 @@
+//! Build test of stegano-core without its default `fs` feature, it is no workspace member on
+//! purpose, so that the feature is not unified back in by the regular build. Run it with
+//! `cargo test --manifest-path build-tests/no-fs/Cargo.toml`
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+    use stegano_core::{SteganoDecoder, SteganoEncoder};
+
+    #[test]
+    fn should_hide_and_unveil_in_memory() {
+        let carrier = RgbaImage::from_pixel(32, 32, Rgba([0x80, 0x40, 0x20, 0xff]));
+
+        let image = SteganoEncoder::new()
+            .use_source(carrier)
+            .add_frame(b"no file was touched")
+            .hide_to_image()
+            .expect("Failed to hide in memory");
+
+        let frames = SteganoDecoder::new()
+            .use_source(image)
+            .unveil_frames()
+            .expect("Failed to unveil in memory");
+        assert_eq!(frames, vec![b"no file was touched".to_vec()]);
+    }
+}
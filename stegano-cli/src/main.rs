@@ -120,7 +120,7 @@ fn main() -> Result<()> {
                 s.force_content_version(ContentVersion::V2);
             }
 
-            s.hide();
+            s.hide()?;
         }
         Some(("unveil", m)) => {
             unveil(
@@ -0,0 +1,364 @@
+//! Quick statistical tests on the bits hidden in a media.
+
+use image::RgbaImage;
+use std::io::{Read, Write};
+
+use crate::media::image::{CodecOptions, LsbCodec};
+use crate::{MediaPrimitive, Message};
+
+/// fewer bits do not allow a meaningful statement about their randomness
+pub const MIN_SAMPLE_BITS: usize = 256;
+
+/// how far the ratio of set bits and of bit changes may deviate from 0.5 for random looking bits,
+/// headers of an embedded message skew both ratios a bit
+const RANDOMNESS_TOLERANCE: f64 = 0.1;
+
+/// monobit and runs test: random bits are set about half of the time,
+/// and the next bit differs from the previous one about half of the time.
+/// Smooth image regions fail both tests, because their LSBs barely change.
+pub fn looks_random(buf: &[u8]) -> bool {
+    let bits = buf.len() * 8;
+    if bits < MIN_SAMPLE_BITS {
+        return false;
+    }
+
+    let ones: u32 = buf.iter().map(|b| b.count_ones()).sum();
+    let mut changes: u32 = buf
+        .iter()
+        .map(|b| (b ^ (b >> 1)) & 0x7f)
+        .map(u8::count_ones)
+        .sum();
+    changes += buf
+        .windows(2)
+        .map(|w| u32::from((w[0] >> 7) != (w[1] & 1)))
+        .sum::<u32>();
+
+    let near_half =
+        |n: u32, total: usize| (n as f64 / total as f64 - 0.5).abs() <= RANDOMNESS_TOLERANCE;
+
+    near_half(ones, bits) && near_half(changes, bits - 1)
+}
+
+/// checks if the region that a payload of `len` bytes would occupy seems to already carry data,
+/// that is it either holds a valid message or its hidden bits look random.
+/// `open` is called for each fresh decoder of the hidden bytes.
+pub fn looks_already_used<'a>(open: impl Fn() -> Box<dyn Read + 'a>, len: usize) -> bool {
+    let holds_message = Message::of(&mut open())
+        .map(|m| !m.is_empty())
+        .unwrap_or(false);
+    if holds_message {
+        return true;
+    }
+
+    let mut region = Vec::with_capacity(len);
+    let _ = open().take(len as u64).read_to_end(&mut region);
+
+    looks_random(&region)
+}
+
+/// a pair of values is only taken into account with at least that many expected occurrences
+const MIN_EXPECTED_PAIR_COUNT: f64 = 5.0;
+
+/// chi-square attack by Westfeld and Pfitzmann: LSB embedding of random data equalizes the
+/// frequencies of the values `2k` and `2k + 1`. Returns the probability of embedding,
+/// from `0.0` (frequencies as of a natural image) to `1.0` (fully equalized frequencies).
+pub fn chi_square_score(values: impl IntoIterator<Item = u8>) -> f64 {
+    let mut histogram = [0u64; 256];
+    for v in values {
+        histogram[v as usize] += 1;
+    }
+
+    score_of_histogram(&histogram)
+}
+
+fn score_of_histogram(histogram: &[u64; 256]) -> f64 {
+    let mut chi = 0.0;
+    let mut pairs = 0;
+    for pair in histogram.chunks(2) {
+        let expected = (pair[0] + pair[1]) as f64 / 2.0;
+        if expected < MIN_EXPECTED_PAIR_COUNT {
+            continue;
+        }
+        chi += (pair[0] as f64 - expected).powi(2) / expected;
+        pairs += 1;
+    }
+    if pairs < 2 {
+        return 0.0;
+    }
+
+    1.0 - regularized_gamma((pairs - 1) as f64 / 2.0, chi / 2.0)
+}
+
+/// regularized lower incomplete gamma function P(a, x), that is the CDF of the chi-square
+/// distribution for `a = df / 2` and `x = chi / 2`
+fn regularized_gamma(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-12;
+    const MAX_ITERATIONS: usize = 1000;
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let prefix = (a * x.ln() - x - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        // series expansion
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        (sum * prefix).min(1.0)
+    } else {
+        // continued fraction for the upper part, by the modified Lentz method
+        let tiny = f64::MIN_POSITIVE / EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..MAX_ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        (1.0 - prefix * h).max(0.0)
+    }
+}
+
+/// Lanczos approximation of ln(Γ(x))
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut series = 1.000_000_000_190_015;
+    for (i, c) in COEFFICIENTS.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// values of all color channels that can carry secret bits, in the order they carry them
+pub fn carrier_values(image: &RgbaImage, opts: &CodecOptions) -> Vec<u8> {
+    LsbCodec::channels(image, opts)
+        .filter_map(|p| match p {
+            MediaPrimitive::ImageColorChannel(v) => Some(v),
+            _ => None,
+        })
+        .collect()
+}
+
+/// peak signal to noise ratio in dB of the red, green and blue channels of `stego` compared
+/// to `cover`, infinite if they are equal. The higher, the less visible is the distortion
+pub fn psnr(cover: &RgbaImage, stego: &RgbaImage) -> f64 {
+    let (squared_errors, count) = cover
+        .pixels()
+        .zip(stego.pixels())
+        .flat_map(|(a, b)| a.0[..3].iter().zip(b.0[..3].iter()))
+        .fold((0u64, 0u64), |(sum, count), (a, b)| {
+            let error = *a as i64 - *b as i64;
+            (sum + (error * error) as u64, count + 1)
+        });
+    if squared_errors == 0 {
+        return f64::INFINITY;
+    }
+    let mse = squared_errors as f64 / count as f64;
+
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// like [`psnr`], but texture masks the distortion: the error of each channel is reduced by
+/// the mean absolute difference of the cover channel to its horizontal and vertical neighbours.
+/// So on a smooth cover it equals [`psnr`], while noise in the cover hides changes of its size
+pub fn masked_psnr(cover: &RgbaImage, stego: &RgbaImage) -> f64 {
+    let (width, height) = cover.dimensions();
+    let value = |x: u32, y: u32, c: usize| cover.get_pixel(x, y).0[c] as i64;
+    let mut squared_errors = 0u64;
+    let mut count = 0u64;
+    for (x, y, pixel) in cover.enumerate_pixels() {
+        for c in 0..3 {
+            let neighbours = [
+                (x.checked_sub(1), Some(y)),
+                ((x + 1 < width).then_some(x + 1), Some(y)),
+                (Some(x), y.checked_sub(1)),
+                (Some(x), (y + 1 < height).then_some(y + 1)),
+            ];
+            let (texture, n) = neighbours
+                .into_iter()
+                .filter_map(|(x, y)| Some(value(x?, y?, c)))
+                .fold((0, 0), |(sum, n), v| {
+                    (sum + (v - pixel.0[c] as i64).abs(), n + 1)
+                });
+            let masking = texture / n.max(1);
+            let error = (pixel.0[c] as i64 - stego.get_pixel(x, y).0[c] as i64).abs();
+            let visible = (error - masking).max(0);
+            squared_errors += (visible * visible) as u64;
+            count += 1;
+        }
+    }
+    if squared_errors == 0 {
+        return f64::INFINITY;
+    }
+    let mse = squared_errors as f64 / count as f64;
+
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// stands in for a compressed payload, which looks like random data
+pub(crate) fn random_payload(len: usize) -> Vec<u8> {
+    let mut x: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            (x >> 32) as u8
+        })
+        .collect()
+}
+
+/// estimates the largest payload in bytes, that keeps the [`chi_square_score`] of the carrier
+/// below `max_score`. The payload is simulated by random data, that is embedded with growing size.
+pub fn capacity_under_chi_square(image: &RgbaImage, opts: &CodecOptions, max_score: f64) -> usize {
+    let original = carrier_values(image, opts);
+    let capacity = original.len() / 8;
+
+    let mut stego = image.clone();
+    let _ = LsbCodec::encoder(&mut stego, opts).write_all(&random_payload(capacity));
+    let embedded = carrier_values(&stego, opts);
+
+    let mut histogram = [0u64; 256];
+    for v in original.iter() {
+        histogram[*v as usize] += 1;
+    }
+    if score_of_histogram(&histogram) >= max_score {
+        return 0;
+    }
+
+    let mut best = 0;
+    for byte in 0..capacity {
+        for i in byte * 8..byte * 8 + 8 {
+            histogram[original[i] as usize] -= 1;
+            histogram[embedded[i] as usize] += 1;
+        }
+        if score_of_histogram(&histogram) >= max_score {
+            break;
+        }
+        best = byte + 1;
+    }
+
+    best
+}
+
+/// the [`chi_square_score`] of the carrier for `points + 1` payload sizes, evenly spaced from 0
+/// to the capacity in bytes. The payload is simulated by random data, each size embeds a prefix
+/// of the same data, like [`capacity_under_chi_square`] does
+pub fn chi_square_curve(
+    image: &RgbaImage,
+    opts: &CodecOptions,
+    points: usize,
+) -> Vec<(usize, f64)> {
+    let original = carrier_values(image, opts);
+    let capacity = original.len() / 8;
+
+    let mut stego = image.clone();
+    let _ = LsbCodec::encoder(&mut stego, opts).write_all(&random_payload(capacity));
+    let embedded = carrier_values(&stego, opts);
+
+    let mut histogram = [0u64; 256];
+    for v in original.iter() {
+        histogram[*v as usize] += 1;
+    }
+    let mut embedded_bytes = 0;
+    (0..=points)
+        .map(|point| {
+            let size = capacity * point / points.max(1);
+            for i in embedded_bytes * 8..size * 8 {
+                histogram[original[i] as usize] -= 1;
+                histogram[embedded[i] as usize] += 1;
+            }
+            embedded_bytes = size;
+
+            (size, score_of_histogram(&histogram))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_compute_the_chi_square_distribution() {
+        // CDF of the chi-square distribution with 2 degrees of freedom is 1 - e^(-x/2)
+        assert!((regularized_gamma(1.0, 1.5) - (1.0 - (-1.5f64).exp())).abs() < 1e-9);
+        assert!((regularized_gamma(1.0, 10.0) - (1.0 - (-10.0f64).exp())).abs() < 1e-9);
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn should_score_equalized_pairs_high_and_natural_values_low() {
+        let equalized = (0..4000).map(|i| (i % 64) as u8);
+        assert!(chi_square_score(equalized) > 0.99);
+
+        let natural = (0..4000).map(|i| ((i % 32) * 2) as u8);
+        assert!(chi_square_score(natural) < 0.01);
+    }
+
+    #[test]
+    fn should_compute_the_psnr() {
+        let cover = RgbaImage::from_pixel(4, 4, image::Rgba([10, 20, 30, 255]));
+        assert_eq!(psnr(&cover, &cover), f64::INFINITY);
+
+        let mut stego = cover.clone();
+        stego.pixels_mut().for_each(|p| p.0[0] += 1);
+        // mean squared error of 1/3
+        let expected = 10.0 * (255.0f64 * 255.0 * 3.0).log10();
+        assert!((psnr(&cover, &stego) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn should_consider_constant_bits_not_random() {
+        assert!(!looks_random(&[0x00; 64]));
+        assert!(!looks_random(&[0xff; 64]));
+        assert!(!looks_random(&[0x0f; 64]), "half set, but too few changes");
+        assert!(!looks_random(&[0x55; 64]), "half set, but too many changes");
+    }
+
+    #[test]
+    fn should_consider_pseudo_random_bits_random() {
+        let mut x: u32 = 0x1234_5678;
+        let buf: Vec<u8> = (0..128)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect();
+
+        assert!(looks_random(&buf));
+        assert!(!looks_random(&buf[..16]), "too small sample");
+    }
+}
@@ -0,0 +1,156 @@
+//! Async variants of the file based APIs, for use inside of a tokio runtime.
+//! They run the blocking APIs on the blocking thread pool, so that reading, hiding,
+//! unveiling and writing do not stall the runtime and behave exactly like them.
+
+use crate::{ExtractedFile, Result, SteganoDecoder, SteganoEncoder, SteganoError};
+
+/// runs `f` on the blocking thread pool with `this` moved in, and puts it back afterwards
+async fn blocking<S, T, F>(this: &mut S, f: F) -> Result<T>
+where
+    S: Default + Send + 'static,
+    T: Send + 'static,
+    F: FnOnce(&mut S) -> Result<T> + Send + 'static,
+{
+    let mut moved = std::mem::take(this);
+    let (moved, result) = tokio::task::spawn_blocking(move || {
+        let result = f(&mut moved);
+
+        (moved, result)
+    })
+    .await
+    .map_err(|e| SteganoError::IoError(std::io::Error::other(e)))?;
+    *this = moved;
+
+    result
+}
+
+impl SteganoEncoder {
+    /// like [`SteganoEncoder::use_media`], but reads the file on the blocking thread pool
+    pub async fn use_media_async(&mut self, input_file: &str) -> Result<&mut Self> {
+        let input_file = input_file.to_owned();
+        blocking(self, move |encoder| {
+            encoder.use_media(&input_file).map(|_| ())
+        })
+        .await?;
+
+        Ok(self)
+    }
+
+    /// like [`SteganoEncoder::hide`], but hides and writes the target on the blocking thread pool
+    pub async fn hide_async(&mut self) -> Result<&Self> {
+        blocking(self, |encoder| encoder.hide().map(|_| ())).await?;
+
+        Ok(self)
+    }
+}
+
+impl SteganoDecoder {
+    /// like [`SteganoDecoder::use_media`], but reads the file on the blocking thread pool
+    pub async fn use_media_async(&mut self, input_file: &str) -> Result<&mut Self> {
+        let input_file = input_file.to_owned();
+        blocking(self, move |decoder| {
+            decoder.use_media(&input_file).map(|_| ())
+        })
+        .await?;
+
+        Ok(self)
+    }
+
+    /// like [`SteganoDecoder::unveil_with_manifest`], but unveils and writes the files into
+    /// the folder `dir` on the blocking thread pool
+    pub async fn unveil_async(&mut self, dir: &str) -> Result<Vec<ExtractedFile>> {
+        let dir = dir.to_owned();
+        blocking(self, move |decoder| decoder.unveil_with_manifest(&dir)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn should_round_trip_a_file_through_the_async_api() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("secret.png");
+        let secret_media = secret_media.to_str().unwrap();
+
+        SteganoEncoder::new()
+            .hide_file("../resources/secrets/Blah.txt")
+            .use_media_async("../resources/plain/carrier-image.png")
+            .await?
+            .write_to(secret_media)
+            .hide_async()
+            .await?;
+
+        let extracted = SteganoDecoder::new()
+            .use_media_async(secret_media)
+            .await?
+            .unveil_async(out_dir.path().to_str().unwrap())
+            .await?;
+
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(extracted[0].name, "Blah.txt");
+        assert_eq!(
+            std::fs::read(&extracted[0].path)?,
+            std::fs::read("../resources/secrets/Blah.txt")?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_fail_without_a_target() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media_async("../resources/plain/carrier-image.png")
+            .await?
+            .hide_message("nowhere to go");
+
+        let result = encoder.hide_async().await.map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::MissingTarget)));
+        assert!(encoder.carrier.is_some(), "the encoder is put back");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_hide_in_the_palette_like_the_blocking_api() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier_path = out_dir.path().join("indexed.png");
+        let secret_media = out_dir.path().join("indexed-with-secret.png");
+        {
+            let file = std::fs::File::create(&carrier_path)?;
+            let mut encoder = png::Encoder::new(file, 16, 16);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(
+                (0..=255u8)
+                    .flat_map(|c| [c, 255 - c, c / 2])
+                    .collect::<Vec<_>>(),
+            );
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&(0..=255u8).collect::<Vec<_>>())
+                .unwrap();
+        }
+
+        SteganoEncoder::new()
+            .use_palette_embedding(true)
+            .use_media_async(carrier_path.to_str().unwrap())
+            .await?
+            .add_frame(b"Hello palette")
+            .write_to(secret_media.to_str().unwrap())
+            .hide_async()
+            .await?;
+
+        let frames = SteganoDecoder::new()
+            .use_palette_embedding(true)
+            .use_media_async(secret_media.to_str().unwrap())
+            .await?
+            .unveil_frames()?;
+        assert_eq!(frames, vec![b"Hello palette".to_vec()]);
+
+        Ok(())
+    }
+}
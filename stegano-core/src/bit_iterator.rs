@@ -49,3 +49,44 @@ where
         }
     }
 }
+
+/// packs bits back into bytes, in the order [`BitIterator`] yields them: the lowest bit first.
+/// The last byte is padded with zeros, if the number of bits is not a multiple of 8
+pub fn bits_to_bytes<I: Iterator<Item = bool>>(bits: I) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (i, bit) in bits.enumerate() {
+        if i % 8 == 0 {
+            bytes.push(0);
+        }
+        if bit {
+            *bytes.last_mut().unwrap() |= 1 << (i % 8);
+        }
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_pack_the_bits_of_bit_iterator_back_into_bytes() {
+        for data in [&b""[..], &[0x01], &[0xa5, 0x00, 0xff], b"Hello World!"] {
+            let bits = BitIterator::new(data).map(|b| b == 1);
+
+            assert_eq!(bits_to_bytes(bits), data);
+        }
+    }
+
+    #[test]
+    fn should_pad_the_last_byte_with_zeros() {
+        let bits = [true, false, true].into_iter();
+        assert_eq!(bits_to_bytes(bits), vec![0b101]);
+
+        let bits = BitIterator::new(&[0xffu8, 0xff][..])
+            .take(11)
+            .map(|b| b == 1);
+        assert_eq!(bits_to_bytes(bits), vec![0xff, 0b111]);
+    }
+}
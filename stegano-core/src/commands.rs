@@ -1,7 +1,7 @@
 use crate::media::audio::wav_iter::AudioWavIter;
 use crate::media::image::LsbCodec;
 use crate::universal_decoder::{Decoder, OneBitUnveil};
-use crate::{CodecOptions, Media, Message, RawMessage, SteganoError};
+use crate::{CodecOptions, ExtractedFile, Media, Message, RawMessage, SteganoError};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
@@ -13,51 +13,69 @@ pub fn unveil(
 ) -> Result<(), SteganoError> {
     let media = Media::from_file(secret_media)?;
 
-    let files = match media {
+    let msg = match media {
         Media::Image(image) => {
             let mut decoder = LsbCodec::decoder(&image, opts);
-            let msg = Message::of(&mut decoder);
-            let mut files = msg.files;
-
-            if let Some(text) = msg.text {
-                files.push(("secret-message.txt".to_owned(), text.as_bytes().to_vec()));
-            }
-
-            files
+            Message::of(&mut decoder)?
         }
         Media::Audio(audio) => {
             let mut decoder = Decoder::new(AudioWavIter::new(audio.1.into_iter()), OneBitUnveil);
+            Message::of(&mut decoder)?
+        }
+    };
 
-            let msg = Message::of(&mut decoder);
-            let mut files = msg.files;
+    write_files(files_of(msg), destination, false)?;
 
-            if let Some(text) = msg.text {
-                files.push(("secret-message.txt".to_owned(), text.as_bytes().to_vec()));
-            }
+    Ok(())
+}
 
-            files
-        }
-    };
+/// all files of a message, a text is unveiled as `secret-message.txt`
+pub(crate) fn files_of(msg: Message) -> Vec<(String, Vec<u8>)> {
+    let mut files = msg.files;
 
+    if let Some(text) = msg.text {
+        files.push(("secret-message.txt".to_owned(), text.as_bytes().to_vec()));
+    }
+
+    files
+}
+
+/// writes the files into the destination folder, only the file names are used, never the paths.
+/// With `sync` each file is synced to disk before it counts as written
+pub(crate) fn write_files(
+    files: Vec<(String, Vec<u8>)>,
+    destination: &Path,
+    sync: bool,
+) -> Result<Vec<ExtractedFile>, SteganoError> {
     if files.is_empty() {
         return Err(SteganoError::NoSecretData);
     }
 
+    let mut extracted = Vec::with_capacity(files.len());
     for (file_name, buf) in files.iter().map(|(file_name, buf)| {
         let file = Path::new(file_name).file_name().unwrap().to_str().unwrap();
 
         (file, buf)
     }) {
         let target_file = destination.join(file_name);
-        let mut target_file =
-            File::create(target_file).map_err(|source| SteganoError::WriteError { source })?;
+        let mut file =
+            File::create(&target_file).map_err(|source| SteganoError::WriteError { source })?;
 
-        target_file
-            .write_all(buf.as_slice())
+        file.write_all(buf.as_slice())
             .map_err(|source| SteganoError::WriteError { source })?;
+        if sync {
+            file.sync_all()
+                .map_err(|source| SteganoError::WriteError { source })?;
+        }
+
+        extracted.push(ExtractedFile {
+            name: file_name.to_owned(),
+            size: buf.len() as u64,
+            path: target_file,
+        });
     }
 
-    Ok(())
+    Ok(extracted)
 }
 
 /// unveil all raw data, no content format interpretation is happening.
@@ -0,0 +1,166 @@
+//! Reed-Solomon forward error correction for the hidden payload.
+//!
+//! The payload is split into blocks of at most 255 bytes, each block carries `2 * redundancy`
+//! parity bytes and so can correct up to `redundancy` corrupted bytes.
+
+use reed_solomon::{Decoder, Encoder};
+
+use crate::{Result, SteganoError};
+
+/// size of one block, data and parity bytes together
+const BLOCK_SIZE: usize = 255;
+
+/// the highest redundancy that still leaves room for one data byte per block
+pub const MAX_REDUNDANCY: u8 = 127;
+
+/// redundancy of a payload whose parity is kept in a second carrier, see [`split_parity`]
+pub const PAIR_REDUNDANCY: u8 = 16;
+
+/// number of parity bytes per block, fails if the redundancy is out of range
+pub(crate) fn parity_len(redundancy: u8) -> Result<usize> {
+    if redundancy == 0 || redundancy > MAX_REDUNDANCY {
+        return Err(SteganoError::InvalidParameter(format!(
+            "FEC redundancy must be between 1 and {MAX_REDUNDANCY}, but was {redundancy}"
+        )));
+    }
+
+    Ok(redundancy as usize * 2)
+}
+
+/// number of encoded bytes, whole blocks, that carry the first `len` bytes of the data
+pub(crate) fn blocks_len(len: usize, redundancy: u8) -> Result<usize> {
+    let parity = parity_len(redundancy)?;
+
+    Ok(len.div_ceil(BLOCK_SIZE - parity) * BLOCK_SIZE)
+}
+
+/// number of bytes that `encode()` turns `len` bytes of data into, the last block may be short
+pub(crate) fn encoded_len(len: usize, redundancy: u8) -> Result<usize> {
+    let parity = parity_len(redundancy)?;
+    let data = BLOCK_SIZE - parity;
+
+    let rest = match len % data {
+        0 => 0,
+        rest => rest + parity,
+    };
+
+    Ok(len / data * BLOCK_SIZE + rest)
+}
+
+/// appends parity bytes to each block of the data
+pub fn encode(data: &[u8], redundancy: u8) -> Result<Vec<u8>> {
+    let parity = parity_len(redundancy)?;
+    let encoder = Encoder::new(parity);
+
+    Ok(data
+        .chunks(BLOCK_SIZE - parity)
+        .flat_map(|block| encoder.encode(block).to_vec())
+        .collect())
+}
+
+/// corrects up to `redundancy` corrupted bytes per block and strips the parity bytes
+pub fn decode(data: &[u8], redundancy: u8) -> Result<Vec<u8>> {
+    let parity = parity_len(redundancy)?;
+    let decoder = Decoder::new(parity);
+
+    let mut decoded = Vec::with_capacity(data.len());
+    for block in data.chunks(BLOCK_SIZE) {
+        if block.len() <= parity {
+            return Err(SteganoError::MalformedSecretData);
+        }
+        let corrected = decoder
+            .correct(block, None)
+            .map_err(|_e| SteganoError::MalformedSecretData)?;
+        decoded.extend_from_slice(corrected.data());
+    }
+
+    Ok(decoded)
+}
+
+/// the parity bytes of each block of the data, without the data, e.g. to keep them apart
+pub fn parity_of(data: &[u8], redundancy: u8) -> Result<Vec<u8>> {
+    let parity = parity_len(redundancy)?;
+
+    Ok(encode(data, redundancy)?
+        .chunks(BLOCK_SIZE)
+        .flat_map(|block| block[block.len() - parity..].to_vec())
+        .collect())
+}
+
+/// like [`decode`] for data and parity that were kept apart, see [`parity_of`]
+pub fn decode_with_parity(data: &[u8], parity: &[u8], redundancy: u8) -> Result<Vec<u8>> {
+    let parity_len = parity_len(redundancy)?;
+    let blocks = data.chunks(BLOCK_SIZE - parity_len);
+    if blocks.len() * parity_len != parity.len() {
+        return Err(SteganoError::MalformedSecretData);
+    }
+    let encoded: Vec<u8> = blocks
+        .zip(parity.chunks(parity_len))
+        .flat_map(|(block, parity)| [block, parity].concat())
+        .collect();
+
+    decode(&encoded, redundancy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_correct_up_to_redundancy_errors_per_block() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let mut encoded = encode(&data, 3).unwrap();
+        assert_eq!(
+            encoded.len(),
+            data.len() + 5 * 6,
+            "5 blocks with 6 parity bytes each"
+        );
+
+        for i in [0, 17, 200, 255, 256, 600, 1020] {
+            encoded[i] ^= 0xff;
+        }
+
+        assert_eq!(decode(&encoded, 3).unwrap(), data);
+        assert_eq!(encoded_len(data.len(), 3).unwrap(), encoded.len());
+        assert_eq!(encoded_len(249 * 2, 3).unwrap(), 255 * 2);
+    }
+
+    #[test]
+    fn should_correct_data_with_parity_kept_apart() {
+        let data: Vec<u8> = (0..600).map(|i| (i % 199) as u8).collect();
+        let mut parity = parity_of(&data, 4).unwrap();
+        assert_eq!(parity.len(), 3 * 8, "3 blocks with 8 parity bytes each");
+
+        let mut corrupted = data.clone();
+        for i in [3, 100, 400, 599] {
+            corrupted[i] ^= 0xff;
+        }
+        parity[9] ^= 0xff;
+
+        assert_eq!(decode_with_parity(&corrupted, &parity, 4).unwrap(), data);
+        assert!(decode_with_parity(&corrupted, &parity[1..], 4).is_err());
+    }
+
+    #[test]
+    fn should_fail_for_too_many_errors() {
+        let data = vec![0x42; 100];
+        let mut encoded = encode(&data, 1).unwrap();
+        for b in encoded.iter_mut().take(10) {
+            *b ^= 0xff;
+        }
+
+        assert!(decode(&encoded, 1).is_err());
+    }
+
+    #[test]
+    fn should_reject_invalid_redundancy() {
+        assert!(matches!(
+            encode(b"foo", 0),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            encode(b"foo", 128),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,455 @@
+//! # Message wire format
+//!
+//! The layout of a hidden message, independent of the media it is hidden in.
+//! Every message starts with one byte for its content version, there are no further magic bytes:
+//!
+//! | version         | layout                                                                 |
+//! |-----------------|------------------------------------------------------------------------|
+//! | [`VERSION_1`]   | utf-8 text, terminated by [`V1_TERMINATOR`]                            |
+//! | [`VERSION_2`]   | zip archive, terminated by [`V2_TERMINATOR`]                           |
+//! | [`VERSION_4`]   | `u32` big endian payload length, followed by the zip archive           |
+//! | [`VERSION_5`]   | `u16` big endian header length, the header fields, then like version 4 |
+//! | [`VERSION_6`]   | file index, then the content of all files back to back, not zipped    |
+//!
+//! Images can have a [`TRAVERSAL_PREAMBLE`] of [`TRAVERSAL_PREAMBLE_LEN`] bytes in front of the
+//! message, in the default traversal. It holds the
+//! [`crate::media::image::cursor::TraversalOrder`] of the message, see its `to_bytes()`,
+//! padded with zeros. The message is hidden in that order, around the pixels of the preamble.
+//!
+//! Each bit plane up to [`MAX_BIT_PLANE`] can carry its own payload instead of a message,
+//! prefixed by its `u32` big endian length, see [`crate::media::image::bit_plane`].
+//!
+//! Images can instead have an [`ALIGNMENT_MARKER`] in front of the message, followed by the
+//! `u32` little endian width and height of the region that holds the message, row by row. Both
+//! are inset by [`ALIGNMENT_MARGIN`] pixels, see [`crate::media::image::alignment`].
+//!
+//! Images can hide only in the pixels of a grayscale mask that are above [`MASK_THRESHOLD`],
+//! see [`crate::media::image::cursor::Masked`]. The mask is not hidden, the decoder needs it too.
+//!
+//! The file index of version 6 is a `u16` big endian number of files, followed by
+//! `[name length: u16][utf-8 name][offset: u32][size: u32]` for each file, all big endian.
+//! The offset counts from the end of the index, so that one file can be read without the others.
+//!
+//! Header fields of version 5 are laid out as `[tag: u8][length: u16 big endian][value]`,
+//! readers skip tags they do not know. Known tags are:
+//! - [`FIELD_COVER_HASH`]: 32 bytes SHA-256 of the carrier content that is not used for hiding
+//! - [`FIELD_FEC_REDUNDANCY`]: 1 byte, the payload is Reed-Solomon coded, see [`crate::fec`]
+//! - [`FIELD_CHECKSUM`]: 1 byte algorithm ([`CHECKSUM_CRC32`] or [`CHECKSUM_SHA256`]),
+//!   followed by the checksum of the payload before it is Reed-Solomon coded
+//! - [`FIELD_PROVENANCE`]: `u64` big endian creation time in unix seconds, followed by the
+//!   utf-8 version of the crate that created the message
+//! - [`FIELD_COMMENT`]: utf-8 free text, at most [`MAX_COMMENT_LEN`] bytes
+//! - [`FIELD_MAC`]: 32 bytes HMAC-SHA256 of the payload before it is Reed-Solomon coded,
+//!   keyed by a secret that both sides share
+//! - [`FIELD_PAYLOAD_LENGTH`]: the `u32` big endian payload length three times, it is taken
+//!   bitwise by majority instead of the payload length behind the header, so that a flipped
+//!   bit in one copy does not break the message
+//! - [`FIELD_CHANNEL_ORDER`]: 1 byte, the [`crate::media::image::cursor::ChannelOrder`] the
+//!   message was hidden in, [`CHANNEL_ORDER_BGRA`] or [`CHANNEL_ORDER_ARGB`], RGBA has no field
+//! - [`FIELD_CHANNEL_BIT_DEPTHS`]: 3 bytes, the number of low bits of the red, green and blue
+//!   channel the message was hidden in
+//! - [`FIELD_PREVIEW`]: a JPEG thumbnail of the hidden content, at most [`MAX_PREVIEW_LEN`] bytes
+//! - [`FIELD_HINT`]: utf-8 cleartext hint how the message was protected, at most [`MAX_HINT_LEN`]
+//!   bytes. It is no secret, anyone who finds the message can read it
+//! - [`FIELD_KDF_ITERATIONS`]: `u32` big endian number of PBKDF2-HMAC-SHA256 iterations that
+//!   stretch the key of the [`FIELD_MAC`], from [`MIN_KDF_ITERATIONS`] to [`MAX_KDF_ITERATIONS`].
+//!   Without it the key is used as it is
+//! - [`FIELD_KDF_SALT`]: [`KDF_SALT_LEN`] random bytes that salt the PBKDF2 of
+//!   [`FIELD_KDF_ITERATIONS`]. Without it a fixed salt is used
+//! - [`FIELD_SIGNATURE`]: 64 bytes Ed25519 signature of the payload before it is Reed-Solomon
+//!   coded, by the secret key of the one who hid it, anyone with the public key can verify it
+//! - [`FIELD_CHECKERBOARD`]: no value, the message was hidden only in the pixels where
+//!   `(x + y) % 2 == 0`, see [`crate::media::image::cursor::Checkerboard`]
+//!
+//! ## Example
+//! ```rust
+//! use stegano_core::format::{decode_message, encode_message, VERSION_4};
+//! use stegano_core::Message;
+//!
+//! let mut m = Message::empty();
+//! m.add_file_data("hello.txt", b"Hello World!".to_vec());
+//!
+//! let bytes = encode_message(&m).unwrap();
+//! assert_eq!(bytes[0], VERSION_4);
+//!
+//! let m = decode_message(&bytes).unwrap();
+//! assert_eq!(m.files[0].0, "hello.txt");
+//! ```
+
+use thiserror::Error;
+
+use crate::{Message, Result};
+
+/// text only, terminated by [`V1_TERMINATOR`]
+pub const VERSION_1: u8 = 0x01;
+/// zip archive, terminated by [`V2_TERMINATOR`]
+pub const VERSION_2: u8 = 0x02;
+/// length prefixed zip archive
+pub const VERSION_4: u8 = 0x04;
+/// header fields plus a length prefixed zip archive
+pub const VERSION_5: u8 = 0x05;
+/// file index plus the files as they are
+pub const VERSION_6: u8 = 0x06;
+
+/// first byte of the preamble that tells the traversal of the message, no content version
+pub const TRAVERSAL_PREAMBLE: u8 = 0x07;
+/// length of the traversal preamble in bytes, including [`TRAVERSAL_PREAMBLE`]
+pub const TRAVERSAL_PREAMBLE_LEN: usize = 10;
+
+/// first bytes of the alignment marker in front of a message that survives cropping,
+/// see [`crate::media::image::alignment`]
+pub const ALIGNMENT_MARKER: [u8; 8] = [0x53, 0x54, 0x41, 0x4c, 0xa5, 0x1f, 0x6e, 0xd2];
+/// number of pixels on each side of the image that stay untouched by the alignment marker
+/// and the message behind it, so that they can be cropped away
+pub const ALIGNMENT_MARGIN: u32 = 16;
+
+/// highest bit plane of a color channel that can carry a payload, 0 is the LSB
+pub const MAX_BIT_PLANE: u8 = 7;
+
+/// pixels of a mask with a higher value carry the secret, the others stay untouched
+pub const MASK_THRESHOLD: u8 = 127;
+
+/// end of the text of content version 1
+pub const V1_TERMINATOR: u8 = 0xff;
+/// end of the zip archive of content version 2
+pub const V2_TERMINATOR: u16 = 0xffff;
+
+/// bytes in front of the value of a header field, one for the tag and two for the length
+pub const FIELD_PREFIX_LEN: usize = 3;
+/// header field holding the hash of the carriers content that is not used for hiding
+pub const FIELD_COVER_HASH: u8 = 0x01;
+/// length of the value of [`FIELD_COVER_HASH`]
+pub const COVER_HASH_LEN: usize = 32;
+/// header field holding the redundancy of the Reed-Solomon coded payload
+pub const FIELD_FEC_REDUNDANCY: u8 = 0x02;
+/// header field holding the algorithm and the checksum of the payload
+pub const FIELD_CHECKSUM: u8 = 0x03;
+/// header field holding the creation time and the version of the creating crate
+pub const FIELD_PROVENANCE: u8 = 0x04;
+/// header field holding a human readable comment, that describes the message
+pub const FIELD_COMMENT: u8 = 0x05;
+/// maximum length of the value of [`FIELD_COMMENT`] in bytes
+pub const MAX_COMMENT_LEN: usize = 1024;
+/// header field holding the HMAC-SHA256 of the payload
+pub const FIELD_MAC: u8 = 0x06;
+/// length of the value of [`FIELD_MAC`]
+pub const MAC_LEN: usize = 32;
+/// header field holding the payload length redundantly
+pub const FIELD_PAYLOAD_LENGTH: u8 = 0x07;
+/// number of copies of the payload length in [`FIELD_PAYLOAD_LENGTH`]
+pub const PAYLOAD_LENGTH_COPIES: usize = 3;
+/// header field holding the channel order of the pixels the message was hidden in
+pub const FIELD_CHANNEL_ORDER: u8 = 0x08;
+/// channel order of [`FIELD_CHANNEL_ORDER`], blue, green, red, alpha
+pub const CHANNEL_ORDER_BGRA: u8 = 0x01;
+/// channel order of [`FIELD_CHANNEL_ORDER`], alpha, red, green, blue
+pub const CHANNEL_ORDER_ARGB: u8 = 0x02;
+/// header field holding the bits of each color channel the message was hidden in
+pub const FIELD_CHANNEL_BIT_DEPTHS: u8 = 0x09;
+/// header field holding a JPEG thumbnail that previews the hidden content
+pub const FIELD_PREVIEW: u8 = 0x0a;
+/// maximum length of the value of [`FIELD_PREVIEW`] in bytes
+pub const MAX_PREVIEW_LEN: usize = 8 * 1024;
+/// thumbnails wider or higher than this many pixels are scaled down before they are stored
+pub const MAX_PREVIEW_DIMENSION: u32 = 64;
+/// header field holding a cleartext hint how the message was protected, e.g. which passphrase
+pub const FIELD_HINT: u8 = 0x0b;
+/// maximum length of the value of [`FIELD_HINT`] in bytes
+pub const MAX_HINT_LEN: usize = 256;
+/// header field holding the iterations of the key derivation of the MAC key
+pub const FIELD_KDF_ITERATIONS: u8 = 0x0c;
+/// fewest iterations of [`FIELD_KDF_ITERATIONS`] that are accepted, fewer would be too weak
+pub const MIN_KDF_ITERATIONS: u32 = 10_000;
+/// most iterations of [`FIELD_KDF_ITERATIONS`] that are accepted, so that a forged header
+/// cannot keep the decoder busy for hours
+pub const MAX_KDF_ITERATIONS: u32 = 10_000_000;
+/// header field telling that the message was hidden in a checkerboard pattern of pixels
+pub const FIELD_CHECKERBOARD: u8 = 0x0d;
+/// header field holding the Ed25519 signature of the payload
+pub const FIELD_SIGNATURE: u8 = 0x0e;
+/// length of the value of [`FIELD_SIGNATURE`]
+pub const SIGNATURE_LEN: usize = 64;
+/// header field holding the salt of the key derivation of the MAC key
+pub const FIELD_KDF_SALT: u8 = 0x10;
+/// length of the value of [`FIELD_KDF_SALT`]
+pub const KDF_SALT_LEN: usize = 16;
+/// algorithm of [`FIELD_CHECKSUM`], 4 bytes big endian CRC-32
+pub const CHECKSUM_CRC32: u8 = 0x01;
+/// algorithm of [`FIELD_CHECKSUM`], 32 bytes SHA-256
+pub const CHECKSUM_SHA256: u8 = 0x02;
+
+/// signature every file entry of the zip payload starts with
+pub const ZIP_LOCAL_FILE_HEADER: [u8; 4] = *b"PK\x03\x04";
+
+/// Describes why a buffer is not a valid message
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// there was not even a content version byte
+    #[error("Message is empty")]
+    Empty,
+
+    /// the content version byte is not known
+    #[error("Content version {0:#04x} is not supported")]
+    UnsupportedVersion(u8),
+
+    /// the buffer ended before the named part was read completely
+    #[error("Message ended unexpectedly while reading the {0}")]
+    Truncated(&'static str),
+
+    /// a header field has a value that does not fit its tag
+    #[error("Header field {0:#04x} is malformed")]
+    MalformedField(u8),
+
+    /// the payload does not match the checksum of its header
+    #[error("Message payload does not match its checksum")]
+    ChecksumMismatch,
+
+    /// the text of content version 1 is not valid utf-8
+    #[error("Message text is not valid utf-8")]
+    InvalidText,
+
+    /// a file entry of the zip payload could not be read
+    #[error("Message payload is not a valid zip archive")]
+    InvalidPayload,
+}
+
+/// serializes a message, fails if the message contains anything its content version cannot represent
+pub fn encode_message(message: &Message) -> Result<Vec<u8>> {
+    message.to_bytes()
+}
+
+/// parses a message from a buffer, that can be longer than the message itself
+pub fn decode_message(mut buf: &[u8]) -> Result<Message> {
+    Message::of(&mut buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChecksumAlgo, ContentVersion, Secret, SteganoError};
+    use std::sync::Arc;
+
+    fn format_error(buf: &[u8]) -> FormatError {
+        match decode_message(buf) {
+            Err(SteganoError::InvalidFormat(e)) => e,
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(_) => panic!("garbage was decoded as message"),
+        }
+    }
+
+    #[test]
+    fn should_round_trip_a_message() {
+        let mut m = Message::empty();
+        m.add_file_data("foo.txt", b"foo".to_vec());
+        m.add_file_data("bar.txt", b"bar".to_vec());
+
+        let buf = encode_message(&m).unwrap();
+        assert_eq!(buf[0], VERSION_4);
+        assert_eq!(&buf[5..9], &ZIP_LOCAL_FILE_HEADER);
+
+        let decoded = decode_message(&buf).unwrap();
+        assert_eq!(decoded.header, ContentVersion::V4);
+        assert_eq!(decoded.files, m.files);
+    }
+
+    #[test]
+    fn should_fail_for_empty_or_unknown_input() {
+        assert_eq!(format_error(&[]), FormatError::Empty);
+        assert_eq!(
+            format_error(&[0x42, 0x00]),
+            FormatError::UnsupportedVersion(0x42)
+        );
+    }
+
+    #[test]
+    fn should_fail_for_truncated_input() {
+        let mut m = Message::empty();
+        m.add_file_data("foo.txt", b"foo".to_vec());
+        let buf = encode_message(&m).unwrap();
+
+        assert_eq!(
+            format_error(&buf[..3]),
+            FormatError::Truncated("payload length")
+        );
+        assert_eq!(
+            format_error(&buf[..buf.len() - 1]),
+            FormatError::Truncated("payload")
+        );
+        assert_eq!(
+            format_error(&[VERSION_5, 0x00, 0x08, FIELD_COVER_HASH]),
+            FormatError::Truncated("header fields")
+        );
+    }
+
+    #[test]
+    fn should_fail_for_malformed_input() {
+        assert_eq!(
+            format_error(&[VERSION_1, 0xc3, 0x28, V1_TERMINATOR]),
+            FormatError::InvalidText
+        );
+        assert_eq!(
+            format_error(&[VERSION_5, 0x00, 0x04, FIELD_COVER_HASH, 0x00, 0x01, 0xab]),
+            FormatError::MalformedField(FIELD_COVER_HASH)
+        );
+    }
+
+    #[test]
+    fn should_round_trip_with_each_checksum_algorithm() {
+        for algo in [
+            ChecksumAlgo::None,
+            ChecksumAlgo::Crc32,
+            ChecksumAlgo::Sha256,
+        ] {
+            let mut m = Message::empty();
+            m.header = ContentVersion::V5;
+            m.checksum = algo;
+            m.add_file_data("foo.txt", b"foo".to_vec());
+
+            let decoded = decode_message(&encode_message(&m).unwrap()).unwrap();
+            assert_eq!(decoded.checksum, algo);
+            assert_eq!(decoded.files, m.files);
+        }
+    }
+
+    #[test]
+    fn should_detect_a_corrupted_payload_by_its_checksum() {
+        for algo in [ChecksumAlgo::Crc32, ChecksumAlgo::Sha256] {
+            let mut m = Message::empty();
+            m.header = ContentVersion::V5;
+            m.checksum = algo;
+            m.add_file_data("foo.txt", b"foo".to_vec());
+
+            let mut buf = encode_message(&m).unwrap();
+            let last = buf.len() - 1;
+            buf[last] ^= 0x01;
+            assert_eq!(
+                format_error(&buf),
+                FormatError::ChecksumMismatch,
+                "{algo:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_detect_a_tampered_payload_by_its_mac() {
+        let mut m = Message::empty();
+        m.header = ContentVersion::V5;
+        m.mac_key = Some(Arc::new(Secret::new(b"key".to_vec())));
+        m.add_file_data("foo.txt", b"foo".to_vec());
+
+        let mut buf = encode_message(&m).unwrap();
+        assert_eq!(
+            decode_message(&buf).unwrap().mac.map(|mac| mac.len()),
+            Some(MAC_LEN)
+        );
+        assert!(Message::verify_mac(&mut &buf[..], b"key", usize::MAX).unwrap());
+
+        let last = buf.len() - 1;
+        buf[last] ^= 0x01;
+        assert!(!Message::verify_mac(&mut &buf[..], b"key", usize::MAX).unwrap());
+    }
+
+    #[test]
+    fn should_stretch_the_mac_key_with_the_salt_of_the_header() {
+        let mut m = Message::empty();
+        m.header = ContentVersion::V5;
+        m.mac_key = Some(Arc::new(Secret::new(b"key".to_vec())));
+        m.kdf_iterations = Some(MIN_KDF_ITERATIONS);
+        m.add_file_data("foo.txt", b"foo".to_vec());
+
+        m.kdf_salt = Some([0x01; KDF_SALT_LEN]);
+        let salted = encode_message(&m).unwrap();
+        assert_eq!(decode_message(&salted).unwrap().kdf_salt, m.kdf_salt);
+        assert!(Message::verify_mac(&mut &salted[..], b"key", usize::MAX).unwrap());
+
+        m.kdf_salt = Some([0x02; KDF_SALT_LEN]);
+        let resalted = encode_message(&m).unwrap();
+        assert_ne!(
+            decode_message(&salted).unwrap().mac,
+            decode_message(&resalted).unwrap().mac
+        );
+    }
+
+    #[test]
+    fn should_reject_forged_kdf_iterations() {
+        let mut m = Message::empty();
+        m.header = ContentVersion::V5;
+        m.mac_key = Some(Arc::new(Secret::new(b"key".to_vec())));
+        m.kdf_iterations = Some(MIN_KDF_ITERATIONS);
+        m.add_file_data("foo.txt", b"foo".to_vec());
+        let buf = encode_message(&m).unwrap();
+        let field = [FIELD_KDF_ITERATIONS, 0x00, 0x04];
+        let at = buf.windows(3).position(|w| w == field).unwrap() + field.len();
+
+        for forged in [0, MIN_KDF_ITERATIONS - 1, MAX_KDF_ITERATIONS + 1, u32::MAX] {
+            let mut buf = buf.clone();
+            buf[at..at + 4].copy_from_slice(&forged.to_be_bytes());
+
+            assert_eq!(
+                format_error(&buf),
+                FormatError::MalformedField(FIELD_KDF_ITERATIONS)
+            );
+            assert!(matches!(
+                Message::verify_mac(&mut &buf[..], b"key", usize::MAX),
+                Err(SteganoError::InvalidFormat(FormatError::MalformedField(
+                    FIELD_KDF_ITERATIONS
+                )))
+            ));
+        }
+    }
+
+    #[test]
+    fn should_detect_a_tampered_payload_by_its_signature() {
+        let public_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]).verifying_key();
+        let mut m = Message::empty();
+        m.header = ContentVersion::V5;
+        m.signing_key = Some(Arc::new(Secret::new([7; 32])));
+        m.add_file_data("foo.txt", b"foo".to_vec());
+
+        let mut buf = encode_message(&m).unwrap();
+        assert_eq!(
+            decode_message(&buf).unwrap().signature.map(|s| s.len()),
+            Some(SIGNATURE_LEN)
+        );
+        let verify = |buf: &[u8]| {
+            Message::verify_signature(&mut &buf[..], public_key.as_bytes(), usize::MAX).unwrap()
+        };
+        assert!(verify(&buf));
+
+        let last = buf.len() - 1;
+        buf[last] ^= 0x01;
+        assert!(!verify(&buf));
+    }
+
+    #[test]
+    fn should_survive_a_flipped_bit_in_one_copy_of_the_payload_length() {
+        let mut m = Message::empty();
+        m.header = ContentVersion::V5;
+        m.redundant_length = true;
+        m.add_file_data("foo.txt", b"foo".to_vec());
+        let buf = encode_message(&m).unwrap();
+
+        // version, header length, tag and length of the only field, then its first copy
+        let first_copy = 1 + 2 + FIELD_PREFIX_LEN;
+        let header_len = u16::from_be_bytes([buf[1], buf[2]]) as usize;
+        let behind_header = 1 + 2 + header_len;
+        for (at, bit) in [
+            (first_copy + 3, 0x01),
+            (first_copy + 4, 0x80),
+            (behind_header, 0x10),
+        ] {
+            let mut corrupted = buf.clone();
+            corrupted[at] ^= bit;
+
+            let m = decode_message(&corrupted).unwrap();
+            assert!(m.redundant_length);
+            assert_eq!(m.files, vec![("foo.txt".to_string(), b"foo".to_vec())]);
+        }
+
+        // without the redundant copies, the same flip breaks the message
+        m.redundant_length = false;
+        let mut buf = encode_message(&m).unwrap();
+        buf[1 + 2] ^= 0x10;
+        assert_eq!(format_error(&buf), FormatError::Truncated("payload"));
+    }
+}
@@ -0,0 +1,89 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::Read;
+
+use crate::{Result, SteganoError};
+
+/// A sequence of independent payloads, each stored as `u32 length + bytes`.
+/// The sequence is terminated by a frame of length zero.
+pub struct FramedMessage {
+    pub frames: Vec<Vec<u8>>,
+}
+
+impl FramedMessage {
+    pub fn new(frames: Vec<Vec<u8>>) -> Self {
+        Self { frames }
+    }
+
+    /// reads consecutive frames until the zero length terminator
+    pub fn of(dec: &mut dyn Read) -> Result<Self> {
+        let mut frames = Vec::new();
+
+        loop {
+            let len = dec
+                .read_u32::<BigEndian>()
+                .map_err(|_| SteganoError::MalformedSecretData)?;
+            if len == 0 {
+                break;
+            }
+
+            let mut frame = Vec::new();
+            dec.take(len as u64)
+                .read_to_end(&mut frame)
+                .map_err(|source| SteganoError::ReadError { source })?;
+            if frame.len() != len as usize {
+                return Err(SteganoError::MalformedSecretData);
+            }
+            frames.push(frame);
+        }
+
+        Ok(Self::new(frames))
+    }
+}
+
+impl From<&FramedMessage> for Vec<u8> {
+    fn from(m: &FramedMessage) -> Vec<u8> {
+        let mut v = Vec::new();
+        for frame in m.frames.iter().filter(|f| !f.is_empty()) {
+            v.write_u32::<BigEndian>(frame.len() as u32)
+                .expect("Failed to write the frame size.");
+            v.extend_from_slice(frame);
+        }
+        v.write_u32::<BigEndian>(0)
+            .expect("Failed to write the frame terminator.");
+
+        v
+    }
+}
+
+#[cfg(test)]
+mod framed_message_tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn should_read_frames_until_terminator() {
+        const BUF: [u8; 15] = [0, 0, 0, 2, b'H', b'e', 0, 0, 0, 1, b'!', 0, 0, 0, 0];
+
+        let m = FramedMessage::of(&mut Cursor::new(&BUF[..])).unwrap();
+        assert_eq!(m.frames, vec![b"He".to_vec(), b"!".to_vec()]);
+    }
+
+    #[test]
+    fn should_fail_for_truncated_frame() {
+        const BUF: [u8; 6] = [0, 0, 0, 9, b'H', b'e'];
+
+        assert!(matches!(
+            FramedMessage::of(&mut Cursor::new(&BUF[..])),
+            Err(SteganoError::MalformedSecretData)
+        ));
+    }
+
+    #[test]
+    fn should_convert_into_vec_of_bytes_and_back() {
+        let m = FramedMessage::new(vec![b"one".to_vec(), b"two".to_vec()]);
+        let b: Vec<u8> = (&m).into();
+
+        let m = FramedMessage::of(&mut Cursor::new(b)).unwrap();
+        assert_eq!(m.frames, vec![b"one".to_vec(), b"two".to_vec()]);
+    }
+}
@@ -12,11 +12,14 @@
 //! ```rust
 //! use stegano_core::{SteganoCore, SteganoEncoder};
 //!
+//! # fn main() -> stegano_core::Result<()> {
 //! SteganoCore::encoder()
 //!     .hide_file("Cargo.toml")
-//!     .use_media("../resources/plain/carrier-image.png").unwrap()
+//!     .use_media("../resources/plain/carrier-image.png")?
 //!     .write_to("image-with-a-file-inside.png")
-//!     .hide();
+//!     .hide()?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ## Unveil data from an image
@@ -26,16 +29,19 @@
 //! use stegano_core::commands::unveil;
 //! use std::path::Path;
 //!
+//! # fn main() -> stegano_core::Result<()> {
 //! SteganoCore::encoder()
 //!     .hide_file("Cargo.toml")
-//!     .use_media("../resources/plain/carrier-image.png").unwrap()
+//!     .use_media("../resources/plain/carrier-image.png")?
 //!     .write_to("image-with-a-file-inside.png")
-//!     .hide();
+//!     .hide()?;
 //!
 //! unveil(
 //!     &Path::new("image-with-a-file-inside.png"),
 //!     &Path::new("./"),
-//!     &CodecOptions::default());
+//!     &CodecOptions::default())?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! [core]: ./struct.SteganoCore.html
@@ -78,19 +84,52 @@ pub mod raw_message;
 
 pub use raw_message::*;
 
+pub mod framed_message;
+
+pub use framed_message::*;
+
+pub mod sharded_message;
+
+pub use sharded_message::*;
+
+pub mod analysis;
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "fs")]
 pub mod commands;
+pub mod fec;
+pub mod format;
 pub mod media;
+pub mod secret;
 pub mod universal_decoder;
 pub mod universal_encoder;
 
+use crate::media::audio::wav_iter::{AudioWavIter, AudioWavIterMut};
+use crate::universal_decoder::{Decoder, OneBitUnveil};
+use crate::universal_encoder::{Encoder, OneBitHide};
+
 use hound::{WavReader, WavSpec, WavWriter};
-use image::RgbaImage;
+use image::{GrayImage, ImageFormat, RgbaImage};
+use log::debug;
+use sha2::{Digest, Sha256};
 use std::default::Default;
-use std::fs::File;
-use std::path::Path;
+use std::io::Read;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use zeroize::Zeroizing;
 
+use crate::media::image::bitmap::BitmapImage;
+pub use crate::media::image::cursor::{Channel, ChannelOrder, TraversalOrder};
+use crate::media::image::dng::DngPreview;
+use crate::media::image::gif::GifImage;
+use crate::media::image::lsb_codec::Concealer;
+use crate::media::image::palette::PaletteImage;
 pub use crate::media::image::CodecOptions;
+pub use crate::secret::Secret;
 
 #[derive(Error, Debug)]
 pub enum SteganoError {
@@ -118,6 +157,14 @@ pub enum SteganoError {
     #[error("Write error")]
     WriteError { source: std::io::Error },
 
+    /// Represents a target file that cannot be written, found before hiding starts.
+    /// For example, a target in a folder that does not exist
+    #[error("Target {path} is not writable")]
+    TargetNotWritable {
+        path: String,
+        source: std::io::Error,
+    },
+
     /// Represents a failure when encoding an audio file.
     #[error("Audio encoding error")]
     AudioEncodingError,
@@ -130,23 +177,128 @@ pub enum SteganoError {
     #[error("Audio creation error")]
     AudioCreationError,
 
+    /// Represents secret data that could not be parsed. For example, a truncated frame
+    #[error("Secret data is malformed")]
+    MalformedSecretData,
+
+    /// Represents a message content that cannot be stored in the chosen content version.
+    /// For example, files with content version 1
+    #[error("{feature} is not supported by content version {version:#04x}")]
+    FeatureNotSupportedInVersion { feature: &'static str, version: u8 },
+
+    /// Represents a target media that would be stored lossy and so destroy the secret data.
+    /// For example, a lossy WebP image
+    #[error("Target media would be stored lossy")]
+    LossyTargetMedia,
+
+    /// Represents a verification of something that was never embedded.
+    /// For example, verifying the cover of a message without a cover hash
+    #[error("No {0} was embedded")]
+    NotEmbedded(&'static str),
+
+    /// Represents secret data that does not follow the message format, see [`format`]
+    #[error(transparent)]
+    InvalidFormat(#[from] format::FormatError),
+
+    /// Represents a parameter that is out of its valid range
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    /// Represents an encoder that was asked to hide without a carrier media
+    #[error("No carrier media was given")]
+    MissingCarrier,
+
+    /// Represents an encoder that was asked to hide without a target file, see [`SteganoEncoder::write_to`]
+    #[error("No target file was given")]
+    MissingTarget,
+
+    /// Represents a carrier that seems to already contain secret data, see [`SteganoEncoder::strict`]
+    #[error("Carrier media seems to already contain secret data")]
+    CarrierAlreadyUsed,
+
+    /// Represents a carrier that cannot hold the payload, not even with the most bits per channel
+    #[error(
+        "Payload of {needed} bytes does not fit into the carrier capacity of {capacity} bytes"
+    )]
+    CarrierTooSmall { needed: usize, capacity: usize },
+
+    /// Represents a list of carrier candidates where none can hold the payload,
+    /// see [`SteganoEncoder::carrier_candidates`]
+    #[error("None of the carrier candidates can hold the payload")]
+    NoSuitableCarrier,
+
+    /// Represents a payload that cannot be hidden without more distortion than allowed,
+    /// see [`SteganoEncoder::max_distortion_psnr`]
+    #[error("Payload cannot be hidden with a PSNR of at least {psnr} dB")]
+    CannotMeetDistortionTarget { psnr: f64 },
+
+    /// Represents an unveiled text that is not valid utf-8, e.g. because it was unveiled
+    /// with the wrong passphrase
+    #[error("Unveiled text is not valid utf-8")]
+    InvalidUtf8,
+
+    /// Represents a set of shards where the one with the given index is missing
+    #[error("Shard {0} of the set is missing")]
+    MissingShard(u16),
+
+    /// Represents a file name that is not part of the message, see [`SteganoDecoder::extract_file`]
+    #[error("No file named {0} was hidden")]
+    FileNotFound(String),
+
+    /// Represents a file that is bigger than the carrier can carry
+    #[error("File of {size} bytes is larger than the carrier capacity of {capacity} bytes")]
+    FileTooLarge { size: u64, capacity: usize },
+
+    /// Represents an operation that would hold more bytes in memory than its budget allows
+    #[error("{needed} bytes exceed the memory budget of {budget} bytes")]
+    MemoryBudgetExceeded { needed: usize, budget: usize },
+
+    /// Represents raw bytes that are no text in the given encoding, see [`RawMessage::decode_as`]
+    #[error("Byte {position} is not valid {encoding:?}")]
+    InvalidText {
+        encoding: TextEncoding,
+        position: usize,
+    },
+
+    /// Represents a secret that does not read back from the carrier as it was hidden,
+    /// see [`SteganoEncoder::verify_after_hide`]
+    #[error("Hidden secret does not read back as it was hidden")]
+    VerificationFailed,
+
+    /// Represents a hide that ran longer than allowed, see [`SteganoEncoder::time_budget`]
+    #[error("Hiding exceeded its time budget")]
+    TimeBudgetExceeded,
+
+    /// Represents an operation that was aborted via a [`CancellationToken`]
+    #[error("Operation was cancelled")]
+    Cancelled,
+
     /// Represents all other cases of `std::io::Error`.
     #[error(transparent)]
     IoError(#[from] std::io::Error),
 }
 
+/// shared flag that allows to abort a running hide operation, e.g. from a GUI thread
+pub type CancellationToken = Arc<AtomicBool>;
+
 /// wrap the low level data types that carries information
 #[derive(Debug, Eq, PartialEq)]
 pub enum MediaPrimitive {
     ImageColorChannel(u8),
     AudioSample(i16),
+    /// the bits of a `f32` channel, see [`f32::to_bits`]
+    #[cfg(feature = "hdr")]
+    ImageFloatChannel(u32),
 }
 
-/// mutable primitive for storing stegano data
-#[derive(Debug, Eq, PartialEq)]
+/// mutable primitive for storing stegano data, floats are not `Eq`
+#[derive(Debug, PartialEq)]
+#[cfg_attr(not(feature = "hdr"), derive(Eq))]
 pub enum MediaPrimitiveMut<'a> {
     ImageColorChannel(&'a mut u8),
     AudioSample(&'a mut i16),
+    #[cfg(feature = "hdr")]
+    ImageFloatChannel(&'a mut f32),
     None,
 }
 
@@ -163,6 +315,10 @@ impl HideBit for MediaPrimitiveMut<'_> {
             MediaPrimitiveMut::AudioSample(s) => {
                 *s = (*s & (i16::MAX - 1)) | if bit { 1 } else { 0 };
             }
+            #[cfg(feature = "hdr")]
+            MediaPrimitiveMut::ImageFloatChannel(f) => {
+                *f = f32::from_bits((f.to_bits() & !1) | bit as u32);
+            }
             MediaPrimitiveMut::None => {}
         }
         Ok(())
@@ -178,8 +334,58 @@ pub enum Media {
     Audio(WavAudio),
 }
 
+impl From<RgbaImage> for Media {
+    fn from(image: RgbaImage) -> Self {
+        Media::Image(image)
+    }
+}
+
+impl From<WavAudio> for Media {
+    fn from(audio: WavAudio) -> Self {
+        Media::Audio(audio)
+    }
+}
+
 pub struct SteganoCore {}
 
+/// true for the lossless image formats, which one of the codecs of this crate hides in
+fn is_carrier_format(format: ImageFormat) -> bool {
+    matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Bmp | ImageFormat::Gif
+    ) || (format == ImageFormat::WebP && cfg!(feature = "webp"))
+        || (format == ImageFormat::OpenExr && cfg!(feature = "hdr"))
+}
+
+/// true for a path with a PNG extension, only PNGs have a palette, EXIF orientation or metadata
+#[cfg(feature = "fs")]
+fn is_png(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.eq_ignore_ascii_case("png"))
+        .unwrap_or(false)
+}
+
+/// bundles the files into one zip archive in memory, fails with `SteganoError::NoSecretData`
+/// if there are none
+fn zip_files(files: Vec<(String, Vec<u8>)>) -> Result<Vec<u8>> {
+    use std::io::Write;
+
+    if files.is_empty() {
+        return Err(SteganoError::NoSecretData);
+    }
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, buf) in files {
+        zip.start_file(name, options)
+            .map_err(std::io::Error::other)?;
+        zip.write_all(&buf)?;
+    }
+    let zip = zip.finish().map_err(std::io::Error::other)?;
+
+    Ok(zip.into_inner())
+}
+
 impl SteganoCore {
     pub fn encoder() -> SteganoEncoder {
         SteganoEncoder::with_options(CodecOptions::default())
@@ -188,6 +394,189 @@ impl SteganoCore {
     pub fn encoder_with_options(opts: CodecOptions) -> SteganoEncoder {
         SteganoEncoder::with_options(opts)
     }
+
+    pub fn decoder() -> SteganoDecoder {
+        SteganoDecoder::with_options(CodecOptions::default())
+    }
+
+    pub fn decoder_with_options(opts: CodecOptions) -> SteganoDecoder {
+        SteganoDecoder::with_options(opts)
+    }
+
+    /// the image formats that this build can read carriers of, e.g. for a file open filter.
+    /// They depend on the enabled features of this crate and of `image`
+    pub fn supported_carrier_formats() -> Vec<ImageFormat> {
+        ImageFormat::all()
+            .filter(|format| is_carrier_format(*format) && format.reading_enabled())
+            .collect()
+    }
+
+    /// the image formats that this build can write the carrier with the secret as. Only
+    /// lossless formats keep the LSBs, so lossy formats like JPEG are never part of it
+    pub fn supported_output_formats() -> Vec<ImageFormat> {
+        ImageFormat::all()
+            .filter(|format| is_carrier_format(*format) && format.writing_enabled())
+            .collect()
+    }
+
+    /// moves the secret to a new image: unveils the files, text and comment of `src_stego`,
+    /// hides them in `new_carrier` with the default options and writes that to `out`.
+    /// Fails with `SteganoError::CarrierTooSmall` if they do not fit into `new_carrier`
+    #[cfg(feature = "fs")]
+    pub fn transfer(src_stego: &str, new_carrier: &str, out: &str) -> Result<()> {
+        let decoded = Self::decoder().use_media(src_stego)?.unveil_message()?;
+        if !decoded.found {
+            return Err(SteganoError::NoSecretData);
+        }
+
+        let mut encoder = Self::encoder();
+        encoder.use_media(new_carrier)?;
+        for (name, buf) in decoded.files {
+            encoder.message.add_file_data(&name, buf);
+        }
+        if let Some(text) = decoded.text.as_ref() {
+            encoder.hide_message(text);
+        }
+        if let Some(comment) = decoded.comment {
+            encoder.set_comment(&comment);
+        }
+        if encoder.utilization().unwrap_or(0.0) > 1.0 {
+            return Err(SteganoError::CarrierTooSmall {
+                needed: encoder.message.payload_len(),
+                capacity: encoder.capacity(),
+            });
+        }
+        encoder.write_to(out).hide()?;
+
+        Ok(())
+    }
+
+    /// unveils each image inside the zip or tar `archive`, without extracting it to disk first,
+    /// e.g. for a forensic scan of thousands of images. Each entry gets its result, in the order
+    /// of the archive: the hidden files and text bundled as by [`SteganoDecoder::unveil_as_zip`],
+    /// or the error of just that entry, `SteganoError::NoSecretData` for a clean image and
+    /// `SteganoError::UnsupportedMedia` for a file of another format. Fails as a whole only if
+    /// the archive cannot be read
+    #[cfg(feature = "fs")]
+    pub fn unveil_from_archive(archive: &str) -> Result<Vec<(String, Result<Vec<u8>>)>> {
+        let read_error = |e| SteganoError::ReadError {
+            source: std::io::Error::other(e),
+        };
+        let mut file =
+            std::fs::File::open(archive).map_err(|source| SteganoError::ReadError { source })?;
+        let mut magic = [0; 2];
+        let is_zip =
+            file.read_exact(&mut magic).is_ok() && magic == format::ZIP_LOCAL_FILE_HEADER[..2];
+        std::io::Seek::rewind(&mut file).map_err(|source| SteganoError::ReadError { source })?;
+
+        let mut entries = Vec::new();
+        if is_zip {
+            let mut zip = zip::ZipArchive::new(file).map_err(read_error)?;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i).map_err(read_error)?;
+                if entry.is_dir() {
+                    continue;
+                }
+                let mut buf = Vec::new();
+                entry
+                    .read_to_end(&mut buf)
+                    .map_err(|source| SteganoError::ReadError { source })?;
+                entries.push((entry.name().to_owned(), buf));
+            }
+        } else {
+            let mut tar = tar::Archive::new(file);
+            for entry in tar
+                .entries()
+                .map_err(|source| SteganoError::ReadError { source })?
+            {
+                let mut entry = entry.map_err(|source| SteganoError::ReadError { source })?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry
+                    .path()
+                    .map_err(|source| SteganoError::ReadError { source })?
+                    .to_string_lossy()
+                    .into_owned();
+                let mut buf = Vec::new();
+                entry
+                    .read_to_end(&mut buf)
+                    .map_err(|source| SteganoError::ReadError { source })?;
+                entries.push((name, buf));
+            }
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|(name, buf)| {
+                let extension = Path::new(&name)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or_default();
+                let unveiled = Media::from_bytes(&buf, extension).and_then(|media| {
+                    let msg = Self::decoder().use_source(media).unveil_message()?;
+                    if !msg.checksum_ok {
+                        return Err(format::FormatError::ChecksumMismatch.into());
+                    }
+                    let mut files = msg.files;
+                    if let Some(text) = msg.text {
+                        files.push(("secret-message.txt".to_owned(), text.into_bytes()));
+                    }
+                    zip_files(files)
+                });
+                (name, unveiled)
+            })
+            .collect())
+    }
+
+    /// unveils each of the `images` into its own folder in `out_dir`, named like the image
+    /// without extension, so that equally named secrets do not overwrite each other.
+    /// The result of each image is collected in the order of `images`, one failing image does
+    /// not stop the others. Fails as a whole only if `out_dir` cannot be created
+    #[cfg(feature = "fs")]
+    pub fn unveil_batch(images: &[&str], out_dir: &str) -> Result<Vec<Result<Vec<ExtractedFile>>>> {
+        std::fs::create_dir_all(out_dir).map_err(|source| SteganoError::WriteError { source })?;
+
+        Ok(images
+            .iter()
+            .map(|image| {
+                let name = Path::new(image)
+                    .file_stem()
+                    .ok_or(SteganoError::UnsupportedMedia)?;
+                let dir = Path::new(out_dir).join(name);
+                std::fs::create_dir_all(&dir)
+                    .map_err(|source| SteganoError::WriteError { source })?;
+
+                Self::decoder()
+                    .use_media(image)?
+                    .unveil_with_manifest(dir.to_str().unwrap())
+            })
+            .collect())
+    }
+}
+
+/// hides the file `payload` in the media file `carrier` and writes the result to `out`,
+/// with the default options. A one shot of the [`SteganoEncoder`] builder
+#[cfg(feature = "fs")]
+pub fn hide(carrier: &str, payload: &str, out: &str) -> Result<()> {
+    SteganoCore::encoder()
+        .use_media(carrier)?
+        .hide_file(payload)
+        .write_to(out)
+        .hide()?;
+
+    Ok(())
+}
+
+/// unveils all files hidden in the media file `stego` into the folder `out`,
+/// with the default options. A one shot of the [`SteganoDecoder`] builder
+#[cfg(feature = "fs")]
+pub fn unveil(stego: &str, out: &str) -> Result<()> {
+    SteganoCore::decoder()
+        .use_media(stego)?
+        .unveil_with_manifest(out)?;
+
+    Ok(())
 }
 
 pub trait Hide {
@@ -200,38 +589,257 @@ pub trait Hide {
 }
 
 impl Media {
+    #[cfg(feature = "fs")]
     pub fn from_file(f: &Path) -> Result<Self> {
-        if let Some(ext) = f.extension() {
-            let ext = ext.to_str().unwrap().to_lowercase();
-            match ext.as_str() {
-                "png" => Ok(Self::Image(
-                    image::open(f)
-                        .map_err(|_e| SteganoError::InvalidImageMedia)?
-                        .to_rgba8(),
-                )),
-                "wav" => {
-                    let mut reader =
-                        WavReader::open(f).map_err(|_e| SteganoError::InvalidAudioMedia)?;
-                    let spec = reader.spec();
-                    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
-
-                    Ok(Self::Audio((spec, samples)))
+        Self::read_file(f).map(|(media, _)| media)
+    }
+
+    /// like `from_file()`, but also returns the bytes of the file, so that e.g. the palette or
+    /// the metadata of a PNG can be taken from them without reading the file once more
+    #[cfg(feature = "fs")]
+    fn read_file(f: &Path) -> Result<(Self, Vec<u8>)> {
+        let Some(ext) = f.extension() else {
+            return Err(SteganoError::UnsupportedMedia);
+        };
+        let ext = ext.to_str().unwrap().to_lowercase();
+        let invalid = match ext.as_str() {
+            "png" | "bmp" => SteganoError::InvalidImageMedia,
+            #[cfg(feature = "webp")]
+            "webp" => SteganoError::InvalidImageMedia,
+            "wav" => SteganoError::InvalidAudioMedia,
+            _ => return Err(SteganoError::UnsupportedMedia),
+        };
+        let buf = std::fs::read(f).map_err(|_e| invalid)?;
+
+        Ok((Self::from_bytes(&buf, &ext)?, buf))
+    }
+
+    /// decodes a media from memory, `extension` names its format like the extension of a file would
+    pub fn from_bytes(buf: &[u8], extension: &str) -> Result<Self> {
+        match extension.to_lowercase().as_str() {
+            "png" => Ok(Self::Image(
+                image::load_from_memory_with_format(buf, image::ImageFormat::Png)
+                    .map_err(|_e| SteganoError::InvalidImageMedia)?
+                    .to_rgba8(),
+            )),
+            "bmp" => Ok(Self::Image(
+                image::load_from_memory_with_format(buf, image::ImageFormat::Bmp)
+                    .map_err(|_e| SteganoError::InvalidImageMedia)?
+                    .to_rgba8(),
+            )),
+            #[cfg(feature = "webp")]
+            "webp" => Ok(Self::Image(
+                image::load_from_memory_with_format(buf, image::ImageFormat::WebP)
+                    .map_err(|_e| SteganoError::InvalidImageMedia)?
+                    .to_rgba8(),
+            )),
+            "wav" => {
+                let mut reader = WavReader::new(std::io::Cursor::new(buf))
+                    .map_err(|_e| SteganoError::InvalidAudioMedia)?;
+                let spec = reader.spec();
+                let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+
+                Ok(Self::Audio((spec, samples)))
+            }
+            _ => Err(SteganoError::UnsupportedMedia),
+        }
+    }
+
+    /// encodes the media in memory, `extension` names the format like for [`Media::from_bytes`]
+    pub fn to_bytes(&self, extension: &str) -> Result<Vec<u8>> {
+        let mut buf = std::io::Cursor::new(Vec::new());
+        match (self, extension.to_lowercase().as_str()) {
+            (Media::Image(i), "png") => i
+                .write_to(&mut buf, image::ImageOutputFormat::Png)
+                .map_err(|_e| SteganoError::ImageEncodingError)?,
+            (Media::Image(i), "bmp") => i
+                .write_to(&mut buf, image::ImageOutputFormat::Bmp)
+                .map_err(|_e| SteganoError::ImageEncodingError)?,
+            #[cfg(feature = "webp")]
+            (Media::Image(i), "webp") => return media::image::webp::encode_lossless(i),
+            (Media::Audio((spec, samples)), "wav") => {
+                let mut writer = WavWriter::new(&mut buf, *spec)
+                    .map_err(|_| SteganoError::AudioCreationError)?;
+                for s in samples {
+                    writer
+                        .write_sample(*s)
+                        .map_err(|_| SteganoError::AudioEncodingError)?;
                 }
-                _ => Err(SteganoError::UnsupportedMedia),
+                writer
+                    .finalize()
+                    .map_err(|_| SteganoError::AudioEncodingError)?;
             }
-        } else {
-            Err(SteganoError::UnsupportedMedia)
+            _ => return Err(SteganoError::UnsupportedMedia),
+        }
+
+        Ok(buf.into_inner())
+    }
+
+    /// applies an EXIF orientation to an image, see [`media::image::orientation::apply_orientation`],
+    /// audio is returned as it is
+    pub fn oriented(self, orientation: u8) -> Self {
+        match self {
+            Media::Image(image) => Media::Image(media::image::orientation::apply_orientation(
+                image,
+                orientation,
+            )),
+            audio => audio,
+        }
+    }
+
+    /// hides the given raw bytes, no content format is applied
+    pub fn hide_bytes(&mut self, buf: &[u8], opts: &CodecOptions) -> Result<&mut Self> {
+        debug!("hiding {} bytes in the LSBs of the media", buf.len());
+        if self.encoder(opts).write_all(buf).is_err() {
+            return Err(self.encoding_error(opts));
+        }
+
+        Ok(self)
+    }
+
+    /// like `hide_bytes()` for a message with a deferred checksum: the checksum is computed
+    /// while the payload is hidden, then the bytes in front of the payload are hidden again
+    /// with it. That saves the pass over the payload, that computing it up front takes.
+    /// Returns the checksum
+    pub fn hide_bytes_streaming_checksum(
+        &mut self,
+        buf: &[u8],
+        checksum: &message::DeferredChecksum,
+        opts: &CodecOptions,
+    ) -> Result<Vec<u8>> {
+        use std::io::Write;
+
+        debug!("hiding {} bytes, hashing the payload on the way", buf.len());
+        let payload = checksum.payload.clone();
+        let streamed = {
+            let mut encoder = self.encoder(opts);
+            encoder.write_all(&buf[..payload.start]).and_then(|_| {
+                let mut hashing = message::HashingWriter::new(encoder.as_mut(), checksum.algo);
+                hashing.write_all(&buf[payload.clone()])?;
+                let digest = hashing.finalize();
+                encoder.write_all(&buf[payload.end..])?;
+
+                Ok(digest)
+            })
+        };
+        let Ok(digest) = streamed else {
+            return Err(self.encoding_error(opts));
+        };
+        let mut header = buf[..payload.start].to_vec();
+        checksum.patch_with(&mut header, &digest);
+        if self.encoder(opts).write_all(&header).is_err() {
+            return Err(self.encoding_error(opts));
+        }
+
+        Ok(digest)
+    }
+
+    /// builds an encoder that writes raw bytes into the media
+    fn encoder<'a>(&'a mut self, opts: &CodecOptions) -> Box<dyn std::io::Write + 'a> {
+        match self {
+            Media::Image(i) => media::image::LsbCodec::encoder(i, opts),
+            Media::Audio((_spec, samples)) => {
+                let encoder = Encoder::new(AudioWavIterMut::new(samples.iter_mut()), OneBitHide);
+                match opts.cancel.as_ref() {
+                    Some(token) => Box::new(encoder.with_cancel(token.clone())),
+                    None => Box::new(encoder),
+                }
+            }
+        }
+    }
+
+    /// the error of a failed write into the encoder of the media
+    fn encoding_error(&self, opts: &CodecOptions) -> SteganoError {
+        match self {
+            _ if opts.is_cancelled() => SteganoError::Cancelled,
+            Media::Image(_) if opts.is_past_deadline() => SteganoError::TimeBudgetExceeded,
+            Media::Image(_) => SteganoError::ImageEncodingError,
+            Media::Audio(_) => SteganoError::AudioEncodingError,
+        }
+    }
+
+    /// SHA-256 over all media content that is never touched by hiding, e.g. all but the LSBs
+    pub fn cover_hash(&self, opts: &CodecOptions) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        match self {
+            Media::Image(image) => {
+                let mask = match opts.concealer {
+                    Concealer::LeastSignificantBit => u8::MAX
+                        .checked_shl(opts.bit_depths().into_iter().max().unwrap_or(1) as u32)
+                        .unwrap_or(0),
+                    Concealer::LowFrequencies => 0b1111_0000,
+                    // adding 1 carries into any bit, hiding binds no cover then
+                    Concealer::LsbMatching => 0,
+                };
+                hasher.update(image.width().to_be_bytes());
+                hasher.update(image.height().to_be_bytes());
+                for chunk in image.as_raw().chunks(4096) {
+                    let masked: Vec<u8> = chunk.iter().map(|c| c & mask).collect();
+                    hasher.update(&masked);
+                }
+            }
+            Media::Audio((_spec, samples)) => {
+                for s in samples {
+                    hasher.update((s & !1).to_be_bytes());
+                }
+            }
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// ordering seed derived from the [`Media::cover_hash`], so that it is the same before and after hiding
+    pub fn cover_seed(&self, opts: &CodecOptions) -> u64 {
+        let hash = self.cover_hash(opts);
+
+        u64::from_be_bytes(hash[..8].try_into().unwrap())
+    }
+
+    /// checks if the region that a payload of `len` bytes would occupy seems to already carry data,
+    /// that is it either holds a valid message or its hidden bits look random
+    pub fn looks_already_used(&self, len: usize, opts: &CodecOptions) -> bool {
+        analysis::looks_already_used(|| self.decoder(opts), len)
+    }
+
+    /// number of bytes the media can carry at most, including the message headers
+    pub fn capacity(&self, opts: &CodecOptions) -> usize {
+        match self {
+            Media::Image(image) => media::image::LsbCodec::channels(image, opts).count() / 8,
+            Media::Audio((_spec, samples)) => samples.len() / 8,
+        }
+    }
+
+    /// builds a decoder that reads the raw bytes hidden in the media
+    pub fn decoder<'a>(&'a self, opts: &CodecOptions) -> Box<dyn Read + 'a> {
+        match self {
+            Media::Image(image) => media::image::LsbCodec::decoder(image, opts),
+            Media::Audio((_spec, samples)) => Box::new(Decoder::new(
+                AudioWavIter::new(samples.iter().copied()),
+                OneBitUnveil,
+            )),
         }
     }
 }
 
+#[cfg(feature = "fs")]
 pub trait Persist {
     fn save_as(&mut self, _: &Path) -> Result<()>;
 }
 
+#[cfg(feature = "fs")]
 impl Persist for Media {
     fn save_as(&mut self, file: &Path) -> Result<()> {
         match self {
+            #[cfg(feature = "webp")]
+            Media::Image(i)
+                if file
+                    .extension()
+                    .map(|e| e.eq_ignore_ascii_case("webp"))
+                    .unwrap_or(false) =>
+            {
+                let buf = media::image::webp::encode_lossless(i)?;
+                std::fs::write(file, buf).map_err(|source| SteganoError::WriteError { source })
+            }
             Media::Image(i) => i.save(file).map_err(|_e| SteganoError::ImageEncodingError),
             Media::Audio((spec, samples)) => {
                 let mut writer =
@@ -265,45 +873,92 @@ impl Hide for Media {
         message: &Message,
         opts: &CodecOptions,
     ) -> Result<&mut Media> {
-        let buf: Vec<u8> = message.into();
-
-        match self {
-            Media::Image(i) => {
-                let (width, height) = i.dimensions();
-                let _space_to_fill = (width * height * 3) / 8;
-                let mut encoder = media::image::LsbCodec::encoder(i, opts);
-
-                encoder
-                    .write_all(buf.as_ref())
-                    .map_err(|_e| SteganoError::ImageEncodingError)?
-            }
-            Media::Audio((_spec, samples)) => {
-                let mut encoder = media::audio::LsbCodec::encoder(samples);
-
-                encoder
-                    .write_all(buf.as_ref())
-                    .map_err(|_e| SteganoError::AudioEncodingError)?
-            }
-        }
+        let buf = message.to_bytes()?;
 
-        Ok(self)
+        self.hide_bytes(buf.as_ref(), opts)
     }
 }
 
 pub struct SteganoEncoder {
     options: CodecOptions,
+    #[cfg(feature = "fs")]
     target: Option<String>,
+    #[cfg(feature = "fs")]
+    candidates: Vec<String>,
+    #[cfg(feature = "fs")]
+    chosen_candidate: Option<usize>,
+    #[cfg(feature = "fs")]
+    pair: Option<(Media, Media)>,
+    #[cfg(feature = "fs")]
+    parity_target: Option<String>,
     carrier: Option<Media>,
     message: Message,
+    frames: Vec<Vec<u8>>,
+    content_version_forced: bool,
+    bind_cover_hash: bool,
+    derive_seed_from_cover: bool,
+    pre_dither: bool,
+    strict: bool,
+    provenance: bool,
+    memory_budget: Option<usize>,
+    time_budget: Option<Duration>,
+    palette: Option<PaletteImage>,
+    palette_embedding: bool,
+    spare_palette_slots: bool,
+    bitmap: Option<BitmapImage>,
+    normalize_output: bool,
+    metadata_channel: bool,
+    metadata_payload: Option<Vec<u8>>,
+    max_distortion_psnr: Option<f64>,
+    gif: Option<GifImage>,
+    dng: Option<DngPreview>,
+    per_frame: bool,
+    luminance: bool,
+    alignment_marker: bool,
+    planes: Vec<(u8, Vec<u8>)>,
+    verify_after_hide: bool,
 }
 
 impl Default for SteganoEncoder {
     fn default() -> Self {
         Self {
             options: CodecOptions::default(),
+            #[cfg(feature = "fs")]
             target: None,
+            #[cfg(feature = "fs")]
+            candidates: Vec::new(),
+            #[cfg(feature = "fs")]
+            chosen_candidate: None,
+            #[cfg(feature = "fs")]
+            pair: None,
+            #[cfg(feature = "fs")]
+            parity_target: None,
             carrier: None,
             message: Message::empty(),
+            frames: Vec::new(),
+            content_version_forced: false,
+            bind_cover_hash: false,
+            derive_seed_from_cover: false,
+            pre_dither: false,
+            strict: false,
+            provenance: false,
+            memory_budget: None,
+            time_budget: None,
+            palette: None,
+            palette_embedding: false,
+            spare_palette_slots: false,
+            bitmap: None,
+            normalize_output: false,
+            metadata_channel: false,
+            metadata_payload: None,
+            max_distortion_psnr: None,
+            gif: None,
+            dng: None,
+            per_frame: false,
+            luminance: false,
+            alignment_marker: false,
+            planes: Vec::new(),
+            verify_after_hide: false,
         }
     }
 }
@@ -319,18 +974,131 @@ impl SteganoEncoder {
         }
     }
 
+    /// a 1 bit per pixel PNG is used as [`BitmapImage`], its pixels carry the secret.
+    /// The EXIF orientation of a PNG is applied and not written to the target, so that the
+    /// stored pixels are as displayed, see [`SteganoDecoder::respect_exif_orientation`]
+    #[cfg(feature = "fs")]
     pub fn use_media(&mut self, input_file: &str) -> Result<&mut Self> {
         let path = Path::new(input_file);
-        self.carrier = Some(Media::from_file(path)?);
+        let (mut media, buf) = Media::read_file(path)?;
+        let png = is_png(path).then_some(buf.as_slice());
+        if let Some(orientation) = png.and_then(media::image::orientation::exif_orientation) {
+            media = media.oriented(orientation);
+        }
+        self.carrier = Some(media);
+        self.palette = png.map(PaletteImage::decode).transpose()?.flatten();
+        self.bitmap = png.map(BitmapImage::decode).transpose()?.flatten();
+
+        Ok(self)
+    }
+
+    /// uses a DNG raw camera file as carrier, the secret is hidden in the LSBs of its uncompressed
+    /// 8 bit RGB preview, so the raw sensor data and the metadata stay untouched, see
+    /// [`DngPreview`]. Fails with `SteganoError::UnsupportedMedia` if it has no such preview
+    #[cfg(feature = "fs")]
+    pub fn use_carrier_dng(&mut self, input_file: &str) -> Result<&mut Self> {
+        self.dng = Some(DngPreview::open(Path::new(input_file))?);
+
+        Ok(self)
+    }
+
+    /// uses an animated GIF as carrier, the secret is hidden in the palette indices of its
+    /// frames, see [`GifImage`]. Only the first frame carries it, unless [`SteganoEncoder::embed_per_frame`]
+    #[cfg(feature = "fs")]
+    pub fn use_carrier_gif(&mut self, input_file: &str) -> Result<&mut Self> {
+        self.gif = Some(GifImage::open(Path::new(input_file))?);
+
+        Ok(self)
+    }
+
+    /// uses any image as carrier of a GIF target: it is quantized to a palette deterministically,
+    /// and the secret is hidden in the palette indices, which survive saving and reloading the
+    /// GIF, see [`GifImage::quantize`]. The decoder reads the indices with
+    /// [`SteganoDecoder::use_carrier_gif`]. The target is written as GIF, whatever its extension
+    #[cfg(feature = "fs")]
+    pub fn use_carrier_quantized_gif(&mut self, input_file: &str) -> Result<&mut Self> {
+        let image = image::open(input_file).map_err(|_e| SteganoError::InvalidImageMedia)?;
+        self.gif = Some(GifImage::quantize(&image.to_rgba8())?);
 
         Ok(self)
     }
 
+    /// hides the same secret into every frame of the GIF carrier, so that it survives the loss
+    /// of frames, the decoder recovers it from any single frame. The capacity is that of the
+    /// smallest frame, see [`SteganoEncoder::use_carrier_gif`]
+    pub fn embed_per_frame(&mut self, per_frame: bool) -> &mut Self {
+        self.per_frame = per_frame;
+
+        self
+    }
+
+    /// uses an in memory carrier, e.g. an image that was decoded elsewhere
+    pub fn use_source(&mut self, source: impl Into<Media>) -> &mut Self {
+        self.carrier = Some(source.into());
+
+        self
+    }
+
+    /// uses a raw RGBA buffer as carrier, e.g. from a GPU readback, 4 bytes per pixel row by row.
+    /// Fails with `SteganoError::InvalidParameter` if the buffer length does not match the dimensions
+    pub fn use_carrier_raw(&mut self, rgba: Vec<u8>, width: u32, height: u32) -> Result<&mut Self> {
+        let invalid = SteganoError::InvalidParameter(format!(
+            "{} bytes are no RGBA buffer of {width}x{height} pixels",
+            rgba.len()
+        ));
+        if rgba.len() as u64 != width as u64 * height as u64 * 4 {
+            return Err(invalid);
+        }
+        let image = RgbaImage::from_raw(width, height, rgba).ok_or(invalid)?;
+
+        Ok(self.use_source(image))
+    }
+
+    #[cfg(feature = "fs")]
     pub fn write_to(&mut self, output_file: &str) -> &mut Self {
         self.target = Some(output_file.to_owned());
         self
     }
 
+    /// hides the payload in carrier `a` and its Reed-Solomon parity in carrier `b`, instead of
+    /// the carrier set by `use_media`. Together they correct up to [`fec::PAIR_REDUNDANCY`]
+    /// corrupted bytes per block of the payload, no matter in which carrier, see
+    /// [`SteganoDecoder::use_carrier_pair`]. `hide()` writes `a` to the target of `write_to`
+    /// and `b` to the target of [`SteganoEncoder::write_parity_to`]
+    #[cfg(feature = "fs")]
+    pub fn use_carrier_pair(&mut self, a: &str, b: &str) -> Result<&mut Self> {
+        self.pair = Some((
+            Media::from_file(Path::new(a))?,
+            Media::from_file(Path::new(b))?,
+        ));
+
+        Ok(self)
+    }
+
+    /// the target of the parity carrier, see [`SteganoEncoder::use_carrier_pair`]
+    #[cfg(feature = "fs")]
+    pub fn write_parity_to(&mut self, output_file: &str) -> &mut Self {
+        self.parity_target = Some(output_file.to_owned());
+
+        self
+    }
+
+    /// carriers to try one after the other, `hide()` uses the first that can hold the payload
+    /// instead of the carrier set by `use_media`, see [`SteganoEncoder::chosen_candidate`].
+    /// If none can, `hide()` fails with `SteganoError::NoSuitableCarrier`
+    #[cfg(feature = "fs")]
+    pub fn carrier_candidates(&mut self, paths: Vec<&str>) -> &mut Self {
+        self.candidates = paths.into_iter().map(str::to_owned).collect();
+
+        self
+    }
+
+    /// index of the carrier candidate that `hide()` used, see [`SteganoEncoder::carrier_candidates`]
+    #[cfg(feature = "fs")]
+    pub fn chosen_candidate(&self) -> Option<usize> {
+        self.chosen_candidate
+    }
+
     pub fn hide_message(&mut self, msg: &str) -> &mut Self {
         self.message
             .add_file_data("secret-message.txt", msg.as_bytes().to_vec());
@@ -338,15 +1106,50 @@ impl SteganoEncoder {
         self
     }
 
+    /// panics if the file is not readable or does not fit, see [`SteganoEncoder::try_hide_file`]
+    #[cfg(feature = "fs")]
     pub fn hide_file(&mut self, input_file: &str) -> &mut Self {
-        {
-            let _f = File::open(input_file).expect("Data file was not readable.");
+        self.try_hide_file(input_file)
+            .expect("Data file was not readable.")
+    }
+
+    /// like `hide_file()`, but fails with `SteganoError::ReadError` if the file is not readable.
+    /// If the carrier is already set, files larger than its capacity fail early with
+    /// `SteganoError::FileTooLarge`
+    /// With a [`SteganoEncoder::memory_budget`] a file that would not fit in it is not read, and
+    /// fails with `SteganoError::MemoryBudgetExceeded`
+    #[cfg(feature = "fs")]
+    pub fn try_hide_file(&mut self, input_file: &str) -> Result<&mut Self> {
+        let size = std::fs::metadata(input_file)
+            .map_err(|source| SteganoError::ReadError { source })?
+            .len();
+        let capacity = self.capacity();
+        if self.carrier.is_some() && size > capacity as u64 {
+            return Err(SteganoError::FileTooLarge { size, capacity });
+        }
+        let file_name = Path::new(input_file)
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or(SteganoError::InvalidParameter(format!(
+                "{input_file} has no file name"
+            )))?;
+        if let Some(budget) = self.memory_budget {
+            let size = usize::try_from(size).unwrap_or(usize::MAX);
+            let needed = self
+                .message
+                .uncompressed_payload_len()
+                .saturating_add(message::zip_entry_len(file_name.len(), size));
+            message::check_budget(needed, budget)?;
         }
-        self.message.add_file(input_file);
 
-        self
+        let data =
+            std::fs::read(input_file).map_err(|source| SteganoError::ReadError { source })?;
+        self.message.add_file_data(file_name, data);
+
+        Ok(self)
     }
 
+    #[cfg(feature = "fs")]
     pub fn hide_files(&mut self, input_files: Vec<&str>) -> &mut Self {
         self.message.files = Vec::new();
         input_files.iter().for_each(|&f| {
@@ -356,321 +1159,5752 @@ impl SteganoEncoder {
         self
     }
 
+    /// moves the hidden file of that name to the front, so that it is hidden first, and is the
+    /// most likely to survive a carrier that gets cut off, see [`SteganoDecoder::unveil_best_effort`].
+    /// The others keep their order, the decoder knows the files by their names anyway.
+    /// Fails with `SteganoError::FileNotFound` if no such file was added yet
+    pub fn prioritize_file(&mut self, name: &str) -> Result<&mut Self> {
+        let files = &mut self.message.files;
+        let at = files
+            .iter()
+            .position(|(file_name, _)| file_name == name)
+            .ok_or_else(|| SteganoError::FileNotFound(name.to_owned()))?;
+        files[..=at].rotate_right(1);
+
+        Ok(self)
+    }
+
+    /// enforces a content version, `hide()` fails with `SteganoError::FeatureNotSupportedInVersion`
+    /// if the message contains anything the version cannot represent
     pub fn force_content_version(&mut self, c: ContentVersion) -> &mut Self {
         self.message.header = c;
+        self.content_version_forced = true;
 
         self
     }
 
-    pub fn hide(&mut self) -> &Self {
-        {
-            // TODO this hack needs to be implemented as well :(
-            // if self.message.header == ContentVersion::V2 {
-            //     space_to_fill -= buf.len();
-            //
-            //     for _ in 0..space_to_fill {
-            //         dec.write_all(&[0])
-            //             .expect("Failed to terminate version 2 content.");
-            //     }
-            // }
-        }
+    /// stores the files as they are behind an index of their names, offsets and sizes, that is
+    /// content version 6, so that [`SteganoDecoder::extract_file`] reads only the one file.
+    /// The files are not compressed and header fields are not supported then
+    pub fn with_file_index(&mut self, index: bool) -> &mut Self {
+        match index {
+            true => self.force_content_version(ContentVersion::V6),
+            false => {
+                self.message.header = ContentVersion::V4;
+                self.content_version_forced = false;
 
-        if let Some(media) = self.carrier.as_mut() {
-            media
-                // .hide_message(&self.message)
-                .hide_message_with_options(&self.message, &self.options)
-                .expect("Failed to hide message in media")
-                .save_as(Path::new(self.target.as_ref().unwrap()))
-                .expect("Failed to save media");
+                self
+            }
         }
+    }
+
+    /// records a hash of the carriers content that is not used for hiding (e.g. all but the LSBs),
+    /// so that [`SteganoDecoder::verify_cover`] can detect later modifications of the visible content
+    pub fn bind_cover_hash(&mut self, bind: bool) -> &mut Self {
+        self.bind_cover_hash = bind;
 
         self
     }
-}
-
-#[cfg(test)]
-mod e2e_tests {
-    use super::*;
-    use crate::commands::{unveil, unveil_raw};
-    use std::fs;
-    use std::io::Read;
-    use tempfile::TempDir;
 
-    const BASE_IMAGE: &str = "../resources/Base.png";
+    /// sets the LSBs by LSB matching instead of replacing them, see [`Concealer::LsbMatching`].
+    /// The chi-square attack does not see it, and unveiling is the same.
+    /// Only with 1 bit per channel and neither a cover hash nor a seed derived from the cover,
+    /// because a carry changes the higher bits as well, `hide()` fails with
+    /// `SteganoError::InvalidParameter` otherwise
+    pub fn use_lsb_matching(&mut self, matching: bool) -> &mut Self {
+        self.options.concealer = match matching {
+            true => Concealer::LsbMatching,
+            false => Concealer::LeastSignificantBit,
+        };
 
-    #[test]
-    #[should_panic(expected = "Data file was not readable.")]
-    fn should_panic_on_invalid_data_file() {
-        SteganoEncoder::new().hide_file("foofile");
+        self
     }
 
-    #[test]
-    #[should_panic(expected = "Data file was not readable.")]
-    fn should_panic_on_invalid_data_file_among_valid() {
-        SteganoEncoder::new().hide_files(vec!["Cargo.toml", "foofile"]);
+    /// hides in the LSB of the luminance of each pixel, instead of its red, green and blue LSBs,
+    /// see [`media::image::luminance`]. That is 1 bit per pixel, and for some images less
+    /// perceptible. Images only, see [`SteganoDecoder::use_luminance`]
+    pub fn use_luminance(&mut self, luminance: bool) -> &mut Self {
+        self.luminance = luminance;
+
+        self
     }
 
-    #[test]
-    fn should_panic_for_invalid_carrier_image_file() {
-        let mut encoder = SteganoEncoder::new();
-        let result = encoder.use_media("some_random_file.png");
-        match result.err() {
-            Some(SteganoError::InvalidImageMedia) => (),
-            _ => panic!(),
-        }
+    /// reads the secret back right after it was hidden and fails with
+    /// `SteganoError::VerificationFailed` if it differs. It is read from the carrier in memory,
+    /// so nothing is reloaded from disk, and a failed `hide()` writes no target at all.
+    /// The metadata channel stores the secret as it is, so there is nothing to verify there
+    pub fn verify_after_hide(&mut self, verify: bool) -> &mut Self {
+        self.verify_after_hide = verify;
+
+        self
     }
 
-    #[test]
-    fn should_panic_for_invalid_media_file() {
-        let mut encoder = SteganoEncoder::new();
-        let result = encoder.use_media("Cargo.toml");
-        match result.err() {
-            Some(SteganoError::UnsupportedMedia) => (),
-            _ => panic!(),
+    /// queues `data` for the bit `plane` of every color channel, 0 being the LSB, up to
+    /// [`format::MAX_BIT_PLANE`]. Each plane carries its own payload, all are written in one
+    /// `hide()` instead of the message, see [`media::image::bit_plane`]. Queuing a plane again
+    /// replaces its payload. Fails with `SteganoError::InvalidParameter` for a plane out of range.
+    /// Images only, see [`SteganoDecoder::unveil_plane`]
+    pub fn hide_in_plane(&mut self, plane: u8, data: &[u8]) -> Result<&mut Self> {
+        if plane > format::MAX_BIT_PLANE {
+            return Err(SteganoError::InvalidParameter(format!(
+                "bit plane must be between 0 and {}, but is {plane}",
+                format::MAX_BIT_PLANE
+            )));
         }
+        self.planes.retain(|(p, _)| *p != plane);
+        self.planes.push((plane, data.to_vec()));
+
+        Ok(self)
     }
 
-    #[test]
+    /// hides behind an alignment marker, so that the secret can be unveiled from a copy with up
+    /// to [`format::ALIGNMENT_MARGIN`] pixels cropped off each side, e.g. a screenshot,
+    /// see [`media::image::alignment`]. The margin carries nothing, so the capacity shrinks a bit.
+    /// Images only, see [`SteganoDecoder::use_alignment_marker`]
+    pub fn use_alignment_marker(&mut self, marker: bool) -> &mut Self {
+        self.alignment_marker = marker;
+
+        self
+    }
+
+    /// hides in the LSBs of the palette colors of an indexed PNG, instead of the pixels.
+    /// The pixel indices stay untouched, but the capacity is limited to 96 bytes, so frames or
+    /// a content version 1 text fit best. `hide()` fails with `SteganoError::UnsupportedMedia`
+    /// for carriers without palette. See [`SteganoDecoder::use_palette_embedding`]
+    pub fn use_palette_embedding(&mut self, palette: bool) -> &mut Self {
+        self.palette_embedding = palette;
+
+        self
+    }
+
+    /// hides in the colors of the palette slots of an indexed PNG, that no pixel refers to,
+    /// instead of the pixels. The image looks exactly the same and survives any edit that keeps
+    /// the indices, but what fits depends on the spare slots, at most 3 bytes each.
+    /// `hide()` fails with `SteganoError::UnsupportedMedia` for carriers without palette.
+    /// See [`PaletteImage::spare_slots`] and [`SteganoDecoder::use_spare_palette_slots`]
+    pub fn use_spare_palette_slots(&mut self, spare_slots: bool) -> &mut Self {
+        self.spare_palette_slots = spare_slots;
+
+        self
+    }
+
+    /// stores the message base64 encoded in a zTXt chunk of the PNG target, instead of the pixel
+    /// LSBs, for carriers whose pixels must stay exactly as they are. That is not hidden from
+    /// anyone who lists the chunks, see [`media::image::metadata`]. The target must be a PNG,
+    /// see [`SteganoDecoder::use_metadata_channel`]. Images only.
+    pub fn use_metadata_channel(&mut self, metadata: bool) -> &mut Self {
+        self.metadata_channel = metadata;
+
+        self
+    }
+
+    /// fills the LSBs of all channels that may carry the secret with Floyd-Steinberg style noise
+    /// before hiding, so that smooth regions do not reveal by their untouched LSBs where the
+    /// secret ends,
+    /// see [`media::image::dither::dither_lsb_plane`]. The decoder needs nothing, it reads the
+    /// hidden LSBs as they are. Images only.
+    pub fn pre_dither(&mut self, dither: bool) -> &mut Self {
+        self.pre_dither = dither;
+
+        self
+    }
+
+    /// refuses carriers that seem to already contain secret data, `hide()` then returns
+    /// `SteganoError::CarrierAlreadyUsed`. The test is a heuristic, carriers with very noisy
+    /// colors may be refused as well.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+
+        self
+    }
+
+    /// number of bytes the carrier can carry at most, including the message headers
+    pub fn capacity(&self) -> usize {
+        if self.metadata_channel && matches!(self.carrier, Some(Media::Image(_))) {
+            return media::image::metadata::CAPACITY;
+        }
+        if let (true, Some(palette)) = (self.spare_palette_slots, self.palette.as_ref()) {
+            return palette.spare_slots_capacity();
+        }
+        if let (true, Some(palette)) = (self.palette_embedding, self.palette.as_ref()) {
+            return palette.capacity();
+        }
+        if let Some(bitmap) = self.bitmap.as_ref() {
+            return bitmap.capacity();
+        }
+        if let (true, Some(Media::Image(image))) = (self.luminance, self.carrier.as_ref()) {
+            return media::image::luminance::capacity(image);
+        }
+        if let (true, Some(Media::Image(image))) = (self.alignment_marker, self.carrier.as_ref()) {
+            return media::image::alignment::capacity(image);
+        }
+        if let Some(dng) = self.dng.as_ref() {
+            return dng.capacity();
+        }
+        if let Some(gif) = self.gif.as_ref() {
+            return match self.per_frame {
+                true => gif.capacity(),
+                false => gif.frame_capacity(0),
+            };
+        }
+        self.carrier
+            .as_ref()
+            .map(|media| media.capacity(&self.options))
+            .unwrap_or(0)
+    }
+
+    /// the fraction of the capacity that the message occupies, from 0.0 to 1.0, above 1.0
+    /// it does not fit. `None` without carrier or without anything to hide.
+    /// Header fields that are added by hiding, like the cover hash, count only after `hide()`.
+    /// The higher it is, the easier the secret is detected, so a larger carrier might be better
+    pub fn utilization(&self) -> Option<f64> {
+        let capacity = self.capacity();
+        if capacity == 0 || (self.message.is_empty() && self.frames.is_empty()) {
+            return None;
+        }
+        let payload_len = match self.frames.is_empty() {
+            true => self.message.payload_len(),
+            false => Vec::<u8>::from(&FramedMessage::new(self.frames.clone())).len(),
+        };
+
+        Some(payload_len as f64 / capacity as f64)
+    }
+
+    /// what each queued file takes of the capacity, in the order the files were added, so that
+    /// the list can be trimmed until it fits. A file takes what the payload grows by with it,
+    /// that is including its zip entry, the headers count only for the cumulative lengths.
+    /// Like `utilization()` it does not know the header fields that are added by hiding
+    pub fn capacity_breakdown(&self) -> CapacityBreakdown {
+        let capacity = self.capacity();
+        let lens = self.message.cumulative_payload_lens();
+        let files = self
+            .message
+            .files
+            .iter()
+            .zip(lens.windows(2))
+            .map(|((name, _), lens)| FileCapacity {
+                name: name.clone(),
+                needed: lens[1] - lens[0],
+                cumulative: lens[1],
+                fits: lens[1] <= capacity,
+            })
+            .collect();
+
+        CapacityBreakdown {
+            capacity,
+            fits: lens.last().is_some_and(|len| *len <= capacity),
+            files,
+        }
+    }
+
+    /// picks the smallest number of bits per color channel, from 1 to 4, whose capacity fits
+    /// `payload_len` bytes, so that the distortion is as low as possible, and uses it for hiding.
+    /// Returns the chosen depth, the decoder needs the same `bits_per_channel` in its [`CodecOptions`].
+    /// Fails with `SteganoError::CarrierTooSmall` if not even 4 bits are enough
+    pub fn auto_bit_depth(&mut self, payload_len: usize) -> Result<u8> {
+        if self.carrier.is_none() && self.bitmap.is_none() {
+            return Err(SteganoError::MissingCarrier);
+        }
+        for bits in 1..=4 {
+            self.options.bits_per_channel = bits;
+            if self.capacity() >= payload_len {
+                return Ok(bits);
+            }
+        }
+
+        Err(SteganoError::CarrierTooSmall {
+            needed: payload_len,
+            capacity: self.capacity(),
+        })
+    }
+
+    /// keeps the distortion of the carrier within a PSNR of at least `db`, e.g. 45 dB, see
+    /// [`analysis::psnr`]. `hide()` picks the fewest bits per channel that hold the payload and
+    /// fails with `SteganoError::CannotMeetDistortionTarget` if they distort the image more than
+    /// that. The decoder needs the same `bits_per_channel`, see [`SteganoEncoder::bits_per_channel`].
+    /// Images only
+    pub fn max_distortion_psnr(&mut self, db: f64) -> &mut Self {
+        self.max_distortion_psnr = Some(db);
+
+        self
+    }
+
+    /// hides in the `bits` low bits of every color channel, from 1 to 8. More bits multiply the
+    /// capacity, but also the visible distortion, see [`CodecOptions::bits_per_channel`].
+    /// Fails with `SteganoError::InvalidParameter` if `bits` is out of range, the decoder needs
+    /// the same depth, see [`SteganoDecoder::use_bit_depth`]. Images only.
+    pub fn use_bit_depth(&mut self, bits: u8) -> Result<&mut Self> {
+        let opts = CodecOptions {
+            bits_per_channel: bits,
+            ..self.options.clone()
+        };
+        opts.validate()?;
+        self.options = opts;
+
+        Ok(self)
+    }
+
+    /// probes the highest number of bits per color channel, from 1 to 8, that the carrier takes
+    /// without visible distortion: a random test pattern fills the carrier at each depth, and the
+    /// depth counts if the [`analysis::masked_psnr`] stays at or above `psnr_floor`, e.g. 40 dB.
+    /// Noisy carriers mask more distortion than smooth ones, so they allow a higher depth.
+    /// The carrier stays untouched, 0 if no depth is good enough or the carrier is no image
+    pub fn max_imperceptible_depth(&self, psnr_floor: f64) -> u8 {
+        let Some(Media::Image(image)) = self.carrier.as_ref() else {
+            return 0;
+        };
+
+        (1..=8)
+            .filter(|bits| {
+                let opts = CodecOptions {
+                    bits_per_channel: *bits,
+                    ..self.options.clone()
+                };
+                let capacity = media::image::LsbCodec::channels(image, &opts).count() / 8;
+                let mut stego = image.clone();
+                let _ = media::image::LsbCodec::encoder(&mut stego, &opts)
+                    .write_all(&analysis::random_payload(capacity));
+                analysis::masked_psnr(image, &stego) >= psnr_floor
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// number of low bits of every color channel that carry the secret, e.g. as picked by
+    /// [`SteganoEncoder::max_distortion_psnr`] or [`SteganoEncoder::auto_bit_depth`]
+    pub fn bits_per_channel(&self) -> u8 {
+        self.options.bits_per_channel
+    }
+
+    /// estimates how many bytes can be hidden, while the chi-square attack on the carrier
+    /// scores below `max_score` (from 0.0 to 1.0), see [`analysis::chi_square_score`].
+    /// Note: it's an estimate based on random data, the real payload may score slightly different.
+    /// Images only, for other carriers it's 0.
+    pub fn capacity_under_detectability(&self, max_score: f64) -> usize {
+        match self.carrier.as_ref() {
+            Some(Media::Image(image)) => {
+                analysis::capacity_under_chi_square(image, &self.options, max_score)
+            }
+            _ => 0,
+        }
+    }
+
+    /// the chi-square score of the carrier for growing payload sizes, from 0 bytes to the
+    /// capacity in 16 even steps, e.g. to plot the tradeoff of capacity and detectability and
+    /// pick an operating point, see [`analysis::chi_square_curve`]. Like
+    /// [`SteganoEncoder::capacity_under_detectability`] it's based on random data.
+    /// Images only, for other carriers it's empty.
+    pub fn tradeoff_curve(&self) -> Vec<(usize, f64)> {
+        match self.carrier.as_ref() {
+            Some(Media::Image(image)) => analysis::chi_square_curve(image, &self.options, 16),
+            _ => Vec::new(),
+        }
+    }
+
+    /// how well the carrier suits hiding, from 0.0 to 100.0, e.g. for guidance in a UI.
+    /// It scales the entropy of the carrier, see [`media::image::lsb_codec::carrier_entropy`],
+    /// by the capacity that is left after the message. `None` without image carrier.
+    pub fn carrier_score(&self) -> Option<f64> {
+        let Some(Media::Image(image)) = self.carrier.as_ref() else {
+            return None;
+        };
+        let entropy = media::image::lsb_codec::carrier_entropy(image) / 8.0;
+        let free = match self.utilization() {
+            Some(utilization) => (1.0 - utilization).clamp(0.0, 1.0),
+            None if self.capacity() == 0 => 0.0,
+            None => 1.0,
+        };
+
+        Some(entropy * free * 100.0)
+    }
+
+    /// spreads the secret over the image in an order that is derived from the passphrase,
+    /// so that it can only be found with the same passphrase, see [`SteganoDecoder::use_passphrase_ordering`].
+    /// Note: this is no encryption, but it adds no payload overhead either. Images only.
+    pub fn use_passphrase_ordering(&mut self, passphrase: &str) -> &mut Self {
+        let seed = media::image::cursor::passphrase_seed(passphrase);
+        self.options.ordering_seed = Some(Arc::new(Secret::new(seed)));
+
+        self
+    }
+
+    /// like `use_passphrase_ordering()` with the first line of the file as passphrase,
+    /// so that it never shows up in the arguments or the shell history. Like there, the
+    /// passphrase only orders the pixels, it does not keep the secret confidential.
+    /// Fails with `SteganoError::ReadError` if the file is not readable
+    #[cfg(feature = "fs")]
+    pub fn order_passphrase_from_file(&mut self, path: &str) -> Result<&mut Self> {
+        Ok(self.use_passphrase_ordering(&passphrase_of_file(path)?))
+    }
+
+    /// like `use_passphrase_ordering()` with the value of the environment variable as passphrase,
+    /// it does not keep the secret confidential either.
+    /// Fails with `SteganoError::InvalidParameter` if it is not set
+    pub fn order_passphrase_from_env(&mut self, var: &str) -> Result<&mut Self> {
+        Ok(self.use_passphrase_ordering(&passphrase_of_env(var)?))
+    }
+
+    /// hides only in every `n`th row of the image, the capacity shrinks to about `1 / n`.
+    /// The stride is no part of the message, the decoder needs the same one,
+    /// see [`SteganoDecoder::row_stride`]. Images only.
+    pub fn row_stride(&mut self, n: usize) -> &mut Self {
+        self.options.row_stride = u32::try_from(n).unwrap_or(u32::MAX);
+
+        self
+    }
+
+    /// hides only in the pixels where `(x + y) % 2 == 0`, a checkerboard that spreads the changes
+    /// evenly over the image. The capacity halves. It is kept in the header of the message,
+    /// that requires content version 5, the decoder finds it on its own. Images only.
+    pub fn checkerboard(&mut self, checkerboard: bool) -> &mut Self {
+        self.options.checkerboard = checkerboard;
+        self.message.checkerboard = checkerboard;
+
+        self
+    }
+
+    /// hides only in the pixels that are white in the grayscale `mask`, that is above
+    /// [`format::MASK_THRESHOLD`], e.g. to keep a face or a logo untouched. The mask has the
+    /// dimensions of the carrier, else `hide()` fails with `SteganoError::InvalidParameter`.
+    /// It is not hidden, the decoder needs the same mask out of band, or derives it the same
+    /// way from the carrier, see [`SteganoDecoder::use_mask`]. Images only.
+    pub fn use_mask(&mut self, mask: &GrayImage) -> &mut Self {
+        self.options.mask = Some(Arc::new(mask.clone()));
+
+        self
+    }
+
+    /// leaves fully transparent pixels (alpha 0) untouched, they would not hide anything visible
+    /// but give away that the carrier was changed. The capacity shrinks accordingly,
+    /// see [`SteganoDecoder::skip_transparent`]. Images only.
+    pub fn skip_transparent(&mut self, skip: bool) -> &mut Self {
+        self.options.skip_transparent = skip;
+
+        self
+    }
+
+    /// hides in `r`, `g` and `b` low bits of the red, green and blue channel, each from 1 to 8,
+    /// e.g. more in blue that the eye sees least. The capacity is the sum of all channel bits.
+    /// The depths are kept in the header of the message, which requires content version 5,
+    /// but the decoder needs the same [`CodecOptions::channel_bit_depths`] to read it.
+    /// Images only.
+    pub fn channel_bit_depths(&mut self, r: u8, g: u8, b: u8) -> &mut Self {
+        self.options.channel_bit_depths = Some([r, g, b]);
+        self.message.channel_bit_depths = Some([r, g, b]);
+
+        self
+    }
+
+    /// hides only in `channel` of each pixel, commonly green, like the simplest tools do.
+    /// The capacity is the number of pixels times the bits per channel, divided by 8.
+    /// The channel is no part of the message, the decoder needs the same one,
+    /// see [`SteganoDecoder::single_channel`]. Images only.
+    pub fn single_channel(&mut self, channel: Channel) -> &mut Self {
+        self.options.single_channel = Some(channel);
+
+        self
+    }
+
+    /// visits the channels in `order`, e.g. row by row. The order is kept in a preamble in the
+    /// first pixels, so the decoder finds it on its own, see [`media::image::cursor::TraversalOrder`].
+    /// It replaces the traversal of `use_passphrase_ordering`. Images only.
+    pub fn traversal_order(&mut self, order: TraversalOrder) -> &mut Self {
+        self.options.traversal = Some(order);
+
+        self
+    }
+
+    /// starts the message at the `pixel`th pixel of the column based traversal, so that it does
+    /// not depend on the very first pixels, e.g. of a format that garbles them. The pixels in
+    /// between stay untouched. Only the offset is hidden in the first pixels, in the traversal
+    /// preamble, so the decoder finds the start on its own. See [`TraversalOrder::Offset`]
+    pub fn start_at_pixel(&mut self, pixel: u32) -> &mut Self {
+        self.traversal_order(TraversalOrder::Offset { pixel })
+    }
+
+    /// hides in the channels as a tool would, that names the bytes of a pixel by `order`.
+    /// Anything but `ChannelOrder::Rgba` is kept in the header of the message, it requires
+    /// content version 5, the decoder finds it on its own. Frames keep no header. Images only.
+    pub fn channel_order(&mut self, order: ChannelOrder) -> &mut Self {
+        self.options.channel_order = order;
+        self.message.channel_order = order;
+
+        self
+    }
+
+    /// spreads the secret over the image in an order that is derived from the carrier itself,
+    /// from the bits that hiding never touches, see [`Media::cover_seed`]. So no seed has to be
+    /// shared, the decoder recomputes it, see [`SteganoDecoder::derive_seed_from_cover`].
+    /// Note: it replaces a passphrase ordering, and any change of the carrier colors breaks unveiling. Images only.
+    pub fn derive_seed_from_cover(&mut self, derive: bool) -> &mut Self {
+        self.derive_seed_from_cover = derive;
+
+        self
+    }
+
+    /// adds Reed-Solomon parity to the payload, so that up to `redundancy` corrupted bytes
+    /// per 255 byte block can be corrected on unveil. `redundancy` must be between 1 and 127,
+    /// otherwise `hide()` fails with `SteganoError::InvalidParameter`.
+    /// Note: the header in front of the payload is not protected.
+    pub fn with_fec(&mut self, redundancy: u8) -> &mut Self {
+        self.message.fec_redundancy = Some(redundancy);
+
+        self
+    }
+
+    /// caps the bytes of the message that are held in memory, `hide()` fails with
+    /// `SteganoError::MemoryBudgetExceeded` for bigger messages before it serializes them, and
+    /// so does `try_hide_file()` before it reads a file. The files count as they are, without
+    /// compression. The carrier itself is not counted
+    pub fn memory_budget(&mut self, bytes: usize) -> &mut Self {
+        self.memory_budget = Some(bytes);
+
+        self
+    }
+
+    /// caps the time of each `hide()`, from preparing the message to the last bit written.
+    /// It fails with `SteganoError::TimeBudgetExceeded` once the budget is used up, checked
+    /// while writing like a cancellation, and no output is written then. Images only
+    pub fn time_budget(&mut self, duration: Duration) -> &mut Self {
+        self.time_budget = Some(duration);
+
+        self
+    }
+
+    /// records the creation time and the version of this crate in the header, for archival.
+    /// Read it via [`SteganoDecoder::provenance`], requires content version 5
+    pub fn embed_provenance(&mut self, provenance: bool) -> &mut Self {
+        self.provenance = provenance;
+
+        self
+    }
+
+    /// sets a human readable comment, that some UIs display. It describes the message and is
+    /// stored in the header, not in the payload. Comments longer than [`format::MAX_COMMENT_LEN`]
+    /// bytes make `hide()` fail with `SteganoError::InvalidParameter`, requires content version 5
+    pub fn set_comment(&mut self, text: &str) -> &mut Self {
+        self.message.comment = Some(text.to_owned());
+
+        self
+    }
+
+    /// stores a short cleartext hint in the header, e.g. which passphrase or key protects the
+    /// message, so that whoever unveils it knows if one was used at all. The hint is no secret,
+    /// anyone can read it without the key, see [`SteganoDecoder::hint`], so it must never hold
+    /// the key itself. Hints longer than [`format::MAX_HINT_LEN`] bytes make `hide()` fail with
+    /// `SteganoError::InvalidParameter`, requires content version 5
+    pub fn set_hint(&mut self, hint: &str) -> &mut Self {
+        self.message.hint = Some(hint.to_owned());
+
+        self
+    }
+
+    /// stores a JPEG thumbnail of `thumb` in the header, that previews the hidden content
+    /// without unveiling it. Thumbnails larger than [`format::MAX_PREVIEW_DIMENSION`] are
+    /// scaled down, a JPEG longer than [`format::MAX_PREVIEW_LEN`] bytes makes `hide()` fail
+    /// with `SteganoError::InvalidParameter`, requires content version 5.
+    /// Fails with `SteganoError::ImageEncodingError` if the thumbnail cannot be encoded as JPEG
+    pub fn embed_preview(&mut self, thumb: &RgbaImage) -> Result<&mut Self> {
+        let max = format::MAX_PREVIEW_DIMENSION;
+        let thumb = if thumb.width() > max || thumb.height() > max {
+            let scale = max as f64 / thumb.width().max(thumb.height()) as f64;
+            let width = ((thumb.width() as f64 * scale) as u32).max(1);
+            let height = ((thumb.height() as f64 * scale) as u32).max(1);
+            image::imageops::thumbnail(thumb, width, height)
+        } else {
+            thumb.clone()
+        };
+        let mut jpeg = Vec::new();
+        image::DynamicImage::ImageRgba8(thumb)
+            .to_rgb8()
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageOutputFormat::Jpeg(75),
+            )
+            .map_err(|_e| SteganoError::ImageEncodingError)?;
+        self.message.preview = Some(jpeg);
+
+        Ok(self)
+    }
+
+    /// adds a checksum of the payload to the header, it is verified on unveil, a corrupted
+    /// payload then fails with `SteganoError::InvalidFormat(FormatError::ChecksumMismatch)`.
+    /// CRC-32 is fast, SHA-256 is collision resistant. Any other than `ChecksumAlgo::None`
+    /// requires content version 5.
+    pub fn checksum_algorithm(&mut self, algo: ChecksumAlgo) -> &mut Self {
+        self.message.checksum = algo;
+
+        self
+    }
+
+    /// adds a HMAC-SHA256 of the payload keyed by `key` to the header, so that the receiver, who
+    /// knows the key, can detect tampering, see [`SteganoDecoder::verify_mac`]. Unlike a checksum
+    /// it cannot be forged without the key. Requires content version 5.
+    pub fn authenticate_with_key(&mut self, key: &[u8]) -> &mut Self {
+        self.message.mac_key = Some(Arc::new(Secret::new(key.to_vec())));
+
+        self
+    }
+
+    /// adds an Ed25519 signature of the payload by `secret_key` to the header, so that anyone with
+    /// the public key can confirm who hid it, see [`SteganoDecoder::verify_signature`]. Unlike a
+    /// MAC no secret has to be shared. Fails with `SteganoError::InvalidParameter` if the key is
+    /// not 32 bytes. Requires content version 5.
+    pub fn sign_with(&mut self, secret_key: &[u8]) -> Result<&mut Self> {
+        let key = secret_key.try_into().map_err(|_| {
+            SteganoError::InvalidParameter(format!(
+                "an Ed25519 secret key has 32 bytes, not {}",
+                secret_key.len()
+            ))
+        })?;
+        self.message.signing_key = Some(Arc::new(Secret::new(key)));
+
+        Ok(self)
+    }
+
+    /// stretches the key of [`SteganoEncoder::authenticate_with_key`] by PBKDF2-HMAC-SHA256 with
+    /// `iterations` and a random salt, so that guessing the key costs that much more. Both are
+    /// stored in the header, the decoder uses them on [`SteganoDecoder::verify_mac`]. Fails with
+    /// `SteganoError::InvalidParameter` below [`format::MIN_KDF_ITERATIONS`] or above
+    /// [`format::MAX_KDF_ITERATIONS`]. Requires content version 5.
+    /// PBKDF2 has no memory cost, so `memory_kib` must be 0, any other value fails with
+    /// `SteganoError::InvalidParameter` instead of being ignored. It is there for a memory hard
+    /// key derivation like Argon2, that this crate does not have
+    pub fn kdf_params(&mut self, iterations: u32, memory_kib: u32) -> Result<&mut Self> {
+        if memory_kib != 0 {
+            return Err(SteganoError::InvalidParameter(format!(
+                "a kdf memory cost of {memory_kib} KiB is not supported, PBKDF2 uses none"
+            )));
+        }
+        if iterations < format::MIN_KDF_ITERATIONS {
+            return Err(SteganoError::InvalidParameter(format!(
+                "{iterations} kdf iterations are too weak, at least {} are needed",
+                format::MIN_KDF_ITERATIONS
+            )));
+        }
+        if iterations > format::MAX_KDF_ITERATIONS {
+            return Err(SteganoError::InvalidParameter(format!(
+                "{iterations} kdf iterations are too many, at most {} are read back",
+                format::MAX_KDF_ITERATIONS
+            )));
+        }
+        let mut salt = [0; format::KDF_SALT_LEN];
+        getrandom::getrandom(&mut salt).map_err(|e| {
+            SteganoError::InvalidParameter(format!("no random salt for the kdf: {e}"))
+        })?;
+        self.message.kdf_iterations = Some(iterations);
+        self.message.kdf_salt = Some(salt);
+
+        Ok(self)
+    }
+
+    /// stores the payload length three times in the header, so that a flipped bit in one copy
+    /// is outvoted by the others, instead of breaking the whole message. Requires content version 5.
+    pub fn redundant_length(&mut self, redundant: bool) -> &mut Self {
+        self.message.redundant_length = redundant;
+
+        self
+    }
+
+    /// stores the files uncompressed, e.g. when they are compressed already.
+    /// Compression is on by default, the decoder handles both
+    pub fn compress(&mut self, compress: bool) -> &mut Self {
+        self.message.compression = compress;
+
+        self
+    }
+
+    /// writes a PNG target as canonical PNG, see [`media::image::canonical::encode_png`]. So the
+    /// target depends only on the carrier pixels and the secret, even if the carrier had EXIF,
+    /// text or time chunks. Hiding the same twice gives the same file, as long as nothing else of the
+    /// message is random or timed, like an `embed_provenance`
+    pub fn normalize_output(&mut self, normalize: bool) -> &mut Self {
+        self.normalize_output = normalize;
+
+        self
+    }
+
+    /// applies the settings of environment variables, those not set keep their values:
+    /// - `STEGANO_BIT_DEPTH` bits per color channel from 1 to 8, see [`CodecOptions::bits_per_channel`]
+    /// - `STEGANO_COMPRESS` `true` or `false`, see [`SteganoEncoder::compress`]
+    /// - `STEGANO_SEED` pixel ordering seed, a `u64`, see [`CodecOptions::ordering_seed`]
+    /// - `STEGANO_ROW_STRIDE` see [`SteganoEncoder::row_stride`]
+    /// - `STEGANO_CHECKSUM` `none`, `crc32` or `sha256`, see [`SteganoEncoder::checksum_algorithm`]
+    ///
+    /// Malformed values fail with `SteganoError::InvalidParameter`
+    pub fn configure_from_env(&mut self) -> Result<&mut Self> {
+        let value = |name: &str| -> Result<Option<String>> {
+            std::env::var_os(name)
+                .map(|v| {
+                    v.into_string().map_err(|_| {
+                        SteganoError::InvalidParameter(format!("{name} is not valid unicode"))
+                    })
+                })
+                .transpose()
+        };
+        let malformed = |name: &str, v: &str| {
+            SteganoError::InvalidParameter(format!("{name}={v} is malformed"))
+        };
+
+        if let Some(v) = value("STEGANO_BIT_DEPTH")? {
+            self.options.bits_per_channel = v
+                .parse()
+                .ok()
+                .filter(|bits| (1..=8).contains(bits))
+                .ok_or_else(|| malformed("STEGANO_BIT_DEPTH", &v))?;
+        }
+        if let Some(v) = value("STEGANO_COMPRESS")? {
+            self.message.compression = match v.to_lowercase().as_str() {
+                "true" | "1" | "yes" => true,
+                "false" | "0" | "no" => false,
+                _ => return Err(malformed("STEGANO_COMPRESS", &v)),
+            };
+        }
+        if let Some(v) = value("STEGANO_SEED")? {
+            let seed: u64 = v.parse().map_err(|_| malformed("STEGANO_SEED", &v))?;
+            self.options.ordering_seed = Some(Arc::new(Secret::new(seed)));
+        }
+        if let Some(v) = value("STEGANO_ROW_STRIDE")? {
+            self.options.row_stride = v
+                .parse()
+                .ok()
+                .filter(|stride| *stride > 0)
+                .ok_or_else(|| malformed("STEGANO_ROW_STRIDE", &v))?;
+        }
+        if let Some(v) = value("STEGANO_CHECKSUM")? {
+            self.message.checksum = match v.to_lowercase().as_str() {
+                "none" => ChecksumAlgo::None,
+                "crc32" => ChecksumAlgo::Crc32,
+                "sha256" => ChecksumAlgo::Sha256,
+                _ => return Err(malformed("STEGANO_CHECKSUM", &v)),
+            };
+        }
+
+        Ok(self)
+    }
+
+    /// appends a length delimited frame, frames are unveiled in the order they were added
+    /// by [`SteganoDecoder::unveil_frames`].
+    /// Note: as soon as one frame is added the frames replace the regular message content.
+    pub fn add_frame(&mut self, data: &[u8]) -> &mut Self {
+        self.frames.push(data.to_vec());
+
+        self
+    }
+
+    /// keeps the settings of this encoder, without carrier, target and what is hidden, so that
+    /// many encoders with the same settings can be made, see [`EncoderTemplate::encoder`].
+    /// The comment and preview describe the hidden content, and the cancellation token belongs to
+    /// one `hide()`, so those are dropped as well
+    pub fn into_template(self) -> EncoderTemplate {
+        let mut message = self.message;
+        message.files.clear();
+        message.text = None;
+        message.comment = None;
+        message.preview = None;
+        message.cover_hash = None;
+        message.provenance = None;
+        message.mac = None;
+        message.signature = None;
+        let mut options = self.options;
+        options.cancel = None;
+        options.deadline = None;
+
+        EncoderTemplate {
+            options,
+            message,
+            content_version_forced: self.content_version_forced,
+            bind_cover_hash: self.bind_cover_hash,
+            derive_seed_from_cover: self.derive_seed_from_cover,
+            pre_dither: self.pre_dither,
+            strict: self.strict,
+            provenance: self.provenance,
+            memory_budget: self.memory_budget,
+            time_budget: self.time_budget,
+            palette_embedding: self.palette_embedding,
+            spare_palette_slots: self.spare_palette_slots,
+            normalize_output: self.normalize_output,
+            metadata_channel: self.metadata_channel,
+            max_distortion_psnr: self.max_distortion_psnr,
+            per_frame: self.per_frame,
+            luminance: self.luminance,
+            alignment_marker: self.alignment_marker,
+            verify_after_hide: self.verify_after_hide,
+        }
+    }
+
+    /// allows to abort a running hide by setting the given token, `hide()`, `hide_to_image()`,
+    /// `hide_sharded()` and the carrier pair then return `SteganoError::Cancelled` and no output
+    /// is written. The LSBs of image and audio stop right away, the other carriers, e.g. a GIF,
+    /// a DNG or the palette, are checked once the secret is in them
+    pub fn with_cancel(&mut self, token: CancellationToken) -> &mut Self {
+        self.options.cancel = Some(token);
+
+        self
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn hide(&mut self) -> Result<&Self> {
+        self.start_clock();
+        if self.pair.is_some() {
+            return self.hide_in_pair();
+        }
+        if self.carrier.is_none()
+            && self.candidates.is_empty()
+            && self.gif.is_none()
+            && self.dng.is_none()
+        {
+            return Ok(self);
+        }
+
+        let target = self.target.clone().ok_or(SteganoError::MissingTarget)?;
+        self.check_target_writable()?;
+        let target = Path::new(&target);
+        let existed = target.exists();
+        if self.gif.is_some() {
+            let buf = self.secret_bytes()?;
+            self.gif
+                .as_mut()
+                .unwrap()
+                .hide_in_frames(&buf, self.per_frame)?;
+        } else if self.dng.is_some() {
+            let buf = self.secret_bytes()?;
+            let dng = self.dng.as_mut().unwrap();
+            if buf.len() > dng.capacity() {
+                return Err(SteganoError::CarrierTooSmall {
+                    needed: buf.len(),
+                    capacity: dng.capacity(),
+                });
+            }
+            dng.encoder()
+                .write_all(&buf)
+                .map_err(|_e| SteganoError::ImageEncodingError)?;
+        } else if self.candidates.is_empty() {
+            self.embed()?;
+        } else {
+            self.embed_in_a_candidate()?;
+        }
+        if self.options.is_cancelled() {
+            return Err(SteganoError::Cancelled);
+        }
+        let in_palette = self.palette_embedding || self.spare_palette_slots;
+        let saved = match (in_palette, self.palette.as_mut()) {
+            _ if self.metadata_channel => self.save_with_metadata(target),
+            _ if self.gif.is_some() => self.gif.as_mut().unwrap().save_as(target),
+            _ if self.dng.is_some() => self.dng.as_mut().unwrap().save_as(target),
+            (true, Some(palette)) => palette.save_as(target),
+            _ => match self.bitmap.as_mut() {
+                Some(bitmap) => bitmap.save_as(target),
+                None if self.normalize_output => self.save_normalized(target),
+                None => self.carrier.as_mut().unwrap().save_as(target),
+            },
+        };
+        if let Err(e) = saved {
+            // do not leave a half written file behind, but never remove one that was there before
+            if !existed {
+                let _ = std::fs::remove_file(target);
+            }
+            return Err(e);
+        }
+
+        Ok(self)
+    }
+
+    /// hides the payload and its parity in the carrier pair, as shards 0 and 1 of 2
+    #[cfg(feature = "fs")]
+    fn hide_in_pair(&mut self) -> Result<&Self> {
+        let target = self.target.clone().ok_or_else(|| {
+            SteganoError::InvalidParameter("the payload carrier has no target".to_owned())
+        })?;
+        let parity_target = self.parity_target.clone().ok_or_else(|| {
+            SteganoError::InvalidParameter("the parity carrier has no target".to_owned())
+        })?;
+        self.check_target_writable()?;
+        self.message.cover_hash = None;
+        let data = self.secret_bytes()?;
+        let parity = fec::parity_of(&data, fec::PAIR_REDUNDANCY)?;
+        let shards = [(0, data), (1, parity)].map(|(index, data)| Shard {
+            index,
+            count: 2,
+            data,
+        });
+
+        let (a, b) = self.pair.as_mut().unwrap();
+        for (media, shard) in [a, b].into_iter().zip(&shards) {
+            let buf = Vec::from(shard);
+            let capacity = media.capacity(&self.options);
+            if buf.len() > capacity {
+                return Err(SteganoError::CarrierTooSmall {
+                    needed: buf.len(),
+                    capacity,
+                });
+            }
+        }
+        let (a, b) = self.pair.as_mut().unwrap();
+        for (media, shard) in [&mut *a, &mut *b].into_iter().zip(&shards) {
+            media.hide_bytes(&Vec::from(shard), &self.options)?;
+        }
+        if self.options.is_cancelled() {
+            return Err(SteganoError::Cancelled);
+        }
+        for (media, target) in [a, b].into_iter().zip([target, parity_target]) {
+            media.save_as(Path::new(&target))?;
+        }
+
+        Ok(self)
+    }
+
+    /// embeds into the first carrier candidate that is large enough
+    #[cfg(feature = "fs")]
+    fn embed_in_a_candidate(&mut self) -> Result<()> {
+        self.chosen_candidate = None;
+        for (i, candidate) in self.candidates.clone().iter().enumerate() {
+            self.use_media(candidate)?;
+            match self.embed() {
+                Err(SteganoError::CarrierTooSmall { .. }) => continue,
+                result => result?,
+            }
+            self.chosen_candidate = Some(i);
+
+            return Ok(());
+        }
+
+        Err(SteganoError::NoSuitableCarrier)
+    }
+
+    /// saves an image carrier as canonical PNG, see [`SteganoEncoder::normalize_output`],
+    /// all other targets and media as usual
+    #[cfg(feature = "fs")]
+    fn save_normalized(&mut self, target: &Path) -> Result<()> {
+        let is_png = target
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("png"));
+        match self.carrier.as_mut().unwrap() {
+            Media::Image(image) if is_png => {
+                let file = std::fs::File::create(target)
+                    .map_err(|source| SteganoError::WriteError { source })?;
+                media::image::canonical::encode_png(image, std::io::BufWriter::new(file))
+            }
+            media => media.save_as(target),
+        }
+    }
+
+    /// saves the carrier pixels as they are, with the message in the metadata of the PNG
+    #[cfg(feature = "fs")]
+    fn save_with_metadata(&self, target: &Path) -> Result<()> {
+        let extension = target.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let file =
+            std::fs::File::create(target).map_err(|source| SteganoError::WriteError { source })?;
+
+        self.encode_with_metadata(extension, std::io::BufWriter::new(file))
+    }
+
+    /// encodes the carrier pixels as they are as PNG, with the message in its metadata
+    #[cfg(feature = "fs")]
+    fn encode_with_metadata(&self, extension: &str, w: impl std::io::Write) -> Result<()> {
+        match (
+            extension.eq_ignore_ascii_case("png"),
+            self.carrier.as_ref(),
+            self.metadata_payload.as_ref(),
+        ) {
+            (true, Some(Media::Image(image)), Some(payload)) => {
+                media::image::metadata::encode_png(image, payload, w)
+            }
+            _ => Err(SteganoError::UnsupportedMedia),
+        }
+    }
+
+    /// fails with `SteganoError::TargetNotWritable` if the file set by `write_to` cannot be
+    /// created or opened for writing, `hide()` checks that before all the work of hiding.
+    /// An existing target is left as it is, one that did not exist is removed again
+    #[cfg(feature = "fs")]
+    pub fn check_target_writable(&self) -> Result<()> {
+        let Some(target) = self.target.as_ref() else {
+            return Ok(());
+        };
+        let existed = Path::new(target).exists();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(target)
+            .map_err(|source| SteganoError::TargetNotWritable {
+                path: target.clone(),
+                source,
+            })?;
+        if !existed {
+            let _ = std::fs::remove_file(target);
+        }
+
+        Ok(())
+    }
+
+    /// spreads the message over several carriers, each pair is `(carrier, target)`, e.g. when
+    /// the message is too big for one carrier. Carriers may have different formats, see
+    /// [`SteganoDecoder::unveil_shards`] to put the shards back together.
+    /// Note: the carrier set by `use_media` is not used and no cover hash is bound.
+    #[cfg(feature = "fs")]
+    pub fn hide_sharded(&mut self, carriers: &[(&str, &str)]) -> Result<&Self> {
+        self.start_clock();
+        let count = u16::try_from(carriers.len())
+            .map_err(|_| SteganoError::InvalidParameter("too many carriers".to_owned()))?;
+        self.message.cover_hash = None;
+        let shards = Shard::split(&self.secret_bytes()?, count)?;
+
+        let mut hidden = Vec::with_capacity(shards.len());
+        for (shard, (carrier, target)) in shards.iter().zip(carriers) {
+            let mut media = Media::from_file(Path::new(carrier))?;
+            media.hide_bytes(&Vec::from(shard), &self.options)?;
+            hidden.push((media, target));
+        }
+        if self.options.is_cancelled() {
+            return Err(SteganoError::Cancelled);
+        }
+        for (mut media, target) in hidden {
+            media.save_as(Path::new(target))?;
+        }
+
+        Ok(self)
+    }
+
+    /// like `hide()`, but returns the image with the secret instead of writing it to the target
+    pub fn hide_to_image(&mut self) -> Result<RgbaImage> {
+        self.start_clock();
+        if self.metadata_channel {
+            return Err(SteganoError::InvalidParameter(
+                "the metadata channel needs a PNG target, the image alone would lose the message"
+                    .to_owned(),
+            ));
+        }
+        if self.spare_palette_slots {
+            return Err(SteganoError::InvalidParameter(
+                "the spare palette slots need a PNG target, no pixel of the image shows them"
+                    .to_owned(),
+            ));
+        }
+        self.embed()?;
+        match (
+            self.palette.as_ref(),
+            self.bitmap.as_ref(),
+            self.carrier.as_ref(),
+        ) {
+            (Some(palette), _, _) if self.palette_embedding => Ok(palette.to_rgba()),
+            (_, Some(bitmap), _) => Ok(bitmap.to_rgba()),
+            (_, _, Some(Media::Image(image))) => Ok(image.clone()),
+            _ => Err(SteganoError::UnsupportedMedia),
+        }
+    }
+
+    /// sets the deadline of the time budget, if any, from now on
+    fn start_clock(&mut self) {
+        self.options.deadline = self.time_budget.map(|budget| Instant::now() + budget);
+    }
+
+    /// hides the payloads queued by `hide_in_plane()`, each in its bit plane
+    fn embed_in_planes(&mut self) -> Result<()> {
+        if !self.message.is_empty() || !self.frames.is_empty() {
+            return Err(SteganoError::InvalidParameter(
+                "the bit planes carry their own payloads, there is no room for the message"
+                    .to_owned(),
+            ));
+        }
+        let Media::Image(image) = self.carrier.as_mut().ok_or(SteganoError::MissingCarrier)? else {
+            return Err(SteganoError::UnsupportedMedia);
+        };
+        for (plane, data) in &self.planes {
+            media::image::bit_plane::hide(image, *plane, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// hides the message or frames in the carrier, or its palette
+    fn embed(&mut self) -> Result<()> {
+        if self.options.concealer == Concealer::LsbMatching
+            && (self.options.is_multi_bit() || self.bind_cover_hash || self.derive_seed_from_cover)
+        {
+            return Err(SteganoError::InvalidParameter(
+                "LSB matching hides 1 bit per channel and binds no cover".to_string(),
+            ));
+        }
+        self.options.validate()?;
+        if !self.planes.is_empty() {
+            return self.embed_in_planes();
+        }
+        let media = self.carrier.as_mut().ok_or(SteganoError::MissingCarrier)?;
+        if let Media::Image(image) = media {
+            self.options.check_mask(image)?;
+        }
+        if self.derive_seed_from_cover {
+            self.options.ordering_seed =
+                Some(Arc::new(Secret::new(media.cover_seed(&self.options))));
+        }
+        self.message.cover_hash = self
+            .bind_cover_hash
+            .then(|| media.cover_hash(&self.options));
+        let (mut buf, mut deferred_checksum) = self.secret_bytes_deferring_checksum()?;
+        // only the pixel LSBs compute the checksum while hiding, the others hide all at once
+        let streams_checksum = self.max_distortion_psnr.is_none()
+            && !self.metadata_channel
+            && !self.palette_embedding
+            && !self.spare_palette_slots
+            && self.bitmap.is_none()
+            && !self.luminance
+            && !self.alignment_marker;
+        if !streams_checksum {
+            if let Some(checksum) = deferred_checksum.take() {
+                checksum.patch(&mut buf);
+            }
+        }
+        if let Some(db) = self.max_distortion_psnr {
+            self.options.bits_per_channel = self.bit_depth_within_psnr(&buf, db)?;
+        }
+        let media = self.carrier.as_mut().ok_or(SteganoError::MissingCarrier)?;
+        let opts = &self.options;
+        if self.metadata_channel {
+            if !matches!(media, Media::Image(_)) {
+                return Err(SteganoError::UnsupportedMedia);
+            }
+            self.metadata_payload = Some(std::mem::take(&mut buf));
+        } else if self.spare_palette_slots {
+            self.palette
+                .as_mut()
+                .ok_or(SteganoError::UnsupportedMedia)?
+                .hide_in_spare_slots(&buf)?;
+        } else if self.palette_embedding {
+            let palette = self
+                .palette
+                .as_mut()
+                .ok_or(SteganoError::UnsupportedMedia)?;
+            if buf.len() > palette.capacity() {
+                return Err(SteganoError::CarrierTooSmall {
+                    needed: buf.len(),
+                    capacity: palette.capacity(),
+                });
+            }
+            if self.strict && analysis::looks_already_used(|| palette.decoder(opts), buf.len()) {
+                return Err(SteganoError::CarrierAlreadyUsed);
+            }
+            palette
+                .encoder(opts)
+                .write_all(&buf)
+                .map_err(|_e| SteganoError::ImageEncodingError)?;
+        } else if let Some(bitmap) = self.bitmap.as_mut() {
+            if buf.len() > bitmap.capacity() {
+                return Err(SteganoError::CarrierTooSmall {
+                    needed: buf.len(),
+                    capacity: bitmap.capacity(),
+                });
+            }
+            if self.strict && analysis::looks_already_used(|| bitmap.decoder(), buf.len()) {
+                return Err(SteganoError::CarrierAlreadyUsed);
+            }
+            bitmap
+                .encoder()
+                .write_all(&buf)
+                .map_err(|_e| SteganoError::ImageEncodingError)?;
+        } else if self.luminance {
+            let Media::Image(image) = media else {
+                return Err(SteganoError::UnsupportedMedia);
+            };
+            media::image::luminance::hide(image, &buf)?;
+        } else if self.alignment_marker {
+            let Media::Image(image) = media else {
+                return Err(SteganoError::UnsupportedMedia);
+            };
+            media::image::alignment::hide(image, &buf)?;
+        } else {
+            let capacity = media.capacity(opts);
+            debug!(
+                "the carrier holds {capacity} bytes, the payload needs {} bytes",
+                buf.len()
+            );
+            if buf.len() > capacity {
+                return Err(SteganoError::CarrierTooSmall {
+                    needed: buf.len(),
+                    capacity,
+                });
+            }
+            if self.strict && media.looks_already_used(buf.len(), opts) {
+                return Err(SteganoError::CarrierAlreadyUsed);
+            }
+            if let (true, Media::Image(image)) = (self.pre_dither, &mut *media) {
+                media::image::dither::dither_lsb_plane(image, opts)
+                    .map_err(|_e| SteganoError::ImageEncodingError)?;
+            }
+            match (opts.traversal, &mut *media) {
+                (Some(order), Media::Image(image)) => {
+                    media::image::LsbCodec::hide_traversal_preamble(image, order)
+                        .map_err(|_e| SteganoError::ImageEncodingError)?
+                }
+                (Some(_), _) => return Err(SteganoError::UnsupportedMedia),
+                (None, _) => {}
+            }
+            match deferred_checksum {
+                Some(checksum) => {
+                    let digest = media.hide_bytes_streaming_checksum(&buf, &checksum, opts)?;
+                    checksum.patch_with(&mut buf, &digest);
+                }
+                None => {
+                    media.hide_bytes(buf.as_ref(), opts)?;
+                }
+            }
+        }
+        if self.options.is_cancelled() {
+            return Err(SteganoError::Cancelled);
+        }
+        if self.options.is_past_deadline() {
+            return Err(SteganoError::TimeBudgetExceeded);
+        }
+        if self.verify_after_hide && !self.metadata_channel {
+            self.verify_hidden(&buf)?;
+        }
+
+        Ok(())
+    }
+
+    /// checks that the carrier in memory reads back `expected`, see `verify_after_hide()`
+    fn verify_hidden(&self, expected: &[u8]) -> Result<()> {
+        let mut hidden = vec![0; expected.len()];
+        self.hidden_decoder()?
+            .read_exact(&mut hidden)
+            .map_err(|_| SteganoError::VerificationFailed)?;
+
+        match hidden == expected {
+            true => Ok(()),
+            false => Err(SteganoError::VerificationFailed),
+        }
+    }
+
+    /// reads what `embed()` hid from the carrier in memory, like the decoder in the same mode
+    fn hidden_decoder(&self) -> Result<Box<dyn Read + '_>> {
+        let opts = &self.options;
+        let palette = || self.palette.as_ref().ok_or(SteganoError::UnsupportedMedia);
+
+        Ok(if self.spare_palette_slots {
+            palette()?.spare_slots_decoder()
+        } else if self.palette_embedding {
+            palette()?.decoder(opts)
+        } else if let Some(bitmap) = self.bitmap.as_ref() {
+            bitmap.decoder()
+        } else {
+            match self.carrier.as_ref().ok_or(SteganoError::MissingCarrier)? {
+                Media::Image(image) if self.luminance => media::image::luminance::decoder(image),
+                Media::Image(image) if self.alignment_marker => {
+                    media::image::alignment::decoder(image)?
+                }
+                media => media.decoder(opts),
+            }
+        })
+    }
+
+    /// the fewest bits per channel that hold `buf`, if hiding it keeps a PSNR of `db`
+    fn bit_depth_within_psnr(&self, buf: &[u8], db: f64) -> Result<u8> {
+        let Some(Media::Image(image)) = self.carrier.as_ref() else {
+            return Err(SteganoError::UnsupportedMedia);
+        };
+        let opts = (1..=8)
+            .map(|bits| CodecOptions {
+                bits_per_channel: bits,
+                ..self.options.clone()
+            })
+            .find(|opts| media::image::LsbCodec::channels(image, opts).count() / 8 >= buf.len())
+            .ok_or(SteganoError::CannotMeetDistortionTarget { psnr: db })?;
+
+        // more bits never distort less, so the fewest that fit are the only ones to try
+        let mut stego = image.clone();
+        media::image::LsbCodec::encoder(&mut stego, &opts)
+            .write_all(buf)
+            .map_err(|_e| SteganoError::ImageEncodingError)?;
+        match analysis::psnr(image, &stego) >= db {
+            true => Ok(opts.bits_per_channel),
+            false => Err(SteganoError::CannotMeetDistortionTarget { psnr: db }),
+        }
+    }
+
+    /// the serialized message or frames
+    #[cfg(feature = "fs")]
+    fn secret_bytes(&mut self) -> Result<Vec<u8>> {
+        let (mut buf, deferred_checksum) = self.secret_bytes_deferring_checksum()?;
+        if let Some(checksum) = deferred_checksum {
+            checksum.patch(&mut buf);
+        }
+
+        Ok(buf)
+    }
+
+    /// like `secret_bytes()`, see [`Message::to_bytes_deferring_checksum`]
+    fn secret_bytes_deferring_checksum(
+        &mut self,
+    ) -> Result<(Vec<u8>, Option<message::DeferredChecksum>)> {
+        self.message.provenance = self.provenance.then(Provenance::now);
+        if !self.content_version_forced && !self.message.header_fields().is_empty() {
+            self.message.header = ContentVersion::V5;
+        }
+
+        let budget = self.memory_budget.unwrap_or(usize::MAX);
+        let (buf, deferred_checksum) = if self.frames.is_empty() {
+            message::check_budget(self.message.uncompressed_payload_len(), budget)?;
+            self.message.to_bytes_deferring_checksum()?
+        } else {
+            // each frame behind its length, then an empty frame
+            let frames_len = self
+                .frames
+                .iter()
+                .filter(|f| !f.is_empty())
+                .map(|f| 4 + f.len())
+                .sum::<usize>();
+            message::check_budget(frames_len + 4, budget)?;
+            ((&FramedMessage::new(self.frames.clone())).into(), None)
+        };
+        debug!(
+            "serialized the secret into {} bytes of content version {:#04x}",
+            buf.len(),
+            self.message.header.to_u8()
+        );
+
+        Ok((buf, deferred_checksum))
+    }
+}
+
+/// the settings of a [`SteganoEncoder`] without its carrier and content, e.g. the bit depth,
+/// compression and channels, see [`SteganoEncoder::into_template`]
+#[derive(Clone)]
+pub struct EncoderTemplate {
+    options: CodecOptions,
+    message: Message,
+    content_version_forced: bool,
+    bind_cover_hash: bool,
+    derive_seed_from_cover: bool,
+    pre_dither: bool,
+    strict: bool,
+    provenance: bool,
+    memory_budget: Option<usize>,
+    time_budget: Option<Duration>,
+    palette_embedding: bool,
+    spare_palette_slots: bool,
+    normalize_output: bool,
+    metadata_channel: bool,
+    max_distortion_psnr: Option<f64>,
+    per_frame: bool,
+    luminance: bool,
+    alignment_marker: bool,
+    verify_after_hide: bool,
+}
+
+impl EncoderTemplate {
+    /// a fresh encoder with the settings of the template, that still needs a carrier and
+    /// something to hide
+    pub fn encoder(&self) -> SteganoEncoder {
+        SteganoEncoder {
+            options: self.options.clone(),
+            message: self.message.clone(),
+            content_version_forced: self.content_version_forced,
+            bind_cover_hash: self.bind_cover_hash,
+            derive_seed_from_cover: self.derive_seed_from_cover,
+            pre_dither: self.pre_dither,
+            strict: self.strict,
+            provenance: self.provenance,
+            memory_budget: self.memory_budget,
+            time_budget: self.time_budget,
+            palette_embedding: self.palette_embedding,
+            spare_palette_slots: self.spare_palette_slots,
+            normalize_output: self.normalize_output,
+            metadata_channel: self.metadata_channel,
+            max_distortion_psnr: self.max_distortion_psnr,
+            per_frame: self.per_frame,
+            luminance: self.luminance,
+            alignment_marker: self.alignment_marker,
+            verify_after_hide: self.verify_after_hide,
+            ..SteganoEncoder::default()
+        }
+    }
+}
+
+/// what the queued files take of the capacity of the carrier, see [`SteganoEncoder::capacity_breakdown`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapacityBreakdown {
+    /// the capacity of the carrier in bytes
+    pub capacity: usize,
+    /// the files in the order they were added
+    pub files: Vec<FileCapacity>,
+    /// true if the whole message fits into the carrier
+    pub fits: bool,
+}
+
+/// what a single file takes of the capacity, see [`CapacityBreakdown`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCapacity {
+    /// name of the file, as it is hidden
+    pub name: String,
+    /// bytes the payload grows by with this file
+    pub needed: usize,
+    /// bytes of the payload with this file and all before it
+    pub cumulative: usize,
+    /// true if this file and all before it fit into the carrier
+    pub fits: bool,
+}
+
+/// the structure of a hidden message without its content, see [`SteganoDecoder::describe`].
+/// It displays as an indented tree, e.g. for inspection on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageDescription {
+    pub version: ContentVersion,
+    /// true if any file is deflated in the zip archive
+    pub compressed: bool,
+    /// true if the payload carries a MAC, see [`SteganoEncoder::authenticate_with_key`]
+    pub authenticated: bool,
+    /// names and sizes in bytes of the hidden files
+    pub files: Vec<(String, usize)>,
+    /// length in bytes of the text of content version 1
+    pub text: Option<usize>,
+    /// names of the header fields that are set, see [`Message::header_fields`]
+    pub header_fields: Vec<&'static str>,
+    pub checksum: ChecksumAlgo,
+    /// false if the payload does not match its checksum, messages without checksum are ok
+    pub checksum_ok: bool,
+}
+
+impl std::fmt::Display for MessageDescription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+
+        writeln!(
+            f,
+            "message of content version {:#04x}",
+            self.version.to_u8()
+        )?;
+        writeln!(f, "  compressed: {}", yes_no(self.compressed))?;
+        writeln!(f, "  authenticated: {}", yes_no(self.authenticated))?;
+        match (self.checksum, self.checksum_ok) {
+            (ChecksumAlgo::None, _) => writeln!(f, "  checksum: none")?,
+            (algo, ok) => writeln!(
+                f,
+                "  checksum: {algo:?}, {}",
+                if ok { "ok" } else { "mismatch" }
+            )?,
+        }
+        if !self.header_fields.is_empty() {
+            writeln!(f, "  header fields:")?;
+            for field in &self.header_fields {
+                writeln!(f, "    {field}")?;
+            }
+        }
+        if let Some(len) = self.text {
+            writeln!(f, "  text: {len} bytes")?;
+        }
+        writeln!(f, "  files: {}", self.files.len())?;
+        for (name, size) in &self.files {
+            writeln!(f, "    {name}: {size} bytes")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// the settings a message was hidden with, as far as the header and the carrier tell them,
+/// see [`SteganoDecoder::settings`]. There is no encryption, so there is nothing to report of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingSettings {
+    pub version: ContentVersion,
+    /// low bits of the red, green and blue channel that carry the message
+    pub bit_depths: [u8; 3],
+    /// the order the bytes of a pixel were named in, see [`SteganoEncoder::channel_order`]
+    pub channel_order: ChannelOrder,
+    /// the traversal told by the preamble, `None` for the default column based one
+    pub traversal: Option<TraversalOrder>,
+    /// true if the first file is deflated in the zip archive
+    pub compressed: bool,
+    /// true if the payload carries a MAC, see [`SteganoEncoder::authenticate_with_key`]
+    pub authenticated: bool,
+    /// see [`SteganoEncoder::kdf_params`]
+    pub kdf_iterations: Option<u32>,
+    /// true if the payload carries a signature, see [`SteganoEncoder::sign_with`]
+    pub signed: bool,
+    pub checksum: ChecksumAlgo,
+    /// see [`SteganoEncoder::with_fec`]
+    pub fec_redundancy: Option<u8>,
+    /// see [`SteganoEncoder::checkerboard`]
+    pub checkerboard: bool,
+}
+
+/// a file that was written by [`SteganoDecoder::unveil_with_manifest`]
+#[cfg(feature = "fs")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedFile {
+    /// name of the file, as it was hidden
+    pub name: String,
+    /// size in bytes
+    pub size: u64,
+    /// where the file was written to
+    pub path: PathBuf,
+}
+
+/// everything that was hidden in a message, see [`SteganoDecoder::unveil_message`]
+#[derive(Debug)]
+pub struct DecodedMessage {
+    pub files: Vec<(String, Vec<u8>)>,
+    /// the text of content version 1
+    pub text: Option<String>,
+    pub comment: Option<String>,
+    pub provenance: Option<Provenance>,
+    pub cover_hash: Option<[u8; 32]>,
+    /// false if the payload does not match its checksum, messages without checksum are ok
+    pub checksum_ok: bool,
+    /// false if the carrier holds no message, e.g. it is clean, all else is empty then
+    pub found: bool,
+}
+
+#[derive(Default)]
+pub struct SteganoDecoder {
+    options: CodecOptions,
+    source: Option<Media>,
+    palette: Option<PaletteImage>,
+    palette_embedding: bool,
+    spare_palette_slots: bool,
+    bitmap: Option<BitmapImage>,
+    bitmap_pixels: bool,
+    skip_prefix: usize,
+    derive_seed_from_cover: bool,
+    exif_orientation: Option<u8>,
+    respect_exif_orientation: bool,
+    memory_budget: Option<usize>,
+    channel_order_set: bool,
+    checkerboard_set: bool,
+    metadata_channel: bool,
+    metadata_payload: Option<Vec<u8>>,
+    gif: Option<GifImage>,
+    gif_frame: Option<usize>,
+    dng: Option<DngPreview>,
+    paired_payload: Option<Vec<u8>>,
+    luminance: bool,
+    alignment_marker: bool,
+    sync_output: bool,
+}
+
+impl SteganoDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(opts: CodecOptions) -> Self {
+        Self {
+            options: opts,
+            ..Self::default()
+        }
+    }
+
+    /// a 1 bit per pixel PNG is read as [`BitmapImage`], see [`SteganoEncoder::use_media`].
+    /// Such a PNG re-saved by another encoder is read as bitmap with [`SteganoDecoder::read_as_bitmap`]
+    #[cfg(feature = "fs")]
+    pub fn use_media(&mut self, input_file: &str) -> Result<&mut Self> {
+        let path = Path::new(input_file);
+        let (source, buf) = Media::read_file(path)?;
+        let png = is_png(path).then_some(buf.as_slice());
+        self.palette = png.map(PaletteImage::decode).transpose()?.flatten();
+        self.bitmap = png.map(BitmapImage::decode).transpose()?.flatten();
+        self.source = Some(source);
+        self.apply_bitmap_pixels();
+        self.metadata_payload = png
+            .map(media::image::metadata::decode_payload)
+            .transpose()?
+            .flatten();
+        self.exif_orientation = png.and_then(media::image::orientation::exif_orientation);
+        self.apply_exif_orientation();
+
+        Ok(self)
+    }
+
+    /// reads the secret from the frames of an animated GIF, see [`SteganoEncoder::use_carrier_gif`].
+    /// It is read from the first frame that holds a message, unless [`SteganoDecoder::gif_frame`]
+    #[cfg(feature = "fs")]
+    pub fn use_carrier_gif(&mut self, input_file: &str) -> Result<&mut Self> {
+        self.gif = Some(GifImage::open(Path::new(input_file))?);
+
+        Ok(self)
+    }
+
+    /// reads the secret from the preview of a DNG, see [`SteganoEncoder::use_carrier_dng`]
+    #[cfg(feature = "fs")]
+    pub fn use_carrier_dng(&mut self, input_file: &str) -> Result<&mut Self> {
+        self.dng = Some(DngPreview::open(Path::new(input_file))?);
+
+        Ok(self)
+    }
+
+    /// reads the payload from carrier `a` and corrects it by the parity of carrier `b`, see
+    /// [`SteganoEncoder::use_carrier_pair`], the carriers can be given in any order.
+    /// Fails with `SteganoError::MissingShard` if both carry the same half of the pair and with
+    /// `SteganoError::MalformedSecretData` if there are more corrupted bytes than can be corrected
+    #[cfg(feature = "fs")]
+    pub fn use_carrier_pair(&mut self, a: &str, b: &str) -> Result<&mut Self> {
+        let mut shards = [a, b]
+            .iter()
+            .map(|f| {
+                let media = Media::from_file(Path::new(f))?;
+                let shard = Shard::of(&mut media.decoder(&self.options));
+                shard
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if shards.iter().any(|s| s.count != 2) {
+            return Err(SteganoError::MalformedSecretData);
+        }
+        shards.sort_by_key(|s| s.index);
+        if let Some(missing) = (0..2).find(|i| shards[*i as usize].index != *i) {
+            return Err(SteganoError::MissingShard(missing));
+        }
+        self.paired_payload = Some(fec::decode_with_parity(
+            &shards[0].data,
+            &shards[1].data,
+            fec::PAIR_REDUNDANCY,
+        )?);
+
+        Ok(self)
+    }
+
+    /// reads the secret from this frame of the GIF only, counted from 0
+    pub fn gif_frame(&mut self, frame: usize) -> &mut Self {
+        self.gif_frame = Some(frame);
+
+        self
+    }
+
+    /// applies the EXIF orientation of the media before decoding, for carriers whose pixels were
+    /// rotated or flipped, e.g. by a phone gallery, while an orientation tag keeps the displayed
+    /// image as it was when the secret was hidden. PNG only, it works before or after `use_media`
+    pub fn respect_exif_orientation(&mut self, respect: bool) -> &mut Self {
+        self.respect_exif_orientation = respect;
+        self.apply_exif_orientation();
+
+        self
+    }
+
+    fn apply_exif_orientation(&mut self) {
+        if !self.respect_exif_orientation {
+            return;
+        }
+        if let (Some(orientation), Some(source)) = (self.exif_orientation, self.source.take()) {
+            self.source = Some(source.oriented(orientation));
+            self.exif_orientation = None;
+        }
+    }
+
+    /// uses an in memory media, e.g. the image returned by [`SteganoEncoder::hide_to_image`]
+    pub fn use_source(&mut self, source: impl Into<Media>) -> &mut Self {
+        self.source = Some(source.into());
+        self.apply_bitmap_pixels();
+
+        self
+    }
+
+    /// reads an image of only opaque black and white pixels as [`BitmapImage`], e.g. a 1 bit
+    /// per pixel PNG that another encoder re-saved with more bits per pixel. It is not guessed,
+    /// as an ordinary black and white image looks the same. It works before or after `use_media`
+    pub fn read_as_bitmap(&mut self, bitmap: bool) -> &mut Self {
+        self.bitmap_pixels = bitmap;
+        self.apply_bitmap_pixels();
+
+        self
+    }
+
+    fn apply_bitmap_pixels(&mut self) {
+        if !self.bitmap_pixels || self.bitmap.is_some() {
+            return;
+        }
+        if let Some(Media::Image(image)) = self.source.as_ref() {
+            self.bitmap = BitmapImage::from_rgba(image);
+        }
+    }
+
+    /// visits the image in the order derived from the passphrase, that was used on hiding,
+    /// see [`SteganoEncoder::use_passphrase_ordering`]
+    pub fn use_passphrase_ordering(&mut self, passphrase: &str) -> &mut Self {
+        let seed = media::image::cursor::passphrase_seed(passphrase);
+        self.options.ordering_seed = Some(Arc::new(Secret::new(seed)));
+
+        self
+    }
+
+    /// see [`SteganoEncoder::order_passphrase_from_file`]
+    #[cfg(feature = "fs")]
+    pub fn order_passphrase_from_file(&mut self, path: &str) -> Result<&mut Self> {
+        Ok(self.use_passphrase_ordering(&passphrase_of_file(path)?))
+    }
+
+    /// see [`SteganoEncoder::order_passphrase_from_env`]
+    pub fn order_passphrase_from_env(&mut self, var: &str) -> Result<&mut Self> {
+        Ok(self.use_passphrase_ordering(&passphrase_of_env(var)?))
+    }
+
+    /// reads the `bits` low bits of every color channel like on hiding,
+    /// see [`SteganoEncoder::use_bit_depth`]
+    pub fn use_bit_depth(&mut self, bits: u8) -> Result<&mut Self> {
+        let opts = CodecOptions {
+            bits_per_channel: bits,
+            ..self.options.clone()
+        };
+        opts.validate()?;
+        self.options = opts;
+
+        Ok(self)
+    }
+
+    /// reads only every `n`th row like on hiding, see [`SteganoEncoder::row_stride`]
+    pub fn row_stride(&mut self, n: usize) -> &mut Self {
+        self.options.row_stride = u32::try_from(n).unwrap_or(u32::MAX);
+
+        self
+    }
+
+    /// reads only the pixels of the checkerboard pattern, or not. Without it, the pattern that
+    /// [`SteganoEncoder::checkerboard`] kept in the header is found on its own
+    pub fn checkerboard(&mut self, checkerboard: bool) -> &mut Self {
+        self.options.checkerboard = checkerboard;
+        self.checkerboard_set = true;
+
+        self
+    }
+
+    /// reads only the pixels that are white in the `mask`, the same mask that
+    /// [`SteganoEncoder::use_mask`] hid with, it is not found on its own
+    pub fn use_mask(&mut self, mask: &GrayImage) -> &mut Self {
+        self.options.mask = Some(Arc::new(mask.clone()));
+
+        self
+    }
+
+    /// skips fully transparent pixels like on hiding, see [`SteganoEncoder::skip_transparent`]
+    pub fn skip_transparent(&mut self, skip: bool) -> &mut Self {
+        self.options.skip_transparent = skip;
+
+        self
+    }
+
+    /// reads as many low bits of each channel as on hiding,
+    /// see [`SteganoEncoder::channel_bit_depths`]
+    pub fn channel_bit_depths(&mut self, r: u8, g: u8, b: u8) -> &mut Self {
+        self.options.channel_bit_depths = Some([r, g, b]);
+
+        self
+    }
+
+    /// reads only the one channel like on hiding, see [`SteganoEncoder::single_channel`]
+    pub fn single_channel(&mut self, channel: Channel) -> &mut Self {
+        self.options.single_channel = Some(channel);
+
+        self
+    }
+
+    /// reads the channels as named by `order`, to match how another tool hid the secret.
+    /// Without it, the channel order that [`SteganoEncoder::channel_order`] kept in the header
+    /// is found on its own, a wrong order fails like any broken message
+    pub fn channel_order(&mut self, order: ChannelOrder) -> &mut Self {
+        self.options.channel_order = order;
+        self.channel_order_set = true;
+
+        self
+    }
+
+    /// visits the image in the order derived from the media itself,
+    /// see [`SteganoEncoder::derive_seed_from_cover`]
+    pub fn derive_seed_from_cover(&mut self, derive: bool) -> &mut Self {
+        self.derive_seed_from_cover = derive;
+
+        self
+    }
+
+    /// reads the message from the metadata of a PNG instead of its pixels,
+    /// see [`SteganoEncoder::use_metadata_channel`]
+    pub fn use_metadata_channel(&mut self, metadata: bool) -> &mut Self {
+        self.metadata_channel = metadata;
+
+        self
+    }
+
+    /// scans for the alignment marker and reads the secret behind it, wherever the crop moved
+    /// it to, see [`SteganoEncoder::use_alignment_marker`]
+    pub fn use_alignment_marker(&mut self, marker: bool) -> &mut Self {
+        self.alignment_marker = marker;
+
+        self
+    }
+
+    /// reads the secret from the LSB of the luminance of each pixel,
+    /// see [`SteganoEncoder::use_luminance`]
+    pub fn use_luminance(&mut self, luminance: bool) -> &mut Self {
+        self.luminance = luminance;
+
+        self
+    }
+
+    /// reads the secret from the palette colors of an indexed PNG,
+    /// see [`SteganoEncoder::use_palette_embedding`]
+    pub fn use_palette_embedding(&mut self, palette: bool) -> &mut Self {
+        self.palette_embedding = palette;
+
+        self
+    }
+
+    /// reads the secret from the spare palette slots of an indexed PNG,
+    /// see [`SteganoEncoder::use_spare_palette_slots`]
+    pub fn use_spare_palette_slots(&mut self, spare_slots: bool) -> &mut Self {
+        self.spare_palette_slots = spare_slots;
+
+        self
+    }
+
+    /// caps the bytes of the hidden payload and unpacked files that are held in memory, bigger
+    /// messages fail with `SteganoError::MemoryBudgetExceeded`, before they are read.
+    /// [`SteganoDecoder::unveil_into`] streams the files, so only a buffered payload counts there
+    pub fn memory_budget(&mut self, bytes: usize) -> &mut Self {
+        self.memory_budget = Some(bytes);
+
+        self
+    }
+
+    /// syncs each unveiled file to disk with `File::sync_all()` before it counts as written, so
+    /// it survives a crash right after, e.g. in data recovery tooling. Off by default, as a sync
+    /// is slow. For [`SteganoDecoder::unveil_with_manifest`] and [`SteganoDecoder::unveil_shards`]
+    pub fn sync_output(&mut self, sync: bool) -> &mut Self {
+        self.sync_output = sync;
+
+        self
+    }
+
+    /// parses the hidden message within the memory budget
+    fn message(&self) -> Result<Message> {
+        Message::of_with_budget(
+            &mut self.decoder()?,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )
+    }
+
+    /// discards the first `bytes` hidden bytes before the message is read,
+    /// e.g. a fixed preamble that another tool writes in front of the payload
+    pub fn skip_prefix(&mut self, bytes: usize) -> &mut Self {
+        self.skip_prefix = bytes;
+
+        self
+    }
+
+    /// decoder of the hidden bytes, either from the media or its palette, behind the prefix
+    fn decoder(&self) -> Result<Box<dyn Read + '_>> {
+        Ok(self.decoder_and_header()?.0)
+    }
+
+    /// the decoder and the header of the message, if it was read to find the layout of the pixels
+    fn decoder_and_header(&self) -> Result<(Box<dyn Read + '_>, Option<Message>)> {
+        self.options.validate()?;
+        let mut header = None;
+        let mut decoder = if self.metadata_channel {
+            let payload = self
+                .metadata_payload
+                .as_ref()
+                .ok_or(SteganoError::NoSecretData)?;
+            Box::new(payload.as_slice())
+        } else if let Some(payload) = self.paired_payload.as_ref() {
+            Box::new(payload.as_slice())
+        } else if let Some(gif) = self.gif.as_ref() {
+            let frame = self.gif_frame.unwrap_or_else(|| {
+                (0..gif.frame_count())
+                    .find(|frame| {
+                        gif.frame_decoder(*frame)
+                            .is_some_and(|mut d| Message::peek_payload_size(&mut d).is_ok())
+                    })
+                    .unwrap_or(0)
+            });
+            gif.frame_decoder(frame).ok_or_else(|| {
+                SteganoError::InvalidParameter(format!(
+                    "frame {frame} does not exist, the GIF has {} frames",
+                    gif.frame_count()
+                ))
+            })?
+        } else if let Some(dng) = self.dng.as_ref() {
+            dng.decoder()
+        } else if self.spare_palette_slots {
+            self.palette
+                .as_ref()
+                .ok_or(SteganoError::UnsupportedMedia)?
+                .spare_slots_decoder()
+        } else if self.palette_embedding {
+            let palette = self
+                .palette
+                .as_ref()
+                .ok_or(SteganoError::UnsupportedMedia)?;
+            palette.decoder(&self.options)
+        } else if let Some(bitmap) = self.bitmap.as_ref() {
+            bitmap.decoder()
+        } else if self.luminance {
+            match self.source.as_ref().ok_or(SteganoError::NoSecretData)? {
+                Media::Image(image) => media::image::luminance::decoder(image),
+                _ => return Err(SteganoError::UnsupportedMedia),
+            }
+        } else if self.alignment_marker {
+            match self.source.as_ref().ok_or(SteganoError::NoSecretData)? {
+                Media::Image(image) => media::image::alignment::decoder(image)?,
+                _ => return Err(SteganoError::UnsupportedMedia),
+            }
+        } else {
+            let media = self.source.as_ref().ok_or(SteganoError::NoSecretData)?;
+            let mut opts = self.options.clone();
+            if let Media::Image(image) = media {
+                opts.check_mask(image)?;
+            }
+            if self.derive_seed_from_cover {
+                opts.ordering_seed = Some(Arc::new(Secret::new(media.cover_seed(&self.options))));
+            }
+            if let (None, Media::Image(image)) = (opts.traversal, media) {
+                opts.traversal = media::image::LsbCodec::read_traversal_preamble(image);
+            }
+            if let Media::Image(_) = media {
+                (opts, header) = self.detect_layout(media, opts);
+            }
+            media.decoder(&opts)
+        };
+        std::io::copy(
+            &mut decoder.by_ref().take(self.skip_prefix as u64),
+            &mut std::io::sink(),
+        )?;
+
+        Ok((decoder, header))
+    }
+
+    /// the header of the message, it is read only once if the layout of the pixels was found
+    fn peek_header(&self) -> Result<Message> {
+        match self.decoder_and_header()? {
+            (_, Some(header)) => Ok(header),
+            (mut decoder, None) => Message::peek_header(&mut decoder),
+        }
+    }
+
+    /// the options with the channel order and checkerboard that the header of the
+    /// message says it is hidden with, and that header. Each layout is read once, the configured
+    /// one first. Any other has to be confirmed by a header of content version 5, as only that
+    /// tells it, else the configured one is kept
+    fn detect_layout(&self, media: &Media, opts: CodecOptions) -> (CodecOptions, Option<Message>) {
+        let peek = |opts: &CodecOptions| {
+            let mut decoder = media.decoder(opts);
+            std::io::copy(
+                &mut decoder.by_ref().take(self.skip_prefix as u64),
+                &mut std::io::sink(),
+            )
+            .ok()?;
+            Message::peek_header(&mut decoder).ok()
+        };
+        let confirms = |header: &Message, opts: &CodecOptions| {
+            header.header == ContentVersion::V5
+                && header.channel_order == opts.channel_order
+                && header.checkerboard == opts.checkerboard
+        };
+
+        let header = peek(&opts);
+        if header.as_ref().is_some_and(|h| confirms(h, &opts)) {
+            return (opts, header);
+        }
+        let checkerboards = match self.checkerboard_set || opts.checkerboard {
+            true => vec![opts.checkerboard],
+            false => vec![false, true],
+        };
+        let channel_orders = match self.channel_order_set {
+            true => vec![opts.channel_order],
+            false => ChannelOrder::ALL.to_vec(),
+        };
+        for &checkerboard in &checkerboards {
+            for &channel_order in &channel_orders {
+                if (checkerboard, channel_order) == (opts.checkerboard, opts.channel_order) {
+                    continue;
+                }
+                let candidate = CodecOptions {
+                    checkerboard,
+                    channel_order,
+                    ..opts.clone()
+                };
+                if let Some(h) = peek(&candidate).filter(|h| confirms(h, &candidate)) {
+                    return (candidate, Some(h));
+                }
+            }
+        }
+
+        (opts, header)
+    }
+
+    /// checks if the content of the media, that is not used for hiding, is still the same as
+    /// when the secret was hidden. Requires [`SteganoEncoder::bind_cover_hash`] on hiding.
+    pub fn verify_cover(&self) -> Result<bool> {
+        let media = self.source.as_ref().ok_or(SteganoError::NoSecretData)?;
+        let msg = self.message()?;
+        let expected = msg
+            .cover_hash
+            .ok_or(SteganoError::NotEmbedded("cover hash"))?;
+
+        Ok(media.cover_hash(&self.options) == expected)
+    }
+
+    /// checks that the payload was hidden with the same key by [`SteganoEncoder::authenticate_with_key`].
+    /// Fails with `SteganoError::NotEmbedded` if the message has no MAC
+    pub fn verify_mac(&mut self, key: &[u8]) -> Result<bool> {
+        Message::verify_mac(
+            &mut self.decoder()?,
+            key,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )
+    }
+
+    /// checks that the payload was signed by the secret key of `public_key`, see
+    /// [`SteganoEncoder::sign_with`]. Fails with `SteganoError::NotEmbedded` if the message is
+    /// not signed, and with `SteganoError::InvalidParameter` if the public key is none
+    pub fn verify_signature(&mut self, public_key: &[u8]) -> Result<bool> {
+        Message::verify_signature(
+            &mut self.decoder()?,
+            public_key,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )
+    }
+
+    /// the iterations that stretch the MAC key, set by [`SteganoEncoder::kdf_params`],
+    /// `None` if the key is used as it is. Only the header is read
+    pub fn kdf_iterations(&mut self) -> Result<Option<u32>> {
+        Message::peek_kdf_iterations(&mut self.decoder()?)
+    }
+
+    /// when and by which version the message was created,
+    /// `None` if it was hidden without [`SteganoEncoder::embed_provenance`]
+    pub fn provenance(&mut self) -> Result<Option<Provenance>> {
+        Ok(self.message()?.provenance)
+    }
+
+    /// size in bytes of the hidden payload, read from the header without extracting it,
+    /// see [`Message::peek_payload_size`]
+    pub fn hidden_size(&self) -> Result<usize> {
+        Message::peek_payload_size(&mut self.decoder()?)
+    }
+
+    /// names and sizes of the hidden files, with a file index only the index is read,
+    /// see [`SteganoEncoder::with_file_index`]
+    pub fn list_files(&self) -> Result<Vec<(String, usize)>> {
+        Message::list_files(
+            &mut self.decoder()?,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )
+    }
+
+    /// the content of the hidden file `name`, the other files are not unpacked into memory.
+    /// With a file index they are skipped without being read at all,
+    /// see [`SteganoEncoder::with_file_index`]. Fails with `SteganoError::FileNotFound`
+    /// if there is no such file
+    pub fn extract_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        Message::extract_file(
+            &mut self.decoder()?,
+            name,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )
+    }
+
+    /// describes the structure of the message, its version, flags, files with their sizes and
+    /// header fields, and whether it matches its checksum. Nothing is written anywhere, and a
+    /// checksum mismatch is no error here, see [`MessageDescription`]
+    pub fn describe(&mut self) -> Result<MessageDescription> {
+        let (msg, checksum_ok) = Message::of_reporting_checksum(
+            &mut self.decoder()?,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )?;
+        let mut header_fields = msg.header_fields();
+        if msg.mac.is_some() {
+            header_fields.push("mac");
+        }
+        if msg.signature.is_some() {
+            header_fields.push("signature");
+        }
+
+        Ok(MessageDescription {
+            version: msg.header,
+            compressed: msg.compression && !msg.files.is_empty(),
+            authenticated: msg.mac.is_some(),
+            files: msg
+                .files
+                .iter()
+                .map(|(name, buf)| (name.clone(), buf.len()))
+                .collect(),
+            text: msg.text.as_ref().map(String::len),
+            header_fields,
+            checksum: msg.checksum,
+            checksum_ok,
+        })
+    }
+
+    /// the settings the message was hidden with, read from its header and the traversal
+    /// preamble, the files are not unpacked. The bits per channel of this decoder are tried
+    /// first, then the other bit depths, so they need not be known up front,
+    /// see [`EmbeddingSettings`]
+    pub fn settings(&mut self) -> Result<EmbeddingSettings> {
+        let configured = self.options.clone();
+        let candidates = match configured.channel_bit_depths {
+            Some(_) => vec![configured.bits_per_channel],
+            None => std::iter::once(configured.bits_per_channel)
+                .chain((1..=8).filter(|bits| *bits != configured.bits_per_channel))
+                .collect(),
+        };
+        // with the wrong bits a header of content version 1 or 2 reads from almost any noise,
+        // so only the versions with a length or an index to check are taken for a bit depth
+        let mut detected = None;
+        for bits in candidates {
+            self.options.bits_per_channel = bits;
+            let probe = self
+                .peek_header()
+                .ok()
+                .filter(|m| !matches!(m.header, ContentVersion::V1 | ContentVersion::V2));
+            if let Some(msg) = probe {
+                detected = Some((msg, self.options.bit_depths()));
+                break;
+            }
+        }
+        self.options = configured;
+        let (msg, bit_depths) = match detected {
+            Some(detected) => detected,
+            None => (self.peek_header()?, self.options.bit_depths()),
+        };
+        let traversal = self.options.traversal.or(match self.source.as_ref() {
+            Some(Media::Image(image)) => media::image::LsbCodec::read_traversal_preamble(image),
+            _ => None,
+        });
+
+        Ok(EmbeddingSettings {
+            version: msg.header,
+            bit_depths: msg.channel_bit_depths.unwrap_or([
+                bit_depths[0],
+                bit_depths[1],
+                bit_depths[2],
+            ]),
+            channel_order: msg.channel_order,
+            traversal,
+            compressed: msg.compression,
+            authenticated: msg.mac.is_some(),
+            kdf_iterations: msg.kdf_iterations,
+            signed: msg.signature.is_some(),
+            checksum: msg.checksum,
+            fec_redundancy: msg.fec_redundancy,
+            checkerboard: msg.checkerboard,
+        })
+    }
+
+    /// the payload that was hidden in the bit `plane` by [`SteganoEncoder::hide_in_plane`],
+    /// independent of the payloads of the other planes. Images only
+    pub fn unveil_plane(&mut self, plane: u8) -> Result<Vec<u8>> {
+        match self.source.as_ref().ok_or(SteganoError::NoSecretData)? {
+            Media::Image(image) => media::image::bit_plane::unveil(image, plane),
+            _ => Err(SteganoError::UnsupportedMedia),
+        }
+    }
+
+    /// the comment set by [`SteganoEncoder::set_comment`], `None` if there is none
+    pub fn comment(&mut self) -> Result<Option<String>> {
+        Ok(self.message()?.comment)
+    }
+
+    /// the hint set by [`SteganoEncoder::set_hint`], `None` if there is none. Only the header is
+    /// read, so it needs no key and works even if the payload does not match its checksum
+    pub fn hint(&mut self) -> Result<Option<String>> {
+        Message::peek_hint(&mut self.decoder()?)
+    }
+
+    /// the JPEG thumbnail set by [`SteganoEncoder::embed_preview`], `None` if there is none
+    pub fn preview(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(self.message()?.preview)
+    }
+
+    /// unveils all files into the folder `dir` and returns what was written, e.g. for auditing.
+    /// Fails with `SteganoError::InvalidFormat(FormatError::ChecksumMismatch)` for a corrupted payload
+    #[cfg(feature = "fs")]
+    pub fn unveil_with_manifest(&mut self, dir: &str) -> Result<Vec<ExtractedFile>> {
+        let msg = self.unveil_message()?;
+        if !msg.checksum_ok {
+            return Err(format::FormatError::ChecksumMismatch.into());
+        }
+        let mut files = msg.files;
+        if let Some(text) = msg.text {
+            files.push(("secret-message.txt".to_owned(), text.into_bytes()));
+        }
+
+        commands::write_files(files, Path::new(dir), self.sync_output)
+    }
+
+    /// bundles all hidden files into one zip archive in memory, with their names, e.g. to hand
+    /// them on as a single artifact. A text is added as `secret-message.txt`
+    pub fn unveil_as_zip(&mut self) -> Result<Vec<u8>> {
+        let msg = self.message()?;
+        let mut files = msg.files;
+        if let Some(text) = msg.text {
+            files.push(("secret-message.txt".to_owned(), text.into_bytes()));
+        }
+
+        zip_files(files)
+    }
+
+    /// unveils whatever part of the payload can be read, next to the error that stopped it, e.g.
+    /// of a carrier that was cropped or cut off. Meant for forensic recovery, the bytes are the
+    /// zip archive or a prefix of it, see [`Message::payload_best_effort`]
+    pub fn unveil_best_effort(&mut self) -> (Vec<u8>, Option<SteganoError>) {
+        match self.decoder() {
+            Ok(mut decoder) => {
+                Message::payload_best_effort(&mut decoder, self.memory_budget.unwrap_or(usize::MAX))
+            }
+            Err(e) => (Vec::new(), Some(e)),
+        }
+    }
+
+    /// unveils the whole message in one pass: files, text, metadata and whether the payload
+    /// matches its checksum. Unlike the other unveil methods a checksum mismatch is no error here,
+    /// neither is a carrier without message, `found` is false then
+    pub fn unveil_message(&mut self) -> Result<DecodedMessage> {
+        let found = message::absent_as_none(Message::of_reporting_checksum(
+            &mut self.decoder()?,
+            self.memory_budget.unwrap_or(usize::MAX),
+        ))?
+        .filter(|(msg, _checksum_ok)| !msg.is_empty());
+        let Some((msg, checksum_ok)) = found else {
+            return Ok(DecodedMessage {
+                files: Vec::new(),
+                text: None,
+                comment: None,
+                provenance: None,
+                cover_hash: None,
+                checksum_ok: true,
+                found: false,
+            });
+        };
+
+        Ok(DecodedMessage {
+            files: msg.files,
+            text: msg.text,
+            comment: msg.comment,
+            provenance: msg.provenance,
+            cover_hash: msg.cover_hash,
+            checksum_ok,
+            found: true,
+        })
+    }
+
+    /// streams the content of the hidden files, or the text, straight into `w` as it is decoded,
+    /// so that even huge files are never held in memory completely. Returns the number of bytes
+    /// written. See [`Message::stream_content`] for the limitations
+    pub fn unveil_into<W: std::io::Write>(&mut self, mut w: W) -> Result<u64> {
+        Message::stream_content(
+            &mut self.decoder()?,
+            &mut w,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )
+    }
+
+    /// puts the shards of the given media files back together and unveils the files into the
+    /// folder `dir`. The media files can be given in any order and format, each shard knows its
+    /// index, see [`SteganoEncoder::hide_sharded`]. Fails with `SteganoError::MissingShard` if the set is incomplete
+    #[cfg(feature = "fs")]
+    pub fn unveil_shards(&mut self, media_files: &[&str], dir: &str) -> Result<Vec<ExtractedFile>> {
+        let shards = media_files
+            .iter()
+            .map(|f| {
+                let media = Media::from_file(Path::new(f))?;
+                let shard = Shard::of(&mut media.decoder(&self.options));
+                shard
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let buf = Shard::reassemble(shards)?;
+
+        let msg = Message::of(&mut buf.as_slice())?;
+        commands::write_files(commands::files_of(msg), Path::new(dir), self.sync_output)
+    }
+
+    /// unveils the text hidden by [`SteganoEncoder::hide_message`], or the text of content version 1.
+    /// The bytes are never converted lossy, if they are not valid utf-8 it fails with `SteganoError::InvalidUtf8`
+    pub fn unveil_text(&mut self) -> Result<String> {
+        let msg = self.message().map_err(|e| match e {
+            SteganoError::InvalidFormat(format::FormatError::InvalidText) => {
+                SteganoError::InvalidUtf8
+            }
+            e => e,
+        })?;
+        if let Some(text) = msg.text {
+            return Ok(text);
+        }
+        let (_name, buf) = msg
+            .files
+            .into_iter()
+            .find(|(name, _buf)| name == "secret-message.txt")
+            .ok_or(SteganoError::NoSecretData)?;
+
+        String::from_utf8(buf).map_err(|_| SteganoError::InvalidUtf8)
+    }
+
+    /// the first `max_bytes` of the text hidden by [`SteganoEncoder::hide_message`], cut at the
+    /// last whole character, e.g. for a quick preview in a file browser. The rest of the text is
+    /// not unpacked, see [`Message::peek_text`]
+    pub fn peek_text(&mut self, max_bytes: usize) -> Result<String> {
+        Message::peek_text(
+            &mut self.decoder()?,
+            max_bytes,
+            self.memory_budget.unwrap_or(usize::MAX),
+        )
+    }
+
+    /// unveils all frames written by [`SteganoEncoder::add_frame`], in the order they were added
+    pub fn unveil_frames(&mut self) -> Result<Vec<Vec<u8>>> {
+        Ok(FramedMessage::of(&mut self.decoder()?)?.frames)
+    }
+}
+
+/// the first line of the file, without its line break. It is wiped from memory, like the
+/// rest of the file, once it is dropped
+#[cfg(feature = "fs")]
+fn passphrase_of_file(path: &str) -> Result<Zeroizing<String>> {
+    let content = Zeroizing::new(
+        std::fs::read_to_string(path).map_err(|source| SteganoError::ReadError { source })?,
+    );
+
+    Ok(Zeroizing::new(
+        content.lines().next().unwrap_or_default().to_owned(),
+    ))
+}
+
+/// the value of the environment variable, that is read only now and wiped once it is dropped
+fn passphrase_of_env(var: &str) -> Result<Zeroizing<String>> {
+    std::env::var(var)
+        .map(Zeroizing::new)
+        .map_err(|e| SteganoError::InvalidParameter(format!("no passphrase in ${var}: {e}")))
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod e2e_tests {
+    use super::*;
+    use crate::commands::{unveil, unveil_raw};
+    use std::fs::{self, File};
+    use std::io::Read;
+    use std::sync::atomic::Ordering;
+    use tempfile::TempDir;
+
+    const BASE_IMAGE: &str = "../resources/Base.png";
+
+    #[test]
+    #[should_panic(expected = "Data file was not readable.")]
+    fn should_panic_on_invalid_data_file() {
+        SteganoEncoder::new().hide_file("foofile");
+    }
+
+    #[test]
+    #[should_panic(expected = "Data file was not readable.")]
+    fn should_panic_on_invalid_data_file_among_valid() {
+        SteganoEncoder::new().hide_files(vec!["Cargo.toml", "foofile"]);
+    }
+
+    #[test]
+    fn should_panic_for_invalid_carrier_image_file() {
+        let mut encoder = SteganoEncoder::new();
+        let result = encoder.use_media("some_random_file.png");
+        match result.err() {
+            Some(SteganoError::InvalidImageMedia) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_panic_for_invalid_media_file() {
+        let mut encoder = SteganoEncoder::new();
+        let result = encoder.use_media("Cargo.toml");
+        match result.err() {
+            Some(SteganoError::UnsupportedMedia) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
     fn carrier_item_mut_should_allow_to_mutate_colors() {
         let mut color: u8 = 8;
         let c = MediaPrimitiveMut::ImageColorChannel(&mut color);
 
-        if let MediaPrimitiveMut::ImageColorChannel(i) = c {
-            *i = 9;
+        if let MediaPrimitiveMut::ImageColorChannel(i) = c {
+            *i = 9;
+        }
+
+        assert_eq!(color, 9);
+    }
+
+    #[test]
+    fn should_accept_a_png_as_target_file() {
+        SteganoEncoder::new().write_to("/tmp/out-test-image.png");
+    }
+
+    #[test]
+    fn should_hide_and_unveil_one_text_file_in_wav() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media_p = out_dir.path().join("secret.wav");
+        let secret_media_f = secret_media_p.to_str().unwrap();
+
+        SteganoEncoder::new()
+            .hide_file("Cargo.toml")
+            .use_media("../resources/plain/carrier-audio.wav")?
+            .write_to(secret_media_f)
+            .hide()?;
+
+        let l = fs::metadata(secret_media_p.as_path())
+            .expect("Secret media was not written.")
+            .len();
+        assert!(l > 0, "File is not supposed to be empty");
+
+        unveil(
+            secret_media_p.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+
+        let given_decoded_secret = out_dir.path().join("Cargo.toml");
+        assert_eq_file_content(
+            &given_decoded_secret,
+            "Cargo.toml".as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_and_unveil_one_text_file() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("secret.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+
+        SteganoEncoder::new()
+            .hide_file("Cargo.toml")
+            .use_media("../resources/with_text/hello_world.png")?
+            .write_to(image_with_secret)
+            .hide()?;
+
+        let l = fs::metadata(image_with_secret)
+            .expect("Output image was not written.")
+            .len();
+        assert!(l > 0, "File is not supposed to be empty");
+
+        unveil(
+            image_with_secret_path.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+
+        let given_decoded_secret = out_dir.path().join("Cargo.toml");
+        assert_eq_file_content(
+            &given_decoded_secret,
+            "Cargo.toml".as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_raw_unveil_a_message() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let expected_file = out_dir.path().join("hello_world.bin");
+        let raw_decoded_secret = expected_file.to_str().unwrap();
+
+        unveil_raw(
+            Path::new("../resources/with_text/hello_world.png"),
+            expected_file.as_path(),
+        )?;
+
+        let l = fs::metadata(raw_decoded_secret)
+            .expect("Output file was not written.")
+            .len();
+
+        // TODO content verification needs to be done as well
+        assert_ne!(l, 0, "Output raw data file was empty.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_and_unveil_a_binary_file() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_to_hide = "../resources/secrets/random_1666_byte.bin";
+        let image_with_secret_path = out_dir.path().join("random_1666_byte.bin.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let expected_file = out_dir.path().join("random_1666_byte.bin");
+
+        SteganoEncoder::new()
+            .hide_file(secret_to_hide)
+            .use_media(BASE_IMAGE)?
+            .write_to(image_with_secret)
+            .hide()?;
+
+        let l = fs::metadata(image_with_secret)
+            .expect("Output image was not written.")
+            .len();
+        assert!(l > 0, "File is not supposed to be empty");
+
+        unveil(
+            image_with_secret_path.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+        assert_eq_file_content(
+            &expected_file,
+            secret_to_hide.as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_and_unveil_a_zip_file() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_to_hide = "../resources/secrets/zip_with_2_files.zip";
+        let image_with_secret_path = out_dir.path().join("zip_with_2_files.zip.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let expected_file = out_dir.path().join("zip_with_2_files.zip");
+
+        SteganoEncoder::new()
+            .hide_file(secret_to_hide)
+            .use_media(BASE_IMAGE)?
+            .write_to(image_with_secret)
+            .hide()?;
+
+        assert_file_not_empty(image_with_secret);
+
+        unveil(
+            image_with_secret_path.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+
+        assert_eq_file_content(
+            &expected_file,
+            secret_to_hide.as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_ensure_content_v2_compatibility() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let decoded_secret = out_dir.path().join("Blah.txt");
+
+        unveil(
+            Path::new("../resources/with_attachment/Blah.txt.png"),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+
+        assert_eq_file_content(
+            &decoded_secret,
+            "../resources/secrets/Blah.txt".as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_ensure_content_v2_compatibility_with_2_files_reading() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let decoded_secret_1 = out_dir.path().join("Blah.txt");
+        let decoded_secret_2 = out_dir.path().join("Blah-2.txt");
+
+        unveil(
+            Path::new("../resources/with_attachment/Blah.txt__and__Blah-2.txt.png"),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+        assert_eq_file_content(
+            &decoded_secret_1,
+            "../resources/secrets/Blah.txt".as_ref(),
+            "Unveiled data file #1 did not match expected",
+        );
+
+        assert_eq_file_content(
+            &decoded_secret_2,
+            "../resources/secrets/Blah-2.txt".as_ref(),
+            "Unveiled data file #2 did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_ensure_content_v2_compatibility_with_2_files_writing() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("Blah.txt.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let secret_to_hide = "../resources/secrets/Blah.txt";
+
+        SteganoEncoder::new()
+            .force_content_version(ContentVersion::V2)
+            .use_media(BASE_IMAGE)?
+            .hide_file(secret_to_hide)
+            .write_to(image_with_secret)
+            .hide()?;
+
+        assert_file_not_empty(image_with_secret);
+
+        unveil(
+            image_with_secret_path.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+
+        let decoded_secret = out_dir.path().join("Blah.txt");
+        assert_eq_file_content(
+            decoded_secret.as_ref(),
+            secret_to_hide.as_ref(),
+            "Unveiled data file did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_for_files_with_content_version_1() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("v1.png");
+
+        let result = SteganoEncoder::new()
+            .force_content_version(ContentVersion::V1)
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(image_with_secret_path.to_str().unwrap())
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(
+            result,
+            Err(SteganoError::FeatureNotSupportedInVersion {
+                feature: "files",
+                version: 0x01
+            })
+        ));
+        assert!(!image_with_secret_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_correct_corrupted_payload_bytes_with_fec() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("fec.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let secret_to_hide = "../resources/secrets/Blah.txt";
+
+        SteganoEncoder::new()
+            .with_fec(4)
+            .use_media(BASE_IMAGE)?
+            .hide_file(secret_to_hide)
+            .write_to(image_with_secret)
+            .hide()?;
+
+        // flip one bit of a few bytes behind the 11 bytes of header
+        let mut image = image::open(image_with_secret).unwrap().to_rgba8();
+        let height = image.height();
+        for byte in [15, 30, 45, 60] {
+            let channel = byte * 8;
+            let pixel = channel / 3;
+            image.get_pixel_mut(pixel / height, pixel % height).0[(channel % 3) as usize] ^= 1;
+        }
+        image.save(image_with_secret).unwrap();
+
+        unveil(
+            image_with_secret_path.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+        assert_eq_file_content(
+            &out_dir.path().join("Blah.txt"),
+            secret_to_hide.as_ref(),
+            "Corrupted data was not corrected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_for_invalid_fec_redundancy() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("fec.png");
+
+        let result = SteganoEncoder::new()
+            .with_fec(0)
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(image_with_secret_path.to_str().unwrap())
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::InvalidParameter(_))));
+        assert!(!image_with_secret_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_verify_the_checksum_on_unveil() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("checksum.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+
+        SteganoEncoder::new()
+            .checksum_algorithm(ChecksumAlgo::Crc32)
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(image_with_secret)
+            .hide()?;
+
+        let extracted = SteganoDecoder::new()
+            .use_media(image_with_secret)?
+            .unveil_with_manifest(out_dir.path().to_str().unwrap())?;
+        assert_eq!(extracted[0].name, "Blah.txt");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_an_already_used_carrier_in_strict_mode() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let first_path = out_dir.path().join("first.png");
+        let first = first_path.to_str().unwrap();
+        let second_path = out_dir.path().join("second.png");
+
+        // note: `Base.png` is no fresh carrier, it already holds a "Hello World!" text
+        SteganoEncoder::new()
+            .strict(true)
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(first)
+            .hide()?;
+        assert_file_not_empty(first);
+
+        let result = SteganoEncoder::new()
+            .strict(true)
+            .use_media(first)?
+            .hide_file("../resources/secrets/Blah-2.txt")
+            .write_to(second_path.to_str().unwrap())
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::CarrierAlreadyUsed)));
+        assert!(!second_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_detect_modified_cover_content() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("bound.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let secret_to_hide = "../resources/secrets/Blah.txt";
+
+        SteganoEncoder::new()
+            .bind_cover_hash(true)
+            .use_media(BASE_IMAGE)?
+            .hide_file(secret_to_hide)
+            .write_to(image_with_secret)
+            .hide()?;
+
+        assert!(SteganoDecoder::new()
+            .use_media(image_with_secret)?
+            .verify_cover()?);
+
+        let mut image = image::open(image_with_secret).unwrap().to_rgba8();
+        image.get_pixel_mut(200, 100).0[0] ^= 0b1000_0000;
+        image.save(image_with_secret).unwrap();
+
+        assert!(!SteganoDecoder::new()
+            .use_media(image_with_secret)?
+            .verify_cover()?);
+
+        unveil(
+            image_with_secret_path.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+        assert_eq_file_content(
+            &out_dir.path().join("Blah.txt"),
+            secret_to_hide.as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_to_verify_cover_without_cover_hash() -> Result<()> {
+        let result = SteganoDecoder::new()
+            .use_media("../resources/with_attachment/Blah.txt.png")?
+            .verify_cover();
+
+        assert!(matches!(result, Err(SteganoError::NotEmbedded(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "webp")]
+    fn should_hide_and_unveil_in_lossless_webp() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("secret.webp");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let secret_to_hide = "../resources/secrets/Blah.txt";
+
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_file(secret_to_hide)
+            .write_to(image_with_secret)
+            .hide()?;
+
+        let written = fs::read(image_with_secret)?;
+        assert_eq!(&written[12..16], b"VP8L", "WebP should be lossless");
+
+        unveil(
+            image_with_secret_path.as_path(),
+            out_dir.path(),
+            &CodecOptions::default(),
+        )?;
+        assert_eq_file_content(
+            &out_dir.path().join("Blah.txt"),
+            secret_to_hide.as_ref(),
+            "Unveiled data did not match expected",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_write_target_when_cancelled() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let image_with_secret_path = out_dir.path().join("cancelled.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let token = CancellationToken::default();
+        token.store(true, Ordering::Relaxed);
+
+        let result = SteganoEncoder::new()
+            .with_cancel(token)
+            .hide_file("../resources/secrets/random_1666_byte.bin")
+            .use_media(BASE_IMAGE)?
+            .write_to(image_with_secret)
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::Cancelled)));
+        assert!(
+            !image_with_secret_path.exists(),
+            "No output should be written for a cancelled hide"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_stop_a_running_hide_when_cancelled() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let target = out_dir.path().join("cancelled.png");
+        let token = CancellationToken::default();
+        let canceller = {
+            let token = token.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                token.store(true, Ordering::Relaxed);
+            })
+        };
+
+        // far too much to hide in 50ms, so the token is set while the hide is still running
+        let result = SteganoEncoder::new()
+            .use_source(RgbaImage::new(2048, 2048))
+            .with_cancel(token)
+            .compress(false)
+            .hide_message(&"a".repeat(1_500_000))
+            .write_to(target.to_str().unwrap())
+            .hide()
+            .map(|_| ());
+        canceller.join().unwrap();
+
+        assert!(matches!(result, Err(SteganoError::Cancelled)));
+        assert!(!target.exists(), "No output should be written");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_stop_a_running_hide_in_passphrase_order_when_cancelled() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let target = out_dir.path().join("cancelled.png");
+        let token = CancellationToken::default();
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_source(RgbaImage::new(2048, 2048))
+            .use_passphrase_ordering("secret")
+            .with_cancel(token.clone())
+            .compress(false)
+            .hide_message(&"a".repeat(1_500_000))
+            .write_to(target.to_str().unwrap());
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            token.store(true, Ordering::Relaxed);
+        });
+
+        // the passphrase is stretched already, so the token is set while the secret is written
+        let result = encoder.hide().map(|_| ());
+        canceller.join().unwrap();
+
+        assert!(matches!(result, Err(SteganoError::Cancelled)));
+        assert!(!target.exists(), "No output should be written");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_write_audio_target_when_cancelled() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let target = out_dir.path().join("cancelled.wav");
+
+        let result = SteganoEncoder::new()
+            .with_cancel(Arc::new(AtomicBool::new(true)))
+            .use_media("../resources/plain/carrier-audio.wav")?
+            .hide_message("never hidden")
+            .write_to(target.to_str().unwrap())
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::Cancelled)));
+        assert!(!target.exists(), "No output should be written");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_without_a_target() -> Result<()> {
+        let result = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_message("nowhere to go")
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::MissingTarget)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_an_existing_target_when_saving_fails() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let target = out_dir.path().join("existing.unknown");
+        fs::write(&target, b"was here before")?;
+
+        let result = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_message("cannot be saved")
+            .write_to(target.to_str().unwrap())
+            .hide()
+            .map(|_| ());
+
+        assert!(result.is_err());
+        assert_eq!(fs::read(&target)?, b"was here before");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_write_target_when_out_of_time() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier_path = out_dir.path().join("large.png");
+        RgbaImage::from_raw(1024, 1024, analysis::random_payload(1024 * 1024 * 4))
+            .unwrap()
+            .save(&carrier_path)
+            .expect("Cannot save the large carrier");
+        let image_with_secret_path = out_dir.path().join("out-of-time.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        let secret_path = out_dir.path().join("secret.bin");
+        fs::write(&secret_path, analysis::random_payload(256 * 1024))?;
+
+        let result = SteganoEncoder::new()
+            .time_budget(Duration::from_nanos(1))
+            .hide_file(secret_path.to_str().unwrap())
+            .use_media(carrier_path.to_str().unwrap())?
+            .write_to(image_with_secret)
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::TimeBudgetExceeded)));
+        assert!(
+            !image_with_secret_path.exists(),
+            "No output should be written for a hide out of time"
+        );
+
+        SteganoEncoder::new()
+            .time_budget(Duration::from_secs(600))
+            .hide_message("in time")
+            .use_media(BASE_IMAGE)?
+            .write_to(image_with_secret)
+            .hide()?;
+        assert!(image_with_secret_path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_trip_in_memory_without_touching_files() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .add_frame(b"in memory")
+            .hide_to_image()?;
+
+        let frames = SteganoDecoder::new().use_source(image).unveil_frames()?;
+        assert_eq!(frames, vec![b"in memory".to_vec()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_in_a_raw_rgba_buffer() -> Result<()> {
+        let (width, height) = (64, 32);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| (i % 251) as u8).collect();
+
+        let image = SteganoEncoder::new()
+            .use_carrier_raw(rgba, width, height)?
+            .add_frame(b"from a raw buffer")
+            .hide_to_image()?;
+        assert_eq!(image.dimensions(), (width, height));
+
+        let frames = SteganoDecoder::new().use_source(image).unveil_frames()?;
+        assert_eq!(frames, vec![b"from a raw buffer".to_vec()]);
+
+        let result = SteganoEncoder::new()
+            .use_carrier_raw(vec![0; 10], width, height)
+            .map(|_| ());
+        assert!(matches!(result, Err(SteganoError::InvalidParameter(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_stream_a_large_payload_into_a_writer() -> Result<()> {
+        /// counts the bytes, but keeps none of them
+        struct CountingWriter(u64);
+
+        impl std::io::Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0 += buf.len() as u64;
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let out_dir = TempDir::new()?;
+        let secret_path = out_dir.path().join("large.bin");
+        fs::write(&secret_path, analysis::random_payload(300_000))?;
+
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file(secret_path.to_str().unwrap())
+            .hide_to_image()?;
+
+        let mut counter = CountingWriter(0);
+        let written = SteganoDecoder::new()
+            .use_source(image)
+            .unveil_into(&mut counter)?;
+
+        assert_eq!(written, 300_000);
+        assert_eq!(counter.0, 300_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reassemble_shards_of_a_png_and_a_bmp() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let path_of = |name: &str| out_dir.path().join(name).to_str().unwrap().to_owned();
+        let bmp_carrier = path_of("carrier.bmp");
+        image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .save(&bmp_carrier)
+            .unwrap();
+        let (png_shard, bmp_shard) = (path_of("shard.png"), path_of("shard.bmp"));
+
+        SteganoEncoder::new()
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_file("../resources/secrets/random_1666_byte.bin")
+            .hide_sharded(&[
+                ("../resources/plain/carrier-image.png", &png_shard),
+                (&bmp_carrier, &bmp_shard),
+            ])?;
+
+        let extracted = SteganoDecoder::new()
+            .unveil_shards(&[&bmp_shard, &png_shard], out_dir.path().to_str().unwrap())?;
+
+        assert_eq!(extracted.len(), 2);
+        assert_eq_file_content(
+            &extracted[0].path,
+            Path::new("../resources/secrets/Blah.txt"),
+            "Blah.txt was not reassembled",
+        );
+        assert_eq_file_content(
+            &extracted[1].path,
+            Path::new("../resources/secrets/random_1666_byte.bin"),
+            "random_1666_byte.bin was not reassembled",
+        );
+
+        let result = SteganoDecoder::new()
+            .unveil_shards(&[&bmp_shard], out_dir.path().to_str().unwrap())
+            .map(|_| ());
+        assert!(matches!(result, Err(SteganoError::MissingShard(0))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_for_a_missing_file_instead_of_panicking() {
+        let result = SteganoEncoder::new()
+            .try_hide_file("../resources/secrets/does-not-exist.txt")
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::ReadError { .. })));
+    }
+
+    #[test]
+    fn should_refuse_a_file_larger_than_the_carrier_early() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_path = out_dir.path().join("large.bin");
+        fs::write(&secret_path, vec![0; 4096])?;
+
+        let result = SteganoEncoder::new()
+            .use_carrier_raw(vec![0; 32 * 32 * 4], 32, 32)?
+            .try_hide_file(secret_path.to_str().unwrap())
+            .map(|_| ());
+
+        assert!(matches!(
+            result,
+            Err(SteganoError::FileTooLarge { size: 4096, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_trip_the_provenance() -> Result<()> {
+        let hide = |provenance| {
+            SteganoEncoder::new()
+                .embed_provenance(provenance)
+                .use_media("../resources/plain/carrier-image.png")?
+                .hide_message("archived")
+                .hide_to_image()
+        };
+        let before = Provenance::now().created_at;
+
+        let provenance = SteganoDecoder::new()
+            .use_source(hide(true)?)
+            .provenance()?
+            .expect("provenance was not embedded");
+        assert!(provenance.created_at >= before);
+        assert!(provenance.created_at <= Provenance::now().created_at);
+        assert_eq!(provenance.tool_version, env!("CARGO_PKG_VERSION"));
+
+        let provenance = SteganoDecoder::new()
+            .use_source(hide(false)?)
+            .provenance()?;
+        assert_eq!(provenance, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_in_a_1_bpp_bitmap() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier = out_dir.path().join("fax.png");
+        let image_with_secret_path = out_dir.path().join("fax-with-secret.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        {
+            let mut encoder = png::Encoder::new(File::create(&carrier)?, 64, 64);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::One);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0b1111_0000; 8 * 64]).unwrap();
+        }
+
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_media(carrier.to_str().unwrap())?;
+        assert_eq!(encoder.capacity(), 64 * 64 / 8);
+        encoder
+            .add_frame(b"sent by fax")
+            .write_to(image_with_secret)
+            .hide()?;
+
+        assert!(BitmapImage::open(&image_with_secret_path)?.is_some());
+        let frames = SteganoDecoder::new()
+            .use_media(image_with_secret)?
+            .unveil_frames()?;
+        assert_eq!(frames, vec![b"sent by fax".to_vec()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_trip_a_comment() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .set_comment("holiday pictures, 2nd batch")
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_message("the real secret")
+            .hide_to_image()?;
+
+        let comment = SteganoDecoder::new().use_source(image).comment()?;
+        assert_eq!(comment.as_deref(), Some("holiday pictures, 2nd batch"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_an_over_long_comment() -> Result<()> {
+        let result = SteganoEncoder::new()
+            .set_comment(&"x".repeat(format::MAX_COMMENT_LEN + 1))
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_message("the real secret")
+            .hide_to_image()
+            .map(|_| ());
+
+        assert!(matches!(result, Err(SteganoError::InvalidParameter(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_round_trip_a_hint() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .set_hint("the usual one, with the year")
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_message("the real secret")
+            .hide_to_image()?;
+
+        let hint = SteganoDecoder::new().use_source(image).hint()?;
+        assert_eq!(hint.as_deref(), Some("the usual one, with the year"));
+
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_message("the real secret")
+            .hide_to_image()?;
+        assert_eq!(SteganoDecoder::new().use_source(image).hint()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_read_the_hint_without_the_key() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .authenticate_with_key(b"shared secret")
+            .set_hint("the one from the meeting")
+            .hide_to_image()?;
+
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_source(image);
+        assert_eq!(decoder.hint()?.as_deref(), Some("the one from the meeting"));
+        assert!(!decoder.verify_mac(b"guessed secret")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_from_an_exif_rotated_copy() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let rotated_path = out_dir.path().join("rotated.png");
+        let rotated = rotated_path.to_str().unwrap();
+
+        let image = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .add_frame(b"upright")
+            .hide_to_image()?;
+
+        // like a gallery that rotates the pixels, but keeps the displayed image by an orientation
+        let mut png = Vec::new();
+        image::imageops::rotate270(&image)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        fs::write(&rotated_path, test_utils::with_exif_orientation(&png, 6))?;
+
+        let frames = SteganoDecoder::new()
+            .use_media(rotated)?
+            .unveil_frames()
+            .unwrap_or_default();
+        assert_ne!(frames, vec![b"upright".to_vec()]);
+
+        let frames = SteganoDecoder::new()
+            .use_media(rotated)?
+            .respect_exif_orientation(true)
+            .unveil_frames()?;
+        assert_eq!(frames, vec![b"upright".to_vec()]);
+
+        // hiding in the rotated copy normalizes it back to the displayed orientation
+        let normalized = SteganoEncoder::new()
+            .use_media(rotated)?
+            .add_frame(b"normalized")
+            .hide_to_image()?;
+        assert_eq!(normalized.dimensions(), image.dimensions());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_within_the_memory_budget() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_path = out_dir.path().join("large.bin");
+        fs::write(&secret_path, analysis::random_payload(20_000))?;
+        let secret = secret_path.to_str().unwrap();
+
+        let result = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file(secret)
+            .memory_budget(4096)
+            .hide_to_image()
+            .map(|_| ());
+        assert!(matches!(
+            result,
+            Err(SteganoError::MemoryBudgetExceeded { budget: 4096, .. })
+        ));
+
+        // a sparse file of 1 GiB, that is never read
+        let huge_path = out_dir.path().join("huge.bin");
+        fs::File::create(&huge_path)?.set_len(1 << 30)?;
+        let mut encoder = SteganoEncoder::new();
+        let result = encoder
+            .memory_budget(4096)
+            .try_hide_file(huge_path.to_str().unwrap())
+            .map(|_| ());
+        assert!(matches!(
+            result,
+            Err(SteganoError::MemoryBudgetExceeded { budget: 4096, .. })
+        ));
+        assert!(encoder.message.files.is_empty());
+
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file(secret)
+            .hide_to_image()?;
+
+        let mut decoder = SteganoDecoder::new();
+        decoder.memory_budget(4096).use_source(image);
+        let result = decoder.unveil_with_manifest(out_dir.path().to_str().unwrap());
+        assert!(matches!(
+            result,
+            Err(SteganoError::MemoryBudgetExceeded { budget: 4096, .. })
+        ));
+
+        // streaming never buffers the payload, so it stays within the budget
+        let mut unveiled = Vec::new();
+        assert_eq!(decoder.unveil_into(&mut unveiled)?, 20_000);
+        assert_eq!(unveiled, fs::read(secret)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_tell_the_hidden_size_without_extracting() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/random_1666_byte.bin")
+            .hide_to_image()?;
+
+        let size = SteganoDecoder::new().use_source(image).hidden_size()?;
+        // random data does not compress, the zip entry adds its headers and the file name
+        assert!(
+            (1666..1666 + 200).contains(&size),
+            "hidden size {size} does not match"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_pick_the_smallest_bit_depth_that_fits() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_path = out_dir.path().join("secret.bin");
+        fs::write(&secret_path, analysis::random_payload(1800))?;
+        let secret = secret_path.to_str().unwrap();
+
+        // 64x64 pixels carry 1536 bytes with 1 bit and 3072 bytes with 2 bits per channel
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_carrier_raw(vec![0x80; 64 * 64 * 4], 64, 64)?;
+        assert_eq!(encoder.auto_bit_depth(2000)?, 2);
+        assert_eq!(encoder.capacity(), 3072);
+        let image = encoder.hide_file(secret).hide_to_image()?;
+
+        let mut unveiled = Vec::new();
+        SteganoDecoder::with_options(CodecOptions {
+            bits_per_channel: 2,
+            ..CodecOptions::default()
+        })
+        .use_source(image)
+        .unveil_into(&mut unveiled)?;
+        assert_eq!(unveiled, fs::read(secret)?);
+
+        assert!(matches!(
+            encoder.auto_bit_depth(7000),
+            Err(SteganoError::CarrierTooSmall {
+                needed: 7000,
+                capacity: 6144
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_unicode_text_exactly() -> Result<()> {
+        let text = "Grüße aus Köln 🦀🔐 — 你好";
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_message(text)
+            .hide_to_image()?;
+
+        assert_eq!(SteganoDecoder::new().use_source(image).unveil_text()?, text);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_peek_at_the_start_of_a_long_text() -> Result<()> {
+        let text = "Grüße 🦀 ".repeat(1000);
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_message(&text)
+            .hide_to_image()?;
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_source(image);
+
+        // the crab would end at byte 12, so it is cut away as a whole
+        let preview = decoder.peek_text(10)?;
+        assert_eq!(preview, "Grüße ");
+        assert!(preview.len() <= 10 && text.starts_with(&preview));
+        assert_eq!(decoder.peek_text(12)?, "Grüße 🦀");
+        assert_eq!(decoder.peek_text(usize::MAX)?, text);
+
+        let clean = SteganoDecoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .peek_text(10);
+        assert!(clean.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_to_unveil_invalid_utf8_as_text() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media("../resources/plain/carrier-image.png")?
+            .message
+            .add_file_data("secret-message.txt", vec![0xc3, 0x28]);
+        let image = encoder.hide_to_image()?;
+        let result = SteganoDecoder::new().use_source(image).unveil_text();
+        assert!(matches!(result, Err(SteganoError::InvalidUtf8)));
+
+        // content version 1 carries the text without zip
+        let mut media = Media::from_file(Path::new("../resources/plain/carrier-image.png"))?;
+        media.hide_bytes(&[0x01, 0xc3, 0x28, 0xff], &CodecOptions::default())?;
+        let result = SteganoDecoder::new().use_source(media).unveil_text();
+        assert!(matches!(result, Err(SteganoError::InvalidUtf8)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_with_the_seed_derived_from_the_cover() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .derive_seed_from_cover(true)
+            .hide_message("no seed needed")
+            .hide_to_image()?;
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(image.clone())
+            .derive_seed_from_cover(true)
+            .unveil_text()?;
+        assert_eq!(unveiled, "no seed needed");
+
+        let mut modified = image;
+        modified.get_pixel_mut(100, 100).0[0] ^= 0b1000_0000;
+        let unveiled = SteganoDecoder::new()
+            .use_source(modified)
+            .derive_seed_from_cover(true)
+            .unveil_text();
+        assert!(
+            !matches!(unveiled.as_deref(), Ok("no seed needed")),
+            "A modified cover should lead to another order"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_leave_transparent_pixels_untouched() -> Result<()> {
+        // the left half is fully transparent
+        let origin = RgbaImage::from_fn(64, 32, |x, y| {
+            let alpha = if x < 32 { 0 } else { 0xff };
+            image::Rgba([(x * 4) as u8, (y * 8) as u8, 0x55, alpha])
+        });
+
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_source(origin.clone()).skip_transparent(true);
+        assert_eq!(encoder.capacity(), 32 * 32 * 3 / 8);
+        let image = encoder.hide_message("only where visible").hide_to_image()?;
+
+        for (x, y, pixel) in origin.enumerate_pixels() {
+            if pixel.0[3] == 0 {
+                assert_eq!(image.get_pixel(x, y), pixel, "Pixel ({x}, {y}) was touched");
+            }
+        }
+        assert_ne!(image, origin);
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(image)
+            .skip_transparent(true)
+            .unveil_text()?;
+        assert_eq!(unveiled, "only where visible");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_the_whole_message_in_one_pass() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_message("and a text")
+            .checksum_algorithm(ChecksumAlgo::Crc32)
+            .set_comment("both")
+            .hide_to_image()?;
+
+        let msg = SteganoDecoder::new().use_source(image).unveil_message()?;
+        let names: Vec<&str> = msg.files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Blah.txt", "secret-message.txt"]);
+        assert_eq!(msg.files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+        assert_eq!(msg.files[1].1, b"and a text");
+        assert_eq!(
+            msg.text, None,
+            "Only content version 1 carries a plain text"
+        );
+        assert_eq!(msg.comment.as_deref(), Some("both"));
+        assert!(msg.checksum_ok);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_in_every_third_row_only() -> Result<()> {
+        let origin = RgbaImage::from_pixel(64, 30, image::Rgba([0x80, 0x80, 0x80, 0xff]));
+
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_source(origin.clone()).row_stride(3);
+        assert_eq!(encoder.capacity(), 64 * 10 * 3 / 8);
+        let image = encoder.hide_message("between the lines").hide_to_image()?;
+
+        for (x, y, pixel) in origin.enumerate_pixels() {
+            if y % 3 != 0 {
+                assert_eq!(image.get_pixel(x, y), pixel, "Pixel ({x}, {y}) was touched");
+            }
+        }
+        let unveiled = SteganoDecoder::new()
+            .use_source(image)
+            .row_stride(3)
+            .unveil_text()?;
+        assert_eq!(unveiled, "between the lines");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_configure_the_encoder_from_env() -> Result<()> {
+        let vars = [
+            ("STEGANO_BIT_DEPTH", "2"),
+            ("STEGANO_COMPRESS", "false"),
+            ("STEGANO_SEED", "42"),
+            ("STEGANO_ROW_STRIDE", "3"),
+            ("STEGANO_CHECKSUM", "sha256"),
+        ];
+        for (name, value) in vars {
+            std::env::set_var(name, value);
+        }
+        let mut encoder = SteganoEncoder::new();
+        let configured = encoder.configure_from_env().map(|_| ());
+        std::env::set_var("STEGANO_BIT_DEPTH", "nine");
+        let malformed = SteganoEncoder::new().configure_from_env().map(|_| ());
+        for (name, _) in vars {
+            std::env::remove_var(name);
+        }
+
+        configured?;
+        assert_eq!(encoder.options.bits_per_channel, 2);
+        assert!(!encoder.message.compression);
+        assert_eq!(encoder.options.ordering_seed.map(|s| *s.expose()), Some(42));
+        assert_eq!(encoder.options.row_stride, 3);
+        assert_eq!(encoder.message.checksum, ChecksumAlgo::Sha256);
+        assert!(matches!(malformed, Err(SteganoError::InvalidParameter(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_stretch_the_mac_key_with_the_iterations_of_the_header() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .authenticate_with_key(b"shared secret")
+            .kdf_params(20_000, 0)?
+            .hide_to_image()?;
+
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_source(image.clone());
+        assert_eq!(decoder.kdf_iterations()?, Some(20_000));
+        assert!(decoder.verify_mac(b"shared secret")?);
+        assert!(!decoder.verify_mac(b"guessed secret")?);
+        let unveiled = decoder.unveil_message()?;
+        assert_eq!(
+            unveiled.files[0].1,
+            fs::read("../resources/secrets/Blah.txt")?
+        );
+
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .authenticate_with_key(b"shared secret")
+            .hide_to_image()?;
+        assert_eq!(
+            SteganoDecoder::new().use_source(image).kdf_iterations()?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_unsupported_kdf_params() {
+        let result = SteganoEncoder::new()
+            .kdf_params(format::MIN_KDF_ITERATIONS - 1, 0)
+            .map(|_| ());
+        assert!(matches!(result, Err(SteganoError::InvalidParameter(_))));
+        assert!(SteganoEncoder::new()
+            .kdf_params(format::MIN_KDF_ITERATIONS, 0)
+            .is_ok());
+        let result = SteganoEncoder::new()
+            .kdf_params(format::MAX_KDF_ITERATIONS + 1, 0)
+            .map(|_| ());
+        assert!(matches!(result, Err(SteganoError::InvalidParameter(_))));
+        let result = SteganoEncoder::new()
+            .kdf_params(format::MIN_KDF_ITERATIONS, 64 * 1024)
+            .map(|_| ());
+        assert!(matches!(result, Err(SteganoError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn should_make_encoders_with_the_settings_of_the_template() -> Result<()> {
+        let mut original = SteganoEncoder::new();
+        original
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .use_bit_depth(2)?
+            .compress(false)
+            .checksum_algorithm(ChecksumAlgo::Sha256)
+            .single_channel(Channel::Blue)
+            .set_comment("only for the first image")
+            .verify_after_hide(true);
+        let template = original.into_template();
+
+        for _ in 0..2 {
+            let encoder = template.encoder();
+            assert_eq!(encoder.options.bits_per_channel, 2);
+            assert_eq!(encoder.options.single_channel, Some(Channel::Blue));
+            assert!(!encoder.message.compression);
+            assert_eq!(encoder.message.checksum, ChecksumAlgo::Sha256);
+            assert!(encoder.verify_after_hide);
+            assert!(encoder.carrier.is_none());
+            assert!(encoder.message.files.is_empty());
+            assert_eq!(encoder.message.comment, None);
+        }
+
+        let image = template
+            .encoder()
+            .use_media(BASE_IMAGE)?
+            .hide_message("hidden with the template")
+            .hide_to_image()?;
+        let unveiled = SteganoDecoder::new()
+            .use_source(image)
+            .use_bit_depth(2)?
+            .single_channel(Channel::Blue)
+            .unveil_message()?;
+        assert_eq!(unveiled.files[0].1, b"hidden with the template".to_vec());
+        assert!(unveiled.checksum_ok);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_verify_the_mac_with_the_right_key_only() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .authenticate_with_key(b"shared secret")
+            .hide_to_image()?;
+
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_source(image);
+        assert!(decoder.verify_mac(b"shared secret")?);
+        assert!(!decoder.verify_mac(b"guessed secret")?);
+
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+        let result = SteganoDecoder::new()
+            .use_source(image)
+            .verify_mac(b"shared secret");
+        assert!(matches!(result, Err(SteganoError::NotEmbedded("MAC"))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_verify_the_signature_with_the_public_key_of_the_signer_only() -> Result<()> {
+        let secret_key = [7; 32];
+        let public_key = ed25519_dalek::SigningKey::from_bytes(&secret_key).verifying_key();
+        let other_key = ed25519_dalek::SigningKey::from_bytes(&[8; 32]).verifying_key();
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .sign_with(&secret_key)?
+            .hide_to_image()?;
+
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_source(image);
+        assert!(decoder.verify_signature(public_key.as_bytes())?);
+        assert!(!decoder.verify_signature(other_key.as_bytes())?);
+        assert!(matches!(
+            decoder.verify_signature(b"too short"),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+        assert!(decoder.describe()?.header_fields.contains(&"signature"));
+        assert!(matches!(
+            SteganoEncoder::new().sign_with(b"too short"),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+        let result = SteganoDecoder::new()
+            .use_source(image)
+            .verify_signature(public_key.as_bytes());
+        assert!(matches!(
+            result,
+            Err(SteganoError::NotEmbedded("signature"))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_all_files_as_one_zip() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_files(vec![
+                "../resources/secrets/Blah.txt",
+                "../resources/secrets/Blah-2.txt",
+            ])
+            .hide_to_image()?;
+
+        let zip = SteganoDecoder::new().use_source(image).unveil_as_zip()?;
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), 2);
+        for (i, name) in ["Blah.txt", "Blah-2.txt"].into_iter().enumerate() {
+            let entry = archive.by_index(i).unwrap();
+            let expected = fs::metadata(format!("../resources/secrets/{name}"))?.len();
+            assert_eq!(entry.name(), name);
+            assert_eq!(entry.size(), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_the_end_of_the_secret_by_pre_dithering() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_path = out_dir.path().join("secret.bin");
+        fs::write(&secret_path, analysis::random_payload(20_000))?;
+        let secret = secret_path.to_str().unwrap();
+        let hidden_bits = 20_000 * 8;
+
+        // how much more the region with the secret is suspected than the region behind it
+        let suspicion_gap = |image: &RgbaImage| {
+            let values = analysis::carrier_values(image, &CodecOptions::default());
+            let (secret, rest) = values.split_at(hidden_bits);
+            analysis::chi_square_score(secret.iter().copied())
+                - analysis::chi_square_score(rest.iter().copied())
+        };
+
+        let plain = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file(secret)
+            .hide_to_image()?;
+        let dithered = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file(secret)
+            .pre_dither(true)
+            .hide_to_image()?;
+        assert!(suspicion_gap(&plain) > 0.5);
+        assert!(suspicion_gap(&dithered) < 0.01);
+
+        let mut unveiled = Vec::new();
+        SteganoDecoder::new()
+            .use_source(dithered)
+            .unveil_into(&mut unveiled)?;
+        assert_eq!(unveiled, fs::read(secret)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_and_unveil_a_text_file_by_the_one_shots() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let one_shot = out_dir.path().join("one-shot.png");
+        let builder = out_dir.path().join("builder.png");
+        let one_shot_dir = out_dir.path().join("one-shot");
+        let builder_dir = out_dir.path().join("builder");
+        fs::create_dir(&one_shot_dir)?;
+        fs::create_dir(&builder_dir)?;
+
+        hide(
+            BASE_IMAGE,
+            "../resources/secrets/Blah.txt",
+            one_shot.to_str().unwrap(),
+        )?;
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(builder.to_str().unwrap())
+            .hide()?;
+        assert_eq!(
+            image::open(&one_shot).unwrap().to_rgba8(),
+            image::open(&builder).unwrap().to_rgba8()
+        );
+
+        crate::unveil(one_shot.to_str().unwrap(), one_shot_dir.to_str().unwrap())?;
+        SteganoDecoder::new()
+            .use_media(builder.to_str().unwrap())?
+            .unveil_with_manifest(builder_dir.to_str().unwrap())?;
+        assert_eq!(
+            fs::read(one_shot_dir.join("Blah.txt"))?,
+            fs::read(builder_dir.join("Blah.txt"))?
+        );
+        assert_eq!(
+            fs::read(one_shot_dir.join("Blah.txt"))?,
+            fs::read("../resources/secrets/Blah.txt")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_the_utilization_of_the_capacity() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        assert_eq!(encoder.utilization(), None);
+        encoder.use_media(BASE_IMAGE)?;
+        assert_eq!(encoder.utilization(), None);
+
+        encoder.hide_file("../resources/secrets/Blah.txt");
+        let mut message = Message::empty();
+        message.add_file("../resources/secrets/Blah.txt");
+        let expected = message.payload_len() as f64 / encoder.capacity() as f64;
+        assert_eq!(encoder.utilization(), Some(expected));
+        assert!(expected > 0.0 && expected < 1.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_only_in_the_checkerboard_pattern() -> Result<()> {
+        let origin = image::open(BASE_IMAGE).unwrap().to_rgba8();
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_source(origin.clone());
+        let full = encoder.capacity();
+        encoder.checkerboard(true);
+        assert!(encoder.capacity().abs_diff(full / 2) <= 1);
+
+        let secret = "on black squares only ".repeat(encoder.capacity() / 2 / 22);
+        let image = encoder.hide_message(&secret).hide_to_image()?;
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if (x + y) % 2 == 1 {
+                assert_eq!(
+                    pixel,
+                    origin.get_pixel(x, y),
+                    "pixel ({x}, {y}) was changed"
+                );
+            }
+        }
+        let unveiled = SteganoDecoder::new()
+            .use_source(image.clone())
+            .unveil_text()?;
+        assert_eq!(unveiled, secret);
+        assert!(
+            SteganoDecoder::new()
+                .use_source(image)
+                .checkerboard(false)
+                .unveil_text()
+                .is_err(),
+            "the plain pattern should not read it"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_only_in_the_white_pixels_of_a_mask() -> Result<()> {
+        let origin = image::open(BASE_IMAGE).unwrap().to_rgba8();
+        let (width, height) = origin.dimensions();
+        let mask = image::GrayImage::from_fn(width, height, |_, y| match y < height / 2 {
+            true => image::Luma([0xff]),
+            false => image::Luma([0]),
+        });
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_source(origin.clone());
+        let full = encoder.capacity();
+        encoder.use_mask(&mask);
+        let half = width as usize * (height / 2) as usize * 3 / 8;
+        assert_eq!(encoder.capacity(), half, "full capacity is {full}");
+
+        let secret = "top half only ".repeat(encoder.capacity() / 2 / 14);
+        let image = encoder.hide_message(&secret).hide_to_image()?;
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if y >= height / 2 {
+                assert_eq!(
+                    pixel,
+                    origin.get_pixel(x, y),
+                    "pixel ({x}, {y}) was changed"
+                );
+            }
+        }
+        let unveiled = SteganoDecoder::new()
+            .use_source(image.clone())
+            .use_mask(&mask)
+            .unveil_text()?;
+        assert_eq!(unveiled, secret);
+        assert!(
+            SteganoDecoder::new()
+                .use_source(image)
+                .unveil_text()
+                .is_err(),
+            "without the mask it should not read it"
+        );
+        let wrong_size = image::GrayImage::new(width / 2, height);
+        assert!(matches!(
+            SteganoEncoder::new()
+                .use_source(origin)
+                .use_mask(&wrong_size)
+                .hide_message("x")
+                .hide_to_image(),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_find_the_channel_order_of_its_own_output() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .channel_order(ChannelOrder::Bgra)
+            .hide_message("hidden in blue, green and red")
+            .hide_to_image()?;
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(image.clone())
+            .unveil_text()?;
+        assert_eq!(unveiled, "hidden in blue, green and red");
+
+        let wrong = SteganoDecoder::new()
+            .use_source(image)
+            .channel_order(ChannelOrder::Argb)
+            .unveil_text();
+        assert!(matches!(wrong, Err(SteganoError::InvalidFormat(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_in_the_channel_order_of_another_tool() -> Result<()> {
+        // another tool that sees the pixels as BGRA, it keeps no channel order in the header
+        let mut message = Message::empty();
+        message.add_file_data("external.txt", b"from another tool".to_vec());
+        let opts = CodecOptions {
+            channel_order: ChannelOrder::Bgra,
+            ..CodecOptions::default()
+        };
+        let mut image = image::open(BASE_IMAGE).unwrap().to_rgba8();
+        media::image::LsbCodec::encoder(&mut image, &opts).write_all(&message.to_bytes()?)?;
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(image)
+            .channel_order(ChannelOrder::Bgra)
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files,
+            vec![("external.txt".to_string(), b"from another tool".to_vec())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_a_batch_despite_an_invalid_image() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("with-secret.png");
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+        let target = out_dir.path().join("unveiled");
+
+        let results = SteganoCore::unveil_batch(
+            &[
+                secret_media.to_str().unwrap(),
+                "../resources/secrets/Blah.txt",
+            ],
+            target.to_str().unwrap(),
+        )?;
+
+        assert_eq!(results.len(), 2);
+        let files = results[0].as_ref().expect("the first image carries a file");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "Blah.txt");
+        assert_eq!(files[0].path, target.join("with-secret").join("Blah.txt"));
+        assert!(results[1].is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_find_no_message_in_a_clean_carrier() -> Result<()> {
+        let clean = "../resources/plain/carrier-image.png";
+        let image = image::open(clean).unwrap().to_rgba8();
+        let mut decoder = media::image::LsbCodec::decoder(&image, &CodecOptions::default());
+        assert!(Message::of_optional(&mut decoder)?.is_none());
+
+        let decoded = SteganoDecoder::new().use_media(clean)?.unveil_message()?;
+        assert!(!decoded.found);
+        assert!(decoded.files.is_empty() && decoded.text.is_none());
+
+        let out_dir = TempDir::new()?;
+        assert!(matches!(
+            SteganoDecoder::new()
+                .use_media(clean)?
+                .unveil_with_manifest(out_dir.path().to_str().unwrap()),
+            Err(SteganoError::NoSecretData)
+        ));
+        assert_eq!(fs::read_dir(out_dir.path())?.count(), 0);
+
+        let decoded = SteganoDecoder::new()
+            .use_media("../resources/with_text/hello_world.png")?
+            .unveil_message()?;
+        assert!(decoded.found);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_with_its_own_bit_depth_per_channel() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_media(BASE_IMAGE)?.channel_bit_depths(1, 1, 2);
+        let (width, height) = image::open(BASE_IMAGE).unwrap().to_rgba8().dimensions();
+        assert_eq!(
+            encoder.capacity(),
+            width as usize * height as usize * (1 + 1 + 2) / 8
+        );
+        let image = encoder
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(image.clone())
+            .channel_bit_depths(1, 1, 2)
+            .unveil_message()?;
+        assert_eq!(unveiled.files.len(), 1);
+        assert_eq!(
+            unveiled.files[0].1,
+            fs::read("../resources/secrets/Blah.txt")?
+        );
+
+        for (r, g, b) in [(1, 1, 1), (2, 1, 1), (1, 1, 3)] {
+            let mismatched = SteganoDecoder::new()
+                .use_source(image.clone())
+                .channel_bit_depths(r, g, b)
+                .unveil_with_manifest(TempDir::new()?.path().to_str().unwrap());
+            assert!(mismatched.is_err(), "{r}/{g}/{b} should not unveil");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_reject_bit_depths_out_of_range() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        for bits in [0, 9, u8::MAX] {
+            assert!(matches!(
+                encoder.use_bit_depth(bits).map(|_| ()),
+                Err(SteganoError::InvalidParameter(_))
+            ));
+        }
+        assert_eq!(encoder.bits_per_channel(), 1);
+        let image = encoder
+            .use_bit_depth(3)?
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+        assert_eq!(encoder.bits_per_channel(), 3);
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(image.clone())
+            .use_bit_depth(3)?
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files[0].1,
+            fs::read("../resources/secrets/Blah.txt")?
+        );
+        assert!(SteganoDecoder::new().use_bit_depth(9).is_err());
+
+        let hidden = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .channel_bit_depths(1, 0, 2)
+            .hide_message("never hidden")
+            .hide_to_image()
+            .map(|_| ());
+        assert!(matches!(hidden, Err(SteganoError::InvalidParameter(_))));
+        let unveiled = SteganoDecoder::new()
+            .use_source(image)
+            .channel_bit_depths(1, 9, 1)
+            .unveil_message()
+            .map(|_| ());
+        assert!(matches!(unveiled, Err(SteganoError::InvalidParameter(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_in_the_green_channel_only() -> Result<()> {
+        let origin = image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8();
+        let (width, height) = origin.dimensions();
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_source(origin.clone())
+            .single_channel(Channel::Green);
+        assert_eq!(encoder.capacity(), width as usize * height as usize / 8);
+        let image = encoder
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+
+        let mut green_changed = false;
+        for (a, b) in origin.pixels().zip(image.pixels()) {
+            assert_eq!(a.0[0], b.0[0], "Red should be untouched");
+            assert_eq!(a.0[2], b.0[2], "Blue should be untouched");
+            green_changed |= a.0[1] != b.0[1];
+        }
+        assert!(green_changed);
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(image)
+            .single_channel(Channel::Green)
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files,
+            vec![(
+                "Blah.txt".to_string(),
+                fs::read("../resources/secrets/Blah.txt")?
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_early_on_a_target_that_is_not_writable() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let target = out_dir.path().join("missing").join("secret.png");
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(target.to_str().unwrap());
+
+        assert!(matches!(
+            encoder.check_target_writable(),
+            Err(SteganoError::TargetNotWritable { .. })
+        ));
+        assert!(matches!(
+            encoder.hide(),
+            Err(SteganoError::TargetNotWritable { .. })
+        ));
+
+        let target = out_dir.path().join("secret.png");
+        encoder.write_to(target.to_str().unwrap());
+        encoder.check_target_writable()?;
+        assert!(!target.exists(), "The check should leave no file behind");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_in_the_metadata_and_keep_the_pixels() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("secret.png");
+        let carrier = "../resources/plain/carrier-image.png";
+        SteganoEncoder::new()
+            .use_media(carrier)?
+            .use_metadata_channel(true)
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+
+        assert_eq!(
+            image::open(&secret_media).unwrap().to_rgba8(),
+            image::open(carrier).unwrap().to_rgba8(),
+            "Pixels should be byte identical"
+        );
+        let unveiled = SteganoDecoder::new()
+            .use_media(secret_media.to_str().unwrap())?
+            .use_metadata_channel(true)
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files,
+            vec![(
+                "Blah.txt".to_string(),
+                fs::read("../resources/secrets/Blah.txt")?
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_in_the_first_carrier_candidate_that_fits() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let tiny = out_dir.path().join("tiny.png");
+        RgbaImage::new(4, 4).save(&tiny).unwrap();
+        let secret_media = out_dir.path().join("secret.png");
+
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .carrier_candidates(vec![
+                tiny.to_str().unwrap(),
+                "../resources/plain/carrier-image.png",
+            ])
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(secret_media.to_str().unwrap());
+        assert_eq!(encoder.hide()?.chosen_candidate(), Some(1));
+
+        let unveiled = SteganoDecoder::new()
+            .use_media(secret_media.to_str().unwrap())?
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files,
+            vec![(
+                "Blah.txt".to_string(),
+                fs::read("../resources/secrets/Blah.txt")?
+            )]
+        );
+
+        encoder.carrier_candidates(vec![tiny.to_str().unwrap()]);
+        assert!(matches!(
+            encoder.hide(),
+            Err(SteganoError::NoSuitableCarrier)
+        ));
+        assert_eq!(encoder.chosen_candidate(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_extract_one_file_by_the_file_index() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .with_file_index(true)
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_file("../resources/secrets/random_1666_byte.bin")
+            .hide_to_image()?;
+
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_source(image);
+        assert_eq!(
+            decoder.list_files()?,
+            vec![
+                ("Blah.txt".to_string(), 25),
+                ("random_1666_byte.bin".to_string(), 1666)
+            ]
+        );
+        assert_eq!(
+            decoder.extract_file("random_1666_byte.bin")?,
+            fs::read("../resources/secrets/random_1666_byte.bin")?
+        );
+        assert!(matches!(
+            decoder.extract_file("missing.txt"),
+            Err(SteganoError::FileNotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_extract_one_file_of_a_zip_payload_by_name() -> Result<()> {
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_source(
+            SteganoEncoder::new()
+                .use_media("../resources/plain/carrier-image.png")?
+                .hide_file("../resources/secrets/Blah.txt")
+                .hide_file("../resources/secrets/Blah-2.txt")
+                .hide_to_image()?,
+        );
+
+        assert_eq!(
+            decoder.extract_file("Blah-2.txt")?,
+            fs::read("../resources/secrets/Blah-2.txt")?
+        );
+        assert!(matches!(
+            decoder.extract_file("Blah-3.txt"),
+            Err(SteganoError::FileNotFound(name)) if name == "Blah-3.txt"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_the_distortion_within_a_psnr_target() -> Result<()> {
+        let origin = image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8();
+        let mut encoder = SteganoEncoder::new();
+        let image = encoder
+            .use_source(origin.clone())
+            .max_distortion_psnr(50.0)
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+        assert!(analysis::psnr(&origin, &image) >= 50.0);
+        assert_eq!(encoder.bits_per_channel(), 1);
+        let unveiled = SteganoDecoder::new().use_source(image).unveil_message()?;
+        assert_eq!(unveiled.files[0].0, "Blah.txt");
+
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_source(origin).max_distortion_psnr(50.0);
+        let too_large = analysis::random_payload(encoder.capacity() * 5 / 4);
+        encoder.message.add_file_data("noise.bin", too_large);
+        assert!(matches!(
+            encoder.hide_to_image(),
+            Err(SteganoError::CannotMeetDistortionTarget { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_allow_a_higher_depth_on_a_noisy_carrier() {
+        let smooth = RgbaImage::from_pixel(64, 64, image::Rgba([120, 130, 140, 0xff]));
+        let noisy = RgbaImage::from_raw(64, 64, analysis::random_payload(64 * 64 * 4)).unwrap();
+
+        let smooth_depth = SteganoEncoder::new()
+            .use_source(smooth)
+            .max_imperceptible_depth(40.0);
+        let noisy_depth = SteganoEncoder::new()
+            .use_source(noisy)
+            .max_imperceptible_depth(40.0);
+
+        assert!(smooth_depth >= 1, "smooth carrier allows {smooth_depth}");
+        assert!(
+            noisy_depth > smooth_depth,
+            "noisy {noisy_depth} vs smooth {smooth_depth}"
+        );
+        assert_eq!(SteganoEncoder::new().max_imperceptible_depth(40.0), 0);
+    }
+
+    #[test]
+    fn should_find_the_traversal_order_by_its_preamble() -> Result<()> {
+        for order in [
+            TraversalOrder::RowMajor,
+            TraversalOrder::Reverse,
+            TraversalOrder::Random { seed: 7 },
+            TraversalOrder::RowStride { stride: 2 },
+        ] {
+            let image = SteganoEncoder::new()
+                .use_media("../resources/plain/carrier-image.png")?
+                .traversal_order(order)
+                .hide_file("../resources/secrets/Blah.txt")
+                .hide_to_image()?;
+            assert_eq!(
+                media::image::LsbCodec::read_traversal_preamble(&image),
+                Some(order)
+            );
+
+            let unveiled = SteganoDecoder::new().use_source(image).unveil_message()?;
+            assert_eq!(
+                unveiled.files,
+                vec![(
+                    "Blah.txt".to_string(),
+                    fs::read("../resources/secrets/Blah.txt")?
+                )],
+                "{order:?} should unveil"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_find_the_start_of_a_message_at_an_offset() -> Result<()> {
+        let origin = image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8();
+        let start = 5000;
+        let image = SteganoEncoder::new()
+            .use_source(origin.clone())
+            .start_at_pixel(start)
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+
+        let height = origin.height();
+        let preamble = media::image::LsbCodec::TRAVERSAL_PREAMBLE_PIXELS as u32;
+        for pixel in preamble..start {
+            let (x, y) = (pixel / height, pixel % height);
+            assert_eq!(
+                image.get_pixel(x, y),
+                origin.get_pixel(x, y),
+                "pixel {pixel} before the offset was changed"
+            );
+        }
+        assert_ne!(image, origin);
+
+        let unveiled = SteganoDecoder::new().use_source(image).unveil_message()?;
+        assert_eq!(
+            unveiled.files,
+            vec![(
+                "Blah.txt".to_string(),
+                fs::read("../resources/secrets/Blah.txt")?
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_store_and_recover_a_preview() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("with-preview.png");
+        let thumb = RgbaImage::from_fn(16, 16, |x, y| {
+            image::Rgba([x as u8 * 16, y as u8 * 16, 0x80, 0xff])
+        });
+
+        SteganoCore::encoder()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .embed_preview(&thumb)?
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+
+        let preview = SteganoCore::decoder()
+            .use_media(secret_media.to_str().unwrap())?
+            .preview()?
+            .expect("preview was stored");
+        let preview = image::load_from_memory(&preview).unwrap();
+        assert_eq!((preview.width(), preview.height()), (16, 16));
+
+        let big = RgbaImage::new(640, 320);
+        let mut encoder = SteganoCore::encoder();
+        encoder.embed_preview(&big)?;
+        let preview = image::load_from_memory(encoder.message.preview.as_ref().unwrap()).unwrap();
+        assert_eq!((preview.width(), preview.height()), (64, 32));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_after_a_lossless_re_save_by_another_encoder() -> Result<()> {
+        use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+        use image::ImageEncoder;
+
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("stego.png");
+        SteganoCore::encoder()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+        let stego = image::open(&secret_media).unwrap().to_rgba8();
+
+        let re_saved = out_dir.path().join("re-saved.png");
+        for (compression, filter) in [
+            (CompressionType::Best, FilterType::Paeth),
+            (CompressionType::Fast, FilterType::NoFilter),
+            (CompressionType::Default, FilterType::Adaptive),
+        ] {
+            PngEncoder::new_with_quality(File::create(&re_saved)?, compression, filter)
+                .write_image(
+                    stego.as_raw(),
+                    stego.width(),
+                    stego.height(),
+                    image::ColorType::Rgba8,
+                )
+                .unwrap();
+            let files = SteganoCore::decoder()
+                .use_media(re_saved.to_str().unwrap())?
+                .unveil_message()?
+                .files;
+            assert_eq!(files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+        }
+
+        // the same pixels with 16 bits per channel
+        image::DynamicImage::ImageRgba8(stego)
+            .to_rgba16()
+            .save(&re_saved)
+            .unwrap();
+        let files = SteganoCore::decoder()
+            .use_media(re_saved.to_str().unwrap())?
+            .unveil_message()?
+            .files;
+        assert_eq!(files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+
+        // a 1 bit per pixel bitmap, re-saved as 8 bit RGBA
+        let bitmap = out_dir.path().join("bitmap.png");
+        {
+            let mut encoder = png::Encoder::new(File::create(&bitmap)?, 64, 64);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::One);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0b1010_0110; 8 * 64]).unwrap();
+        }
+        SteganoCore::encoder()
+            .use_media(bitmap.to_str().unwrap())?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+        let re_saved_bitmap = image::open(&secret_media).unwrap().to_rgba8();
+        re_saved_bitmap.save(&re_saved).unwrap();
+        let files = SteganoCore::decoder()
+            .use_media(re_saved.to_str().unwrap())?
+            .read_as_bitmap(true)
+            .unveil_message()?
+            .files;
+        assert_eq!(files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+        let files = SteganoCore::decoder()
+            .read_as_bitmap(true)
+            .use_source(re_saved_bitmap)
+            .unveil_message()?
+            .files;
+        assert_eq!(files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_score_a_noisy_carrier_higher_than_a_solid_one() {
+        let (width, height) = (64, 64);
+        let noise = analysis::random_payload(width as usize * height as usize * 4);
+        let noisy = RgbaImage::from_raw(width, height, noise).unwrap();
+        let solid = RgbaImage::from_pixel(width, height, image::Rgba([0x40, 0x80, 0xc0, 0xff]));
+        let score = |image: RgbaImage| {
+            SteganoCore::encoder()
+                .use_source(image)
+                .hide_message("Hello World!")
+                .carrier_score()
+                .unwrap()
+        };
+
+        let (noisy, solid) = (score(noisy), score(solid));
+        assert!(noisy > solid, "noisy {noisy} <= solid {solid}");
+        assert!((0.0..=100.0).contains(&noisy));
+        assert!(solid < 25.0);
+        assert_eq!(SteganoCore::encoder().carrier_score(), None);
+    }
+
+    #[test]
+    fn should_unveil_from_each_frame_of_a_gif() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier = out_dir.path().join("carrier.gif");
+        {
+            let palette: Vec<u8> = (0..=255).flat_map(|c| [c, c, 0x80]).collect();
+            let mut encoder = gif::Encoder::new(File::create(&carrier)?, 64, 64, &palette).unwrap();
+            for shift in [0, 7] {
+                let pixels: Vec<u8> = (0..64 * 64).map(|i| (i as u8).rotate_left(shift)).collect();
+                let frame = gif::Frame::from_indexed_pixels(64, 64, pixels, None);
+                encoder.write_frame(&frame).unwrap();
+            }
+        }
+        let secret_media = out_dir.path().join("with-secret.gif");
+        SteganoCore::encoder()
+            .use_carrier_gif(carrier.to_str().unwrap())?
+            .embed_per_frame(true)
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+
+        for frame in 0..2 {
+            let files = SteganoCore::decoder()
+                .use_carrier_gif(secret_media.to_str().unwrap())?
+                .gif_frame(frame)
+                .unveil_message()?
+                .files;
+            assert_eq!(files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+        }
+        assert!(matches!(
+            SteganoCore::decoder()
+                .use_carrier_gif(secret_media.to_str().unwrap())?
+                .gif_frame(2)
+                .unveil_message(),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_from_the_indices_of_a_quantized_gif() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier = "../resources/plain/carrier-image.png";
+        let original = image::open(carrier).unwrap().to_rgba8();
+        let quantize = || -> Result<Vec<u8>> {
+            let mut buf = Vec::new();
+            GifImage::quantize(&original)?.encode(&mut buf)?;
+            Ok(buf)
+        };
+        assert_eq!(quantize()?, quantize()?, "the quantizer is deterministic");
+
+        let secret_media = out_dir.path().join("with-secret.gif");
+        SteganoCore::encoder()
+            .use_carrier_quantized_gif(carrier)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+
+        // saved and reloaded once more, the indices stay as they are
+        let resaved = out_dir.path().join("resaved.gif");
+        GifImage::open(&secret_media)?.save_as(&resaved)?;
+        for gif in [secret_media, resaved] {
+            let files = SteganoCore::decoder()
+                .use_carrier_gif(gif.to_str().unwrap())?
+                .unveil_message()?
+                .files;
+            assert_eq!(files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_from_the_preview_of_a_dng() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier = out_dir.path().join("carrier.dng");
+        let raw = analysis::random_payload(64 * 48 * 2 * 4);
+        let original = test_utils::dng_with_preview(64, 48, &raw);
+        fs::write(&carrier, &original)?;
+        let secret_media = out_dir.path().join("with-secret.dng");
+
+        let mut encoder = SteganoCore::encoder();
+        encoder.use_carrier_dng(carrier.to_str().unwrap())?;
+        assert_eq!(encoder.capacity(), 64 * 48 * 3 / 8);
+        encoder
+            .hide_message("shot on raw")
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+
+        let stego = fs::read(&secret_media)?;
+        assert_eq!(stego.len(), original.len());
+        let preview_end = 8 + 64 * 48 * 3;
+        assert_ne!(stego[..preview_end], original[..preview_end]);
+        assert_eq!(stego[preview_end..], original[preview_end..]);
+
+        let unveiled = SteganoCore::decoder()
+            .use_carrier_dng(secret_media.to_str().unwrap())?
+            .unveil_message()?;
+        assert_eq!(unveiled.files[0].1, b"shot on raw".to_vec());
+
+        let without_preview = [&original[..8], &[0; 4]].concat();
+        assert!(DngPreview::decode(without_preview).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_repair_a_corrupted_payload_by_the_parity_of_the_other_carrier() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let path_of = |name: &str| out_dir.path().join(name).to_str().unwrap().to_owned();
+        let (payload_media, parity_media) = (path_of("payload.png"), path_of("parity.png"));
+        SteganoCore::encoder()
+            .use_carrier_pair(
+                "../resources/plain/carrier-image.png",
+                "../resources/plain/carrier-image.png",
+            )?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(&payload_media)
+            .write_parity_to(&parity_media)
+            .hide()?;
+
+        // flips the LSB of the red channel in 10 pixels, that is 1 bit in each of 10 bytes
+        // behind the shard header, in the default column based traversal
+        let mut image = image::open(&payload_media).unwrap().to_rgba8();
+        for y in (30..60).step_by(3) {
+            image.get_pixel_mut(0, y).0[0] ^= 1;
+        }
+        image.save(&payload_media).unwrap();
+
+        let files = SteganoCore::decoder()
+            .use_carrier_pair(&parity_media, &payload_media)?
+            .unveil_message()?
+            .files;
+        assert_eq!(files[0].1, fs::read("../resources/secrets/Blah.txt")?);
+
+        let result = SteganoCore::decoder()
+            .use_carrier_pair(&payload_media, &payload_media)
+            .map(|_| ());
+        assert!(matches!(result, Err(SteganoError::MissingShard(1))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_log_the_steps_of_a_round_trip() -> Result<()> {
+        use std::sync::Mutex;
+
+        /// keeps every log message, the logger is global so it sees other tests too
+        struct CapturingLogger(Mutex<Vec<String>>);
+
+        impl log::Log for CapturingLogger {
+            fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: CapturingLogger = CapturingLogger(Mutex::new(Vec::new()));
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("logged.png");
+        SteganoCore::encoder()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .checksum_algorithm(ChecksumAlgo::Crc32)
+            .write_to(secret_media.to_str().unwrap())
+            .hide()?;
+        SteganoCore::decoder()
+            .use_media(secret_media.to_str().unwrap())?
+            .unveil_message()?;
+
+        let events = LOGGER.0.lock().unwrap().clone();
+        for expected in [
+            "serialized the secret into",
+            "the carrier holds",
+            "hiding",
+            "traversing",
+            "parsing a message of content version 0x05",
+            "header field 0x03",
+            "parsed",
+            "Crc32 checksum of",
+        ] {
+            assert!(
+                events.iter().any(|e| e.starts_with(expected)),
+                "no log event {expected:?} in {events:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_from_a_cropped_copy_via_the_alignment_marker() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .use_alignment_marker(true)
+            .hide_file("../resources/secrets/Blah.txt")
+            .hide_to_image()?;
+        let (width, height) = image.dimensions();
+        let margin = 10;
+        let cropped = image::imageops::crop_imm(
+            &image,
+            margin,
+            margin,
+            width - 2 * margin,
+            height - 2 * margin,
+        )
+        .to_image();
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(cropped.clone())
+            .use_alignment_marker(true)
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files[0].1,
+            fs::read("../resources/secrets/Blah.txt")?
+        );
+        let sequential = SteganoDecoder::new().use_source(cropped).unveil_message();
+        assert!(sequential.map_or(true, |m| m.files != unveiled.files));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_verify_in_memory_before_the_target_is_written() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("verified.png");
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/random_1666_byte.bin")
+            .checksum_algorithm(ChecksumAlgo::Sha256)
+            .verify_after_hide(true);
+        // verified without any file, there is nothing on disk to reload it from
+        let image = encoder.hide_to_image()?;
+        assert!(!secret_media.exists());
+
+        for luminance in [false, true] {
+            SteganoEncoder::new()
+                .use_media(BASE_IMAGE)?
+                .use_luminance(luminance)
+                .hide_file("../resources/secrets/random_1666_byte.bin")
+                .checksum_algorithm(ChecksumAlgo::Sha256)
+                .verify_after_hide(true)
+                .write_to(secret_media.to_str().unwrap())
+                .hide()?;
+            let unveiled = SteganoDecoder::new()
+                .use_media(secret_media.to_str().unwrap())?
+                .use_luminance(luminance)
+                .unveil_message()?;
+            assert!(unveiled.checksum_ok);
+            assert_eq!(
+                unveiled.files[0].1,
+                fs::read("../resources/secrets/random_1666_byte.bin")?
+            );
+        }
+        let unveiled = SteganoDecoder::new().use_source(image).unveil_message()?;
+        assert!(unveiled.checksum_ok);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_each_bit_plane_independently() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("planes.png");
+        let payloads = [
+            (0, analysis::random_payload(1000)),
+            (1, b"the middle layer".to_vec()),
+            (2, fs::read("../resources/secrets/Blah.txt")?),
+        ];
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_media(BASE_IMAGE)?;
+        for (plane, data) in &payloads {
+            encoder.hide_in_plane(*plane, data)?;
+        }
+        encoder.write_to(secret_media.to_str().unwrap()).hide()?;
+
+        let mut decoder = SteganoDecoder::new();
+        decoder.use_media(secret_media.to_str().unwrap())?;
+        for (plane, data) in &payloads {
+            assert_eq!(&decoder.unveil_plane(*plane)?, data, "plane {plane}");
+        }
+        assert!(decoder.unveil_plane(3).is_err());
+        assert!(matches!(
+            SteganoEncoder::new().hide_in_plane(8, b"x").map(|_| ()),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_from_the_luminance_after_the_color_space_conversions() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_media = out_dir.path().join("luminance.png");
+        let mut encoder = SteganoCore::encoder();
+        encoder
+            .use_media("../resources/plain/carrier-image.png")?
+            .use_luminance(true)
+            .hide_file("../resources/secrets/random_1666_byte.bin")
+            .write_to(secret_media.to_str().unwrap());
+        let (width, height) = image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8()
+            .dimensions();
+        assert_eq!(encoder.capacity(), (width * height / 8) as usize);
+        encoder.hide()?;
+
+        let files = SteganoCore::decoder()
+            .use_media(secret_media.to_str().unwrap())?
+            .use_luminance(true)
+            .unveil_message()?
+            .files;
+        assert_eq!(
+            files[0].1,
+            fs::read("../resources/secrets/random_1666_byte.bin")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_list_the_formats_of_this_build() {
+        let carriers = SteganoCore::supported_carrier_formats();
+        let outputs = SteganoCore::supported_output_formats();
+
+        assert!(carriers.contains(&ImageFormat::Png));
+        assert!(outputs.contains(&ImageFormat::Png));
+        assert!(!outputs.contains(&ImageFormat::Jpeg));
+        assert_eq!(
+            carriers.contains(&ImageFormat::WebP),
+            cfg!(feature = "webp")
+        );
+    }
+
+    #[test]
+    fn should_stream_the_same_checksum_as_a_one_shot_hash() -> Result<()> {
+        let carrier = image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8();
+        for algo in [ChecksumAlgo::Crc32, ChecksumAlgo::Sha256] {
+            for bits_per_channel in [1, 2] {
+                let opts = CodecOptions {
+                    bits_per_channel,
+                    ..CodecOptions::default()
+                };
+                let mut message =
+                    message::Message::new_of_files(&["../resources/secrets/Blah.txt".to_string()]);
+                message.header = ContentVersion::V5;
+                message.checksum = algo;
+
+                let (buf, checksum) = message.to_bytes_deferring_checksum()?;
+                let checksum = checksum.expect("a deferred checksum");
+                assert!(buf[checksum.at..].starts_with(&vec![0; algo.digest(&[]).len()]));
+                let mut streamed = Media::Image(carrier.clone());
+                let digest = streamed.hide_bytes_streaming_checksum(&buf, &checksum, &opts)?;
+                assert_eq!(digest, algo.digest(&buf[checksum.payload.clone()]));
+
+                let mut one_shot = Media::Image(carrier.clone());
+                one_shot.hide_bytes(&message.to_bytes()?, &opts)?;
+                let (Media::Image(streamed), Media::Image(one_shot)) = (streamed, one_shot) else {
+                    unreachable!()
+                };
+                assert_eq!(streamed, one_shot, "{algo:?} at {bits_per_channel} bits");
+            }
+        }
+
+        let image = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .hide_file("../resources/secrets/Blah.txt")
+            .checksum_algorithm(ChecksumAlgo::Sha256)
+            .hide_to_image()?;
+        let msg = SteganoDecoder::new().use_source(image).unveil_message()?;
+        assert!(msg.checksum_ok);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_read_the_order_passphrase_from_a_file_or_the_environment() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let passphrase_file = out_dir.path().join("passphrase");
+        fs::write(&passphrase_file, "from a file\n")?;
+        let passphrase_file = passphrase_file.to_str().unwrap();
+        std::env::set_var("STEGANO_TEST_PASSPHRASE", "from the environment");
+
+        let from_file = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .order_passphrase_from_file(passphrase_file)?
+            .add_frame(b"behind a passphrase file")
+            .hide_to_image()?;
+        let unveiled = SteganoDecoder::new()
+            .use_source(from_file.clone())
+            .use_passphrase_ordering("from a file")
+            .unveil_frames()?;
+        assert_eq!(unveiled, vec![b"behind a passphrase file".to_vec()]);
+
+        let from_env = SteganoEncoder::new()
+            .use_media("../resources/plain/carrier-image.png")?
+            .use_passphrase_ordering("from the environment")
+            .add_frame(b"behind a passphrase variable")
+            .hide_to_image()?;
+        let unveiled = SteganoDecoder::new()
+            .use_source(from_env)
+            .order_passphrase_from_env("STEGANO_TEST_PASSPHRASE")?
+            .unveil_frames()?;
+        assert_eq!(unveiled, vec![b"behind a passphrase variable".to_vec()]);
+
+        let unveiled = SteganoDecoder::new()
+            .use_source(from_file)
+            .order_passphrase_from_file(passphrase_file)?
+            .unveil_frames()?;
+        assert_eq!(unveiled, vec![b"behind a passphrase file".to_vec()]);
+        assert!(matches!(
+            SteganoDecoder::new().order_passphrase_from_env("STEGANO_TEST_NO_PASSPHRASE"),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_the_prefix_of_a_payload_that_was_cut_off() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let secret_path = out_dir.path().join("secret.bin");
+        // three quarters of the capacity, so that the payload reaches into the right half
+        let (width, height) = image::open(BASE_IMAGE).unwrap().to_rgba8().dimensions();
+        let secret_len = width as usize * height as usize * 3 / 8 * 3 / 4;
+        fs::write(&secret_path, analysis::random_payload(secret_len))?;
+        let image = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_file(secret_path.to_str().unwrap())
+            .hide_to_image()?;
+
+        let (payload, error) = SteganoDecoder::new()
+            .use_source(image.clone())
+            .unveil_best_effort();
+        assert!(error.is_none(), "{error:?}");
+        assert!(payload.len() > secret_len);
+
+        // the default traversal goes column by column, so the left half holds the first half
+        let cut_off = image::imageops::crop_imm(&image, 0, 0, width / 2, height).to_image();
+        let (partial, error) = SteganoDecoder::new()
+            .use_source(cut_off)
+            .unveil_best_effort();
+        assert!(
+            matches!(
+                error,
+                Some(SteganoError::InvalidFormat(format::FormatError::Truncated(
+                    "payload"
+                )))
+            ),
+            "{error:?}"
+        );
+        assert!(!partial.is_empty() && partial.len() < payload.len());
+        assert_eq!(partial, payload[..partial.len()]);
+
+        let (nothing, error) = SteganoDecoder::new().unveil_best_effort();
+        assert!(nothing.is_empty());
+        assert!(matches!(error, Some(SteganoError::NoSecretData)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_recover_the_prioritized_file_of_a_payload_that_was_cut_off() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let (width, height) = image::open(BASE_IMAGE).unwrap().to_rgba8().dimensions();
+        let file_len = width as usize * height as usize * 3 / 8 / 3;
+        let bulk_path = out_dir.path().join("bulk.bin");
+        let key_path = out_dir.path().join("key.bin");
+        let key = analysis::random_payload(file_len);
+        fs::write(&bulk_path, analysis::random_payload(file_len))?;
+        fs::write(&key_path, &key)?;
+
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_media(BASE_IMAGE)?
+            .hide_file(bulk_path.to_str().unwrap())
+            .hide_file(key_path.to_str().unwrap());
+        assert!(matches!(
+            encoder.prioritize_file("missing.bin").err(),
+            Some(SteganoError::FileNotFound(_))
+        ));
+        let image = encoder.prioritize_file("key.bin")?.hide_to_image()?;
+
+        // the default traversal goes column by column, so the left half holds the first half
+        let cut_off = image::imageops::crop_imm(&image, 0, 0, width / 2, height).to_image();
+        let (partial, error) = SteganoDecoder::new()
+            .use_source(cut_off)
+            .unveil_best_effort();
+        assert!(error.is_some());
+
+        let mut zip = std::io::Cursor::new(partial);
+        let mut first = zip::read::read_zipfile_from_stream(&mut zip)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.name(), "key.bin");
+        let mut recovered = Vec::new();
+        first.read_to_end(&mut recovered)?;
+        assert_eq!(recovered, key);
+        drop(first);
+
+        let mut rest = Vec::new();
+        let second = zip::read::read_zipfile_from_stream(&mut zip)
+            .ok()
+            .flatten()
+            .map(|mut file| file.read_to_end(&mut rest));
+        assert!(
+            !matches!(second, Some(Ok(len)) if len == file_len),
+            "the other file was cut off"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_by_lsb_matching_with_less_chi_square_suspicion() -> Result<()> {
+        let carrier = image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8();
+        let out_dir = TempDir::new()?;
+        let secret_path = out_dir.path().join("secret.bin");
+        fs::write(&secret_path, analysis::random_payload(20_000))?;
+        let hidden_bits = 20_000 * 8;
+        let hide = |matching| {
+            SteganoEncoder::new()
+                .use_source(carrier.clone())
+                .use_lsb_matching(matching)
+                .hide_file(secret_path.to_str().unwrap())
+                .hide_to_image()
+        };
+        let (replaced, matched) = (hide(false)?, hide(true)?);
+
+        let msg = SteganoDecoder::new()
+            .use_source(matched.clone())
+            .unveil_message()?;
+        assert_eq!(msg.files[0].1, fs::read(&secret_path)?);
+        let changes = media::image::lsb_codec::channel_change_report(&carrier, &matched);
+        assert!(Channel::RGB
+            .iter()
+            .any(|c| changes.channel(*c).changed_bit_planes().len() > 1));
+
+        // only the region with the secret, the untouched rest would dilute the score
+        let score = |image: &RgbaImage| {
+            let values = analysis::carrier_values(image, &CodecOptions::default());
+            analysis::chi_square_score(values[..hidden_bits].iter().copied())
+        };
+        assert!(
+            score(&matched) < score(&replaced),
+            "matching scored {}, replacing {}",
+            score(&matched),
+            score(&replaced)
+        );
+        assert!(matches!(
+            SteganoEncoder::new()
+                .use_source(carrier)
+                .use_lsb_matching(true)
+                .bind_cover_hash(true)
+                .hide_message("bound")
+                .hide_to_image(),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_flag_the_file_that_does_not_fit_anymore() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_source(RgbaImage::new(128, 128));
+        let capacity = encoder.capacity();
+        for (name, len) in [
+            ("a.bin", capacity / 4),
+            ("b.bin", capacity / 2),
+            ("c.bin", capacity / 2),
+        ] {
+            let path = out_dir.path().join(name);
+            fs::write(&path, analysis::random_payload(len))?;
+            encoder.hide_file(path.to_str().unwrap());
         }
 
-        assert_eq!(color, 9);
+        let breakdown = encoder.capacity_breakdown();
+        assert_eq!(breakdown.capacity, capacity);
+        assert!(!breakdown.fits);
+        let names: Vec<&str> = breakdown.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["a.bin", "b.bin", "c.bin"]);
+        let fits: Vec<bool> = breakdown.files.iter().map(|f| f.fits).collect();
+        assert_eq!(fits, [true, true, false]);
+        for file in &breakdown.files[1..] {
+            assert!(
+                file.needed > capacity / 2,
+                "{file:?} lacks the zip overhead"
+            );
+        }
+        assert_eq!(
+            breakdown.files.last().unwrap().cumulative,
+            encoder.message.payload_len()
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn should_accept_a_png_as_target_file() {
-        SteganoEncoder::new().write_to("/tmp/out-test-image.png");
+    fn should_write_the_same_normalized_file_for_carriers_with_other_metadata() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let pixels = image::open("../resources/plain/carrier-image.png")
+            .unwrap()
+            .to_rgba8();
+        let carrier = |name: &str, text: &str| -> Result<String> {
+            let mut png = Vec::new();
+            {
+                let mut encoder = png::Encoder::new(&mut png, pixels.width(), pixels.height());
+                encoder.set_color(png::ColorType::Rgba);
+                encoder
+                    .add_text_chunk("Comment".to_owned(), text.to_owned())
+                    .unwrap();
+                let mut writer = encoder.write_header().unwrap();
+                writer.write_image_data(pixels.as_raw()).unwrap();
+            }
+            let path = out_dir.path().join(name);
+            fs::write(&path, test_utils::with_exif_orientation(&png, 1))?;
+
+            Ok(path.to_str().unwrap().to_owned())
+        };
+        let hide = |carrier: &str, target: &str| -> Result<Vec<u8>> {
+            let target = out_dir.path().join(target);
+            SteganoEncoder::new()
+                .normalize_output(true)
+                .use_media(carrier)?
+                .hide_file("../resources/secrets/Blah.txt")
+                .write_to(target.to_str().unwrap())
+                .hide()?;
+
+            Ok(fs::read(target)?)
+        };
+
+        let first = hide(&carrier("a.png", "first")?, "first.png")?;
+        // the zip entries would carry the time of hiding, in steps of 2 seconds
+        std::thread::sleep(std::time::Duration::from_millis(2100));
+        let second = hide(&carrier("b.png", "second")?, "second.png")?;
+        assert_eq!(first, second);
+
+        for chunk in [b"tEXt", b"eXIf"] {
+            assert!(!first.windows(4).any(|w| w == chunk), "{chunk:?} was kept");
+        }
+        let unveiled = SteganoDecoder::new()
+            .use_source(image::load_from_memory(&first).unwrap().to_rgba8())
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files[0].1,
+            fs::read("../resources/secrets/Blah.txt")?
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn should_hide_and_unveil_one_text_file_in_wav() -> Result<()> {
-        let out_dir = TempDir::new()?;
-        let secret_media_p = out_dir.path().join("secret.wav");
-        let secret_media_f = secret_media_p.to_str().unwrap();
+    fn should_unveil_each_image_of_an_archive() -> Result<()> {
+        use std::io::Write;
 
+        let out_dir = TempDir::new()?;
+        let stego = out_dir.path().join("stego.png");
         SteganoEncoder::new()
-            .hide_file("Cargo.toml")
-            .use_media("../resources/plain/carrier-audio.wav")?
-            .write_to(secret_media_f)
-            .hide();
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(stego.to_str().unwrap())
+            .hide()?;
+        let entries = [
+            ("images/stego.png", fs::read(&stego)?),
+            (
+                "images/clean.png",
+                fs::read("../resources/plain/carrier-image.png")?,
+            ),
+        ];
 
-        let l = fs::metadata(secret_media_p.as_path())
-            .expect("Secret media was not written.")
-            .len();
-        assert!(l > 0, "File is not supposed to be empty");
+        let zip_path = out_dir.path().join("images.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path)?);
+        for (name, buf) in entries.iter() {
+            zip.start_file(*name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(buf)?;
+        }
+        zip.finish().unwrap();
+        let tar_path = out_dir.path().join("images.tar");
+        let mut tar = tar::Builder::new(File::create(&tar_path)?);
+        for (name, buf) in entries.iter() {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(buf.len() as u64);
+            header.set_cksum();
+            tar.append_data(&mut header, name, buf.as_slice())?;
+        }
+        tar.finish()?;
 
-        unveil(
-            secret_media_p.as_path(),
-            out_dir.path(),
-            &CodecOptions::default(),
+        for archive in [zip_path, tar_path] {
+            let results = SteganoCore::unveil_from_archive(archive.to_str().unwrap())?;
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].0, "images/stego.png");
+            let unveiled = results[0].1.as_ref().expect("the stego image carries data");
+            let mut bundle = zip::ZipArchive::new(std::io::Cursor::new(unveiled)).unwrap();
+            let mut secret = Vec::new();
+            bundle
+                .by_name("Blah.txt")
+                .unwrap()
+                .read_to_end(&mut secret)?;
+            assert_eq!(secret, fs::read("../resources/secrets/Blah.txt")?);
+            assert_eq!(results[1].0, "images/clean.png");
+            assert!(
+                matches!(results[1].1, Err(SteganoError::NoSecretData)),
+                "{:?}",
+                results[1].1
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_transfer_a_secret_to_another_carrier() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier_a = out_dir.path().join("a.png");
+        let carrier_b = out_dir.path().join("b.png");
+        SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_file("../resources/secrets/Blah.txt")
+            .write_to(carrier_a.to_str().unwrap())
+            .hide()?;
+
+        SteganoCore::transfer(
+            carrier_a.to_str().unwrap(),
+            "../resources/plain/carrier-image.png",
+            carrier_b.to_str().unwrap(),
         )?;
 
-        let given_decoded_secret = out_dir.path().join("Cargo.toml");
-        assert_eq_file_content(
-            &given_decoded_secret,
-            "Cargo.toml".as_ref(),
-            "Unveiled data did not match expected",
+        let unveiled = SteganoDecoder::new()
+            .use_media(carrier_b.to_str().unwrap())?
+            .unveil_message()?;
+        assert_eq!(
+            unveiled.files,
+            vec![(
+                "Blah.txt".to_string(),
+                fs::read("../resources/secrets/Blah.txt")?
+            )]
         );
 
+        let tiny = out_dir.path().join("tiny.png");
+        RgbaImage::new(4, 4).save(&tiny).unwrap();
+        assert!(matches!(
+            SteganoCore::transfer(
+                carrier_a.to_str().unwrap(),
+                tiny.to_str().unwrap(),
+                out_dir.path().join("never.png").to_str().unwrap(),
+            ),
+            Err(SteganoError::CarrierTooSmall { capacity: 6, .. })
+        ));
+
         Ok(())
     }
 
     #[test]
-    fn should_hide_and_unveil_one_text_file() -> Result<()> {
-        let out_dir = TempDir::new()?;
-        let image_with_secret_path = out_dir.path().join("secret.png");
-        let image_with_secret = image_with_secret_path.to_str().unwrap();
+    fn should_unveil_with_the_right_passphrase_ordering_only() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_passphrase_ordering("right")
+            .use_media(BASE_IMAGE)?
+            .add_frame(b"only for the right passphrase")
+            .hide_to_image()?;
 
-        SteganoEncoder::new()
-            .hide_file("Cargo.toml")
-            .use_media("../resources/with_text/hello_world.png")?
-            .write_to(image_with_secret)
-            .hide();
+        let wrong = SteganoDecoder::new()
+            .use_passphrase_ordering("wrong")
+            .use_source(image.clone())
+            .unveil_frames();
+        assert!(
+            !matches!(wrong, Ok(frames) if frames == vec![b"only for the right passphrase".to_vec()])
+        );
 
-        let l = fs::metadata(image_with_secret)
-            .expect("Output image was not written.")
-            .len();
-        assert!(l > 0, "File is not supposed to be empty");
+        let right = SteganoDecoder::new()
+            .use_passphrase_ordering("right")
+            .use_source(image)
+            .unveil_frames()?;
+        assert_eq!(right, vec![b"only for the right passphrase".to_vec()]);
 
-        unveil(
-            image_with_secret_path.as_path(),
-            out_dir.path(),
-            &CodecOptions::default(),
-        )?;
+        Ok(())
+    }
 
-        let given_decoded_secret = out_dir.path().join("Cargo.toml");
-        assert_eq_file_content(
-            &given_decoded_secret,
-            "Cargo.toml".as_ref(),
-            "Unveiled data did not match expected",
+    #[test]
+    fn should_report_the_settings_a_message_was_hidden_with() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .use_bit_depth(2)?
+            .traversal_order(TraversalOrder::RowMajor)
+            .channel_order(ChannelOrder::Bgra)
+            .checksum_algorithm(ChecksumAlgo::Crc32)
+            .with_fec(4)
+            .authenticate_with_key(b"settings key")
+            .kdf_params(20_000, 0)?
+            .compress(false)
+            .hide_message("how was I hidden?")
+            .hide_to_image()?;
+
+        let settings = SteganoDecoder::new().use_source(image).settings()?;
+        assert_eq!(
+            settings,
+            EmbeddingSettings {
+                version: ContentVersion::V5,
+                bit_depths: [2, 2, 2],
+                channel_order: ChannelOrder::Bgra,
+                traversal: Some(TraversalOrder::RowMajor),
+                compressed: false,
+                authenticated: true,
+                kdf_iterations: Some(20_000),
+                signed: false,
+                checksum: ChecksumAlgo::Crc32,
+                fec_redundancy: Some(4),
+                checkerboard: false,
+            }
         );
 
+        let image = SteganoEncoder::new()
+            .use_media(BASE_IMAGE)?
+            .hide_message("hidden by default")
+            .hide_to_image()?;
+        let settings = SteganoDecoder::new().use_source(image).settings()?;
+        assert_eq!(settings.version, ContentVersion::V4);
+        assert_eq!(settings.bit_depths, [1, 1, 1]);
+        assert_eq!(settings.traversal, None);
+        assert!(settings.compressed);
+        assert!(!settings.authenticated);
+
         Ok(())
     }
 
     #[test]
-    fn should_raw_unveil_a_message() -> Result<()> {
+    fn should_describe_a_message_with_two_files() -> Result<()> {
+        let image = SteganoEncoder::new()
+            .hide_files(vec![
+                "../resources/secrets/Blah.txt",
+                "../resources/secrets/random_1666_byte.bin",
+            ])
+            .checksum_algorithm(ChecksumAlgo::Crc32)
+            .set_comment("two of them")
+            .use_media(BASE_IMAGE)?
+            .hide_to_image()?;
+
+        let description = SteganoDecoder::new().use_source(image).describe()?;
+        assert_eq!(description.version, ContentVersion::V5);
+        assert!(description.compressed);
+        assert!(!description.authenticated);
+        assert_eq!(
+            description.files,
+            vec![
+                ("Blah.txt".to_string(), 25),
+                ("random_1666_byte.bin".to_string(), 1666)
+            ]
+        );
+        assert_eq!(description.header_fields, vec!["checksum", "comment"]);
+        assert!(description.checksum_ok);
+
+        let tree = description.to_string();
+        assert!(tree.starts_with("message of content version 0x05\n"));
+        assert!(tree.contains("  checksum: Crc32, ok\n"));
+        assert!(tree.contains(
+            "  files: 2\n    Blah.txt: 25 bytes\n    random_1666_byte.bin: 1666 bytes\n"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_unveil_synced_to_disk() -> Result<()> {
         let out_dir = TempDir::new()?;
-        let expected_file = out_dir.path().join("hello_world.bin");
-        let raw_decoded_secret = expected_file.to_str().unwrap();
+        let image_with_secret_path = out_dir.path().join("synced.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
 
-        unveil_raw(
-            Path::new("../resources/with_text/hello_world.png"),
-            expected_file.as_path(),
-        )?;
+        SteganoEncoder::new()
+            .hide_file("../resources/secrets/Blah.txt")
+            .use_media(BASE_IMAGE)?
+            .write_to(image_with_secret)
+            .hide()?;
 
-        let l = fs::metadata(raw_decoded_secret)
-            .expect("Output file was not written.")
-            .len();
+        let manifest = SteganoDecoder::new()
+            .use_media(image_with_secret)?
+            .sync_output(true)
+            .unveil_with_manifest(out_dir.path().to_str().unwrap())?;
 
-        // TODO content verification needs to be done as well
-        assert_ne!(l, 0, "Output raw data file was empty.");
+        assert_eq!(manifest.len(), 1);
+        assert_eq!(fs::metadata(&manifest[0].path)?.len(), 25);
 
         Ok(())
     }
 
     #[test]
-    fn should_hide_and_unveil_a_binary_file() -> Result<()> {
+    fn should_unveil_two_files_with_manifest() -> Result<()> {
         let out_dir = TempDir::new()?;
-        let secret_to_hide = "../resources/secrets/random_1666_byte.bin";
-        let image_with_secret_path = out_dir.path().join("random_1666_byte.bin.png");
+        let image_with_secret_path = out_dir.path().join("two-files.png");
         let image_with_secret = image_with_secret_path.to_str().unwrap();
-        let expected_file = out_dir.path().join("random_1666_byte.bin");
+        let unveil_dir = out_dir.path().join("unveiled");
+        fs::create_dir(&unveil_dir)?;
 
         SteganoEncoder::new()
-            .hide_file(secret_to_hide)
+            .hide_files(vec![
+                "../resources/secrets/Blah.txt",
+                "../resources/secrets/random_1666_byte.bin",
+            ])
             .use_media(BASE_IMAGE)?
             .write_to(image_with_secret)
-            .hide();
+            .hide()?;
 
-        let l = fs::metadata(image_with_secret)
-            .expect("Output image was not written.")
-            .len();
-        assert!(l > 0, "File is not supposed to be empty");
+        let manifest = SteganoDecoder::new()
+            .use_media(image_with_secret)?
+            .unveil_with_manifest(unveil_dir.to_str().unwrap())?;
 
-        unveil(
-            image_with_secret_path.as_path(),
-            out_dir.path(),
-            &CodecOptions::default(),
-        )?;
-        assert_eq_file_content(
-            &expected_file,
-            secret_to_hide.as_ref(),
-            "Unveiled data did not match expected",
+        assert_eq!(
+            manifest,
+            vec![
+                ExtractedFile {
+                    name: "Blah.txt".to_string(),
+                    size: 25,
+                    path: unveil_dir.join("Blah.txt"),
+                },
+                ExtractedFile {
+                    name: "random_1666_byte.bin".to_string(),
+                    size: 1666,
+                    path: unveil_dir.join("random_1666_byte.bin"),
+                },
+            ]
         );
+        for file in manifest {
+            assert_eq!(fs::metadata(&file.path)?.len(), file.size);
+        }
 
         Ok(())
     }
 
     #[test]
-    fn should_hide_and_unveil_a_zip_file() -> Result<()> {
+    fn should_estimate_capacity_under_detectability() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_media(BASE_IMAGE)?;
+
+        let capacity = encoder.capacity_under_detectability(0.1);
+        assert!(capacity > 0);
+        assert!(capacity <= encoder.capacity());
+
+        let mut image = image::open(BASE_IMAGE).unwrap().to_rgba8();
+        let opts = CodecOptions::default();
+        media::image::LsbCodec::encoder(&mut image, &opts)
+            .write_all(&analysis::random_payload(capacity))?;
+        let score = analysis::chi_square_score(analysis::carrier_values(&image, &opts));
+        assert!(score < 0.1, "score was {score}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_grow_more_suspicious_with_the_payload() -> Result<()> {
+        let mut encoder = SteganoEncoder::new();
+        encoder.use_media(BASE_IMAGE)?;
+
+        let curve = encoder.tradeoff_curve();
+        assert_eq!(curve.len(), 17);
+        assert_eq!(curve[0].0, 0);
+        assert_eq!(curve[16].0, encoder.capacity());
+        for pair in curve.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{curve:?}");
+            assert!(pair[0].1 <= pair[1].1, "{curve:?}");
+        }
+        assert!(curve[16].1 > 0.9, "a full carrier is suspicious: {curve:?}");
+        assert!(SteganoEncoder::new().tradeoff_curve().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_and_unveil_in_the_palette_of_an_indexed_png() -> Result<()> {
         let out_dir = TempDir::new()?;
-        let secret_to_hide = "../resources/secrets/zip_with_2_files.zip";
-        let image_with_secret_path = out_dir.path().join("zip_with_2_files.zip.png");
+        let carrier_path = out_dir.path().join("indexed.png");
+        let image_with_secret_path = out_dir.path().join("indexed-with-secret.png");
         let image_with_secret = image_with_secret_path.to_str().unwrap();
-        let expected_file = out_dir.path().join("zip_with_2_files.zip");
+        {
+            let file = fs::File::create(&carrier_path)?;
+            let mut encoder = png::Encoder::new(file, 16, 16);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(
+                (0..=255u8)
+                    .flat_map(|c| [c, 255 - c, c / 2])
+                    .collect::<Vec<_>>(),
+            );
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&(0..=255u8).collect::<Vec<_>>())
+                .unwrap();
+        }
 
         SteganoEncoder::new()
-            .hide_file(secret_to_hide)
-            .use_media(BASE_IMAGE)?
+            .use_palette_embedding(true)
+            .use_media(carrier_path.to_str().unwrap())?
+            .add_frame(b"Hello palette")
             .write_to(image_with_secret)
-            .hide();
+            .hide()?;
 
-        assert_file_not_empty(image_with_secret);
+        let original = PaletteImage::open(&carrier_path)?.unwrap();
+        let with_secret =
+            PaletteImage::open(&image_with_secret_path)?.expect("target is still an indexed image");
+        assert_eq!(with_secret.indices(), original.indices());
+        assert_ne!(with_secret.palette(), original.palette());
 
-        unveil(
-            image_with_secret_path.as_path(),
-            out_dir.path(),
-            &CodecOptions::default(),
-        )?;
+        let frames = SteganoDecoder::new()
+            .use_palette_embedding(true)
+            .use_media(image_with_secret)?
+            .unveil_frames()?;
+        assert_eq!(frames, vec![b"Hello palette".to_vec()]);
 
-        assert_eq_file_content(
-            &expected_file,
-            secret_to_hide.as_ref(),
-            "Unveiled data did not match expected",
-        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_fail_when_the_secret_does_not_fit_into_the_palette() -> Result<()> {
+        let out_dir = TempDir::new()?;
+        let carrier_path = out_dir.path().join("indexed.png");
+        let image_with_secret_path = out_dir.path().join("indexed-with-secret.png");
+        {
+            let file = fs::File::create(&carrier_path)?;
+            let mut encoder = png::Encoder::new(file, 16, 16);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(
+                (0..=255u8)
+                    .flat_map(|c| [c, 255 - c, c / 2])
+                    .collect::<Vec<_>>(),
+            );
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&(0..=255u8).collect::<Vec<_>>())
+                .unwrap();
+        }
+
+        let mut encoder = SteganoEncoder::new();
+        encoder
+            .use_palette_embedding(true)
+            .use_media(carrier_path.to_str().unwrap())?;
+        assert_eq!(encoder.capacity(), 256 * 3 / 8);
+
+        let result = encoder
+            .add_frame(&[0xaa; 256 * 3 / 8])
+            .write_to(image_with_secret_path.to_str().unwrap())
+            .hide()
+            .map(|_| ());
+
+        assert!(matches!(
+            result,
+            Err(SteganoError::CarrierTooSmall { capacity: 96, .. })
+        ));
+        assert!(!image_with_secret_path.exists());
 
         Ok(())
     }
 
     #[test]
-    fn should_ensure_content_v2_compatibility() -> Result<()> {
+    fn should_hide_and_unveil_in_the_spare_slots_of_an_indexed_png() -> Result<()> {
         let out_dir = TempDir::new()?;
-        let decoded_secret = out_dir.path().join("Blah.txt");
+        let carrier_path = out_dir.path().join("indexed.png");
+        let image_with_secret_path = out_dir.path().join("indexed-with-secret.png");
+        let image_with_secret = image_with_secret_path.to_str().unwrap();
+        {
+            let file = fs::File::create(&carrier_path)?;
+            let mut encoder = png::Encoder::new(file, 16, 16);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(
+                (0..16u8)
+                    .flat_map(|c| [c * 16, 0, 255 - c])
+                    .collect::<Vec<_>>(),
+            );
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&(0..=255u8).map(|i| i % 16).collect::<Vec<_>>())
+                .unwrap();
+        }
 
-        unveil(
-            Path::new("../resources/with_attachment/Blah.txt.png"),
-            out_dir.path(),
-            &CodecOptions::default(),
-        )?;
+        SteganoEncoder::new()
+            .use_spare_palette_slots(true)
+            .use_media(carrier_path.to_str().unwrap())?
+            .hide_message("in the spare slots")
+            .write_to(image_with_secret)
+            .hide()?;
 
-        assert_eq_file_content(
-            &decoded_secret,
-            "../resources/secrets/Blah.txt".as_ref(),
-            "Unveiled data did not match expected",
-        );
+        let original = PaletteImage::open(&carrier_path)?.unwrap();
+        let with_secret =
+            PaletteImage::open(&image_with_secret_path)?.expect("target is still an indexed image");
+        assert_eq!(with_secret.indices(), original.indices());
+        assert_eq!(&with_secret.palette()[..16 * 3], original.palette());
+        assert_eq!(with_secret.to_rgba(), original.to_rgba());
+
+        let text = SteganoDecoder::new()
+            .use_spare_palette_slots(true)
+            .use_media(image_with_secret)?
+            .unveil_text()?;
+        assert_eq!(text, "in the spare slots");
 
         Ok(())
     }
 
     #[test]
-    fn should_ensure_content_v2_compatibility_with_2_files_reading() -> Result<()> {
+    fn should_fail_palette_embedding_without_palette() -> Result<()> {
         let out_dir = TempDir::new()?;
-        let decoded_secret_1 = out_dir.path().join("Blah.txt");
-        let decoded_secret_2 = out_dir.path().join("Blah-2.txt");
+        let image_with_secret_path = out_dir.path().join("no-palette.png");
 
-        unveil(
-            Path::new("../resources/with_attachment/Blah.txt__and__Blah-2.txt.png"),
-            out_dir.path(),
-            &CodecOptions::default(),
-        )?;
-        assert_eq_file_content(
-            &decoded_secret_1,
-            "../resources/secrets/Blah.txt".as_ref(),
-            "Unveiled data file #1 did not match expected",
-        );
+        let result = SteganoEncoder::new()
+            .use_palette_embedding(true)
+            .use_media(BASE_IMAGE)?
+            .add_frame(b"Hello palette")
+            .write_to(image_with_secret_path.to_str().unwrap())
+            .hide()
+            .map(|_| ());
 
-        assert_eq_file_content(
-            &decoded_secret_2,
-            "../resources/secrets/Blah-2.txt".as_ref(),
-            "Unveiled data file #2 did not match expected",
-        );
+        assert!(matches!(result, Err(SteganoError::UnsupportedMedia)));
+        assert!(!image_with_secret_path.exists());
 
         Ok(())
     }
 
     #[test]
-    fn should_ensure_content_v2_compatibility_with_2_files_writing() -> Result<()> {
+    fn should_skip_a_preamble_in_front_of_the_payload() -> Result<()> {
+        let mut buf = vec![0xde, 0xad, 0xbe, 0xef];
+        buf.extend(Vec::<u8>::from(&FramedMessage::new(vec![
+            b"after the preamble".to_vec(),
+        ])));
+        let mut media = Media::from_file(BASE_IMAGE.as_ref())?;
+        media.hide_bytes(&buf, &CodecOptions::default())?;
+
+        let frames = SteganoDecoder::new()
+            .use_source(media)
+            .skip_prefix(4)
+            .unveil_frames()?;
+        assert_eq!(frames, vec![b"after the preamble".to_vec()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_hide_and_unveil_frames_in_order() -> Result<()> {
         let out_dir = TempDir::new()?;
-        let image_with_secret_path = out_dir.path().join("Blah.txt.png");
+        let image_with_secret_path = out_dir.path().join("frames.png");
         let image_with_secret = image_with_secret_path.to_str().unwrap();
-        let secret_to_hide = "../resources/secrets/Blah.txt";
 
         SteganoEncoder::new()
-            .force_content_version(ContentVersion::V2)
             .use_media(BASE_IMAGE)?
-            .hide_file(secret_to_hide)
+            .add_frame(b"first entry")
+            .add_frame(&[0xde, 0xad, 0xbe, 0xef])
+            .add_frame(b"third and last entry")
             .write_to(image_with_secret)
-            .hide();
+            .hide()?;
 
-        assert_file_not_empty(image_with_secret);
-
-        unveil(
-            image_with_secret_path.as_path(),
-            out_dir.path(),
-            &CodecOptions::default(),
-        )?;
+        let frames = SteganoDecoder::new()
+            .use_media(image_with_secret)?
+            .unveil_frames()?;
 
-        let decoded_secret = out_dir.path().join("Blah.txt");
-        assert_eq_file_content(
-            decoded_secret.as_ref(),
-            secret_to_hide.as_ref(),
-            "Unveiled data file did not match expected",
+        assert_eq!(
+            frames,
+            vec![
+                b"first entry".to_vec(),
+                vec![0xde, 0xad, 0xbe, 0xef],
+                b"third and last entry".to_vec(),
+            ]
         );
 
         Ok(())
@@ -714,4 +6948,77 @@ mod test_utils {
             image::Rgba([i, i + 1, i + 2, i + 3])
         })
     }
+
+    /// a minimal little endian DNG, IFD 0 is an uncompressed 8 bit RGB preview of the given size,
+    /// its sub IFD the 16 bit `raw` sensor data
+    #[cfg(feature = "fs")]
+    pub fn dng_with_preview(width: u32, height: u32, raw: &[u8]) -> Vec<u8> {
+        fn entry(ifd: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32) {
+            ifd.extend(tag.to_le_bytes());
+            ifd.extend(kind.to_le_bytes());
+            ifd.extend(count.to_le_bytes());
+            ifd.extend(value.to_le_bytes());
+        }
+        const SHORT: u16 = 3;
+        const LONG: u16 = 4;
+
+        let preview: Vec<u8> = (0..width * height * 3).map(|i| (i % 251) as u8).collect();
+        let preview_at = 8;
+        let bits_at = preview_at + preview.len();
+        let raw_at = bits_at + 6;
+        let ifd0_at = raw_at + raw.len();
+        let raw_ifd_at = ifd0_at + 2 + 11 * 12 + 4;
+
+        let mut ifd0 = 11u16.to_le_bytes().to_vec();
+        entry(&mut ifd0, 254, LONG, 1, 1);
+        entry(&mut ifd0, 256, LONG, 1, width);
+        entry(&mut ifd0, 257, LONG, 1, height);
+        entry(&mut ifd0, 258, SHORT, 3, bits_at as u32);
+        entry(&mut ifd0, 259, SHORT, 1, 1);
+        entry(&mut ifd0, 262, SHORT, 1, 2);
+        entry(&mut ifd0, 273, LONG, 1, preview_at as u32);
+        entry(&mut ifd0, 277, SHORT, 1, 3);
+        entry(&mut ifd0, 279, LONG, 1, preview.len() as u32);
+        entry(&mut ifd0, 330, LONG, 1, raw_ifd_at as u32);
+        // DNGVersion 1.4.0.0
+        entry(&mut ifd0, 50706, 1, 4, u32::from_le_bytes([1, 4, 0, 0]));
+        ifd0.extend(0u32.to_le_bytes());
+
+        let mut raw_ifd = 9u16.to_le_bytes().to_vec();
+        entry(&mut raw_ifd, 254, LONG, 1, 0);
+        entry(&mut raw_ifd, 256, LONG, 1, width * 2);
+        entry(&mut raw_ifd, 257, LONG, 1, height * 2);
+        entry(&mut raw_ifd, 258, SHORT, 1, 16);
+        entry(&mut raw_ifd, 259, SHORT, 1, 1);
+        // color filter array
+        entry(&mut raw_ifd, 262, SHORT, 1, 32803);
+        entry(&mut raw_ifd, 273, LONG, 1, raw_at as u32);
+        entry(&mut raw_ifd, 277, SHORT, 1, 1);
+        entry(&mut raw_ifd, 279, LONG, 1, raw.len() as u32);
+        raw_ifd.extend(0u32.to_le_bytes());
+
+        let mut dng = b"II*\0".to_vec();
+        dng.extend((ifd0_at as u32).to_le_bytes());
+        dng.extend(preview);
+        dng.extend([8, 0, 8, 0, 8, 0]);
+        dng.extend_from_slice(raw);
+        dng.extend(ifd0);
+        dng.extend(raw_ifd);
+
+        dng
+    }
+
+    /// inserts an `eXIf` chunk with the given orientation right behind the `IHDR` chunk of a PNG
+    pub fn with_exif_orientation(png: &[u8], orientation: u8) -> Vec<u8> {
+        const IHDR_END: usize = 8 + 25;
+        let mut exif = b"MM\0*\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&[0, orientation, 0, 0, 0, 0, 0, 0]);
+
+        let mut chunk = (exif.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(b"eXIf");
+        chunk.extend_from_slice(&exif);
+        chunk.extend_from_slice(&crc32fast::hash(&chunk[4..]).to_be_bytes());
+
+        [&png[..IHDR_END], &chunk, &png[IHDR_END..]].concat()
+    }
 }
@@ -0,0 +1,189 @@
+use image::RgbaImage;
+use std::io::{Cursor, Read};
+
+use crate::format::{ALIGNMENT_MARGIN, ALIGNMENT_MARKER};
+use crate::{Result, SteganoError};
+
+/// length of the marker with the width and height of the region that follows, in bytes
+const MARKER_LEN: usize = ALIGNMENT_MARKER.len() + 8;
+
+/// number of pixels of a row that carry the marker, 3 bits each
+const MARKER_PIXELS: u32 = (MARKER_LEN as u32 * 8).div_ceil(3);
+
+/// Hides behind an alignment marker, so that the secret survives a crop of up to
+/// [`ALIGNMENT_MARGIN`] pixels on each side, e.g. of a screenshot. The marker is the
+/// [`ALIGNMENT_MARKER`] and the `u32` little endian width and height of the region, in the LSBs
+/// of red, green and blue of the pixels of row `ALIGNMENT_MARGIN` from column `ALIGNMENT_MARGIN`
+/// on. The secret follows in the region below it, row by row, the margin stays untouched.
+/// The decoder scans for the marker, so the origin of the region is found again in the crop.
+/// The capacity is the pixels of the region * 3 / 8 bytes.
+///
+/// ## Example of unveiling from a cropped image
+/// ```rust
+/// use stegano_core::media::image::alignment;
+/// use image::{imageops, Rgba, RgbaImage};
+/// use std::io::Read;
+///
+/// let mut image = RgbaImage::from_pixel(96, 64, Rgba([200, 120, 40, 0xff]));
+/// alignment::hide(&mut image, b"Hi").unwrap();
+///
+/// let cropped = imageops::crop_imm(&image, 5, 9, 80, 50).to_image();
+/// let mut buf = [0; 2];
+/// alignment::decoder(&cropped).unwrap().read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"Hi");
+/// ```
+pub fn hide(image: &mut RgbaImage, secret: &[u8]) -> Result<()> {
+    let capacity = capacity(image);
+    if secret.len() > capacity {
+        return Err(SteganoError::CarrierTooSmall {
+            needed: secret.len(),
+            capacity,
+        });
+    }
+    let (width, height) = region(image).ok_or(SteganoError::CarrierTooSmall {
+        needed: secret.len(),
+        capacity,
+    })?;
+    let mut marker = ALIGNMENT_MARKER.to_vec();
+    marker.extend(width.to_le_bytes());
+    marker.extend(height.to_le_bytes());
+
+    let marker_channels = (0..MARKER_PIXELS).flat_map(|x| channels(x, 0));
+    let secret_channels =
+        (0..height).flat_map(|y| (0..width).flat_map(move |x| channels(x, y + 1)));
+    hide_bits(image, marker_channels, &marker);
+    hide_bits(image, secret_channels, secret);
+
+    Ok(())
+}
+
+/// builds a Decoder that reads the secret from the region behind the marker, wherever it is
+/// in the image. Fails with `SteganoError::NotEmbedded` if there is no marker. Pixels of the
+/// region that were cropped away are left out, so the secret is truncated then
+pub fn decoder(image: &RgbaImage) -> Result<Box<dyn Read + '_>> {
+    let (image_width, image_height) = image.dimensions();
+    let (left, top) = find_marker(image).ok_or(SteganoError::NotEmbedded("alignment marker"))?;
+
+    let marker: Vec<u8> = read_bits(
+        image,
+        (0..MARKER_PIXELS).flat_map(|x| channels(left + x, top)),
+    );
+    let dimension = |at: usize| u32::from_le_bytes(marker[at..at + 4].try_into().unwrap());
+    let (width, height) = (dimension(ALIGNMENT_MARKER.len()), dimension(MARKER_LEN - 4));
+    let width = width.min(image_width - left);
+    let height = height.min(image_height - top - 1);
+
+    let secret = read_bits(
+        image,
+        (0..height).flat_map(|y| (0..width).flat_map(move |x| channels(left + x, top + 1 + y))),
+    );
+
+    Ok(Box::new(Cursor::new(secret)))
+}
+
+/// number of bytes that can be hidden in the region below the marker, 3 bits per pixel
+pub fn capacity(image: &RgbaImage) -> usize {
+    region(image)
+        .map(|(width, height)| width as usize * height as usize * 3 / 8)
+        .unwrap_or(0)
+}
+
+/// width and height of the region, `None` if the image is too small for the marker
+fn region(image: &RgbaImage) -> Option<(u32, u32)> {
+    let (width, height) = image.dimensions();
+    let width = width.checked_sub(2 * ALIGNMENT_MARGIN)?;
+    let height = height.checked_sub(2 * ALIGNMENT_MARGIN + 1)?;
+
+    (width >= MARKER_PIXELS && height > 0).then_some((width, height))
+}
+
+/// the red, green and blue channel of the pixel, relative to the marker of the carrier
+fn channels(x: u32, y: u32) -> impl Iterator<Item = (u32, u32, usize)> {
+    (0..3).map(move |c| (x, y, c))
+}
+
+/// writes the bits of `bytes`, lowest first, into the LSBs of the channels of the carrier
+fn hide_bits(
+    image: &mut RgbaImage,
+    channels: impl Iterator<Item = (u32, u32, usize)>,
+    bytes: &[u8],
+) {
+    let bits = bytes
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1));
+    for ((x, y, c), bit) in channels.zip(bits) {
+        let channel = &mut image
+            .get_pixel_mut(x + ALIGNMENT_MARGIN, y + ALIGNMENT_MARGIN)
+            .0[c];
+        *channel = (*channel & !1) | bit;
+    }
+}
+
+/// reads the LSBs of the channels into bytes, lowest bit first
+fn read_bits(image: &RgbaImage, channels: impl Iterator<Item = (u32, u32, usize)>) -> Vec<u8> {
+    let bits: Vec<u8> = channels
+        .map(|(x, y, c)| image.get_pixel(x, y).0[c] & 1)
+        .collect();
+
+    bits.chunks_exact(8)
+        .map(|bits| (0..8).fold(0, |byte, i| byte | (bits[i] << i)))
+        .collect()
+}
+
+/// the pixel where the marker starts, scanned row by row
+fn find_marker(image: &RgbaImage) -> Option<(u32, u32)> {
+    let (width, height) = image.dimensions();
+    let marker_bits: Vec<u8> = ALIGNMENT_MARKER
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1))
+        .collect();
+
+    (0..height).find_map(|y| {
+        let row: Vec<u8> = (0..width)
+            .flat_map(|x| image.get_pixel(x, y).0[..3].iter().map(|c| c & 1))
+            .collect();
+        (0..width.saturating_sub(MARKER_PIXELS - 1))
+            .find(|x| row[*x as usize * 3..].starts_with(&marker_bits))
+            .map(|x| (x, y))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::random_payload;
+    use image::imageops;
+
+    #[test]
+    fn should_find_the_marker_in_any_crop_within_the_margin() -> Result<()> {
+        let noise = random_payload(200 * 120 * 4);
+        let mut image = RgbaImage::from_raw(200, 120, noise).unwrap();
+        let secret = random_payload(capacity(&image));
+        hide(&mut image, &secret)?;
+
+        let margin = ALIGNMENT_MARGIN;
+        for (left, top, right, bottom) in [
+            (0, 0, 0, 0),
+            (margin, margin, margin, margin),
+            (3, 11, 7, 0),
+        ] {
+            let cropped =
+                imageops::crop_imm(&image, left, top, 200 - left - right, 120 - top - bottom)
+                    .to_image();
+            let mut unveiled = Vec::new();
+            decoder(&cropped)?.read_to_end(&mut unveiled)?;
+            assert_eq!(unveiled, secret, "cropped by {left}/{top}/{right}/{bottom}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_tell_when_there_is_no_marker() {
+        let image = RgbaImage::from_raw(100, 100, random_payload(100 * 100 * 4)).unwrap();
+        assert!(matches!(
+            decoder(&image).err(),
+            Some(SteganoError::NotEmbedded("alignment marker"))
+        ));
+    }
+}
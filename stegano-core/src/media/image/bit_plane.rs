@@ -0,0 +1,115 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use image::RgbaImage;
+use std::io::{Cursor, Read};
+
+use crate::format::{FormatError, MAX_BIT_PLANE};
+use crate::{Result, SteganoError};
+
+/// Hides a payload in one bit plane of red, green and blue, that is the bit `plane` of every
+/// channel, 0 being the LSB. Each plane carries its own payload, independent of the others,
+/// so one image can carry layered secrets. The payload is prefixed by its `u32` big endian
+/// length and read in the column based order of the default traversal. Higher planes distort
+/// the image a lot more, plane 7 flips colors by half their range.
+/// The capacity of each plane is pixels * 3 / 8 bytes, minus the length.
+///
+/// ## Example of hiding in two planes
+/// ```rust
+/// use stegano_core::media::image::bit_plane;
+/// use image::{Rgba, RgbaImage};
+///
+/// let mut image = RgbaImage::from_pixel(8, 8, Rgba([200, 120, 40, 0xff]));
+/// bit_plane::hide(&mut image, 0, b"low").unwrap();
+/// bit_plane::hide(&mut image, 3, b"high").unwrap();
+///
+/// assert_eq!(bit_plane::unveil(&image, 0).unwrap(), b"low");
+/// assert_eq!(bit_plane::unveil(&image, 3).unwrap(), b"high");
+/// ```
+pub fn hide(image: &mut RgbaImage, plane: u8, data: &[u8]) -> Result<()> {
+    check_plane(plane)?;
+    let capacity = capacity(image);
+    if data.len() > capacity {
+        return Err(SteganoError::CarrierTooSmall {
+            needed: data.len(),
+            capacity,
+        });
+    }
+    let mut payload = (data.len() as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(data);
+
+    let bits = payload
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1));
+    let height = image.height();
+    for (i, bit) in bits.enumerate() {
+        let pixel = i / 3;
+        let (x, y) = (pixel as u32 / height, pixel as u32 % height);
+        let channel = &mut image.get_pixel_mut(x, y).0[i % 3];
+        *channel = (*channel & !(1 << plane)) | (bit << plane);
+    }
+
+    Ok(())
+}
+
+/// builds a Decoder that reads all bits of the plane, the payload with its length in front
+pub fn decoder(image: &RgbaImage, plane: u8) -> Result<Box<dyn Read + '_>> {
+    check_plane(plane)?;
+    let (width, height) = image.dimensions();
+    let bits: Vec<u8> = (0..width)
+        .flat_map(|x| (0..height).map(move |y| (x, y)))
+        .flat_map(|(x, y)| image.get_pixel(x, y).0[..3].to_vec())
+        .map(|channel| (channel >> plane) & 1)
+        .collect();
+
+    Ok(Box::new(Cursor::new(
+        bits.chunks_exact(8)
+            .map(|bits| (0..8).fold(0, |byte, i| byte | (bits[i] << i)))
+            .collect::<Vec<u8>>(),
+    )))
+}
+
+/// the payload that was hidden in the plane by [`hide`]. Fails with
+/// `SteganoError::InvalidFormat` if the plane carries no payload, that is its length is off
+pub fn unveil(image: &RgbaImage, plane: u8) -> Result<Vec<u8>> {
+    let mut dec = decoder(image, plane)?;
+    let len = dec
+        .read_u32::<BigEndian>()
+        .map_err(|_| FormatError::Truncated("bit plane length"))? as usize;
+    if len > capacity(image) {
+        return Err(FormatError::Truncated("bit plane payload").into());
+    }
+    let mut data = vec![0; len];
+    dec.read_exact(&mut data)
+        .map_err(|_| FormatError::Truncated("bit plane payload"))?;
+
+    Ok(data)
+}
+
+/// number of bytes that can be hidden in each plane, 3 bits per pixel minus the length
+pub fn capacity(image: &RgbaImage) -> usize {
+    (image.pixels().len() * 3 / 8).saturating_sub(4)
+}
+
+fn check_plane(plane: u8) -> Result<()> {
+    match plane <= MAX_BIT_PLANE {
+        true => Ok(()),
+        false => Err(SteganoError::InvalidParameter(format!(
+            "bit plane must be between 0 and {MAX_BIT_PLANE}, but is {plane}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_refuse_a_plane_out_of_range() {
+        let mut image = RgbaImage::new(8, 8);
+        for result in [
+            hide(&mut image, MAX_BIT_PLANE + 1, b"x"),
+            unveil(&image, MAX_BIT_PLANE + 1).map(|_| ()),
+        ] {
+            assert!(matches!(result, Err(SteganoError::InvalidParameter(_))));
+        }
+    }
+}
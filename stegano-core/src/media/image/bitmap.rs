@@ -0,0 +1,243 @@
+use image::{Rgba, RgbaImage};
+use png::{BitDepth, ColorType, Transformations};
+use std::io::{Read, Write};
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufReader, io::BufWriter, path::Path};
+
+use crate::universal_decoder::{Decoder, OneBitUnveil};
+use crate::universal_encoder::{Encoder, OneBitHide};
+#[cfg(feature = "fs")]
+use crate::Persist;
+use crate::{MediaPrimitive, MediaPrimitiveMut, Result, SteganoError};
+
+/// A black and white PNG with 1 bit per pixel, like a fax. Every pixel is treated as one channel,
+/// so hiding flips pixels and the capacity is pixels / 8 bytes.
+/// The grayscale bit depth of 1 in the PNG header marks the layout, it is kept on encoding.
+///
+/// ## Example of hiding in the pixels
+/// ```rust
+/// use stegano_core::media::image::bitmap::BitmapImage;
+///
+/// let mut png = Vec::new();
+/// {
+///     let mut encoder = png::Encoder::new(&mut png, 16, 1);
+///     encoder.set_color(png::ColorType::Grayscale);
+///     encoder.set_depth(png::BitDepth::One);
+///     let mut writer = encoder.write_header().unwrap();
+///     writer.write_image_data(&[0b1010_1010, 0b0000_1111]).unwrap();
+/// }
+///
+/// let mut image = BitmapImage::decode(&png[..]).unwrap().expect("a 1-bpp image");
+/// assert_eq!(image.capacity(), 2);
+/// image.encoder().write_all(b"Hi").unwrap();
+///
+/// let mut buf = [0; 2];
+/// image.decoder().read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"Hi");
+/// ```
+pub struct BitmapImage {
+    width: u32,
+    height: u32,
+    /// one byte per pixel, row by row, 1 is white and 0 is black
+    pixels: Vec<u8>,
+}
+
+impl BitmapImage {
+    /// decodes a PNG, returns `None` if it is not 1 bit grayscale
+    pub fn decode(r: impl Read) -> Result<Option<Self>> {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(Transformations::IDENTITY);
+        let mut reader = decoder
+            .read_info()
+            .map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        let info = reader.info();
+        if (info.color_type, info.bit_depth) != (ColorType::Grayscale, BitDepth::One) {
+            return Ok(None);
+        }
+        let (width, height) = (info.width, info.height);
+
+        let mut packed = vec![0; reader.output_buffer_size()];
+        reader
+            .next_frame(&mut packed)
+            .map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        let row_len = (width as usize).div_ceil(8);
+        let pixels = (0..height as usize)
+            .flat_map(|y| (0..width as usize).map(move |x| (x, y)))
+            .map(|(x, y)| (packed[y * row_len + x / 8] >> (7 - x % 8)) & 1)
+            .collect();
+
+        Ok(Some(Self {
+            width,
+            height,
+            pixels,
+        }))
+    }
+
+    /// the bitmap of an image that has only opaque black and white pixels, e.g. a 1 bit PNG
+    /// that was re-saved with more bits per pixel. `None` for any other pixel
+    pub fn from_rgba(image: &RgbaImage) -> Option<Self> {
+        let pixels = image
+            .pixels()
+            .map(|p| match p.0 {
+                [0, 0, 0, u8::MAX] => Some(0),
+                [u8::MAX, u8::MAX, u8::MAX, u8::MAX] => Some(1),
+                _ => None,
+            })
+            .collect::<Option<Vec<u8>>>()?;
+
+        Some(Self {
+            width: image.width(),
+            height: image.height(),
+            pixels,
+        })
+    }
+
+    /// opens a PNG file, returns `None` if it is no PNG or not 1 bit grayscale
+    #[cfg(feature = "fs")]
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        let is_png = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("png"))
+            .unwrap_or(false);
+        if !is_png {
+            return Ok(None);
+        }
+        let file = File::open(path).map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        Self::decode(BufReader::new(file))
+    }
+
+    /// encodes the image as 1 bit grayscale PNG
+    pub fn encode(&self, w: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(ColorType::Grayscale);
+        encoder.set_depth(BitDepth::One);
+
+        let mut packed = Vec::new();
+        for row in self.pixels.chunks(self.width as usize) {
+            packed.extend(row.chunks(8).map(|bits| {
+                bits.iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, bit)| byte | (bit << (7 - i)))
+            }));
+        }
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&packed))
+            .map_err(|_e| SteganoError::ImageEncodingError)
+    }
+
+    /// number of bytes that can be hidden, one bit per pixel
+    pub fn capacity(&self) -> usize {
+        self.pixels.len() / 8
+    }
+
+    /// builds a Decoder that reads the secret from the pixels, row by row
+    pub fn decoder(&self) -> Box<dyn Read + '_> {
+        Box::new(Decoder::new(
+            self.pixels
+                .iter()
+                .map(|p| MediaPrimitive::ImageColorChannel(*p)),
+            OneBitUnveil,
+        ))
+    }
+
+    /// builds an Encoder that writes the secret into the pixels, row by row
+    pub fn encoder(&mut self) -> Box<dyn Write + '_> {
+        Box::new(Encoder::new(
+            self.pixels
+                .iter_mut()
+                .map(MediaPrimitiveMut::ImageColorChannel),
+            OneBitHide,
+        ))
+    }
+
+    /// expands the pixels to black and white RGBA
+    pub fn to_rgba(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let v = self.pixels[(y * self.width + x) as usize] * u8::MAX;
+            Rgba([v, v, v, u8::MAX])
+        })
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Persist for BitmapImage {
+    fn save_as(&mut self, file: &Path) -> Result<()> {
+        let file = File::create(file).map_err(|source| SteganoError::WriteError { source })?;
+
+        self.encode(BufWriter::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bitmap_png(width: u32, height: u32) -> Vec<u8> {
+        let row_len = (width as usize).div_ceil(8);
+        let mut png = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png, width, height);
+            encoder.set_color(ColorType::Grayscale);
+            encoder.set_depth(BitDepth::One);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&vec![0b1100_1100; row_len * height as usize])
+                .unwrap();
+        }
+        png
+    }
+
+    #[test]
+    fn should_not_decode_an_image_with_more_bits_per_pixel() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(RgbaImage::new(2, 2))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        assert!(BitmapImage::decode(&png[..]).unwrap().is_none());
+    }
+
+    #[test]
+    fn should_read_the_same_bitmap_from_its_rgba_pixels() {
+        let image = BitmapImage::decode(&bitmap_png(13, 5)[..])
+            .unwrap()
+            .unwrap();
+        let rgba = image.to_rgba();
+        assert_eq!(BitmapImage::from_rgba(&rgba).unwrap().pixels, image.pixels);
+
+        let mut gray = rgba;
+        gray.put_pixel(0, 0, Rgba([0x80, 0x80, 0x80, u8::MAX]));
+        assert!(BitmapImage::from_rgba(&gray).is_none());
+    }
+
+    #[test]
+    fn should_round_trip_a_secret_through_a_1_bpp_png() {
+        // 13 pixels wide, so that rows do not end on a byte boundary
+        let png = bitmap_png(13, 5);
+        let mut image = BitmapImage::decode(&png[..]).unwrap().unwrap();
+        assert_eq!(image.capacity(), 13 * 5 / 8);
+        image.encoder().write_all(b"fax!").unwrap();
+
+        let mut png = Vec::new();
+        image.encode(&mut png).unwrap();
+        let image = BitmapImage::decode(&png[..]).unwrap().unwrap();
+
+        let mut buf = [0; 4];
+        image.decoder().read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"fax!");
+        // pixels behind the secret stay untouched
+        assert_eq!(image.to_rgba().get_pixel(11, 4), &Rgba([0, 0, 0, 0xff]));
+        assert_eq!(
+            image.to_rgba().get_pixel(12, 4),
+            &Rgba([0xff, 0xff, 0xff, 0xff])
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use image::RgbaImage;
+use png::{AdaptiveFilterType, BitDepth, ColorType, Compression, FilterType};
+use std::io::Write;
+
+use crate::{Result, SteganoError};
+
+/// encodes the image as canonical PNG, whose bytes depend only on the pixels: 8 bit RGBA,
+/// always with the same compression and filter, and no chunk but `IHDR`, `IDAT` and `IEND`,
+/// so no EXIF, text or time. The same pixels encode to the same file, e.g. for deduplication.
+///
+/// ## Example of encoding the same pixels twice
+/// ```rust
+/// use stegano_core::media::image::canonical::encode_png;
+/// use image::{Rgba, RgbaImage};
+///
+/// let image = RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 0xff]));
+/// let (mut a, mut b) = (Vec::new(), Vec::new());
+/// encode_png(&image, &mut a).unwrap();
+/// encode_png(&image.clone(), &mut b).unwrap();
+/// assert_eq!(a, b);
+/// ```
+pub fn encode_png(image: &RgbaImage, w: impl Write) -> Result<()> {
+    let mut encoder = png::Encoder::new(w, image.width(), image.height());
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    encoder.set_compression(Compression::Default);
+    encoder.set_filter(FilterType::Sub);
+    encoder.set_adaptive_filter(AdaptiveFilterType::NonAdaptive);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(image.as_raw()))
+        .map_err(|_e| SteganoError::ImageEncodingError)
+}
@@ -0,0 +1,883 @@
+use bitstream_io::{BitRead, BitReader, LittleEndian};
+use image::{GenericImageView, GrayImage, RgbaImage};
+use sha2::Sha256;
+use std::io::{Cursor, Error, ErrorKind, Result, Write};
+use std::time::Instant;
+use zeroize::Zeroize;
+
+use crate::format::MASK_THRESHOLD;
+use crate::universal_encoder::{is_cancelled, is_past, HideAlgorithm, CANCEL_CHECK_INTERVAL};
+use crate::{CancellationToken, MediaPrimitive, MediaPrimitiveMut};
+
+/// a single color channel of a RGBA pixel
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channel {
+    /// the channels used by default, alpha is left untouched
+    pub const RGB: [Channel; 3] = [Channel::Red, Channel::Green, Channel::Blue];
+
+    /// index of the channel inside of a `Rgba<u8>` pixel
+    pub fn index(self) -> usize {
+        match self {
+            Channel::Red => 0,
+            Channel::Green => 1,
+            Channel::Blue => 2,
+            Channel::Alpha => 3,
+        }
+    }
+}
+
+/// how the 4 bytes of a pixel are named, by the tool that hid the secret.
+/// The secret is in what the tool calls red, green and blue, in that order.
+/// E.g. for a tool that sees the pixels as `Bgra`, red is the third byte of a RGBA pixel
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum ChannelOrder {
+    #[default]
+    Rgba,
+    Bgra,
+    /// Note: what the tool calls blue is the alpha channel, don't combine it with
+    /// `skip_transparent`, hiding changes which pixels are transparent then
+    Argb,
+}
+
+impl ChannelOrder {
+    /// all orders, the default last
+    pub const ALL: [ChannelOrder; 3] = [ChannelOrder::Bgra, ChannelOrder::Argb, ChannelOrder::Rgba];
+
+    /// the channel of a RGBA pixel, that the tool calls `channel`
+    pub fn channel_of(self, channel: Channel) -> Channel {
+        let channels = match self {
+            Self::Rgba => [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha],
+            Self::Bgra => [Channel::Blue, Channel::Green, Channel::Red, Channel::Alpha],
+            Self::Argb => [Channel::Green, Channel::Blue, Channel::Alpha, Channel::Red],
+        };
+
+        channels[channel.index()]
+    }
+}
+
+/// the traversal of the color channels that carry the secret, with its parameters.
+/// It is kept in a preamble in front of the message, that is written in the default
+/// traversal, so that the decoder finds it on its own, see [`crate::format::TRAVERSAL_PREAMBLE`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum TraversalOrder {
+    /// column by column, top to bottom, see [`SequentialCursor`]
+    #[default]
+    ColumnMajor,
+    /// row by row, left to right, see [`RowMajorCursor`]
+    RowMajor,
+    /// column major backwards, from the last channel of the bottom right pixel
+    Reverse,
+    /// a pseudo random order derived from the seed, see [`RandomCursor`]
+    Random { seed: u64 },
+    /// column major, but only every `stride`th row, see [`RowStride`]
+    RowStride { stride: u32 },
+    /// column major, but starting at the `pixel`th pixel of it, the pixels before stay
+    /// untouched, e.g. because they are unreliable. See [`SkipFirstPixels`]
+    Offset { pixel: u32 },
+}
+
+impl TraversalOrder {
+    /// compact form, one byte for the order followed by its parameters in big endian
+    pub fn to_bytes(self) -> Vec<u8> {
+        match self {
+            Self::ColumnMajor => vec![0],
+            Self::RowMajor => vec![1],
+            Self::Reverse => vec![2],
+            Self::Random { seed } => [&[3][..], &seed.to_be_bytes()].concat(),
+            Self::RowStride { stride } => [&[4][..], &stride.to_be_bytes()].concat(),
+            Self::Offset { pixel } => [&[5][..], &pixel.to_be_bytes()].concat(),
+        }
+    }
+
+    /// parses the compact form of [`TraversalOrder::to_bytes`], bytes behind it are ignored
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        let (tag, params) = buf.split_first()?;
+        match tag {
+            0 => Some(Self::ColumnMajor),
+            1 => Some(Self::RowMajor),
+            2 => Some(Self::Reverse),
+            3 => Some(Self::Random {
+                seed: u64::from_be_bytes(params.get(..8)?.try_into().ok()?),
+            }),
+            4 => Some(Self::RowStride {
+                stride: u32::from_be_bytes(params.get(..4)?.try_into().ok()?),
+            }),
+            5 => Some(Self::Offset {
+                pixel: u32::from_be_bytes(params.get(..4)?.try_into().ok()?),
+            }),
+            _ => None,
+        }
+    }
+
+    /// the cursor that visits the red, green and blue channels in this order
+    pub fn cursor(self, width: u32, height: u32) -> Box<dyn PixelCursor> {
+        match self {
+            Self::ColumnMajor => Box::new(SequentialCursor::new(width, height)),
+            Self::RowMajor => Box::new(RowMajorCursor::new(width, height)),
+            Self::Reverse => Box::new(ReverseCursor::new(width, height)),
+            Self::Random { seed } => Box::new(RandomCursor::new(width, height, seed)),
+            Self::RowStride { stride } => {
+                Box::new(RowStride::new(SequentialCursor::new(width, height), stride))
+            }
+            Self::Offset { pixel } => Box::new(SkipFirstPixels::new(
+                SequentialCursor::new(width, height),
+                height,
+                pixel as u64,
+            )),
+        }
+    }
+}
+
+/// Determines the order in which the color channels of an image carry the secret bits.
+/// Every position returned carries exactly one bit, the cursor is exhausted when it returns `None`.
+///
+/// ## Example of a custom cursor
+/// ```rust
+/// use stegano_core::media::image::cursor::{Channel, PixelCursor};
+///
+/// /// visits the red channel of the first row only
+/// struct FirstRow { x: u32, width: u32 }
+///
+/// impl PixelCursor for FirstRow {
+///     fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+///         if self.x >= self.width {
+///             return None;
+///         }
+///         self.x += 1;
+///         Some((self.x - 1, 0, Channel::Red))
+///     }
+/// }
+/// ```
+pub trait PixelCursor {
+    /// returns the next `(x, y, channel)` to read or write one bit from/to
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)>;
+}
+
+impl<C: PixelCursor + ?Sized> PixelCursor for Box<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        (**self).next_position()
+    }
+}
+
+/// skips all positions of fully transparent pixels, alpha 0, of the given image.
+/// Hiding never touches the alpha channel, so the same pixels are skipped on unveiling
+pub struct SkipTransparent<C: PixelCursor> {
+    cursor: C,
+    width: u32,
+    transparent: Vec<bool>,
+}
+
+impl<C: PixelCursor> SkipTransparent<C> {
+    pub fn new(image: &RgbaImage, cursor: C) -> Self {
+        Self {
+            cursor,
+            width: image.width(),
+            transparent: image.pixels().map(|p| p.0[3] == 0).collect(),
+        }
+    }
+}
+
+impl<C: PixelCursor> PixelCursor for SkipTransparent<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        loop {
+            let (x, y, c) = self.cursor.next_position()?;
+            let i = y as usize * self.width as usize + x as usize;
+            if !self.transparent.get(i).copied().unwrap_or(false) {
+                return Some((x, y, c));
+            }
+        }
+    }
+}
+
+/// visits only the positions of pixels whose value in the grayscale mask is above
+/// [`MASK_THRESHOLD`], e.g. white pixels. Pixels outside of the mask are skipped
+pub struct Masked<C: PixelCursor> {
+    cursor: C,
+    width: u32,
+    height: u32,
+    eligible: Vec<bool>,
+}
+
+impl<C: PixelCursor> Masked<C> {
+    pub fn new(mask: &GrayImage, cursor: C) -> Self {
+        Self {
+            cursor,
+            width: mask.width(),
+            height: mask.height(),
+            eligible: mask.pixels().map(|p| p.0[0] > MASK_THRESHOLD).collect(),
+        }
+    }
+}
+
+impl<C: PixelCursor> PixelCursor for Masked<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        loop {
+            let (x, y, c) = self.cursor.next_position()?;
+            let i = y as usize * self.width as usize + x as usize;
+            if x < self.width && y < self.height && self.eligible[i] {
+                return Some((x, y, c));
+            }
+        }
+    }
+}
+
+/// visits the same positions, but the channels as named by the [`ChannelOrder`]
+pub struct InChannelOrder<C: PixelCursor> {
+    cursor: C,
+    order: ChannelOrder,
+}
+
+impl<C: PixelCursor> InChannelOrder<C> {
+    pub fn new(cursor: C, order: ChannelOrder) -> Self {
+        Self { cursor, order }
+    }
+}
+
+impl<C: PixelCursor> PixelCursor for InChannelOrder<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        let (x, y, c) = self.cursor.next_position()?;
+
+        Some((x, y, self.order.channel_of(c)))
+    }
+}
+
+/// skips the first `pixels` pixels of the default column based traversal, whatever their
+/// channel, e.g. because a preamble is hidden there
+pub struct SkipFirstPixels<C: PixelCursor> {
+    cursor: C,
+    height: u32,
+    pixels: u64,
+}
+
+impl<C: PixelCursor> SkipFirstPixels<C> {
+    pub fn new(cursor: C, height: u32, pixels: u64) -> Self {
+        Self {
+            cursor,
+            height,
+            pixels,
+        }
+    }
+}
+
+impl<C: PixelCursor> PixelCursor for SkipFirstPixels<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        loop {
+            let (x, y, c) = self.cursor.next_position()?;
+            if x as u64 * self.height as u64 + y as u64 >= self.pixels {
+                return Some((x, y, c));
+            }
+        }
+    }
+}
+
+/// visits only the positions of one channel, the others are skipped
+pub struct OnlyChannel<C: PixelCursor> {
+    cursor: C,
+    channel: Channel,
+}
+
+impl<C: PixelCursor> OnlyChannel<C> {
+    pub fn new(cursor: C, channel: Channel) -> Self {
+        Self { cursor, channel }
+    }
+}
+
+impl<C: PixelCursor> PixelCursor for OnlyChannel<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        loop {
+            let (x, y, c) = self.cursor.next_position()?;
+            if c == self.channel {
+                return Some((x, y, c));
+            }
+        }
+    }
+}
+
+/// visits only every `stride`th row, that is the rows where `y % stride == 0`
+pub struct RowStride<C: PixelCursor> {
+    cursor: C,
+    stride: u32,
+}
+
+impl<C: PixelCursor> RowStride<C> {
+    pub fn new(cursor: C, stride: u32) -> Self {
+        Self {
+            cursor,
+            stride: stride.max(1),
+        }
+    }
+}
+
+impl<C: PixelCursor> PixelCursor for RowStride<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        loop {
+            let (x, y, c) = self.cursor.next_position()?;
+            if y % self.stride == 0 {
+                return Some((x, y, c));
+            }
+        }
+    }
+}
+
+/// visits only the pixels of a checkerboard pattern, that is where `(x + y) % 2 == 0`
+pub struct Checkerboard<C: PixelCursor> {
+    cursor: C,
+}
+
+impl<C: PixelCursor> Checkerboard<C> {
+    pub fn new(cursor: C) -> Self {
+        Self { cursor }
+    }
+}
+
+impl<C: PixelCursor> PixelCursor for Checkerboard<C> {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        loop {
+            let (x, y, c) = self.cursor.next_position()?;
+            if (x + y) % 2 == 0 {
+                return Some((x, y, c));
+            }
+        }
+    }
+}
+
+/// the default traversal: column by column, top to bottom, red, green and blue channel of each pixel
+pub struct SequentialCursor {
+    width: u32,
+    height: u32,
+    i: u64,
+}
+
+impl SequentialCursor {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            i: 0,
+        }
+    }
+}
+
+impl PixelCursor for SequentialCursor {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        let channels = Channel::RGB.len() as u64;
+        let pixel = self.i / channels;
+        if pixel >= self.width as u64 * self.height as u64 {
+            return None;
+        }
+        let channel = Channel::RGB[(self.i % channels) as usize];
+        self.i += 1;
+
+        Some((
+            (pixel / self.height as u64) as u32,
+            (pixel % self.height as u64) as u32,
+            channel,
+        ))
+    }
+}
+
+/// row by row, left to right, red, green and blue channel of each pixel
+pub struct RowMajorCursor {
+    width: u32,
+    height: u32,
+    i: u64,
+}
+
+impl RowMajorCursor {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            i: 0,
+        }
+    }
+}
+
+impl PixelCursor for RowMajorCursor {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        let channels = Channel::RGB.len() as u64;
+        let pixel = self.i / channels;
+        if pixel >= self.width as u64 * self.height as u64 {
+            return None;
+        }
+        let channel = Channel::RGB[(self.i % channels) as usize];
+        self.i += 1;
+
+        Some((
+            (pixel % self.width as u64) as u32,
+            (pixel / self.width as u64) as u32,
+            channel,
+        ))
+    }
+}
+
+/// the default traversal backwards, from the blue channel of the bottom right pixel
+pub struct ReverseCursor {
+    height: u32,
+    remaining: u64,
+}
+
+impl ReverseCursor {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            height,
+            remaining: width as u64 * height as u64 * Channel::RGB.len() as u64,
+        }
+    }
+}
+
+impl PixelCursor for ReverseCursor {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        self.remaining = self.remaining.checked_sub(1)?;
+        let channels = Channel::RGB.len() as u64;
+        let pixel = self.remaining / channels;
+
+        Some((
+            (pixel / self.height as u64) as u32,
+            (pixel % self.height as u64) as u32,
+            Channel::RGB[(self.remaining % channels) as usize],
+        ))
+    }
+}
+
+/// visits all red, green and blue channels exactly once in a pseudo random order derived from a seed,
+/// the same seed always leads to the same order. The order is computed channel by channel,
+/// it is never held in memory
+pub struct RandomCursor {
+    width: u64,
+    visited: u64,
+    permutation: Permutation,
+}
+
+impl RandomCursor {
+    pub fn new(width: u32, height: u32, seed: u64) -> Self {
+        let total = width as u64 * height as u64 * Channel::RGB.len() as u64;
+
+        Self {
+            width: width as u64,
+            visited: 0,
+            permutation: Permutation::new(total, seed),
+        }
+    }
+}
+
+impl PixelCursor for RandomCursor {
+    fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+        if self.visited == self.permutation.len {
+            return None;
+        }
+        let i = self.permutation.apply(self.visited);
+        self.visited += 1;
+        let channels = Channel::RGB.len() as u64;
+        let pixel = i / channels;
+
+        Some((
+            (pixel % self.width) as u32,
+            (pixel / self.width) as u32,
+            Channel::RGB[(i % channels) as usize],
+        ))
+    }
+}
+
+/// rounds of the Feistel network of `Permutation`, it must never change like `SplitMix64`
+const PERMUTATION_ROUNDS: usize = 4;
+
+/// a pseudo random permutation of `0..len`. A Feistel network permutes the smallest even number
+/// of bits that holds `len`, values out of range go through it again until they are in range.
+/// So each value is mapped on its own, without a table of the whole order
+struct Permutation {
+    len: u64,
+    half_bits: u32,
+    keys: [u64; PERMUTATION_ROUNDS],
+}
+
+impl Permutation {
+    fn new(len: u64, seed: u64) -> Self {
+        let bits = 64 - len.saturating_sub(1).leading_zeros();
+        let mut rng = SplitMix64(seed);
+
+        Self {
+            len,
+            half_bits: bits.div_ceil(2).max(1),
+            keys: std::array::from_fn(|_| rng.next()),
+        }
+    }
+
+    /// the value that `i` is mapped to, `i` has to be below `len`
+    fn apply(&self, i: u64) -> u64 {
+        let mut value = self.feistel(i);
+        // the cycle of `i` leads back into range, at the latest at `i` itself
+        while value >= self.len {
+            value = self.feistel(value);
+        }
+
+        value
+    }
+
+    fn feistel(&self, value: u64) -> u64 {
+        let mask = u64::MAX >> (64 - self.half_bits);
+        let (mut left, mut right) = (value >> self.half_bits, value & mask);
+        for key in self.keys {
+            let round = SplitMix64(right ^ key).next() & mask;
+            (left, right) = (right, left ^ round);
+        }
+
+        (left << self.half_bits) | right
+    }
+}
+
+/// salt of the passphrase based ordering, it must never change for the same reason as `SplitMix64`
+const PASSPHRASE_SALT: &[u8] = b"stegano-rs pixel ordering";
+/// PBKDF2 rounds, makes guessing the passphrase by trying orderings expensive
+const PASSPHRASE_ROUNDS: u32 = 100_000;
+
+/// derives a `RandomCursor` seed from a passphrase with PBKDF2-HMAC-SHA256
+pub fn passphrase_seed(passphrase: &str) -> u64 {
+    let mut seed = [0u8; 8];
+    pbkdf2::pbkdf2_hmac::<Sha256>(
+        passphrase.as_bytes(),
+        PASSPHRASE_SALT,
+        PASSPHRASE_ROUNDS,
+        &mut seed,
+    );
+    let value = u64::from_be_bytes(seed);
+    seed.zeroize();
+
+    value
+}
+
+/// tiny and stable pseudo random number generator, the sequence must never change
+/// because otherwise images with secrets could not be read anymore
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// iterates the color channels of an image in the order given by a `PixelCursor`
+pub struct CursorColor<'i, C: PixelCursor> {
+    image: &'i RgbaImage,
+    cursor: C,
+    bit_depths: [u8; 4],
+    /// the channel value and how many of its bits are still to come, lowest bit first
+    pending: Option<(u8, u8)>,
+}
+
+impl<'i, C: PixelCursor> CursorColor<'i, C> {
+    pub fn new(image: &'i RgbaImage, cursor: C) -> Self {
+        Self {
+            image,
+            cursor,
+            bit_depths: [1; 4],
+            pending: None,
+        }
+    }
+
+    /// repeats every channel shifted by each of its low bits, as many as its depth says,
+    /// so that each item carries exactly one bit in its LSB.
+    /// The depths are indexed by [`Channel::index`]
+    pub fn with_bit_depths(mut self, bit_depths: [u8; 4]) -> Self {
+        self.bit_depths = bit_depths;
+        self
+    }
+}
+
+impl<C: PixelCursor> Iterator for CursorColor<'_, C> {
+    type Item = MediaPrimitive;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((value, remaining)) = self.pending.take() {
+                if remaining > 1 {
+                    self.pending = Some((value >> 1, remaining - 1));
+                }
+                return Some(MediaPrimitive::ImageColorChannel(value));
+            }
+
+            let (x, y, c) = self.cursor.next_position()?;
+            if !self.image.in_bounds(x, y) {
+                return None;
+            }
+            let value = self.image.get_pixel(x, y).0[c.index()];
+            self.pending = Some((value, self.bit_depths[c.index()].max(1)));
+        }
+    }
+}
+
+/// writes bits into the color channels of an image in the order given by a `PixelCursor`
+pub struct CursorEncoder<'i, C: PixelCursor, A: HideAlgorithm> {
+    image: &'i mut RgbaImage,
+    cursor: C,
+    algorithm: A,
+    cancel: Option<CancellationToken>,
+    deadline: Option<Instant>,
+    bit_depths: [u8; 4],
+}
+
+impl<'i, C: PixelCursor, A: HideAlgorithm> CursorEncoder<'i, C, A> {
+    pub fn new(image: &'i mut RgbaImage, cursor: C, algorithm: A) -> Self {
+        Self {
+            image,
+            cursor,
+            algorithm,
+            cancel: None,
+            deadline: None,
+            bit_depths: [1; 4],
+        }
+    }
+
+    /// aborts any further writing once the given token is set
+    pub fn with_cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// aborts any further writing once the given instant has passed
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// writes `bits` bits into the lowest bits of every color channel, lowest bit first.
+    /// With more than 1 bit the hide algorithm is not used, the bits are set as they are
+    pub fn with_bits_per_channel(mut self, bits: u8) -> Self {
+        self.bit_depths = [bits; 4];
+        self
+    }
+
+    /// like `with_bits_per_channel()`, but with its own number of bits for each channel,
+    /// indexed by [`Channel::index`]
+    pub fn with_bit_depths(mut self, bit_depths: [u8; 4]) -> Self {
+        self.bit_depths = bit_depths;
+        self
+    }
+}
+
+impl<C: PixelCursor, A: HideAlgorithm> Write for CursorEncoder<'_, C, A> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if is_cancelled(self.cancel.as_ref()) {
+            return Err(Error::other("encoding was cancelled"));
+        }
+        if is_past(self.deadline) {
+            return Err(Error::other("encoding ran out of time"));
+        }
+        let mut bit_iter = BitReader::endian(Cursor::new(buf), LittleEndian);
+        let mut bit_written: usize = 0;
+        let mut next_check = CANCEL_CHECK_INTERVAL << 3;
+        while bit_written < buf.len() << 3 {
+            if bit_written >= next_check {
+                // the next `write()` reports the cancellation or the deadline
+                if is_cancelled(self.cancel.as_ref()) || is_past(self.deadline) {
+                    break;
+                }
+                next_check += CANCEL_CHECK_INTERVAL << 3;
+            }
+            let Some((x, y, c)) = self.cursor.next_position() else {
+                break;
+            };
+            if !self.image.in_bounds(x, y) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("cursor position ({x}, {y}) is outside of the image"),
+                ));
+            }
+            let color = &mut self.image.get_pixel_mut(x, y).0[c.index()];
+            let bits = self.bit_depths[c.index()];
+            if bits <= 1 {
+                self.algorithm.encode(
+                    MediaPrimitiveMut::ImageColorChannel(color),
+                    &bit_iter.read_bit(),
+                );
+                bit_written += 1;
+                continue;
+            }
+            for k in 0..bits {
+                let Ok(bit) = bit_iter.read_bit() else {
+                    break;
+                };
+                *color = (*color & !(1 << k)) | ((bit as u8) << k);
+                bit_written += 1;
+            }
+        }
+
+        Ok(bit_written >> 3)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::image::decoder::ImageRgbaColor;
+    use crate::test_utils::prepare_small_image;
+    use crate::universal_encoder::OneBitHide;
+    use std::collections::HashSet;
+    use std::sync::atomic::Ordering;
+
+    /// sets the token once `after` positions were visited, like a user cancelling a running hide
+    struct CancellingCursor<C> {
+        cursor: C,
+        token: CancellationToken,
+        after: usize,
+    }
+
+    impl<C: PixelCursor> PixelCursor for CancellingCursor<C> {
+        fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+            match self.after {
+                0 => self.token.store(true, Ordering::Relaxed),
+                _ => self.after -= 1,
+            }
+            self.cursor.next_position()
+        }
+    }
+
+    #[test]
+    fn sequential_cursor_should_follow_the_default_traversal() {
+        let img = prepare_small_image();
+        let (width, height) = img.dimensions();
+        let by_cursor: Vec<MediaPrimitive> =
+            CursorColor::new(&img, SequentialCursor::new(width, height)).collect();
+        let by_default: Vec<MediaPrimitive> = ImageRgbaColor::new(&img).collect();
+
+        assert_eq!(by_cursor, by_default);
+    }
+
+    #[test]
+    fn random_cursor_should_visit_every_channel_once() {
+        for (width, height) in [(5, 4), (1, 1), (0, 3), (33, 17)] {
+            let mut cursor = RandomCursor::new(width, height, 42);
+            let mut seen = HashSet::new();
+            while let Some(p) = cursor.next_position() {
+                assert!(seen.insert(p), "position {:?} was visited twice", p);
+            }
+
+            assert_eq!(seen.len(), width as usize * height as usize * 3);
+        }
+    }
+
+    #[test]
+    fn cursor_encoder_should_stop_within_a_write_once_cancelled() {
+        let mut img = RgbaImage::new(64, 64);
+        let token = CancellationToken::default();
+        let cursor = CancellingCursor {
+            cursor: RandomCursor::new(64, 64, 42),
+            token: token.clone(),
+            after: 100,
+        };
+        {
+            let mut encoder = CursorEncoder::new(&mut img, cursor, OneBitHide).with_cancel(token);
+            let written = encoder.write(&[0xFF; 1024]).expect("Cannot write");
+            assert_eq!(
+                written, CANCEL_CHECK_INTERVAL,
+                "should stop at the first check"
+            );
+            assert!(
+                encoder.write_all(&[0xFF; 1024]).is_err(),
+                "Writing after cancellation should fail"
+            );
+        }
+
+        let touched = img.pixels().flat_map(|p| p.0).filter(|c| c & 1 == 1);
+        assert_eq!(touched.count(), CANCEL_CHECK_INTERVAL * 8);
+    }
+
+    #[test]
+    fn traversal_order_should_round_trip_to_the_same_positions() {
+        let positions = |mut cursor: Box<dyn PixelCursor>| {
+            std::iter::from_fn(move || cursor.next_position()).collect::<Vec<_>>()
+        };
+
+        for order in [
+            TraversalOrder::ColumnMajor,
+            TraversalOrder::RowMajor,
+            TraversalOrder::Reverse,
+            TraversalOrder::Random { seed: 42 },
+            TraversalOrder::RowStride { stride: 3 },
+            TraversalOrder::Offset { pixel: 7 },
+        ] {
+            let parsed = TraversalOrder::from_bytes(&order.to_bytes());
+            assert_eq!(parsed, Some(order));
+            let expected = positions(order.cursor(5, 4));
+            assert_eq!(positions(parsed.unwrap().cursor(5, 4)), expected);
+
+            let unique: HashSet<_> = expected.iter().collect();
+            assert_eq!(unique.len(), expected.len(), "{order:?} visits twice");
+        }
+        assert_eq!(
+            positions(TraversalOrder::Reverse.cursor(5, 4)),
+            positions(TraversalOrder::ColumnMajor.cursor(5, 4))
+                .into_iter()
+                .rev()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(TraversalOrder::from_bytes(&[3, 0, 1]), None);
+    }
+
+    #[test]
+    fn passphrase_seed_should_be_stable() {
+        assert_eq!(passphrase_seed("secret"), passphrase_seed("secret"));
+        assert_ne!(passphrase_seed("secret"), passphrase_seed("Secret"));
+    }
+
+    /// hidden secrets are only found again in the very same order, so it must never change
+    #[test]
+    fn random_cursor_should_keep_its_order() {
+        use Channel::{Blue, Green, Red};
+        let prefix = |width, height, seed, n| {
+            let mut cursor = RandomCursor::new(width, height, seed);
+            std::iter::from_fn(move || cursor.next_position())
+                .take(n)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            prefix(5, 4, 42, 8),
+            vec![
+                (2, 1, Green),
+                (4, 0, Blue),
+                (3, 0, Red),
+                (4, 2, Red),
+                (3, 0, Blue),
+                (0, 1, Blue),
+                (2, 2, Red),
+                (2, 1, Red),
+            ]
+        );
+        assert_eq!(
+            prefix(4000, 3000, passphrase_seed("secret"), 4),
+            vec![
+                (2240, 471, Blue),
+                (3279, 645, Red),
+                (932, 622, Blue),
+                (212, 2807, Green),
+            ]
+        );
+    }
+
+    #[test]
+    fn random_cursor_should_be_reproducible_by_seed() {
+        let collect = |seed| {
+            let mut cursor = RandomCursor::new(5, 4, seed);
+            std::iter::from_fn(move || cursor.next_position()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(collect(7), collect(7));
+        assert_ne!(collect(7), collect(8));
+    }
+}
@@ -58,7 +58,7 @@ impl<'i> Iterator for ImageRgbaColor<'i> {
     #[inline(always)]
     fn next(&'_ mut self) -> Option<Self::Item> {
         if self.skip_alpha && self.i > 0 {
-            let is_next_alpha = (self.i + 1) % 4 == 0;
+            let is_next_alpha = (self.i + 1).is_multiple_of(4);
             if is_next_alpha {
                 self.pixel.next();
                 self.i += 1;
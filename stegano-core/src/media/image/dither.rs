@@ -0,0 +1,138 @@
+use std::io::{Result, Write};
+
+use image::RgbaImage;
+
+use crate::analysis::random_payload;
+use crate::media::image::lsb_codec::{CodecOptions, Concealer, LsbCodec};
+
+/// Floyd-Steinberg weights of the right, bottom left, bottom and bottom right neighbour
+const WEIGHTS: [(i64, i64, f32); 4] = [
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0),
+    (0, 1, 5.0 / 16.0),
+    (1, 1, 1.0 / 16.0),
+];
+
+/// fills the LSB plane of the channels that carry the secret with `opts` with noise,
+/// Floyd-Steinberg style: each LSB is picked by noise, and how far the picked bit is off from
+/// one half is diffused to the neighbours, so that the ones and zeros are spread evenly instead
+/// of clumping. Only LSBs change, so the cover hash stays the same. After that, the LSBs look
+/// random everywhere, no matter if they carry a secret. The channels that carry nothing, e.g.
+/// transparent or masked pixels, stay untouched
+pub fn dither_lsb_plane(image: &mut RgbaImage, opts: &CodecOptions) -> Result<()> {
+    let carried = carried_channels(image, opts)?;
+    let (width, height) = image.dimensions();
+    let noise = random_payload(width as usize * height as usize * 4);
+    let mut errors = vec![0f32; width as usize * height as usize * 4];
+
+    for y in 0..height {
+        for x in 0..width {
+            for c in 0..4 {
+                let i = (y as usize * width as usize + x as usize) * 4 + c;
+                if !carried[i] {
+                    continue;
+                }
+                let v = image.get_pixel(x, y).0[c];
+                let jitter = noise[i] as f32 / 255.0 - 0.5;
+
+                let wanted = errors[i] + jitter;
+                let (new, error) = if wanted < 0.0 {
+                    (v & !1, wanted + 0.5)
+                } else {
+                    (v | 1, wanted - 0.5)
+                };
+                image.get_pixel_mut(x, y).0[c] = new;
+
+                for (dx, dy, weight) in WEIGHTS {
+                    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                    if nx < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    errors[(ny as usize * width as usize + nx as usize) * 4 + c] += error * weight;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// which bytes of the image buffer carry the secret with `opts`. The carrier is filled once
+/// with ones and once with zeros, the bytes whose LSB differs are the ones that were visited
+fn carried_channels(image: &RgbaImage, opts: &CodecOptions) -> Result<Vec<bool>> {
+    let opts = CodecOptions {
+        concealer: Concealer::LeastSignificantBit,
+        cancel: None,
+        deadline: None,
+        ..opts.clone()
+    };
+    let capacity = LsbCodec::channels(image, &opts).count() / 8;
+    let filled_with = |byte: u8| {
+        let mut probe = image.clone();
+        LsbCodec::encoder(&mut probe, &opts).write_all(&vec![byte; capacity])?;
+
+        Ok::<_, std::io::Error>(probe)
+    };
+
+    Ok(filled_with(0xff)?
+        .iter()
+        .zip(filled_with(0)?.iter())
+        .map(|(ones, zeros)| (ones ^ zeros) & 1 == 1)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::looks_random;
+    use crate::media::image::cursor::Channel;
+    use image::Rgba;
+
+    #[test]
+    fn should_randomize_the_lsbs_of_smooth_regions_only() {
+        let origin = RgbaImage::from_pixel(64, 64, Rgba([0x80, 0x40, 0x20, 0x7f]));
+        let mut image = origin.clone();
+        dither_lsb_plane(&mut image, &CodecOptions::default()).unwrap();
+
+        for (a, b) in origin.pixels().zip(image.pixels()) {
+            assert_eq!(a.0[3], b.0[3], "Alpha should be untouched");
+            for c in 0..3 {
+                assert_eq!(a.0[c] & !1, b.0[c] & !1, "Only the LSB may change");
+            }
+        }
+        let lsbs: Vec<u8> = image
+            .pixels()
+            .flat_map(|p| p.0[..3].to_vec())
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .map(|c| c.iter().enumerate().fold(0, |b, (i, v)| b | ((v & 1) << i)))
+            .collect();
+        assert!(looks_random(&lsbs));
+    }
+
+    #[test]
+    fn should_dither_only_the_channels_that_carry_the_secret() {
+        let mut origin = RgbaImage::from_pixel(64, 64, Rgba([0x80, 0x40, 0x20, 0xff]));
+        for x in 0..64 {
+            origin.put_pixel(x, 0, Rgba([0x80, 0x40, 0x20, 0]));
+        }
+        let opts = CodecOptions {
+            skip_transparent: true,
+            single_channel: Some(Channel::Green),
+            ..CodecOptions::default()
+        };
+        let mut image = origin.clone();
+        dither_lsb_plane(&mut image, &opts).unwrap();
+
+        for (x, y, a) in origin.enumerate_pixels() {
+            let b = image.get_pixel(x, y);
+            for c in [0, 2, 3] {
+                assert_eq!(a.0[c], b.0[c], "Only green may change");
+            }
+            if y == 0 {
+                assert_eq!(a, b, "Transparent pixels should be untouched");
+            }
+        }
+        assert!(origin.pixels().zip(image.pixels()).any(|(a, b)| a != b));
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::{Read, Write};
+use std::ops::Range;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::universal_decoder::{Decoder, OneBitUnveil};
+use crate::universal_encoder::{Encoder, OneBitHide};
+#[cfg(feature = "fs")]
+use crate::Persist;
+use crate::{MediaPrimitive, MediaPrimitiveMut, Result, SteganoError};
+
+const TAG_NEW_SUBFILE_TYPE: u16 = 254;
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_BITS_PER_SAMPLE: u16 = 258;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC: u16 = 262;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_SAMPLES_PER_PIXEL: u16 = 277;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_PLANAR_CONFIGURATION: u16 = 284;
+const TAG_SUB_IFDS: u16 = 330;
+
+/// bit 0 of the subfile type, set for a reduced resolution copy of the main image
+const REDUCED_RESOLUTION: u32 = 1;
+const UNCOMPRESSED: u32 = 1;
+const PHOTOMETRIC_RGB: u32 = 2;
+const CHUNKY: u32 = 1;
+
+/// IFDs nested or chained deeper than this are not followed, e.g. in a file that loops
+const MAX_IFDS: usize = 64;
+
+/// A DNG raw camera file, whose preview carries the secret, so the raw sensor data and all
+/// metadata stay untouched. Only an uncompressed 8 bit RGB preview is supported, that is a
+/// reduced resolution IFD as DNG writers store it in IFD 0. A JPEG preview would lose the bits
+/// when it is compressed again. The secret is hidden in the LSBs of the samples of its strips,
+/// in place, so the file keeps its size and layout. The capacity is pixels * 3 / 8 bytes.
+pub struct DngPreview {
+    bytes: Vec<u8>,
+    strips: Vec<Range<usize>>,
+}
+
+impl DngPreview {
+    /// finds the uncompressed 8 bit RGB preview in a DNG, or any TIFF. Fails with
+    /// `SteganoError::UnsupportedMedia` if there is none, e.g. with only a JPEG preview
+    pub fn decode(bytes: Vec<u8>) -> Result<Self> {
+        let tiff = Tiff::new(&bytes)?;
+        let strips = tiff
+            .ifds()?
+            .into_iter()
+            .find_map(|ifd| tiff.preview_strips(ifd).transpose())
+            .ok_or(SteganoError::UnsupportedMedia)??;
+
+        Ok(Self { bytes, strips })
+    }
+
+    /// opens a DNG file
+    #[cfg(feature = "fs")]
+    pub fn open(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        Self::decode(bytes)
+    }
+
+    /// the whole file with the secret in its preview
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// number of bytes that fit into the preview
+    pub fn capacity(&self) -> usize {
+        self.strips.iter().map(|strip| strip.len()).sum::<usize>() / 8
+    }
+
+    /// builds an Encoder that writes the secret into the samples of the preview, strip by strip
+    pub fn encoder(&mut self) -> Box<dyn Write + '_> {
+        let strips = self.strips.clone();
+        let mut rest = &mut self.bytes[..];
+        let mut consumed = 0;
+        let mut samples = Vec::new();
+        for strip in strips {
+            let (_, tail) = rest.split_at_mut(strip.start - consumed);
+            let (taken, tail) = tail.split_at_mut(strip.len());
+            samples.push(taken);
+            rest = tail;
+            consumed = strip.end;
+        }
+
+        Box::new(Encoder::new(
+            samples
+                .into_iter()
+                .flat_map(|strip| strip.iter_mut())
+                .map(MediaPrimitiveMut::ImageColorChannel),
+            OneBitHide,
+        ))
+    }
+
+    /// builds a Decoder that reads the secret from the samples of the preview, strip by strip
+    pub fn decoder(&self) -> Box<dyn Read + '_> {
+        Box::new(Decoder::new(
+            self.strips
+                .iter()
+                .flat_map(|strip| self.bytes[strip.clone()].iter())
+                .map(|sample| MediaPrimitive::ImageColorChannel(*sample)),
+            OneBitUnveil,
+        ))
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Persist for DngPreview {
+    fn save_as(&mut self, file: &Path) -> Result<()> {
+        std::fs::write(file, &self.bytes).map_err(|source| SteganoError::WriteError { source })
+    }
+}
+
+/// reads the structure of a TIFF, just enough of it to find the strips of the preview
+struct Tiff<'b> {
+    bytes: &'b [u8],
+    little_endian: bool,
+}
+
+impl<'b> Tiff<'b> {
+    fn new(bytes: &'b [u8]) -> Result<Self> {
+        let little_endian = match bytes.get(..4) {
+            Some(b"II*\0") => true,
+            Some(b"MM\0*") => false,
+            _ => return Err(SteganoError::InvalidImageMedia),
+        };
+
+        Ok(Self {
+            bytes,
+            little_endian,
+        })
+    }
+
+    /// offsets of all IFDs, the chain from IFD 0 and the sub IFDs of each
+    fn ifds(&self) -> Result<Vec<usize>> {
+        let mut pending = vec![self.u32_at(4)? as usize];
+        let mut ifds = Vec::new();
+        while let Some(ifd) = pending.pop() {
+            if ifd == 0 || ifds.contains(&ifd) {
+                continue;
+            }
+            if ifds.len() == MAX_IFDS {
+                return Err(SteganoError::InvalidImageMedia);
+            }
+            ifds.push(ifd);
+            let count = self.u16_at(ifd)? as usize;
+            pending.push(self.u32_at(ifd + 2 + count * 12)? as usize);
+            if let Some(sub_ifds) = self.values(ifd, TAG_SUB_IFDS)? {
+                pending.extend(sub_ifds.into_iter().map(|offset| offset as usize));
+            }
+        }
+
+        Ok(ifds)
+    }
+
+    /// the strips of the IFD if it is an uncompressed 8 bit RGB preview
+    fn preview_strips(&self, ifd: usize) -> Result<Option<Vec<Range<usize>>>> {
+        let value = |tag, default| -> Result<u32> {
+            Ok(self
+                .values(ifd, tag)?
+                .and_then(|values| values.first().copied())
+                .unwrap_or(default))
+        };
+        let is_preview = value(TAG_NEW_SUBFILE_TYPE, 0)? & REDUCED_RESOLUTION != 0
+            && value(TAG_COMPRESSION, UNCOMPRESSED)? == UNCOMPRESSED
+            && value(TAG_PHOTOMETRIC, 0)? == PHOTOMETRIC_RGB
+            && value(TAG_SAMPLES_PER_PIXEL, 1)? == 3
+            && value(TAG_PLANAR_CONFIGURATION, CHUNKY)? == CHUNKY
+            && self
+                .values(ifd, TAG_BITS_PER_SAMPLE)?
+                .is_some_and(|bits| bits.iter().all(|b| *b == 8));
+        if !is_preview {
+            return Ok(None);
+        }
+
+        let samples =
+            value(TAG_IMAGE_WIDTH, 0)? as usize * value(TAG_IMAGE_LENGTH, 0)? as usize * 3;
+        let offsets = self.values(ifd, TAG_STRIP_OFFSETS)?.unwrap_or_default();
+        let counts = self.values(ifd, TAG_STRIP_BYTE_COUNTS)?.unwrap_or_default();
+        let mut strips = Vec::new();
+        let mut remaining = samples;
+        for (offset, count) in offsets.into_iter().zip(counts) {
+            let len = (count as usize).min(remaining);
+            let strip = offset as usize..offset as usize + len;
+            if strip.end > self.bytes.len() {
+                return Err(SteganoError::InvalidImageMedia);
+            }
+            remaining -= len;
+            strips.push(strip);
+        }
+        strips.sort_by_key(|strip| strip.start);
+        if strips.windows(2).any(|pair| pair[0].end > pair[1].start) {
+            return Err(SteganoError::InvalidImageMedia);
+        }
+
+        Ok(Some(strips))
+    }
+
+    /// the values of the tag in the IFD, for the integer types BYTE, SHORT, LONG and IFD
+    fn values(&self, ifd: usize, tag: u16) -> Result<Option<Vec<u32>>> {
+        let count = self.u16_at(ifd)? as usize;
+        for entry in (0..count).map(|i| ifd + 2 + i * 12) {
+            if self.u16_at(entry)? != tag {
+                continue;
+            }
+            let size = match self.u16_at(entry + 2)? {
+                1 => 1,
+                3 => 2,
+                4 | 13 => 4,
+                _ => return Err(SteganoError::UnsupportedMedia),
+            };
+            let n = self.u32_at(entry + 4)? as usize;
+            let at = match n * size <= 4 {
+                true => entry + 8,
+                false => self.u32_at(entry + 8)? as usize,
+            };
+            let values = (0..n)
+                .map(|i| match size {
+                    1 => self.byte_at(at + i).map(u32::from),
+                    2 => self.u16_at(at + i * 2).map(u32::from),
+                    _ => self.u32_at(at + i * 4),
+                })
+                .collect::<Result<_>>()?;
+
+            return Ok(Some(values));
+        }
+
+        Ok(None)
+    }
+
+    fn byte_at(&self, at: usize) -> Result<u8> {
+        self.bytes
+            .get(at)
+            .copied()
+            .ok_or(SteganoError::InvalidImageMedia)
+    }
+
+    fn u16_at(&self, at: usize) -> Result<u16> {
+        let bytes = self
+            .bytes
+            .get(at..at + 2)
+            .ok_or(SteganoError::InvalidImageMedia)?;
+        let bytes = [bytes[0], bytes[1]];
+
+        Ok(match self.little_endian {
+            true => u16::from_le_bytes(bytes),
+            false => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32_at(&self, at: usize) -> Result<u32> {
+        let bytes = self
+            .bytes
+            .get(at..at + 4)
+            .ok_or(SteganoError::InvalidImageMedia)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+        Ok(match self.little_endian {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+}
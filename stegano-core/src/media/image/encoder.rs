@@ -59,7 +59,7 @@ impl<'i> Iterator for ImageRgbaColorMut<'i> {
 
     fn next(&'_ mut self) -> Option<Self::Item> {
         if self.skip_alpha && self.i > 0 {
-            let is_next_alpha = (self.i + 1) % 4 == 0;
+            let is_next_alpha = (self.i + 1).is_multiple_of(4);
             if is_next_alpha {
                 self.pixel.next();
                 self.i += 1;
@@ -91,6 +91,7 @@ mod decoder_tests {
                 skip_alpha_channel: true,
                 color_channel_step_increment: 2,
                 concealer: Concealer::LeastSignificantBit,
+                ..CodecOptions::default()
             },
         );
 
@@ -119,6 +120,7 @@ mod decoder_tests {
                 skip_alpha_channel: true,
                 color_channel_step_increment: 3,
                 concealer: Concealer::LeastSignificantBit,
+                ..CodecOptions::default()
             },
         );
 
@@ -0,0 +1,259 @@
+use ::gif::{ColorOutput, DecodeOptions, Frame, Repeat};
+use image::RgbaImage;
+use std::io::{Read, Write};
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufReader, io::BufWriter, path::Path};
+
+use crate::universal_decoder::{Decoder, OneBitUnveil};
+use crate::universal_encoder::{Encoder, OneBitHide};
+#[cfg(feature = "fs")]
+use crate::Persist;
+use crate::{MediaPrimitive, MediaPrimitiveMut, Result, SteganoError};
+
+/// speed of the NeuQuant quantizer of [`GifImage::quantize`], from 1 (best) to 30 (fastest)
+const QUANTIZER_SPEED: i32 = 10;
+
+/// An animated GIF, whose frames keep their palette indices.
+/// Secrets are hidden in the LSBs of the indices of a frame, so a flipped bit swaps the color
+/// with its neighbour in the palette. Pairs of neighbours that include the transparent color
+/// are left out, the palettes are padded to a power of 2 like the GIF encoder pads them.
+/// The capacity of a frame is its eligible pixels / 8 bytes.
+///
+/// ## Example of hiding in every frame
+/// ```rust
+/// use stegano_core::media::image::gif::GifImage;
+/// use std::io::Read;
+///
+/// let mut buf = Vec::new();
+/// {
+///     let palette = [0, 0, 0, 0xff, 0xff, 0xff];
+///     let mut encoder = gif::Encoder::new(&mut buf, 8, 8, &palette).unwrap();
+///     for _ in 0..2 {
+///         let frame = gif::Frame::from_indexed_pixels(8, 8, vec![0; 64], None);
+///         encoder.write_frame(&frame).unwrap();
+///     }
+/// }
+///
+/// let mut image = GifImage::decode(&buf[..]).unwrap();
+/// assert_eq!(image.capacity(), 8);
+/// image.hide_in_frames(b"Hi", true).unwrap();
+///
+/// let mut secret = [0; 2];
+/// image.frame_decoder(1).unwrap().read_exact(&mut secret).unwrap();
+/// assert_eq!(&secret, b"Hi");
+/// ```
+pub struct GifImage {
+    width: u16,
+    height: u16,
+    global_palette: Option<Vec<u8>>,
+    repeat: Repeat,
+    frames: Vec<Frame<'static>>,
+}
+
+impl GifImage {
+    /// decodes a GIF with all of its frames, as palette indices
+    pub fn decode(r: impl Read) -> Result<Self> {
+        let mut options = DecodeOptions::new();
+        options.set_color_output(ColorOutput::Indexed);
+        let mut decoder = options
+            .read_info(r)
+            .map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder
+            .read_next_frame()
+            .map_err(|_e| SteganoError::InvalidImageMedia)?
+        {
+            let mut frame = frame.clone();
+            frame.palette = frame.palette.map(padded);
+            frames.push(frame);
+        }
+
+        Ok(Self {
+            width: decoder.width(),
+            height: decoder.height(),
+            global_palette: decoder.global_palette().map(|p| padded(p.to_vec())),
+            repeat: decoder.repeat(),
+            frames,
+        })
+    }
+
+    /// quantizes a true color image into a GIF of one frame, so that the secret is hidden in its
+    /// palette indices instead of the RGB LSBs, that a GIF encoder would quantize away. The
+    /// quantizer is deterministic, images of at most 256 colors keep them exactly. The palette is
+    /// sorted by luminance, so that a flipped index LSB swaps to a similar color. Fails with
+    /// `SteganoError::UnsupportedMedia` for images wider or higher than a GIF can be
+    pub fn quantize(image: &RgbaImage) -> Result<Self> {
+        let dimension = |d: u32| u16::try_from(d).map_err(|_e| SteganoError::UnsupportedMedia);
+        let (width, height) = (dimension(image.width())?, dimension(image.height())?);
+        let mut rgba = image.as_raw().clone();
+        let mut frame = Frame::from_rgba_speed(width, height, &mut rgba, QUANTIZER_SPEED);
+        sort_palette(&mut frame);
+        frame.palette = frame.palette.map(padded);
+
+        Ok(Self {
+            width,
+            height,
+            global_palette: None,
+            repeat: Repeat::Finite(0),
+            frames: vec![frame],
+        })
+    }
+
+    /// opens a GIF file
+    #[cfg(feature = "fs")]
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        Self::decode(BufReader::new(file))
+    }
+
+    /// encodes the GIF with the same palettes, frames and timing as it was decoded
+    pub fn encode(&self, w: impl Write) -> Result<()> {
+        let palette = self.global_palette.as_deref().unwrap_or_default();
+        let mut encoder = ::gif::Encoder::new(w, self.width, self.height, palette)
+            .map_err(|_e| SteganoError::ImageEncodingError)?;
+        encoder
+            .set_repeat(self.repeat)
+            .map_err(|_e| SteganoError::ImageEncodingError)?;
+        for frame in &self.frames {
+            encoder
+                .write_frame(frame)
+                .map_err(|_e| SteganoError::ImageEncodingError)?;
+        }
+
+        Ok(())
+    }
+
+    /// number of frames of the animation
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// number of bytes that fit into the frame, 0 for a frame that does not exist
+    pub fn frame_capacity(&self, frame: usize) -> usize {
+        self.frames
+            .get(frame)
+            .map(|f| f.buffer.iter().filter(|i| self.is_eligible(f, **i)).count() / 8)
+            .unwrap_or(0)
+    }
+
+    /// number of bytes that fit into every frame
+    pub fn capacity(&self) -> usize {
+        (0..self.frames.len())
+            .map(|frame| self.frame_capacity(frame))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// hides `secret` into every frame, or only into the first one if `per_frame` is false.
+    /// Fails with `SteganoError::CarrierTooSmall` if it does not fit, before any frame is touched
+    pub fn hide_in_frames(&mut self, secret: &[u8], per_frame: bool) -> Result<()> {
+        let capacity = match per_frame {
+            true => self.capacity(),
+            false => self.frame_capacity(0),
+        };
+        if secret.len() > capacity {
+            return Err(SteganoError::CarrierTooSmall {
+                needed: secret.len(),
+                capacity,
+            });
+        }
+        let frames = match per_frame {
+            true => self.frames.len(),
+            false => 1,
+        };
+        for frame in 0..frames {
+            self.frame_encoder(frame).write_all(secret)?;
+        }
+
+        Ok(())
+    }
+
+    /// builds a Decoder that reads the secret from the indices of the frame, row by row
+    pub fn frame_decoder(&self, frame: usize) -> Option<Box<dyn Read + '_>> {
+        let f = self.frames.get(frame)?;
+
+        Some(Box::new(Decoder::new(
+            f.buffer
+                .iter()
+                .filter(move |i| self.is_eligible(f, **i))
+                .map(|i| MediaPrimitive::ImageColorChannel(*i)),
+            OneBitUnveil,
+        )))
+    }
+
+    /// builds an Encoder that writes the secret into the indices of the frame, row by row
+    fn frame_encoder(&mut self, frame: usize) -> Box<dyn Write + '_> {
+        let global = self.global_palette.as_deref();
+        let f = &mut self.frames[frame];
+        let colors = f.palette.as_deref().or(global).unwrap_or_default().len() / 3;
+        let transparent = f.transparent;
+
+        Box::new(Encoder::new(
+            f.buffer
+                .to_mut()
+                .iter_mut()
+                .filter(move |i| eligible(**i, colors, transparent))
+                .map(MediaPrimitiveMut::ImageColorChannel),
+            OneBitHide,
+        ))
+    }
+
+    fn is_eligible(&self, frame: &Frame, index: u8) -> bool {
+        let palette = frame.palette.as_deref().or(self.global_palette.as_deref());
+        let colors = palette.unwrap_or_default().len() / 3;
+
+        eligible(index, colors, frame.transparent)
+    }
+}
+
+/// true if the index and its neighbour, that differs in the LSB, are both opaque colors of the
+/// palette. Neighbours share this, so hiding never changes which pixels are eligible
+fn eligible(index: u8, colors: usize, transparent: Option<u8>) -> bool {
+    (index as usize | 1) < colors && transparent.map(|t| t | 1) != Some(index | 1)
+}
+
+/// sorts the local palette of the frame by luminance and maps its indices to match, so that
+/// neighbours in the palette look alike
+fn sort_palette(frame: &mut Frame) {
+    let Some(palette) = frame.palette.as_mut() else {
+        return;
+    };
+    let colors: Vec<[u8; 3]> = palette
+        .chunks_exact(3)
+        .map(|c| [c[0], c[1], c[2]])
+        .collect();
+    let mut order: Vec<usize> = (0..colors.len()).collect();
+    order.sort_by_key(|i| {
+        let [r, g, b] = colors[*i];
+        (299 * r as u32 + 587 * g as u32 + 114 * b as u32, colors[*i])
+    });
+    let mut new_index = vec![0u8; colors.len()];
+    for (new, old) in order.iter().enumerate() {
+        new_index[*old] = new as u8;
+    }
+
+    *palette = order.iter().flat_map(|i| colors[*i]).collect();
+    for index in frame.buffer.to_mut().iter_mut() {
+        *index = new_index[*index as usize];
+    }
+    frame.transparent = frame.transparent.map(|t| new_index[t as usize]);
+}
+
+/// the palette padded with black to the power of 2 number of colors, the GIF encoder writes
+fn padded(mut palette: Vec<u8>) -> Vec<u8> {
+    let colors = (palette.len() / 3).clamp(2, 256).next_power_of_two();
+    palette.resize(colors * 3, 0);
+
+    palette
+}
+
+#[cfg(feature = "fs")]
+impl Persist for GifImage {
+    fn save_as(&mut self, file: &Path) -> Result<()> {
+        let file = File::create(file).map_err(|source| SteganoError::WriteError { source })?;
+
+        self.encode(BufWriter::new(file))
+    }
+}
@@ -0,0 +1,136 @@
+use image::{ImageFormat, ImageOutputFormat, Rgba32FImage};
+use std::io::{Cursor, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::media::image::lsb_codec::hide_algorithm;
+use crate::media::image::{CodecOptions, LsbCodec};
+use crate::universal_decoder::{Decoder, OneBitUnveil};
+use crate::universal_encoder::Encoder;
+use crate::{MediaPrimitive, MediaPrimitiveMut, Result, SteganoError};
+
+/// decodes an OpenEXR image, its magic number tells it apart from other formats
+pub fn decode_exr(buf: &[u8]) -> Result<Rgba32FImage> {
+    Ok(
+        image::load_from_memory_with_format(buf, ImageFormat::OpenExr)
+            .map_err(|_e| SteganoError::InvalidImageMedia)?
+            .to_rgba32f(),
+    )
+}
+
+/// opens an OpenEXR file
+#[cfg(feature = "fs")]
+pub fn open_exr(path: &Path) -> Result<Rgba32FImage> {
+    let buf = std::fs::read(path).map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+    decode_exr(&buf)
+}
+
+/// encodes as OpenEXR with 32 bit float channels, that keeps every mantissa bit
+pub fn encode_exr(image: &Rgba32FImage) -> Result<Vec<u8>> {
+    let mut buf = Cursor::new(Vec::new());
+    image
+        .write_to(&mut buf, ImageOutputFormat::OpenExr)
+        .map_err(|_e| SteganoError::ImageEncodingError)?;
+
+    Ok(buf.into_inner())
+}
+
+impl LsbCodec {
+    /// builds a decoder that reads the secret from the mantissa LSBs of the float channels
+    ///
+    /// ## Example how to hide and unveil in a HDR image:
+    /// ```rust
+    /// use stegano_core::media::image::{CodecOptions, LsbCodec};
+    /// use image::{Rgba, Rgba32FImage};
+    ///
+    /// let mut image = Rgba32FImage::from_pixel(8, 8, Rgba([0.25, 1.5, 42.0, 1.0]));
+    /// LsbCodec::float_encoder(&mut image, &CodecOptions::default())
+    ///     .write_all(b"HDR!")
+    ///     .expect("Cannot write to codec");
+    ///
+    /// let mut buf = vec![0; 4];
+    /// LsbCodec::float_decoder(&image, &CodecOptions::default())
+    ///     .read_exact(&mut buf[..])
+    ///     .expect("Cannot read 4 bytes from codec");
+    /// assert_eq!(&buf, b"HDR!");
+    /// ```
+    pub fn float_decoder<'i>(input: &'i Rgba32FImage, _opts: &CodecOptions) -> Box<dyn Read + 'i> {
+        Box::new(Decoder::new(Self::float_channels(input), OneBitUnveil))
+    }
+
+    /// iterates the float channels that carry the secret, red, green and blue of each pixel,
+    /// row by row. Infinite and NaN channels are skipped, hiding keeps finite channels finite
+    pub fn float_channels(input: &Rgba32FImage) -> impl Iterator<Item = MediaPrimitive> + '_ {
+        input
+            .pixels()
+            .flat_map(|p| p.0[..3].iter())
+            .filter(|c| c.is_finite())
+            .map(|c| MediaPrimitive::ImageFloatChannel(c.to_bits()))
+    }
+
+    /// builds an encoder that writes the secret into the mantissa LSBs of the float channels.
+    /// The pixel order is fixed, `ordering_seed` and `bits_per_channel` are not used
+    pub fn float_encoder<'i>(
+        carrier: &'i mut Rgba32FImage,
+        opts: &CodecOptions,
+    ) -> Box<dyn Write + 'i> {
+        let channels = carrier
+            .pixels_mut()
+            .flat_map(|p| p.0[..3].iter_mut())
+            .filter(|c| c.is_finite())
+            .map(MediaPrimitiveMut::ImageFloatChannel);
+        let encoder = Encoder::new(channels, hide_algorithm(opts));
+        let encoder = match opts.cancel.as_ref() {
+            Some(token) => encoder.with_cancel(token.clone()),
+            None => encoder,
+        };
+        match opts.deadline {
+            Some(deadline) => Box::new(encoder.with_deadline(deadline)),
+            None => Box::new(encoder),
+        }
+    }
+
+    /// number of bytes a HDR image can carry
+    pub fn float_capacity(input: &Rgba32FImage) -> usize {
+        Self::float_channels(input).count() / 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn should_round_trip_a_message_through_an_exr_file() -> Result<()> {
+        let mut image = Rgba32FImage::from_fn(32, 16, |x, y| {
+            Rgba([x as f32 / 7.0, y as f32 * 13.37, 1e-3 / (x + 1) as f32, 1.0])
+        });
+        // an infinite highlight is no carrier and must stay as it is
+        image.get_pixel_mut(0, 0).0[0] = f32::INFINITY;
+        let origin = image.clone();
+
+        LsbCodec::float_encoder(&mut image, &CodecOptions::default())
+            .write_all(b"Hello HDR!")
+            .expect("Cannot write to codec");
+        let image = decode_exr(&encode_exr(&image)?)?;
+
+        let mut buf = vec![0; 10];
+        LsbCodec::float_decoder(&image, &CodecOptions::default())
+            .read_exact(&mut buf)
+            .expect("Cannot read 10 bytes from codec");
+        assert_eq!(&buf, b"Hello HDR!");
+        assert_eq!(image.get_pixel(0, 0).0[0], f32::INFINITY);
+        let max_distortion = origin
+            .pixels()
+            .zip(image.pixels())
+            .flat_map(|(a, b)| a.0.into_iter().zip(b.0))
+            .filter(|(a, _b)| a.is_finite())
+            .map(|(a, b)| (a.to_bits() as i64 - b.to_bits() as i64).abs())
+            .max();
+        assert_eq!(max_distortion, Some(1), "Only the mantissa LSB may change");
+
+        Ok(())
+    }
+}
@@ -1,11 +1,24 @@
+use crate::format::{TRAVERSAL_PREAMBLE, TRAVERSAL_PREAMBLE_LEN};
+use crate::media::image::cursor::{
+    Channel, ChannelOrder, Checkerboard, CursorColor, CursorEncoder, InChannelOrder, Masked,
+    OnlyChannel, PixelCursor, RandomCursor, RowStride, SequentialCursor, SkipFirstPixels,
+    SkipTransparent, TraversalOrder,
+};
 use crate::media::image::decoder::ImageRgbaColor;
 use crate::media::image::encoder::ImageRgbaColorMut;
 use crate::universal_decoder::{Decoder, OneBitUnveil};
-use crate::universal_encoder::{Encoder, HideAlgorithms, OneBitHide, OneBitInLowFrequencyHide};
-use image::RgbaImage;
-use std::io::{Read, Write};
+use crate::universal_encoder::{
+    Encoder, HideAlgorithms, OneBitHide, OneBitInLowFrequencyHide, OneBitMatchingHide,
+};
+use crate::{CancellationToken, MediaPrimitive, Secret, SteganoError};
+use image::{GrayImage, Rgba, RgbaImage};
+use log::trace;
+use std::io::{Read, Result, Write};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CodecOptions {
     /// would move the by step n each iteration,
     /// Note: the alpha channel is count as regular channel
@@ -14,12 +27,58 @@ pub struct CodecOptions {
     pub skip_alpha_channel: bool,
     /// the concealer strategy
     pub concealer: Concealer,
+    /// if set, encoding stops as soon as the token is cancelled
+    pub cancel: Option<CancellationToken>,
+    /// if set, encoding stops as soon as this instant has passed, see
+    /// [`crate::SteganoEncoder::time_budget`]
+    pub deadline: Option<Instant>,
+    /// if set, the color channels are visited in a pseudo random order derived from this seed,
+    /// see [`RandomCursor`] and [`crate::media::image::cursor::passphrase_seed`]
+    pub ordering_seed: Option<Arc<Secret<u64>>>,
+    /// number of low bits of every color channel that carry the secret, from 1 to 8.
+    /// More bits multiply the capacity, but also the visible distortion.
+    /// Note: with more than 1 bit the channels are visited as by [`SequentialCursor`]
+    /// (or [`RandomCursor`]), `color_channel_step_increment` and `concealer` are not used then
+    pub bits_per_channel: u8,
+    /// if true, fully transparent pixels (alpha 0) carry nothing, they stay untouched.
+    /// Note: the channels are visited as by [`SequentialCursor`] (or [`RandomCursor`]) then
+    pub skip_transparent: bool,
+    /// only every nth row carries the secret, that is the rows where `y % row_stride == 0`,
+    /// e.g. to dodge filters that work along rows. The capacity shrinks to about `1 / row_stride`.
+    /// Note: the channels are visited as by [`SequentialCursor`] (or [`RandomCursor`]) then
+    pub row_stride: u32,
+    /// if true, only the pixels where `(x + y) % 2 == 0` carry the secret, see [`Checkerboard`].
+    /// The capacity halves. Note: the channels are visited as by [`SequentialCursor`]
+    /// (or [`RandomCursor`]) then
+    pub checkerboard: bool,
+    /// how the tool that hides or hid the secret names the bytes of a pixel, see [`ChannelOrder`].
+    /// Note: the channels are visited as by [`SequentialCursor`] (or [`RandomCursor`]) then
+    pub channel_order: ChannelOrder,
+    /// number of low bits of the red, green and blue channel, each from 1 to 8, e.g. more in
+    /// blue that the eye sees least. Overrides `bits_per_channel`, like that it is visited as by
+    /// [`SequentialCursor`] (or [`RandomCursor`])
+    pub channel_bit_depths: Option<[u8; 3]>,
+    /// if set, only this channel of each pixel carries the secret, the others stay untouched.
+    /// The capacity shrinks to a third. Note: the channels are visited as by
+    /// [`SequentialCursor`] (or [`RandomCursor`]) then
+    pub single_channel: Option<Channel>,
+    /// if set, the channels are visited in this order, around the pixels of the traversal
+    /// preamble, see [`LsbCodec::hide_traversal_preamble`]. `ordering_seed` is not used then.
+    /// Note: the channels are visited as by [`SequentialCursor`] (or [`RandomCursor`]) then
+    pub traversal: Option<TraversalOrder>,
+    /// if set, only the pixels that are white in this grayscale mask carry the secret, see
+    /// [`Masked`]. It has the dimensions of the carrier. Note: the channels are visited as by
+    /// [`SequentialCursor`] (or [`RandomCursor`]) then
+    pub mask: Option<Arc<GrayImage>>,
 }
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Concealer {
     LeastSignificantBit,
     LowFrequencies,
+    /// sets the LSB by adding or subtracting 1 instead of replacing it, with 1 bit per channel
+    /// only, see [`OneBitMatchingHide`]. It reads back like `LeastSignificantBit`
+    LsbMatching,
 }
 
 impl Default for CodecOptions {
@@ -28,6 +87,18 @@ impl Default for CodecOptions {
             color_channel_step_increment: 1,
             skip_alpha_channel: true,
             concealer: Concealer::LeastSignificantBit,
+            cancel: None,
+            deadline: None,
+            ordering_seed: None,
+            bits_per_channel: 1,
+            skip_transparent: false,
+            row_stride: 1,
+            checkerboard: false,
+            channel_order: ChannelOrder::Rgba,
+            channel_bit_depths: None,
+            single_channel: None,
+            traversal: None,
+            mask: None,
         }
     }
 }
@@ -40,6 +111,71 @@ impl CodecOptions {
     pub fn get_skip_alpha_channel(&self) -> bool {
         self.skip_alpha_channel
     }
+
+    /// number of low bits that carry the secret, for each channel indexed by
+    /// [`crate::media::image::cursor::Channel::index`]
+    pub fn bit_depths(&self) -> [u8; 4] {
+        let bits = self.bits_per_channel.max(1);
+        match self.channel_bit_depths {
+            Some([r, g, b]) => [r, g, b, bits],
+            None => [bits; 4],
+        }
+    }
+
+    /// true if more than 1 bit of any channel carries the secret
+    pub fn is_multi_bit(&self) -> bool {
+        self.bit_depths().iter().any(|bits| *bits > 1)
+    }
+
+    /// checks that the bits of every channel are between 1 and 8, so that out of range options
+    /// fail with `SteganoError::InvalidParameter` before anything is hidden or unveiled,
+    /// instead of a corrupt embedding
+    pub fn validate(&self) -> crate::Result<()> {
+        if !(1..=8).contains(&self.bits_per_channel) {
+            return Err(SteganoError::InvalidParameter(format!(
+                "bits per channel must be between 1 and 8, but is {}",
+                self.bits_per_channel
+            )));
+        }
+        if let Some(depths) = self
+            .channel_bit_depths
+            .filter(|depths| depths.iter().any(|bits| !(1..=8).contains(bits)))
+        {
+            return Err(SteganoError::InvalidParameter(format!(
+                "bits of each channel must be between 1 and 8, but are {depths:?}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// checks that the mask, if any, has the dimensions of the image, so that a mask of another
+    /// image fails with `SteganoError::InvalidParameter` instead of hiding in the wrong pixels
+    pub fn check_mask(&self, image: &RgbaImage) -> crate::Result<()> {
+        match self.mask.as_ref() {
+            Some(mask) if mask.dimensions() != image.dimensions() => {
+                Err(SteganoError::InvalidParameter(format!(
+                    "the mask is {:?}, but the image is {:?}",
+                    mask.dimensions(),
+                    image.dimensions()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// true if a cancellation token is set and was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .map(|t| t.load(Ordering::Relaxed))
+            .unwrap_or(false)
+    }
+
+    /// true if a deadline is set and has passed
+    pub fn is_past_deadline(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
 }
 
 /// Factory for decoder and encoder
@@ -67,14 +203,114 @@ impl LsbCodec {
     /// ```
     pub fn decoder<'i>(input: &'i RgbaImage, opts: &CodecOptions) -> Box<dyn Read + 'i> {
         Box::new(Decoder::new(
-            ImageRgbaColor::new_with_options(input, opts),
+            Self::channels(input, opts),
             match opts.concealer {
                 Concealer::LeastSignificantBit => OneBitUnveil,
                 Concealer::LowFrequencies => OneBitUnveil,
+                Concealer::LsbMatching => OneBitUnveil,
             },
         ))
     }
 
+    /// iterates the color channels that carry the secret, in the order they carry it.
+    /// With more than 1 bit per channel, every channel is repeated shifted by each of its bits,
+    /// so that each item carries exactly one bit in its LSB
+    pub fn channels<'i>(
+        input: &'i RgbaImage,
+        opts: &CodecOptions,
+    ) -> Box<dyn Iterator<Item = MediaPrimitive> + 'i> {
+        match Self::cursor(input, opts) {
+            None => Box::new(ImageRgbaColor::new_with_options(input, opts)),
+            Some(cursor) => {
+                Box::new(CursorColor::new(input, cursor).with_bit_depths(opts.bit_depths()))
+            }
+        }
+    }
+
+    /// the cursor that the options ask for, `None` for the default column based traversal
+    fn cursor(image: &RgbaImage, opts: &CodecOptions) -> Option<Box<dyn PixelCursor>> {
+        let (width, height) = image.dimensions();
+        trace!(
+            "traversing {width}x{height} pixels with {:?} bit depths",
+            opts.bit_depths()
+        );
+        let cursor: Box<dyn PixelCursor> = match (
+            opts.traversal,
+            opts.ordering_seed.as_deref().map(Secret::expose),
+        ) {
+            (Some(order), _) => Box::new(SkipFirstPixels::new(
+                order.cursor(width, height),
+                height,
+                Self::TRAVERSAL_PREAMBLE_PIXELS,
+            )),
+            (None, Some(seed)) => Box::new(RandomCursor::new(width, height, *seed)),
+            (None, None)
+                if opts.is_multi_bit()
+                    || opts.skip_transparent
+                    || opts.row_stride > 1
+                    || opts.checkerboard
+                    || opts.mask.is_some()
+                    || opts.single_channel.is_some()
+                    || opts.channel_order != ChannelOrder::Rgba =>
+            {
+                Box::new(SequentialCursor::new(width, height))
+            }
+            (None, None) => return None,
+        };
+        let cursor: Box<dyn PixelCursor> = match opts.single_channel {
+            Some(channel) => Box::new(OnlyChannel::new(cursor, channel)),
+            None => cursor,
+        };
+        let cursor: Box<dyn PixelCursor> = match opts.channel_order {
+            ChannelOrder::Rgba => cursor,
+            order => Box::new(InChannelOrder::new(cursor, order)),
+        };
+        let cursor: Box<dyn PixelCursor> = match opts.row_stride {
+            0 | 1 => cursor,
+            stride => Box::new(RowStride::new(cursor, stride)),
+        };
+        let cursor: Box<dyn PixelCursor> = match opts.checkerboard {
+            true => Box::new(Checkerboard::new(cursor)),
+            false => cursor,
+        };
+        let cursor: Box<dyn PixelCursor> = match opts.mask.as_ref() {
+            Some(mask) => Box::new(Masked::new(mask, cursor)),
+            None => cursor,
+        };
+
+        Some(match opts.skip_transparent {
+            true => Box::new(SkipTransparent::new(image, cursor)),
+            false => cursor,
+        })
+    }
+
+    /// number of pixels of the traversal preamble, 3 bits each
+    pub const TRAVERSAL_PREAMBLE_PIXELS: u64 = (TRAVERSAL_PREAMBLE_LEN as u64 * 8).div_ceil(3);
+
+    /// hides the preamble, that tells the traversal of the message, in the first pixels of the
+    /// default traversal, see [`crate::format::TRAVERSAL_PREAMBLE`]. Hide the message with the
+    /// same order in [`CodecOptions::traversal`]
+    pub fn hide_traversal_preamble(carrier: &mut RgbaImage, order: TraversalOrder) -> Result<()> {
+        let mut preamble = vec![TRAVERSAL_PREAMBLE];
+        preamble.extend(order.to_bytes());
+        preamble.resize(TRAVERSAL_PREAMBLE_LEN, 0);
+
+        Self::encoder(carrier, &CodecOptions::default()).write_all(&preamble)
+    }
+
+    /// the traversal told by the preamble, `None` if the image has none
+    pub fn read_traversal_preamble(input: &RgbaImage) -> Option<TraversalOrder> {
+        let mut preamble = [0; TRAVERSAL_PREAMBLE_LEN];
+        Self::decoder(input, &CodecOptions::default())
+            .read_exact(&mut preamble)
+            .ok()?;
+
+        match preamble[0] {
+            TRAVERSAL_PREAMBLE => TraversalOrder::from_bytes(&preamble[1..]),
+            _ => None,
+        }
+    }
+
     /// builds a LSB Image Encoder that implements Write
     /// ## Example how to retrieve an encoder:
     ///
@@ -103,13 +339,446 @@ impl LsbCodec {
     /// assert_eq!(msg, "Hello World!");
     /// ```
     pub fn encoder<'i>(carrier: &'i mut RgbaImage, opts: &CodecOptions) -> Box<dyn Write + 'i> {
-        let algorithm: HideAlgorithms = match opts.concealer {
-            Concealer::LeastSignificantBit => OneBitHide.into(),
-            Concealer::LowFrequencies => OneBitInLowFrequencyHide.into(),
-        };
-        Box::new(Encoder::new(
+        if let Some(cursor) = Self::cursor(carrier, opts) {
+            return Self::with_cursor(cursor).encoder(carrier, opts);
+        }
+
+        let algorithm = hide_algorithm(opts);
+        let encoder = Encoder::new(
             ImageRgbaColorMut::new_with_options(carrier, opts),
             algorithm,
+        );
+        let encoder = match opts.cancel.as_ref() {
+            Some(token) => encoder.with_cancel(token.clone()),
+            None => encoder,
+        };
+        match opts.deadline {
+            Some(deadline) => Box::new(encoder.with_deadline(deadline)),
+            None => Box::new(encoder),
+        }
+    }
+
+    /// builds a codec that visits the color channels in the order given by a custom `PixelCursor`,
+    /// instead of the default column based traversal.
+    /// Note: `color_channel_step_increment` and `skip_alpha_channel` are up to the cursor then.
+    ///
+    /// ## Example how to hide and unveil with a cursor:
+    /// ```rust
+    /// use stegano_core::media::image::{CodecOptions, LsbCodec};
+    /// use stegano_core::media::image::cursor::RandomCursor;
+    ///
+    /// let mut image = image::open("../resources/plain/carrier-image.png")
+    ///     .expect("Cannot open carrier image")
+    ///     .to_rgba8();
+    /// let (width, height) = image.dimensions();
+    ///
+    /// LsbCodec::with_cursor(RandomCursor::new(width, height, 42))
+    ///     .encoder(&mut image, &CodecOptions::default())
+    ///     .write_all(b"Hello World!")
+    ///     .expect("Cannot write to codec");
+    ///
+    /// let mut buf = vec![0; 12];
+    /// LsbCodec::with_cursor(RandomCursor::new(width, height, 42))
+    ///     .decoder(&image, &CodecOptions::default())
+    ///     .read_exact(&mut buf[..])
+    ///     .expect("Cannot read 12 bytes from codec");
+    /// assert_eq!(&buf, b"Hello World!");
+    /// ```
+    pub fn with_cursor<C: PixelCursor>(cursor: C) -> CursorCodec<C> {
+        CursorCodec { cursor }
+    }
+}
+
+/// Factory for decoder and encoder that traverse the image by a `PixelCursor`,
+/// see [`LsbCodec::with_cursor`]
+pub struct CursorCodec<C: PixelCursor> {
+    cursor: C,
+}
+
+impl<C: PixelCursor> CursorCodec<C> {
+    /// builds a LSB Image Decoder that implements Read
+    pub fn decoder<'i>(self, input: &'i RgbaImage, opts: &CodecOptions) -> Box<dyn Read + 'i>
+    where
+        C: 'i,
+    {
+        let channels = CursorColor::new(input, self.cursor).with_bit_depths(opts.bit_depths());
+        Box::new(Decoder::new(
+            channels,
+            match opts.concealer {
+                Concealer::LeastSignificantBit => OneBitUnveil,
+                Concealer::LowFrequencies => OneBitUnveil,
+                Concealer::LsbMatching => OneBitUnveil,
+            },
         ))
     }
+
+    /// builds a LSB Image Encoder that implements Write
+    pub fn encoder<'i>(self, carrier: &'i mut RgbaImage, opts: &CodecOptions) -> Box<dyn Write + 'i>
+    where
+        C: 'i,
+    {
+        let encoder = CursorEncoder::new(carrier, self.cursor, hide_algorithm(opts))
+            .with_bit_depths(opts.bit_depths());
+        let encoder = match opts.cancel.as_ref() {
+            Some(token) => encoder.with_cancel(token.clone()),
+            None => encoder,
+        };
+        match opts.deadline {
+            Some(deadline) => Box::new(encoder.with_deadline(deadline)),
+            None => Box::new(encoder),
+        }
+    }
+}
+
+/// seed of the directions of [`OneBitMatchingHide`]
+const MATCHING_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+pub(crate) fn hide_algorithm(opts: &CodecOptions) -> HideAlgorithms {
+    match opts.concealer {
+        Concealer::LeastSignificantBit => OneBitHide.into(),
+        Concealer::LowFrequencies => OneBitInLowFrequencyHide.into(),
+        Concealer::LsbMatching => OneBitMatchingHide::new(MATCHING_SEED).into(),
+    }
+}
+
+/// color of a changed pixel in [`diff_image`]
+const DIFF_MARKER: Rgba<u8> = Rgba([0xff, 0, 0, 0xff]);
+/// color of an unchanged pixel in [`diff_image`]
+const DIFF_UNCHANGED: Rgba<u8> = Rgba([0, 0, 0, 0xff]);
+
+/// highlights the pixels that differ between the original carrier and the image with the secret.
+/// The result is black, with every changed pixel marked in plain red, so that even a single
+/// changed LSB is visible. Pixels that exist in only one of both images count as changed.
+pub fn diff_image(original: &RgbaImage, stego: &RgbaImage) -> RgbaImage {
+    let (width, height) = original.dimensions();
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        match (original.get_pixel(x, y), stego.get_pixel_checked(x, y)) {
+            (a, Some(b)) if a == b => DIFF_UNCHANGED,
+            _ => DIFF_MARKER,
+        }
+    })
+}
+
+/// the Shannon entropy of the red, green and blue values of all pixels, in bits from 0.0 for a
+/// solid color to 8.0 for values that are spread evenly. Alpha is ignored. Secrets hide badly
+/// in low entropy carriers, because the changed LSBs stand out of the flat color around them
+pub fn carrier_entropy(img: &RgbaImage) -> f64 {
+    let mut histogram = [0usize; 256];
+    for pixel in img.pixels() {
+        for value in &pixel.0[..3] {
+            histogram[*value as usize] += 1;
+        }
+    }
+    let total = img.pixels().len() as f64 * 3.0;
+
+    histogram
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// the changes of one channel between two images, see [`channel_change_report`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelChanges {
+    /// number of pixels whose value of the channel differs
+    pub changed_pixels: usize,
+    /// number of pixels whose bit of the channel differs, from the LSB at 0 to the MSB at 7
+    pub changed_bits: [usize; 8],
+}
+
+impl ChannelChanges {
+    /// the bit positions that differ in at least one pixel
+    pub fn changed_bit_planes(&self) -> Vec<u8> {
+        (0..8)
+            .filter(|bit| self.changed_bits[*bit as usize] > 0)
+            .collect()
+    }
+}
+
+/// the changes of every channel between two images, see [`channel_change_report`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelChangeReport {
+    /// the changes of red, green, blue and alpha, in this order
+    pub channels: [ChannelChanges; 4],
+}
+
+impl ChannelChangeReport {
+    /// the changes of the given channel
+    pub fn channel(&self, channel: Channel) -> &ChannelChanges {
+        &self.channels[channel.index()]
+    }
+}
+
+/// counts per channel which pixels and which of their bits differ between the original carrier
+/// and the image with the secret, that shows if hiding touched only the intended bit planes.
+/// Only the pixels that exist in both images are compared
+pub fn channel_change_report(original: &RgbaImage, stego: &RgbaImage) -> ChannelChangeReport {
+    let mut report = ChannelChangeReport::default();
+    for (x, y, a) in original.enumerate_pixels() {
+        let Some(b) = stego.get_pixel_checked(x, y) else {
+            continue;
+        };
+        for (changes, (a, b)) in report.channels.iter_mut().zip(a.0.iter().zip(b.0)) {
+            let flipped = a ^ b;
+            if flipped == 0 {
+                continue;
+            }
+            changes.changed_pixels += 1;
+            for (bit, count) in changes.changed_bits.iter_mut().enumerate() {
+                *count += usize::from((flipped >> bit) & 1);
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::image::cursor::Channel;
+    use crate::test_utils::prepare_small_image;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn should_validate_the_bit_depths() {
+        for bits in 1..=8 {
+            let opts = CodecOptions {
+                bits_per_channel: bits,
+                channel_bit_depths: Some([1, bits, 8]),
+                ..CodecOptions::default()
+            };
+            assert!(opts.validate().is_ok(), "{bits} bits");
+        }
+        for (bits, depths) in [
+            (0, None),
+            (9, None),
+            (1, Some([1, 0, 1])),
+            (1, Some([9, 1, 1])),
+        ] {
+            let opts = CodecOptions {
+                bits_per_channel: bits,
+                channel_bit_depths: depths,
+                ..CodecOptions::default()
+            };
+            assert!(
+                matches!(opts.validate(), Err(SteganoError::InvalidParameter(_))),
+                "{bits} bits, {depths:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_stop_encoding_once_cancelled() {
+        let origin = image::open("../resources/plain/carrier-image.png")
+            .expect("Cannot open carrier image")
+            .to_rgba8();
+        let mut img = origin.clone();
+        let token = Arc::new(AtomicBool::new(false));
+        let opts = CodecOptions {
+            cancel: Some(token.clone()),
+            ..CodecOptions::default()
+        };
+        {
+            let mut encoder = LsbCodec::encoder(&mut img, &opts);
+            encoder
+                .write_all(&[0xAA; 16])
+                .expect("Cannot write the first 16 bytes");
+
+            token.store(true, Ordering::Relaxed);
+            assert!(
+                encoder.write_all(&[0x55; 1024]).is_err(),
+                "Writing after cancellation should fail"
+            );
+        }
+
+        let touched_channels = 16 * 8;
+        let mut decoded = vec![0; 16];
+        LsbCodec::decoder(&img, &CodecOptions::default())
+            .read_exact(&mut decoded)
+            .expect("Cannot read 16 bytes from codec");
+        assert_eq!(decoded, vec![0xAA; 16]);
+
+        let untouched = ImageRgbaColor::new(&origin)
+            .zip(ImageRgbaColor::new(&img))
+            .skip(touched_channels)
+            .all(|(a, b)| a == b);
+        assert!(
+            untouched,
+            "No color channel after cancellation should change"
+        );
+    }
+
+    #[test]
+    fn should_not_touch_the_carrier_if_cancelled_upfront() {
+        let origin = prepare_small_image();
+        let mut img = origin.clone();
+        let opts = CodecOptions {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..CodecOptions::default()
+        };
+
+        assert!(LsbCodec::encoder(&mut img, &opts)
+            .write_all(&[0xff])
+            .is_err());
+        assert_eq!(origin, img, "Carrier should be untouched");
+    }
+
+    #[test]
+    fn should_round_trip_with_several_bits_per_channel() {
+        let origin = prepare_small_image();
+        let mut img = origin.clone();
+        // 3 bits do not add up to a byte, so bytes span over channels
+        let opts = CodecOptions {
+            bits_per_channel: 3,
+            ..CodecOptions::default()
+        };
+        LsbCodec::encoder(&mut img, &opts)
+            .write_all(b"Hello World!")
+            .expect("Cannot write to codec");
+
+        let mut buf = vec![0; 12];
+        LsbCodec::decoder(&img, &opts)
+            .read_exact(&mut buf)
+            .expect("Cannot read 12 bytes from codec");
+        assert_eq!(&buf, b"Hello World!");
+        let untouched = origin
+            .as_raw()
+            .iter()
+            .zip(img.as_raw())
+            .all(|(a, b)| a & 0b1111_1000 == b & 0b1111_1000);
+        assert!(untouched, "Only the 3 lowest bits should change");
+    }
+
+    #[test]
+    fn should_round_trip_with_its_own_bits_per_channel() {
+        let origin = prepare_small_image();
+        let mut img = origin.clone();
+        let opts = CodecOptions {
+            channel_bit_depths: Some([1, 1, 2]),
+            ..CodecOptions::default()
+        };
+        let capacity = LsbCodec::channels(&img, &opts).count() / 8;
+        assert_eq!(capacity, (img.width() * img.height()) as usize * 4 / 8);
+        let secret = crate::analysis::random_payload(capacity);
+        LsbCodec::encoder(&mut img, &opts)
+            .write_all(&secret)
+            .expect("Cannot write to codec");
+
+        let mut buf = vec![0; capacity];
+        LsbCodec::decoder(&img, &opts)
+            .read_exact(&mut buf)
+            .expect("Cannot read from codec");
+        assert_eq!(buf, secret);
+        for (a, b) in origin.pixels().zip(img.pixels()) {
+            assert_eq!(a.0[0] & !1, b.0[0] & !1, "Only the LSB of red may change");
+            assert_eq!(a.0[1] & !1, b.0[1] & !1, "Only the LSB of green may change");
+            assert_eq!(
+                a.0[2] & !3,
+                b.0[2] & !3,
+                "Only the 2 LSBs of blue may change"
+            );
+            assert_eq!(a.0[3], b.0[3], "Alpha should be untouched");
+        }
+    }
+
+    #[test]
+    fn diff_image_should_mark_exactly_the_touched_pixels() {
+        let origin = RgbaImage::from_pixel(16, 16, Rgba([0x80, 0x40, 0x20, 0xff]));
+        let mut img = origin.clone();
+        // all LSBs are 0, so every written 1 bit changes a color channel
+        LsbCodec::encoder(&mut img, &CodecOptions::default())
+            .write_all(&[0xff; 3])
+            .expect("Cannot write 3 bytes");
+
+        let diff = diff_image(&origin, &img);
+        assert_eq!(diff.dimensions(), origin.dimensions());
+        let changed = diff.pixels().filter(|p| **p != DIFF_UNCHANGED).count();
+        assert_eq!(changed, 3 * 8 / 3, "3 bytes touch 24 channels, so 8 pixels");
+        assert_eq!(diff.get_pixel(0, 7), &DIFF_MARKER);
+        assert_eq!(diff.get_pixel(0, 8), &DIFF_UNCHANGED);
+    }
+
+    /// visits all red, green and blue channels starting at the very last pixel
+    struct ReverseCursor {
+        remaining: u32,
+        width: u32,
+    }
+
+    impl PixelCursor for ReverseCursor {
+        fn next_position(&mut self) -> Option<(u32, u32, Channel)> {
+            if self.remaining == 0 {
+                return None;
+            }
+            self.remaining -= 1;
+            let pixel = self.remaining / 3;
+            Some((
+                pixel % self.width,
+                pixel / self.width,
+                Channel::RGB[(self.remaining % 3) as usize],
+            ))
+        }
+    }
+
+    #[test]
+    fn should_round_trip_with_a_custom_cursor() {
+        let origin = image::open("../resources/plain/carrier-image.png")
+            .expect("Cannot open carrier image")
+            .to_rgba8();
+        let mut img = origin.clone();
+        let (width, height) = img.dimensions();
+        let cursor = || ReverseCursor {
+            remaining: width * height * 3,
+            width,
+        };
+        let secret = b"Hello reversed World!";
+
+        LsbCodec::with_cursor(cursor())
+            .encoder(&mut img, &CodecOptions::default())
+            .write_all(secret)
+            .expect("Cannot write to codec");
+
+        let mut buf = vec![0; secret.len()];
+        LsbCodec::with_cursor(cursor())
+            .decoder(&img, &CodecOptions::default())
+            .read_exact(&mut buf)
+            .expect("Cannot read from codec");
+        assert_eq!(&buf, secret);
+
+        assert_eq!(
+            origin.get_pixel(0, 0),
+            img.get_pixel(0, 0),
+            "The first pixel should be untouched by a reverse cursor"
+        );
+    }
+
+    #[test]
+    fn should_report_changes_in_the_lsb_plane_only_at_1_bit() {
+        let origin = image::open("../resources/plain/carrier-image.png")
+            .expect("Cannot open carrier image")
+            .to_rgba8();
+        let mut img = origin.clone();
+        LsbCodec::encoder(&mut img, &CodecOptions::default())
+            .write_all(&[0xA5; 512])
+            .expect("Cannot write the secret");
+
+        let report = channel_change_report(&origin, &img);
+        for channel in Channel::RGB {
+            let changes = report.channel(channel);
+            assert!(changes.changed_pixels > 0, "{channel:?} was not used");
+            assert_eq!(changes.changed_bit_planes(), vec![0], "{channel:?}");
+            assert_eq!(changes.changed_bits[0], changes.changed_pixels);
+        }
+        assert_eq!(report.channel(Channel::Alpha), &ChannelChanges::default());
+        assert_eq!(
+            channel_change_report(&origin, &origin),
+            ChannelChangeReport::default()
+        );
+    }
 }
@@ -0,0 +1,155 @@
+use image::{Rgba, RgbaImage};
+use std::io::Read;
+
+use crate::universal_decoder::{Decoder, OneBitUnveil};
+use crate::{MediaPrimitive, Result, SteganoError};
+
+/// Hides in the LSB of the luminance of each pixel, in the column based order of the default
+/// traversal. The pixel is converted to YCbCr (full range BT.601, like JPEG), its Y gets the bit
+/// and it is converted back to RGB. Rounding on the way back can flip the bit again, then the
+/// RGB pixel nearby with the bit in its luminance is taken instead. Alpha stays untouched.
+/// The capacity is pixels / 8 bytes.
+///
+/// ## Example of hiding in the luminance
+/// ```rust
+/// use stegano_core::media::image::luminance;
+/// use image::{Rgba, RgbaImage};
+/// use std::io::Read;
+///
+/// let mut image = RgbaImage::from_pixel(4, 4, Rgba([200, 120, 40, 0xff]));
+/// luminance::hide(&mut image, b"Hi").unwrap();
+///
+/// let mut buf = [0; 2];
+/// luminance::decoder(&image).read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"Hi");
+/// ```
+pub fn hide(image: &mut RgbaImage, secret: &[u8]) -> Result<()> {
+    let capacity = capacity(image);
+    if secret.len() > capacity {
+        return Err(SteganoError::CarrierTooSmall {
+            needed: secret.len(),
+            capacity,
+        });
+    }
+    let bits = secret
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1));
+    let height = image.height();
+    for (i, bit) in bits.enumerate() {
+        let (x, y) = (i as u32 / height, i as u32 % height);
+        let pixel = image.get_pixel_mut(x, y);
+        *pixel = with_luma_bit(*pixel, bit).ok_or(SteganoError::ImageEncodingError)?;
+    }
+
+    Ok(())
+}
+
+/// builds a Decoder that reads the LSBs of the luminance of the pixels
+pub fn decoder(image: &RgbaImage) -> Box<dyn Read + '_> {
+    let (width, height) = image.dimensions();
+
+    Box::new(Decoder::new(
+        (0..width)
+            .flat_map(move |x| (0..height).map(move |y| (x, y)))
+            .map(|(x, y)| MediaPrimitive::ImageColorChannel(luma(image.get_pixel(x, y)))),
+        OneBitUnveil,
+    ))
+}
+
+/// number of bytes that can be hidden, one bit per pixel
+pub fn capacity(image: &RgbaImage) -> usize {
+    image.pixels().len() / 8
+}
+
+/// the Y of YCbCr, in integer arithmetic so that encoder and decoder never round differently
+fn luma(pixel: &Rgba<u8>) -> u8 {
+    let [r, g, b, _] = pixel.0.map(u32::from);
+
+    ((299 * r + 587 * g + 114 * b + 500) / 1000) as u8
+}
+
+/// the Cb and Cr of YCbCr
+fn chroma(pixel: &Rgba<u8>) -> (f64, f64) {
+    let [r, g, b, _] = pixel.0.map(f64::from);
+
+    (
+        128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b,
+        128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b,
+    )
+}
+
+/// the RGB pixel of YCbCr, rounded and clamped to 8 bits
+fn to_rgb(y: u8, (cb, cr): (f64, f64), alpha: u8) -> Rgba<u8> {
+    let y = f64::from(y);
+    let channel = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+
+    Rgba([
+        channel(y + 1.402 * (cr - 128.0)),
+        channel(y - 0.344136 * (cb - 128.0) - 0.714136 * (cr - 128.0)),
+        channel(y + 1.772 * (cb - 128.0)),
+        alpha,
+    ])
+}
+
+/// the pixel after the round trip through YCbCr with the bit in the LSB of its luminance,
+/// or the pixel with that luminance that differs least from it, if rounding flips the bit
+fn with_luma_bit(pixel: Rgba<u8>, bit: u8) -> Option<Rgba<u8>> {
+    let y = luma(&pixel);
+    if y & 1 == bit {
+        return Some(pixel);
+    }
+    let target = match y {
+        u8::MAX => y - 1,
+        _ => y + 1,
+    };
+    let converted = to_rgb(target, chroma(&pixel), pixel.0[3]);
+    if luma(&converted) & 1 == bit {
+        return Some(converted);
+    }
+
+    let deltas = || -2i16..=2;
+    deltas()
+        .flat_map(|dr| deltas().flat_map(move |dg| deltas().map(move |db| [dr, dg, db])))
+        .filter_map(|delta| {
+            let mut nearby = pixel;
+            for (channel, d) in nearby.0.iter_mut().zip(delta) {
+                *channel = u8::try_from(*channel as i16 + d).ok()?;
+            }
+            let distance: i16 = delta.iter().map(|d| d * d).sum();
+
+            (luma(&nearby) & 1 == bit).then_some((distance, nearby))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, nearby)| nearby)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::random_payload;
+
+    #[test]
+    fn should_carry_either_bit_in_every_pixel() {
+        let noise = random_payload(64 * 64 * 4);
+        let image = RgbaImage::from_raw(64, 64, noise).unwrap();
+        let extremes = [
+            [0, 0, 0],
+            [255, 255, 255],
+            [255, 0, 0],
+            [0, 255, 255],
+            [255, 0, 255],
+        ];
+
+        for pixel in image
+            .pixels()
+            .copied()
+            .chain(extremes.map(|[r, g, b]| Rgba([r, g, b, 0xff])))
+        {
+            for bit in [0, 1] {
+                let hidden = with_luma_bit(pixel, bit).expect("a pixel with the bit");
+                assert_eq!(luma(&hidden) & 1, bit, "{pixel:?}");
+                assert_eq!(hidden.0[3], pixel.0[3]);
+            }
+        }
+    }
+}
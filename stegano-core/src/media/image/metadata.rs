@@ -0,0 +1,122 @@
+use image::RgbaImage;
+use png::{BitDepth, ColorType};
+use std::io::{Read, Write};
+
+use crate::{Result, SteganoError};
+
+/// keyword of the zTXt chunk that carries the payload
+pub const KEYWORD: &str = "stegano";
+
+/// number of bytes a zTXt chunk can carry at most, a chunk holds less than 2 GiB of base64
+pub const CAPACITY: usize = i32::MAX as usize / 4 * 3;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// encodes the image as 8 bit RGBA PNG, with the pixels as they are and the `payload`
+/// base64 encoded in a zTXt chunk. That is no steganography, any PNG tool lists the chunk,
+/// but it keeps every pixel exactly as it was
+///
+/// ## Example of hiding in the metadata
+/// ```rust
+/// use stegano_core::media::image::metadata;
+/// use image::RgbaImage;
+///
+/// let image = RgbaImage::new(4, 4);
+/// let mut png = Vec::new();
+/// metadata::encode_png(&image, b"Hi", &mut png).unwrap();
+///
+/// assert_eq!(metadata::decode_payload(&png[..]).unwrap(), Some(b"Hi".to_vec()));
+/// ```
+pub fn encode_png(image: &RgbaImage, payload: &[u8], w: impl Write) -> Result<()> {
+    let mut encoder = png::Encoder::new(w, image.width(), image.height());
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    encoder
+        .add_ztxt_chunk(KEYWORD.to_owned(), base64_encode(payload))
+        .map_err(|_e| SteganoError::ImageEncodingError)?;
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(image.as_raw()))
+        .map_err(|_e| SteganoError::ImageEncodingError)
+}
+
+/// the payload of the zTXt chunk written by [`encode_png`], `None` if the PNG has none
+pub fn decode_payload(r: impl Read) -> Result<Option<Vec<u8>>> {
+    let reader = png::Decoder::new(r)
+        .read_info()
+        .map_err(|_e| SteganoError::InvalidImageMedia)?;
+    let Some(chunk) = reader
+        .info()
+        .compressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == KEYWORD)
+    else {
+        return Ok(None);
+    };
+    let text = chunk
+        .get_text()
+        .map_err(|_e| SteganoError::MalformedSecretData)?;
+
+    base64_decode(&text)
+        .map(Some)
+        .ok_or(SteganoError::MalformedSecretData)
+}
+
+/// standard base64 with padding, text chunks hold latin-1 text only
+fn base64_encode(buf: &[u8]) -> String {
+    let mut text = String::with_capacity(buf.len().div_ceil(3) * 4);
+    for group in buf.chunks(3) {
+        let bits = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, b)| bits | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                text.push(BASE64[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+
+    text
+}
+
+/// the bytes of a base64 text written by [`base64_encode`], `None` if it is no such text
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut buf = Vec::with_capacity(text.len() / 4 * 3);
+    for group in text.chunks(4) {
+        let padding = group.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+        let mut bits = 0u32;
+        for (i, c) in group[..4 - padding].iter().enumerate() {
+            let value = BASE64.iter().position(|b| b == c)? as u32;
+            bits |= value << (18 - 6 * i);
+        }
+        buf.extend(bits.to_be_bytes()[1..4 - padding].iter());
+    }
+
+    Some(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_base64() {
+        for len in 0..8 {
+            let buf: Vec<u8> = (0..len).map(|i| 0xf0 ^ (i * 37) as u8).collect();
+            assert_eq!(base64_decode(&base64_encode(&buf)), Some(buf));
+        }
+        assert_eq!(base64_encode(b"Hello!?"), "SGVsbG8hPw==");
+        assert_eq!(base64_decode("SGVsbG8h*w=="), None);
+    }
+}
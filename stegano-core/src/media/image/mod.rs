@@ -1,6 +1,23 @@
+pub mod alignment;
+pub mod bit_plane;
+pub mod bitmap;
+pub mod canonical;
+pub mod cursor;
 pub mod decoder;
+pub mod dither;
+pub mod dng;
 pub mod encoder;
+pub mod gif;
+#[cfg(feature = "hdr")]
+pub mod hdr;
 mod iterators;
 pub mod lsb_codec;
+pub mod luminance;
+pub mod metadata;
+pub mod orientation;
+pub mod palette;
+pub mod tiled;
+#[cfg(feature = "webp")]
+pub mod webp;
 
 pub use lsb_codec::{CodecOptions, LsbCodec};
@@ -0,0 +1,108 @@
+use image::imageops;
+use image::RgbaImage;
+
+/// signature every PNG file starts with
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// EXIF tag of the orientation, a `SHORT` from 1 to 8
+const TAG_ORIENTATION: u16 = 0x0112;
+
+/// the EXIF orientation of a PNG, read from its `eXIf` chunk.
+/// `None` if there is none, or it is 1 that means the pixels are stored as displayed
+pub fn exif_orientation(png: &[u8]) -> Option<u8> {
+    let mut chunks = png.strip_prefix(&PNG_SIGNATURE)?;
+    while chunks.len() >= 12 {
+        let len = u32::from_be_bytes(chunks[..4].try_into().unwrap()) as usize;
+        let data = chunks.get(8..8 + len)?;
+        if &chunks[4..8] == b"eXIf" {
+            return tiff_orientation(data).filter(|o| (2..=8).contains(o));
+        }
+        chunks = chunks.get(12 + len..)?;
+    }
+
+    None
+}
+
+/// transforms the stored pixels as the EXIF orientation says, so that they are as displayed
+pub fn apply_orientation(image: RgbaImage, orientation: u8) -> RgbaImage {
+    match orientation {
+        2 => imageops::flip_horizontal(&image),
+        3 => imageops::rotate180(&image),
+        4 => imageops::flip_vertical(&image),
+        5 => imageops::flip_horizontal(&imageops::rotate90(&image)),
+        6 => imageops::rotate90(&image),
+        7 => imageops::flip_horizontal(&imageops::rotate270(&image)),
+        8 => imageops::rotate270(&image),
+        _ => image,
+    }
+}
+
+/// the orientation tag of the first IFD of a TIFF structure, as EXIF is stored
+fn tiff_orientation(tiff: &[u8]) -> Option<u8> {
+    let read_u16 = |at: usize| -> Option<u16> {
+        let b: [u8; 2] = tiff.get(at..at + 2)?.try_into().ok()?;
+        match &tiff[..2] {
+            b"II" => Some(u16::from_le_bytes(b)),
+            b"MM" => Some(u16::from_be_bytes(b)),
+            _ => None,
+        }
+    };
+    let read_u32 = |at: usize| -> Option<u32> {
+        let (a, b) = (read_u16(at)? as u32, read_u16(at + 2)? as u32);
+        match &tiff[..2] {
+            b"II" => Some(a | (b << 16)),
+            _ => Some((a << 16) | b),
+        }
+    };
+
+    let ifd = read_u32(4)? as usize;
+    let entries = read_u16(ifd)? as usize;
+    (0..entries)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|entry| read_u16(*entry) == Some(TAG_ORIENTATION))
+        .and_then(|entry| read_u16(entry + 8))
+        .and_then(|o| u8::try_from(o).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{prepare_small_image, with_exif_orientation};
+
+    fn png_of(image: &RgbaImage) -> Vec<u8> {
+        let mut png = Vec::new();
+        image
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn should_read_the_orientation_of_the_exif_chunk() {
+        let png = png_of(&prepare_small_image());
+        assert_eq!(exif_orientation(&png), None);
+
+        for orientation in 1..=8 {
+            let expected = (orientation > 1).then_some(orientation);
+            assert_eq!(
+                exif_orientation(&with_exif_orientation(&png, orientation)),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn should_undo_a_rotation_by_applying_the_orientation() {
+        let image = RgbaImage::from_fn(3, 2, |x, y| image::Rgba([x as u8, y as u8, 0, 0xff]));
+
+        // a gallery that stores the pixels rotated counter clockwise, tags them with 6,
+        // so that they are rotated clockwise on display
+        let stored = imageops::rotate270(&image);
+        assert_eq!(apply_orientation(stored, 6), image);
+
+        let stored = imageops::flip_horizontal(&imageops::rotate90(&image));
+        assert_eq!(apply_orientation(stored, 5), image);
+    }
+}
@@ -0,0 +1,319 @@
+use image::{Rgba, RgbaImage};
+use png::{BitDepth, ColorType, Transformations};
+use std::io::{Read, Write};
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufReader, io::BufWriter, path::Path};
+
+use crate::media::image::lsb_codec::{hide_algorithm, CodecOptions};
+use crate::universal_decoder::{Decoder, OneBitUnveil};
+use crate::universal_encoder::Encoder;
+#[cfg(feature = "fs")]
+use crate::Persist;
+use crate::{MediaPrimitive, MediaPrimitiveMut, Result, SteganoError};
+
+/// An indexed PNG image, that keeps its palette and pixel indices apart.
+/// Secrets are hidden in the LSBs of the palette colors, the indices stay untouched.
+/// So the capacity is small, at most 256 colors * 3 channels = 96 bytes.
+///
+/// ## Example of hiding in the palette
+/// ```rust
+/// use stegano_core::media::image::palette::PaletteImage;
+/// use stegano_core::media::image::CodecOptions;
+///
+/// let mut png = Vec::new();
+/// {
+///     let mut encoder = png::Encoder::new(&mut png, 16, 1);
+///     encoder.set_color(png::ColorType::Indexed);
+///     encoder.set_palette((0..48).collect::<Vec<u8>>());
+///     let mut writer = encoder.write_header().unwrap();
+///     writer.write_image_data(&(0..16).collect::<Vec<u8>>()).unwrap();
+/// }
+///
+/// let mut image = PaletteImage::decode(&png[..]).unwrap().expect("an indexed image");
+/// image.encoder(&CodecOptions::default()).write_all(b"Hi").unwrap();
+///
+/// let mut buf = [0; 2];
+/// image.decoder(&CodecOptions::default()).read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"Hi");
+/// ```
+pub struct PaletteImage {
+    width: u32,
+    height: u32,
+    bit_depth: BitDepth,
+    palette: Vec<u8>,
+    trns: Option<Vec<u8>>,
+    indices: Vec<u8>,
+}
+
+impl PaletteImage {
+    /// decodes a PNG, returns `None` if it has no palette
+    pub fn decode(r: impl Read) -> Result<Option<Self>> {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(Transformations::IDENTITY);
+        let mut reader = decoder
+            .read_info()
+            .map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        let info = reader.info();
+        let palette = match (info.color_type, info.palette.as_ref()) {
+            (ColorType::Indexed, Some(palette)) => palette.to_vec(),
+            _ => return Ok(None),
+        };
+        let (width, height, bit_depth) = (info.width, info.height, info.bit_depth);
+        let trns = info.trns.as_ref().map(|t| t.to_vec());
+
+        let mut indices = vec![0; reader.output_buffer_size()];
+        reader
+            .next_frame(&mut indices)
+            .map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        Ok(Some(Self {
+            width,
+            height,
+            bit_depth,
+            palette,
+            trns,
+            indices,
+        }))
+    }
+
+    /// opens a PNG file, returns `None` if it is no PNG or has no palette
+    #[cfg(feature = "fs")]
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        let is_png = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("png"))
+            .unwrap_or(false);
+        if !is_png {
+            return Ok(None);
+        }
+        let file = File::open(path).map_err(|_e| SteganoError::InvalidImageMedia)?;
+
+        Self::decode(BufReader::new(file))
+    }
+
+    /// encodes the image as indexed PNG, with the same indices as it was decoded
+    pub fn encode(&self, w: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(ColorType::Indexed);
+        encoder.set_depth(self.bit_depth);
+        encoder.set_palette(self.palette.as_slice());
+        if let Some(trns) = self.trns.as_ref() {
+            encoder.set_trns(trns.as_slice());
+        }
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.indices))
+            .map_err(|_e| SteganoError::ImageEncodingError)
+    }
+
+    /// the RGB palette, 3 bytes per color
+    pub fn palette(&self) -> &[u8] {
+        &self.palette
+    }
+
+    /// the pixel indices into the palette, packed as of the bit depth
+    pub fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    /// number of bytes that fit into the palette colors, one bit per color channel
+    pub fn capacity(&self) -> usize {
+        self.palette.len() / 8
+    }
+
+    /// builds a Decoder that reads the secret from the palette colors
+    pub fn decoder<'i>(&'i self, _opts: &CodecOptions) -> Box<dyn Read + 'i> {
+        Box::new(Decoder::new(
+            self.palette
+                .iter()
+                .map(|c| MediaPrimitive::ImageColorChannel(*c)),
+            OneBitUnveil,
+        ))
+    }
+
+    /// builds an Encoder that writes the secret into the palette colors
+    pub fn encoder<'i>(&'i mut self, opts: &CodecOptions) -> Box<dyn Write + 'i> {
+        Box::new(Encoder::new(
+            self.palette
+                .iter_mut()
+                .map(MediaPrimitiveMut::ImageColorChannel),
+            hide_algorithm(opts),
+        ))
+    }
+
+    /// the palette slots that no pixel refers to, in ascending order, also those behind the end
+    /// of the palette up to the number of colors of the bit depth.
+    /// Hiding never changes the indices, so these are the same for the decoder
+    pub fn spare_slots(&self) -> Vec<usize> {
+        let mut used = vec![false; 1 << self.bit_depth as usize];
+        for (x, y) in (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y))) {
+            used[self.index_at(x, y)] = true;
+        }
+
+        (0..used.len()).filter(|slot| !used[*slot]).collect()
+    }
+
+    /// number of bytes that fit into the spare slots, 3 per slot
+    pub fn spare_slots_capacity(&self) -> usize {
+        self.spare_slots().len() * 3
+    }
+
+    /// writes the secret as it is into the colors of the spare slots, see [`PaletteImage::spare_slots`].
+    /// No pixel shows these colors, so the image looks the same. The palette grows if a slot
+    /// behind its end is needed. Fails with `SteganoError::CarrierTooSmall` if it does not fit
+    pub fn hide_in_spare_slots(&mut self, secret: &[u8]) -> Result<()> {
+        let slots = self.spare_slots();
+        if secret.len() > slots.len() * 3 {
+            return Err(SteganoError::CarrierTooSmall {
+                needed: secret.len(),
+                capacity: slots.len() * 3,
+            });
+        }
+        for (slot, color) in slots.iter().zip(secret.chunks(3)) {
+            let at = slot * 3;
+            if self.palette.len() < at + 3 {
+                self.palette.resize(at + 3, 0);
+            }
+            self.palette[at..at + color.len()].copy_from_slice(color);
+        }
+
+        Ok(())
+    }
+
+    /// builds a Decoder that reads the secret from the colors of the spare slots,
+    /// slots behind the end of the palette read as black
+    pub fn spare_slots_decoder(&self) -> Box<dyn Read + '_> {
+        let colors = self.spare_slots().into_iter().flat_map(|slot| {
+            (slot * 3..slot * 3 + 3).map(|at| self.palette.get(at).copied().unwrap_or(0))
+        });
+
+        Box::new(std::io::Cursor::new(colors.collect::<Vec<u8>>()))
+    }
+
+    /// the palette index of the pixel, unpacked as of the bit depth
+    fn index_at(&self, x: u32, y: u32) -> usize {
+        let bits = self.bit_depth as usize;
+        let row_len = (self.width as usize * bits).div_ceil(8);
+        let per_byte = 8 / bits;
+        let mask = ((1u16 << bits) - 1) as u8;
+        let byte = self.indices[y as usize * row_len + x as usize / per_byte];
+        let shift = 8 - bits * (x as usize % per_byte + 1);
+
+        ((byte >> shift) & mask) as usize
+    }
+
+    /// expands the palette, pixels with an index outside of the palette become transparent black
+    pub fn to_rgba(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let index = self.index_at(x, y);
+
+            match self.palette.get(index * 3..index * 3 + 3) {
+                Some(rgb) => {
+                    let alpha = self
+                        .trns
+                        .as_ref()
+                        .and_then(|t| t.get(index).copied())
+                        .unwrap_or(u8::MAX);
+                    Rgba([rgb[0], rgb[1], rgb[2], alpha])
+                }
+                None => Rgba([0, 0, 0, 0]),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Persist for PaletteImage {
+    fn save_as(&mut self, file: &Path) -> Result<()> {
+        let file = File::create(file).map_err(|source| SteganoError::WriteError { source })?;
+
+        self.encode(BufWriter::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexed_png(width: u32, bit_depth: BitDepth, indices: &[u8]) -> Vec<u8> {
+        let mut png = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png, width, 1);
+            encoder.set_color(ColorType::Indexed);
+            encoder.set_depth(bit_depth);
+            encoder.set_palette(vec![10, 20, 30, 40, 50, 60, 70, 80, 90]);
+            encoder.set_trns(vec![0xff, 0x80]);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(indices).unwrap();
+        }
+        png
+    }
+
+    #[test]
+    fn should_not_decode_an_image_without_palette() {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(RgbaImage::new(2, 2))
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        assert!(PaletteImage::decode(&png[..]).unwrap().is_none());
+    }
+
+    #[test]
+    fn should_expand_packed_indices() {
+        // 2 bit per pixel: indices 0, 1, 2, 1
+        let png = indexed_png(4, BitDepth::Two, &[0b00_01_10_01]);
+        let image = PaletteImage::decode(&png[..]).unwrap().unwrap();
+
+        let rgba = image.to_rgba();
+        assert_eq!(rgba.get_pixel(0, 0), &Rgba([10, 20, 30, 0xff]));
+        assert_eq!(rgba.get_pixel(1, 0), &Rgba([40, 50, 60, 0x80]));
+        assert_eq!(rgba.get_pixel(2, 0), &Rgba([70, 80, 90, 0xff]));
+        assert_eq!(rgba.get_pixel(3, 0), &Rgba([40, 50, 60, 0x80]));
+    }
+
+    #[test]
+    fn should_keep_the_indices_when_hiding() {
+        let png = indexed_png(3, BitDepth::Eight, &[2, 1, 0]);
+        let mut image = PaletteImage::decode(&png[..]).unwrap().unwrap();
+        image
+            .encoder(&CodecOptions::default())
+            .write_all(&[0xff])
+            .unwrap();
+
+        let mut png = Vec::new();
+        image.encode(&mut png).unwrap();
+        let image = PaletteImage::decode(&png[..]).unwrap().unwrap();
+
+        assert_eq!(image.indices(), &[2, 1, 0]);
+        assert_eq!(&image.palette()[..8], &[11, 21, 31, 41, 51, 61, 71, 81]);
+    }
+
+    #[test]
+    fn should_hide_in_the_spare_slots_without_changing_a_pixel() {
+        // 2 bit per pixel with 3 colors, of which only 0 and 2 are used
+        let png = indexed_png(4, BitDepth::Two, &[0b00_10_10_00]);
+        let mut image = PaletteImage::decode(&png[..]).unwrap().unwrap();
+        assert_eq!(image.spare_slots(), vec![1, 3]);
+        assert!(matches!(
+            image.hide_in_spare_slots(b"too long"),
+            Err(SteganoError::CarrierTooSmall { capacity: 6, .. })
+        ));
+        let rgba = image.to_rgba();
+
+        image.hide_in_spare_slots(b"Hi!\x01").unwrap();
+        let mut png = Vec::new();
+        image.encode(&mut png).unwrap();
+        let image = PaletteImage::decode(&png[..]).unwrap().unwrap();
+
+        assert_eq!(image.to_rgba(), rgba);
+        let mut buf = [0; 6];
+        image.spare_slots_decoder().read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hi!\x01\0\0");
+    }
+}
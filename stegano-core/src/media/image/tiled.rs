@@ -0,0 +1,258 @@
+use image::RgbaImage;
+use png::{BitDepth, ColorType, Transformations};
+use std::io::{Cursor, Read, Write};
+
+use crate::media::image::cursor::ChannelOrder;
+use crate::media::image::lsb_codec::Concealer;
+use crate::media::image::{CodecOptions, LsbCodec};
+use crate::{Result, SteganoError};
+
+/// number of rows of a band, the tiled path holds only one band of pixels at a time
+pub const BAND_ROWS: u32 = 64;
+
+/// reads a PNG band by band of [`BAND_ROWS`] rows, each converted to 8 bit RGBA
+/// like `image::DynamicImage::to_rgba8` does, without decoding the whole image
+pub struct RowBands<R: Read> {
+    reader: png::Reader<R>,
+    color_type: ColorType,
+    top: u32,
+}
+
+impl<R: Read> RowBands<R> {
+    /// reads the PNG header, 16 bit and interlaced PNGs are not supported
+    pub fn new(r: R) -> Result<Self> {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(Transformations::EXPAND);
+        let reader = decoder
+            .read_info()
+            .map_err(|_e| SteganoError::InvalidImageMedia)?;
+        let (color_type, bit_depth) = reader.output_color_type();
+        if bit_depth != BitDepth::Eight || reader.info().interlaced {
+            return Err(SteganoError::UnsupportedMedia);
+        }
+
+        Ok(Self {
+            reader,
+            color_type,
+            top: 0,
+        })
+    }
+
+    /// width and height of the whole image
+    pub fn dimensions(&self) -> (u32, u32) {
+        let info = self.reader.info();
+        (info.width, info.height)
+    }
+
+    /// the next row converted to RGBA
+    fn next_row(&mut self, band: &mut Vec<u8>) -> Result<()> {
+        let color_type = self.color_type;
+        let row = self
+            .reader
+            .next_row()
+            .map_err(|_e| SteganoError::InvalidImageMedia)?
+            .ok_or(SteganoError::InvalidImageMedia)?;
+
+        match color_type {
+            ColorType::Grayscale => band.extend(row.data().iter().flat_map(|g| [*g, *g, *g, 0xff])),
+            ColorType::GrayscaleAlpha => band.extend(
+                row.data()
+                    .chunks_exact(2)
+                    .flat_map(|p| [p[0], p[0], p[0], p[1]]),
+            ),
+            ColorType::Rgb => band.extend(
+                row.data()
+                    .chunks_exact(3)
+                    .flat_map(|p| [p[0], p[1], p[2], 0xff]),
+            ),
+            ColorType::Rgba => band.extend_from_slice(row.data()),
+            ColorType::Indexed => return Err(SteganoError::UnsupportedMedia),
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for RowBands<R> {
+    /// the row of the top of the band and its pixels
+    type Item = Result<(u32, RgbaImage)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (width, height) = self.dimensions();
+        let top = self.top;
+        if top >= height {
+            return None;
+        }
+        let rows = BAND_ROWS.min(height - top);
+        self.top += rows;
+
+        let mut band = Vec::with_capacity(width as usize * rows as usize * 4);
+        for _ in 0..rows {
+            if let Err(e) = self.next_row(&mut band) {
+                self.top = height;
+                return Some(Err(e));
+            }
+        }
+
+        Some(Ok((top, RgbaImage::from_raw(width, rows, band).unwrap())))
+    }
+}
+
+/// index of the hidden bit in the red channel of the pixel, in the default column based traversal
+fn bit_of_pixel(x: u32, y: u32, height: u32) -> usize {
+    (x as usize * height as usize + y as usize) * 3
+}
+
+/// the tiled path knows only the default traversal with 1 bit in red, green and blue
+fn check_default_traversal(opts: &CodecOptions) -> Result<()> {
+    let default = CodecOptions::default();
+    if opts.ordering_seed.is_some()
+        || opts.is_multi_bit()
+        || opts.skip_transparent
+        || opts.row_stride > 1
+        || opts.checkerboard
+        || opts.mask.is_some()
+        || opts.single_channel.is_some()
+        || opts.traversal.is_some()
+        || opts.channel_order != ChannelOrder::Rgba
+        || opts.color_channel_step_increment != default.color_channel_step_increment
+        || opts.skip_alpha_channel != default.skip_alpha_channel
+        || opts.concealer != Concealer::LeastSignificantBit
+    {
+        return Err(SteganoError::InvalidParameter(
+            "the tiled path supports only the default codec options".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+impl LsbCodec {
+    /// like [`LsbCodec::decoder`] for a PNG that is read band by band, so that it never is
+    /// in memory as a whole. Only its LSB plane is, that is 3 bits per pixel instead of 32.
+    /// Supports only the default codec options
+    pub fn tiled_decoder(png: impl Read, opts: &CodecOptions) -> Result<Box<dyn Read>> {
+        check_default_traversal(opts)?;
+        let bands = RowBands::new(png)?;
+        let (width, height) = bands.dimensions();
+
+        let mut plane = vec![0u8; width as usize * height as usize * 3 / 8];
+        for band in bands {
+            let (top, band) = band?;
+            for (x, y, pixel) in band.enumerate_pixels() {
+                let first = bit_of_pixel(x, top + y, height);
+                for (bit, channel) in (first..).zip(&pixel.0[..3]) {
+                    if let Some(byte) = plane.get_mut(bit / 8) {
+                        *byte |= (channel & 1) << (bit % 8);
+                    }
+                }
+            }
+        }
+
+        Ok(Box::new(Cursor::new(plane)))
+    }
+
+    /// like [`LsbCodec::encoder`] for a PNG that is read band by band, the bands are written
+    /// as 8 bit RGBA PNG with the `secret` hidden, as soon as they are read.
+    /// Supports only the default codec options
+    pub fn tiled_encode(
+        png: impl Read,
+        target: impl Write,
+        secret: &[u8],
+        opts: &CodecOptions,
+    ) -> Result<()> {
+        check_default_traversal(opts)?;
+        let bands = RowBands::new(png)?;
+        let (width, height) = bands.dimensions();
+        let capacity = width as usize * height as usize * 3 / 8;
+        if secret.len() > capacity {
+            return Err(SteganoError::CarrierTooSmall {
+                needed: secret.len(),
+                capacity,
+            });
+        }
+
+        let mut encoder = png::Encoder::new(target, width, height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|_e| SteganoError::ImageEncodingError)?;
+        let mut stream = writer
+            .stream_writer()
+            .map_err(|_e| SteganoError::ImageEncodingError)?;
+
+        for band in bands {
+            let (top, mut band) = band?;
+            for (x, y, pixel) in band.enumerate_pixels_mut() {
+                let first = bit_of_pixel(x, top + y, height);
+                for (bit, channel) in (first..).zip(&mut pixel.0[..3]) {
+                    if let Some(byte) = secret.get(bit / 8) {
+                        *channel = (*channel & !1) | ((byte >> (bit % 8)) & 1);
+                    }
+                }
+            }
+            stream
+                .write_all(band.as_raw())
+                .map_err(|_e| SteganoError::ImageEncodingError)?;
+        }
+
+        stream
+            .finish()
+            .map_err(|_e| SteganoError::ImageEncodingError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::random_payload;
+    use crate::Secret;
+    use image::ImageOutputFormat;
+    use std::sync::Arc;
+
+    #[test]
+    fn should_hide_and_unveil_like_the_in_memory_path() -> Result<()> {
+        // a height that is no multiple of the band, so that the last band is shorter
+        let (width, height) = (640, BAND_ROWS * 7 + 13);
+        let noise = random_payload(width as usize * height as usize * 4);
+        let carrier = RgbaImage::from_raw(width, height, noise).unwrap();
+        let mut png = Vec::new();
+        carrier
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .unwrap();
+        let secret = random_payload(100_000);
+
+        let mut in_memory = carrier.clone();
+        LsbCodec::encoder(&mut in_memory, &CodecOptions::default()).write_all(&secret)?;
+        let mut tiled = Vec::new();
+        LsbCodec::tiled_encode(&png[..], &mut tiled, &secret, &CodecOptions::default())?;
+        let tiled = image::load_from_memory(&tiled).unwrap().to_rgba8();
+        assert_eq!(tiled, in_memory);
+
+        let mut png = Vec::new();
+        in_memory
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .unwrap();
+        let mut expected = Vec::new();
+        LsbCodec::decoder(&in_memory, &CodecOptions::default()).read_to_end(&mut expected)?;
+        let mut unveiled = Vec::new();
+        LsbCodec::tiled_decoder(&png[..], &CodecOptions::default())?.read_to_end(&mut unveiled)?;
+        assert_eq!(unveiled, expected);
+        assert_eq!(&unveiled[..secret.len()], &secret[..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn should_refuse_options_of_another_traversal() {
+        let opts = CodecOptions {
+            ordering_seed: Some(Arc::new(Secret::new(42))),
+            ..CodecOptions::default()
+        };
+        assert!(matches!(
+            LsbCodec::tiled_decoder(&[][..], &opts).err(),
+            Some(SteganoError::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use image::codecs::webp::WebPEncoder;
+use image::{ColorType, ImageEncoder, RgbaImage};
+
+use crate::{Result, SteganoError};
+
+/// encodes the image as lossless WebP ("VP8L"), so that no hidden bit gets lost
+pub fn encode_lossless(image: &RgbaImage) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    WebPEncoder::new_lossless(&mut buf)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ColorType::Rgba8,
+        )
+        .map_err(|_e| SteganoError::ImageEncodingError)?;
+    ensure_lossless(&buf)?;
+
+    Ok(buf)
+}
+
+/// guards against lossy WebP data, by checking the RIFF chunks for a lossy "VP8 " bitstream
+pub fn ensure_lossless(data: &[u8]) -> Result<()> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(SteganoError::InvalidImageMedia);
+    }
+
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let fourcc = &data[offset..offset + 4];
+        let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        if fourcc == b"VP8 " {
+            return Err(SteganoError::LossyTargetMedia);
+        }
+        // chunks are padded to an even size
+        offset += 8 + size + (size & 1);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::prepare_small_image;
+
+    #[test]
+    fn should_encode_lossless() {
+        let img = prepare_small_image();
+        let buf = encode_lossless(&img).expect("Cannot encode the image");
+
+        assert_eq!(&buf[12..16], b"VP8L");
+        let decoded = image::load_from_memory(&buf).unwrap().to_rgba8();
+        assert_eq!(decoded, img, "Pixels should survive a lossless round trip");
+    }
+
+    #[test]
+    fn should_reject_lossy_webp() {
+        let mut lossy = b"RIFF\x16\x00\x00\x00WEBPVP8 \x0a\x00\x00\x00".to_vec();
+        lossy.extend_from_slice(&[0; 10]);
+
+        assert!(matches!(
+            ensure_lossless(&lossy),
+            Err(SteganoError::LossyTargetMedia)
+        ));
+    }
+
+    #[test]
+    fn should_reject_lossy_webp_in_extended_format() {
+        let mut lossy = b"RIFF\x26\x00\x00\x00WEBPVP8X\x0a\x00\x00\x00".to_vec();
+        lossy.extend_from_slice(&[0; 10]);
+        lossy.extend_from_slice(b"VP8 \x0a\x00\x00\x00");
+        lossy.extend_from_slice(&[0; 10]);
+
+        assert!(matches!(
+            ensure_lossless(&lossy),
+            Err(SteganoError::LossyTargetMedia)
+        ));
+    }
+}
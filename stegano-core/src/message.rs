@@ -1,59 +1,621 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "fs")]
 use std::fs::File;
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write};
+#[cfg(feature = "fs")]
 use std::path::Path;
-
-#[derive(PartialEq, Eq, Debug)]
+use std::sync::Arc;
+
+use crate::format::{
+    FormatError, CHANNEL_ORDER_ARGB, CHANNEL_ORDER_BGRA, CHECKSUM_CRC32, CHECKSUM_SHA256,
+    COVER_HASH_LEN, FIELD_CHANNEL_BIT_DEPTHS, FIELD_CHANNEL_ORDER, FIELD_CHECKERBOARD,
+    FIELD_CHECKSUM, FIELD_COMMENT, FIELD_COVER_HASH, FIELD_FEC_REDUNDANCY, FIELD_HINT,
+    FIELD_KDF_ITERATIONS, FIELD_KDF_SALT, FIELD_MAC, FIELD_PAYLOAD_LENGTH, FIELD_PREVIEW,
+    FIELD_PROVENANCE, FIELD_SIGNATURE, KDF_SALT_LEN, MAC_LEN, MAX_COMMENT_LEN, MAX_HINT_LEN,
+    MAX_KDF_ITERATIONS, MAX_PREVIEW_LEN, MIN_KDF_ITERATIONS, PAYLOAD_LENGTH_COPIES, SIGNATURE_LEN,
+    V1_TERMINATOR, V2_TERMINATOR, VERSION_1, VERSION_2, VERSION_4, VERSION_5, VERSION_6,
+};
+use crate::media::image::cursor::ChannelOrder;
+use crate::secret::Secret;
+use crate::{fec, Result, SteganoError};
+use log::{debug, trace};
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ContentVersion {
     V1,
     V2,
     V4,
+    /// like V4, plus a block of header fields in front of the payload
+    V5,
+    /// files without zip, behind an index of their names, offsets and sizes
+    V6,
     Unsupported(u8),
 }
 
 impl ContentVersion {
     pub fn to_u8(&self) -> u8 {
         match self {
-            Self::V1 => 0x01,
-            Self::V2 => 0x02,
-            Self::V4 => 0x04,
+            Self::V1 => VERSION_1,
+            Self::V2 => VERSION_2,
+            Self::V4 => VERSION_4,
+            Self::V5 => VERSION_5,
+            Self::V6 => VERSION_6,
             Self::Unsupported(v) => *v,
         }
     }
 
     pub fn from_u8(value: u8) -> Self {
         match value {
-            0x01 => Self::V1,
-            0x02 => Self::V2,
-            0x04 => Self::V4,
+            VERSION_1 => Self::V1,
+            VERSION_2 => Self::V2,
+            VERSION_4 => Self::V4,
+            VERSION_5 => Self::V5,
+            VERSION_6 => Self::V6,
             b => Self::Unsupported(b),
         }
     }
 }
 
+/// algorithm of the checksum over the payload, that is verified on unveil
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum ChecksumAlgo {
+    /// no checksum, only the zip archive itself detects some corruptions
+    #[default]
+    None,
+    /// fast, but only detects accidental corruption
+    Crc32,
+    /// slower, but collision resistant
+    Sha256,
+}
+
+impl ChecksumAlgo {
+    /// the checksum of `buf`, empty for `ChecksumAlgo::None`
+    pub fn digest(&self, buf: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(buf);
+
+        hasher.finalize()
+    }
+
+    /// computes the checksum piece by piece, e.g. while the bytes are hidden
+    pub fn hasher(&self) -> ChecksumHasher {
+        match self {
+            Self::None => ChecksumHasher::None,
+            Self::Crc32 => ChecksumHasher::Crc32(crc32fast::Hasher::new()),
+            Self::Sha256 => ChecksumHasher::Sha256(Sha256::new()),
+        }
+    }
+
+    /// length of the checksum in bytes
+    fn len(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Crc32 => 4,
+            Self::Sha256 => 32,
+        }
+    }
+
+    fn to_u8(self) -> Option<u8> {
+        match self {
+            Self::None => None,
+            Self::Crc32 => Some(CHECKSUM_CRC32),
+            Self::Sha256 => Some(CHECKSUM_SHA256),
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            CHECKSUM_CRC32 => Some(Self::Crc32),
+            CHECKSUM_SHA256 => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+}
+
+/// the state of a checksum that is computed piece by piece, see [`ChecksumAlgo::hasher`]
+pub enum ChecksumHasher {
+    None,
+    Crc32(crc32fast::Hasher),
+    Sha256(Sha256),
+}
+
+impl ChecksumHasher {
+    /// adds the bytes to the checksum
+    pub fn update(&mut self, buf: &[u8]) {
+        match self {
+            Self::None => {}
+            Self::Crc32(hasher) => hasher.update(buf),
+            Self::Sha256(hasher) => Digest::update(hasher, buf),
+        }
+    }
+
+    /// the checksum of all bytes added, like [`ChecksumAlgo::digest`] of them at once
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::None => Vec::new(),
+            Self::Crc32(hasher) => hasher.finalize().to_be_bytes().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// passes the bytes through to the inner writer and adds those it took to a checksum
+pub struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: ChecksumHasher,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W, algo: ChecksumAlgo) -> Self {
+        Self {
+            inner,
+            hasher: algo.hasher(),
+        }
+    }
+
+    /// the checksum of all bytes written
+    pub fn finalize(self) -> Vec<u8> {
+        self.hasher.finalize()
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// a checksum that is left as zeros in a serialized message, so that it can be computed
+/// while the payload is hidden, see [`Message::to_bytes_deferring_checksum`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeferredChecksum {
+    pub algo: ChecksumAlgo,
+    /// offset of the checksum in the serialized message
+    pub at: usize,
+    /// the bytes of the serialized message the checksum is computed over
+    pub payload: std::ops::Range<usize>,
+}
+
+impl DeferredChecksum {
+    /// writes the checksum of the payload into the serialized message
+    pub fn patch(&self, buf: &mut [u8]) {
+        let digest = self.algo.digest(&buf[self.payload.clone()]);
+        self.patch_with(buf, &digest);
+    }
+
+    /// writes the given checksum into the serialized message
+    pub fn patch_with(&self, buf: &mut [u8], digest: &[u8]) {
+        buf[self.at..self.at + digest.len()].copy_from_slice(digest);
+    }
+}
+
+/// when and by which version of this crate a message was created
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Provenance {
+    /// creation time in seconds since the unix epoch
+    pub created_at: u64,
+    /// version of stegano-core that created the message
+    pub tool_version: String,
+}
+
+impl Provenance {
+    /// the provenance of a message created right now, by this version
+    pub fn now() -> Self {
+        Self {
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            tool_version: env!("CARGO_PKG_VERSION").to_owned(),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Message {
     pub header: ContentVersion,
     pub files: Vec<(String, Vec<u8>)>,
     pub text: Option<String>,
+    /// hash of the carrier content that is not used for hiding, see `Media::cover_hash`
+    pub cover_hash: Option<[u8; 32]>,
+    /// number of corrupted bytes per 255 byte block that can be corrected, see `fec`
+    pub fec_redundancy: Option<u8>,
+    /// checksum over the payload, verified when the message is parsed
+    pub checksum: ChecksumAlgo,
+    /// when and by what the message was created
+    pub provenance: Option<Provenance>,
+    /// human readable description, it is not part of the payload
+    pub comment: Option<String>,
+    /// if false the files are stored in the zip archive as they are, without deflate
+    pub compression: bool,
+    /// key of the HMAC-SHA256 that authenticates the payload, the key itself is never hidden
+    pub mac_key: Option<Arc<Secret<Vec<u8>>>>,
+    /// the HMAC-SHA256 read from the header, see [`Message::verify_mac`]
+    pub mac: Option<[u8; MAC_LEN]>,
+    /// iterations of PBKDF2 that stretch `mac_key` before it keys the HMAC, `None` uses it as is
+    pub kdf_iterations: Option<u32>,
+    /// salt of the PBKDF2 of `kdf_iterations`, `None` uses a fixed salt
+    pub kdf_salt: Option<[u8; KDF_SALT_LEN]>,
+    /// Ed25519 secret key that signs the payload, the key itself is never hidden
+    pub signing_key: Option<Arc<Secret<[u8; ed25519_dalek::SECRET_KEY_LENGTH]>>>,
+    /// the Ed25519 signature read from the header, see [`Message::verify_signature`]
+    pub signature: Option<[u8; SIGNATURE_LEN]>,
+    /// stores the payload length redundantly in the header, so that it survives a flipped bit
+    pub redundant_length: bool,
+    /// the channel order the message is hidden in, anything but RGBA is kept in the header
+    pub channel_order: ChannelOrder,
+    /// the bits of the red, green and blue channel the message is hidden in, if they differ
+    pub channel_bit_depths: Option<[u8; 3]>,
+    /// JPEG thumbnail that previews the hidden content, it is not part of the payload
+    pub preview: Option<Vec<u8>>,
+    /// cleartext hint how the message was protected, it is no secret and not part of the payload
+    pub hint: Option<String>,
+    /// the message is hidden only in the pixels where `(x + y) % 2 == 0`, kept in the header
+    pub checkerboard: bool,
 }
 
-// TODO implement Result returning
 impl Message {
-    pub fn of(dec: &mut dyn Read) -> Self {
-        let version = dec.read_u8().expect("Failed to read version header");
+    /// parses a message, see [`crate::format`] for the layout
+    pub fn of(dec: &mut dyn Read) -> Result<Self> {
+        Self::of_with_budget(dec, usize::MAX)
+    }
+
+    /// like `of()`, but `Ok(None)` if there is no message at all, e.g. in a clean carrier.
+    /// That is, if there is no valid header, or the message carries nothing
+    pub fn of_optional(dec: &mut dyn Read) -> Result<Option<Self>> {
+        Ok(absent_as_none(Self::of(dec))?.filter(|m| !m.is_empty()))
+    }
+
+    /// like `of()`, but fails with `SteganoError::MemoryBudgetExceeded` before more than `budget`
+    /// bytes of payload or unpacked files would be held in memory.
+    /// Note: content version 2 has no length, it's buffered up to the end of the carrier
+    pub fn of_with_budget(dec: &mut dyn Read, budget: usize) -> Result<Self> {
+        match Self::of_reporting_checksum(dec, budget)? {
+            (m, true) => Ok(m),
+            (_, false) => Err(FormatError::ChecksumMismatch.into()),
+        }
+    }
+
+    /// like `of_with_budget()`, but a payload that does not match its checksum is no error,
+    /// it's reported as `false` next to the message. Messages without checksum report `true`
+    pub fn of_reporting_checksum(dec: &mut dyn Read, budget: usize) -> Result<(Self, bool)> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+        debug!("parsing a message of content version {version:#04x}");
+
+        match ContentVersion::from_u8(version) {
+            ContentVersion::V1 => Ok((Self::new_of_v1(dec, budget)?, true)),
+            ContentVersion::V2 => Ok((Self::new_of_v2(dec, budget)?, true)),
+            ContentVersion::V4 => Ok((Self::new_of_v4(dec, budget)?, true)),
+            ContentVersion::V5 => Self::new_of_v5(dec, budget),
+            ContentVersion::V6 => Ok((Self::new_of_v6(dec, budget)?, true)),
+            ContentVersion::Unsupported(v) => Err(FormatError::UnsupportedVersion(v).into()),
+        }
+    }
+
+    /// reads only the headers and returns the length of the payload behind them, that is
+    /// the zip archive including its forward error correction, or the text of content version 1.
+    /// Content version 2 has no length, it fails with `SteganoError::FeatureNotSupportedInVersion`
+    pub fn peek_payload_size(dec: &mut dyn Read) -> Result<usize> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+
+        match ContentVersion::from_u8(version) {
+            ContentVersion::V1 => Ok(std::iter::from_fn(|| dec.read_u8().ok())
+                .take_while(|b| *b != V1_TERMINATOR)
+                .count()),
+            ContentVersion::V2 => Err(SteganoError::FeatureNotSupportedInVersion {
+                feature: "payload length",
+                version,
+            }),
+            ContentVersion::V4 => Ok(read_payload_size(dec)? as usize),
+            ContentVersion::V5 => Ok(HeaderFields::read(dec)?.read_payload_size(dec)? as usize),
+            ContentVersion::V6 => Ok(read_file_index(dec)?
+                .iter()
+                .map(IndexEntry::end)
+                .max()
+                .unwrap_or(0) as usize),
+            ContentVersion::Unsupported(v) => Err(FormatError::UnsupportedVersion(v).into()),
+        }
+    }
+
+    /// reads only the header fields and returns the hint, see [`Message::hint`].
+    /// The payload is not read, so it may be corrupted or protected. Only content version 5
+    /// carries a hint, the others give `None`
+    pub fn peek_hint(dec: &mut dyn Read) -> Result<Option<String>> {
+        Ok(peek_header_fields(dec)?.and_then(|fields| fields.hint))
+    }
+
+    /// reads only the header fields and returns the iterations that stretch the MAC key,
+    /// see [`Message::kdf_iterations`]. Only content version 5 carries them
+    pub fn peek_kdf_iterations(dec: &mut dyn Read) -> Result<Option<u32>> {
+        Ok(peek_header_fields(dec)?.and_then(|fields| fields.kdf_iterations))
+    }
+
+    /// parses the headers, and of the payload only as much as tells whether its first file is
+    /// deflated. The files are not read, `files` stays empty, but the index of content version 6
+    /// is. Fails with `FormatError::InvalidPayload` if the payload of content version 4 or 5 is
+    /// no zip archive
+    pub fn peek_header(dec: &mut dyn Read) -> Result<Self> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+        let mut m = Self::new(ContentVersion::from_u8(version));
+        let payload_size = match m.header {
+            ContentVersion::V4 => read_payload_size(dec)?,
+            ContentVersion::V5 => {
+                let fields = HeaderFields::read(dec)?;
+                let payload_size = fields.read_payload_size(dec)?;
+                m.apply_fields(fields);
+                payload_size
+            }
+            ContentVersion::Unsupported(v) => return Err(FormatError::UnsupportedVersion(v).into()),
+            ContentVersion::V6 => {
+                read_file_index(dec)?;
+                m.compression = false;
+                return Ok(m);
+            }
+            _ => {
+                m.compression = false;
+                return Ok(m);
+            }
+        };
+
+        let prefix_len = match m.fec_redundancy {
+            Some(redundancy) => fec::blocks_len(ZIP_LOCAL_HEADER_LEN, redundancy)?,
+            None => ZIP_LOCAL_HEADER_LEN,
+        };
+        let mut prefix = Vec::new();
+        dec.take(prefix_len.min(payload_size as usize) as u64)
+            .read_to_end(&mut prefix)?;
+        if let Some(redundancy) = m.fec_redundancy {
+            prefix = fec::decode(&prefix, redundancy)?;
+        }
+        m.compression = match prefix.get(..4) {
+            Some(ZIP_LOCAL_HEADER) => {
+                let method = prefix.get(8..10).ok_or(FormatError::InvalidPayload)?;
+                method != [0, 0]
+            }
+            Some(ZIP_END_OF_CENTRAL_DIRECTORY) => false,
+            _ => return Err(FormatError::InvalidPayload.into()),
+        };
+
+        Ok(m)
+    }
+
+    /// checks the Ed25519 signature of the header against the payload, with the public key of
+    /// the signer. Fails with `SteganoError::NotEmbedded` if the message is not signed,
+    /// and with `SteganoError::InvalidParameter` if the public key is none
+    pub fn verify_signature(dec: &mut dyn Read, public_key: &[u8], budget: usize) -> Result<bool> {
+        let public_key = public_key
+            .try_into()
+            .ok()
+            .and_then(|key| VerifyingKey::from_bytes(key).ok())
+            .ok_or_else(|| {
+                SteganoError::InvalidParameter("no valid Ed25519 public key".to_owned())
+            })?;
+        let Some(fields) = peek_header_fields(dec)? else {
+            return Err(SteganoError::NotEmbedded("signature"));
+        };
+        let signature = fields
+            .signature
+            .ok_or(SteganoError::NotEmbedded("signature"))?;
+        let (payload, _checksum_ok) = fields.read_payload_unverified(dec, budget)?;
+
+        Ok(public_key
+            .verify(&payload, &Signature::from_bytes(&signature))
+            .is_ok())
+    }
+
+    /// reads only the header fields and tells if the message was hidden in the checkerboard
+    /// pattern, see [`Message::checkerboard`]. Only content version 5 can tell so
+    pub fn peek_checkerboard(dec: &mut dyn Read) -> Result<bool> {
+        Ok(peek_header_fields(dec)?.is_some_and(|fields| fields.checkerboard))
+    }
+
+    /// reads the payload behind the headers as far as it goes, instead of failing without any of
+    /// it. Returns the bytes read, that is a prefix of the payload, next to the error that stopped
+    /// reading, `FormatError::Truncated` if the hidden bytes end before the payload does.
+    /// The payload is returned as hidden, forward error correction and checksum are not applied.
+    /// Only content versions 4 and 5 carry a length to read up to
+    pub fn payload_best_effort(
+        dec: &mut dyn Read,
+        budget: usize,
+    ) -> (Vec<u8>, Option<SteganoError>) {
+        let payload_size = (|| {
+            let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+            match ContentVersion::from_u8(version) {
+                ContentVersion::V4 => read_payload_size(dec),
+                ContentVersion::V5 => HeaderFields::read(dec)?.read_payload_size(dec),
+                ContentVersion::Unsupported(v) => Err(FormatError::UnsupportedVersion(v).into()),
+                _ => Err(SteganoError::FeatureNotSupportedInVersion {
+                    feature: "best effort unveiling",
+                    version,
+                }),
+            }
+        })();
+        let payload_size = match payload_size.and_then(|size| {
+            check_budget(size as usize, budget)?;
+            Ok(size as usize)
+        }) {
+            Ok(size) => size,
+            Err(e) => return (Vec::new(), Some(e)),
+        };
 
-        let version = ContentVersion::from_u8(version);
+        let mut buf = Vec::new();
+        let error = match dec.take(payload_size as u64).read_to_end(&mut buf) {
+            Err(e) => Some(e.into()),
+            Ok(read) if read < payload_size => Some(FormatError::Truncated("payload").into()),
+            Ok(_) => None,
+        };
+        debug!("recovered {} of {payload_size} payload bytes", buf.len());
+
+        (buf, error)
+    }
+
+    /// names and sizes of the hidden files. With content version 6 only the file index is read,
+    /// the others are parsed as a whole, at most `budget` bytes, see `of_with_budget()`
+    pub fn list_files(dec: &mut dyn Read, budget: usize) -> Result<Vec<(String, usize)>> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+        if ContentVersion::from_u8(version) == ContentVersion::V6 {
+            return Ok(read_file_index(dec)?
+                .into_iter()
+                .map(|entry| (entry.name, entry.size as usize))
+                .collect());
+        }
 
-        match version {
-            ContentVersion::V1 => Self::new_of_v1(dec),
-            ContentVersion::V2 => Self::new_of_v2(dec),
-            ContentVersion::V4 => Self::new_of_v4(dec),
-            ContentVersion::Unsupported(_) => {
-                panic!("Seems like you've got an invalid stegano file")
+        let m = Self::of_with_budget(&mut [version].as_slice().chain(dec), budget)?;
+        Ok(m.files
+            .into_iter()
+            .map(|(name, buf)| (name, buf.len()))
+            .collect())
+    }
+
+    /// the content of the hidden file `name`, without unpacking the other files into memory.
+    /// With content version 6 the other files are skipped by the file index, and nothing
+    /// behind the file is read. Fails with `SteganoError::FileNotFound` if there is no such file.
+    /// Note: payloads with forward error correction or a checksum are buffered, see `stream_content()`
+    pub fn extract_file(dec: &mut dyn Read, name: &str, budget: usize) -> Result<Vec<u8>> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+
+        file_prefix(
+            dec,
+            ContentVersion::from_u8(version),
+            name,
+            usize::MAX,
+            budget,
+        )
+    }
+
+    /// the first `max_bytes` of the hidden text, cut at the last whole character, e.g. for a
+    /// quick preview. Like [`Message::extract_file`] nothing behind them is unpacked, unless the
+    /// payload is buffered. Fails with `SteganoError::NoSecretData` if there is no text and with
+    /// `SteganoError::InvalidUtf8` if the bytes are no utf-8, apart from the cut
+    pub fn peek_text(dec: &mut dyn Read, max_bytes: usize, budget: usize) -> Result<String> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+        let buf =
+            match ContentVersion::from_u8(version) {
+                ContentVersion::V1 => {
+                    let mut buf = Vec::new();
+                    while let Ok(b) = dec.read_u8() {
+                        if b == V1_TERMINATOR || buf.len() == max_bytes {
+                            break;
+                        }
+                        buf.push(b);
+                        check_budget(buf.len(), budget)?;
+                    }
+                    buf
+                }
+                version => file_prefix(dec, version, "secret-message.txt", max_bytes, budget)
+                    .map_err(|e| match e {
+                        SteganoError::FileNotFound(_) => SteganoError::NoSecretData,
+                        e => e,
+                    })?,
+            };
+
+        match String::from_utf8(buf) {
+            Ok(text) => Ok(text),
+            // a character that was cut in half at the end
+            Err(e) if e.utf8_error().error_len().is_none() => {
+                let whole = e.utf8_error().valid_up_to();
+                let mut buf = e.into_bytes();
+                buf.truncate(whole);
+                String::from_utf8(buf).map_err(|_| SteganoError::InvalidUtf8)
             }
+            Err(_) => Err(SteganoError::InvalidUtf8),
+        }
+    }
+
+    /// reads only the headers and returns the channel order the message says it is hidden in,
+    /// RGBA for all but content version 5 with [`crate::format::FIELD_CHANNEL_ORDER`]
+    pub fn peek_channel_order(dec: &mut dyn Read) -> Result<ChannelOrder> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+
+        match ContentVersion::from_u8(version) {
+            ContentVersion::V5 => Ok(HeaderFields::read(dec)?.channel_order.unwrap_or_default()),
+            ContentVersion::Unsupported(v) => Err(FormatError::UnsupportedVersion(v).into()),
+            _ => Ok(ChannelOrder::Rgba),
         }
     }
 
+    /// checks the HMAC-SHA256 of the header against the payload, with the given key.
+    /// Fails with `SteganoError::NotEmbedded` if the message has no MAC
+    pub fn verify_mac(dec: &mut dyn Read, key: &[u8], budget: usize) -> Result<bool> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+        if ContentVersion::from_u8(version) != ContentVersion::V5 {
+            return Err(SteganoError::NotEmbedded("MAC"));
+        }
+        let fields = HeaderFields::read(dec)?;
+        let expected = fields.mac.ok_or(SteganoError::NotEmbedded("MAC"))?;
+        let (payload, _checksum_ok) = fields.read_payload_unverified(dec, budget)?;
+
+        Ok(
+            hmac_sha256(&stretched_key(key, fields.kdf_iterations, fields.kdf_salt))
+                .chain_update(&payload)
+                .verify_slice(&expected)
+                .is_ok(),
+        )
+    }
+
+    /// streams the content of the hidden files one after the other, or the text, into `w`
+    /// without holding the whole payload in memory, returns the number of bytes written.
+    /// Note: payloads with forward error correction or a checksum are buffered, they
+    /// need to be corrected and verified as a whole, at most `budget` bytes, see `of_with_budget()`
+    pub fn stream_content(dec: &mut dyn Read, w: &mut dyn Write, budget: usize) -> Result<u64> {
+        let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+
+        match ContentVersion::from_u8(version) {
+            ContentVersion::V1 => {
+                let text = Self::new_of_v1(dec, budget)?.text.unwrap_or_default();
+                w.write_all(text.as_bytes())?;
+
+                Ok(text.len() as u64)
+            }
+            ContentVersion::V2 => stream_files(dec, w),
+            ContentVersion::V4 => {
+                let payload_size = read_payload_size(dec)?;
+                stream_files(&mut dec.take(payload_size as u64), w)
+            }
+            ContentVersion::V6 => {
+                let mut index = read_file_index(dec)?;
+                index.sort_by_key(|entry| entry.offset);
+                let (mut position, mut written) = (0, 0);
+                for entry in index {
+                    let skip = (entry.offset as u64)
+                        .checked_sub(position)
+                        .ok_or(FormatError::InvalidPayload)?;
+                    std::io::copy(&mut (&mut *dec).take(skip), &mut std::io::sink())?;
+                    let copied = std::io::copy(&mut (&mut *dec).take(entry.size as u64), w)?;
+                    if copied < entry.size as u64 {
+                        return Err(FormatError::Truncated("file").into());
+                    }
+                    position = entry.end();
+                    written += copied;
+                }
+
+                Ok(written)
+            }
+            ContentVersion::V5 => {
+                let fields = HeaderFields::read(dec)?;
+                if fields.needs_whole_payload() {
+                    let buf = fields.read_payload(dec, budget)?;
+                    stream_files(&mut buf.as_slice(), w)
+                } else {
+                    let payload_size = fields.read_payload_size(dec)?;
+                    stream_files(&mut dec.take(payload_size as u64), w)
+                }
+            }
+            ContentVersion::Unsupported(v) => Err(FormatError::UnsupportedVersion(v).into()),
+        }
+    }
+
+    #[cfg(feature = "fs")]
     pub fn new_of_files(files: &[String]) -> Self {
         let mut m = Self::new(ContentVersion::V4);
 
@@ -69,6 +631,227 @@ impl Message {
         m
     }
 
+    /// names of all header fields that are set, those require content version 5
+    pub fn header_fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.cover_hash.is_some() {
+            fields.push("cover hash");
+        }
+        if self.fec_redundancy.is_some() {
+            fields.push("forward error correction");
+        }
+        if self.checksum != ChecksumAlgo::None {
+            fields.push("checksum");
+        }
+        if self.provenance.is_some() {
+            fields.push("provenance");
+        }
+        if self.comment.is_some() {
+            fields.push("comment");
+        }
+        if self.mac_key.is_some() {
+            fields.push("mac");
+            if self.kdf_iterations.is_some() {
+                fields.push("kdf iterations");
+            }
+            if self.kdf_salt.is_some() {
+                fields.push("kdf salt");
+            }
+        }
+        if self.signing_key.is_some() {
+            fields.push("signature");
+        }
+        if self.redundant_length {
+            fields.push("redundant length");
+        }
+        if self.channel_order != ChannelOrder::Rgba {
+            fields.push("channel order");
+        }
+        if self.channel_bit_depths.is_some() {
+            fields.push("channel bit depths");
+        }
+        if self.preview.is_some() {
+            fields.push("preview");
+        }
+        if self.hint.is_some() {
+            fields.push("hint");
+        }
+        if self.checkerboard {
+            fields.push("checkerboard");
+        }
+
+        fields
+    }
+
+    /// checks that all contents of the message can be represented by its content version,
+    /// - V1 carries only a text
+    /// - V2 and V4 carry only files
+    /// - V5 carries files and header fields
+    /// - V6 carries only files, behind an index
+    pub fn check_content_version(&self) -> Result<()> {
+        let unsupported = |feature| SteganoError::FeatureNotSupportedInVersion {
+            feature,
+            version: self.header.to_u8(),
+        };
+        let header_field = self.header_fields().first().copied();
+
+        match self.header {
+            ContentVersion::V1 if !self.files.is_empty() => Err(unsupported("files")),
+            ContentVersion::V2 | ContentVersion::V4 | ContentVersion::V5 | ContentVersion::V6
+                if self.text.is_some() =>
+            {
+                Err(unsupported("text"))
+            }
+            ContentVersion::V1 | ContentVersion::V2 | ContentVersion::V4 | ContentVersion::V6
+                if header_field.is_some() =>
+            {
+                Err(unsupported(header_field.unwrap()))
+            }
+            ContentVersion::Unsupported(_) => Err(unsupported("content")),
+            _ => Ok(()),
+        }
+    }
+
+    /// serializes the message, after the content version compatibility was checked
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.validate()?;
+
+        Ok(self.serialize(false)?.0)
+    }
+
+    /// like `to_bytes()`, but the checksum is left as zeros, so that it can be computed while the
+    /// payload is hidden instead of in a pass of its own. `None` is returned next to the bytes
+    /// if there is nothing to defer, that is without checksum or with forward error correction,
+    /// whose checksum is over the payload before it is coded
+    pub fn to_bytes_deferring_checksum(&self) -> Result<(Vec<u8>, Option<DeferredChecksum>)> {
+        self.validate()?;
+
+        self.serialize(true)
+    }
+
+    /// checks all that can make the serialization fail, without serializing
+    fn validate(&self) -> Result<()> {
+        self.check_content_version()?;
+        if let Some(redundancy) = self.fec_redundancy {
+            fec::parity_len(redundancy)?;
+        }
+        if let Some(comment) = self.comment.as_ref().filter(|c| c.len() > MAX_COMMENT_LEN) {
+            return Err(SteganoError::InvalidParameter(format!(
+                "comment of {} bytes is longer than {MAX_COMMENT_LEN} bytes",
+                comment.len()
+            )));
+        }
+        if let Some(preview) = self.preview.as_ref().filter(|p| p.len() > MAX_PREVIEW_LEN) {
+            return Err(SteganoError::InvalidParameter(format!(
+                "preview of {} bytes is longer than {MAX_PREVIEW_LEN} bytes",
+                preview.len()
+            )));
+        }
+        if let Some(hint) = self.hint.as_ref().filter(|h| h.len() > MAX_HINT_LEN) {
+            return Err(SteganoError::InvalidParameter(format!(
+                "hint of {} bytes is longer than {MAX_HINT_LEN} bytes",
+                hint.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// length of the serialized message in bytes, including all headers. The files are
+    /// compressed to know it, but nothing is coded, hashed or signed
+    pub fn payload_len(&self) -> usize {
+        match self.header {
+            ContentVersion::V1 => 1 + self.text.as_ref().map_or(0, String::len) + 1,
+            ContentVersion::V6 => {
+                let index: usize = self.files.iter().map(|(name, _)| 2 + name.len() + 8).sum();
+                let content: usize = self.files.iter().map(|(_, buf)| buf.len()).sum();
+
+                1 + 2 + index + content
+            }
+            _ => self.payload_len_around(self.zip_archive().len()),
+        }
+    }
+
+    /// the payload length if the files were stored in the zip archive without compression, that
+    /// is what the message takes at most, but for a few bytes that deflate adds to data that does
+    /// not compress. Nothing is built, only the lengths of the files are taken
+    pub fn uncompressed_payload_len(&self) -> usize {
+        match self.header {
+            ContentVersion::V1 | ContentVersion::V6 => self.payload_len(),
+            _ => self.payload_len_around(
+                ZIP_END_OF_CENTRAL_DIRECTORY_LEN
+                    + self
+                        .files
+                        .iter()
+                        .map(|(name, buf)| zip_entry_len(name.len(), buf.len()))
+                        .sum::<usize>(),
+            ),
+        }
+    }
+
+    /// the payload length without files, with the first file, with the first two files and
+    /// so on up to all files. The files are compressed only once, see `payload_len()`
+    pub fn cumulative_payload_lens(&self) -> Vec<usize> {
+        // the files take `entry_lens` of their container, the container itself `rest`
+        let (entry_lens, rest) = match self.header {
+            ContentVersion::V1 => (vec![0; self.files.len()], 0),
+            ContentVersion::V6 => {
+                let entry_lens = self
+                    .files
+                    .iter()
+                    .map(|(name, buf)| 2 + name.len() + 8 + buf.len());
+
+                (entry_lens.collect(), 0)
+            }
+            _ => {
+                let zip = self.zip_archive();
+                let entry_lens = zip_entry_lens(&zip);
+                let rest = zip.len() - entry_lens.iter().sum::<usize>();
+
+                (entry_lens, rest)
+            }
+        };
+        let len_of = |entries_len: usize| match self.header {
+            ContentVersion::V1 => self.payload_len(),
+            ContentVersion::V6 => 1 + 2 + entries_len,
+            _ => self.payload_len_around(rest + entries_len),
+        };
+
+        std::iter::once(0)
+            .chain(entry_lens.iter().scan(0, |sum, len| {
+                *sum += len;
+                Some(*sum)
+            }))
+            .map(len_of)
+            .collect()
+    }
+
+    /// length of the serialized message whose zip archive is `zip_len` bytes long
+    fn payload_len_around(&self, zip_len: usize) -> usize {
+        match self.header {
+            ContentVersion::V2 => 1 + zip_len + 2,
+            ContentVersion::V5 => {
+                let payload_len = match self.fec_redundancy {
+                    Some(redundancy) => fec::encoded_len(zip_len, redundancy).unwrap_or(zip_len),
+                    None => zip_len,
+                };
+                let fields_len = self
+                    .header_fields_to_bytes(&[], payload_len as u32, Digests::Zeroed)
+                    .map_or(0, |(fields, _)| fields.len());
+
+                1 + 2 + fields_len + 4 + payload_len
+            }
+            ContentVersion::V4 => 1 + 4 + zip_len,
+            _ => 1 + zip_len,
+        }
+    }
+
+    /// true if the message carries neither files nor a text
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.text.is_none()
+    }
+
+    #[cfg(feature = "fs")]
     pub fn add_file(&mut self, file: &str) -> &mut Self {
         let mut fd = File::open(file).expect("File was not readable");
         let mut fb: Vec<u8> = Vec::new();
@@ -98,27 +881,208 @@ impl Message {
             header: version,
             files: Vec::new(),
             text: None,
+            cover_hash: None,
+            fec_redundancy: None,
+            checksum: ChecksumAlgo::None,
+            provenance: None,
+            comment: None,
+            compression: true,
+            mac_key: None,
+            kdf_iterations: None,
+            kdf_salt: None,
+            mac: None,
+            signing_key: None,
+            signature: None,
+            redundant_length: false,
+            channel_order: ChannelOrder::Rgba,
+            channel_bit_depths: None,
+            preview: None,
+            hint: None,
+            checkerboard: false,
         }
     }
 
-    fn new_of_v4(r: &mut dyn Read) -> Self {
-        let payload_size = r
-            .read_u32::<BigEndian>()
-            .expect("Failed to read payload size header");
+    /// the message and whether its payload matches its checksum
+    fn new_of_v5(r: &mut dyn Read, budget: usize) -> Result<(Self, bool)> {
+        let fields = HeaderFields::read(r)?;
+        let (buf, checksum_ok) = fields.read_payload_unverified(r, budget)?;
 
-        let mut buf = Vec::new();
-        r.take(payload_size as u64)
-            .read_to_end(&mut buf)
-            .expect("Message read of content version 0x04 failed.");
+        let mut m = Self::new_of(buf, budget)?;
+        m.apply_fields(fields);
+
+        Ok((m, checksum_ok))
+    }
+
+    /// takes the header fields of content version 5 into the message
+    fn apply_fields(&mut self, fields: HeaderFields) {
+        self.header = ContentVersion::V5;
+        self.cover_hash = fields.cover_hash;
+        self.fec_redundancy = fields.fec_redundancy;
+        self.checksum = fields.checksum.map(|(algo, _)| algo).unwrap_or_default();
+        self.provenance = fields.provenance;
+        self.comment = fields.comment;
+        self.mac = fields.mac;
+        self.signature = fields.signature;
+        self.redundant_length = fields.payload_size.is_some();
+        self.channel_order = fields.channel_order.unwrap_or_default();
+        self.channel_bit_depths = fields.channel_bit_depths;
+        self.preview = fields.preview;
+        self.hint = fields.hint;
+        self.kdf_iterations = fields.kdf_iterations;
+        self.kdf_salt = fields.kdf_salt;
+        self.checkerboard = fields.checkerboard;
+    }
+
+    /// the header fields, `payload` is the zip archive before forward error correction,
+    /// `payload_size` the length of it after
+    /// returns the offset of the checksum in the fields next to them
+    fn header_fields_to_bytes(
+        &self,
+        payload: &[u8],
+        payload_size: u32,
+        digests: Digests,
+    ) -> Result<(Vec<u8>, Option<usize>)> {
+        fn write_field(fields: &mut Vec<u8>, tag: u8, value: &[u8]) -> Result<()> {
+            fields.push(tag);
+            fields
+                .write_u16::<BigEndian>(length_field(value.len(), "header field")?)
+                .expect("Failed to write the header field size.");
+            fields.extend_from_slice(value);
+
+            Ok(())
+        }
+
+        let mut fields = Vec::new();
+        let mut checksum_at = None;
+
+        if let Some(hash) = self.cover_hash.as_ref() {
+            write_field(&mut fields, FIELD_COVER_HASH, hash)?;
+        }
+        if let Some(redundancy) = self.fec_redundancy {
+            write_field(&mut fields, FIELD_FEC_REDUNDANCY, &[redundancy])?;
+        }
+        if let Some(algo) = self.checksum.to_u8() {
+            let mut value = vec![algo];
+            match digests {
+                Digests::Computed => value.extend(self.checksum.digest(payload)),
+                _ => value.resize(1 + self.checksum.len(), 0),
+            }
+            // behind the tag, the length and the algorithm
+            checksum_at = Some(fields.len() + 4);
+            write_field(&mut fields, FIELD_CHECKSUM, &value)?;
+        }
+        if let Some(provenance) = self.provenance.as_ref() {
+            let mut value = provenance.created_at.to_be_bytes().to_vec();
+            value.extend_from_slice(provenance.tool_version.as_bytes());
+            write_field(&mut fields, FIELD_PROVENANCE, &value)?;
+        }
+        if let Some(comment) = self.comment.as_ref() {
+            write_field(&mut fields, FIELD_COMMENT, comment.as_bytes())?;
+        }
+        if let Some(key) = self.mac_key.as_ref() {
+            let mac = match digests {
+                Digests::Zeroed => [0; MAC_LEN],
+                _ => hmac_sha256(&stretched_key(
+                    key.expose(),
+                    self.kdf_iterations,
+                    self.kdf_salt,
+                ))
+                .chain_update(payload)
+                .finalize()
+                .into_bytes()
+                .into(),
+            };
+            write_field(&mut fields, FIELD_MAC, &mac)?;
+            if let Some(iterations) = self.kdf_iterations {
+                write_field(&mut fields, FIELD_KDF_ITERATIONS, &iterations.to_be_bytes())?;
+            }
+            if let Some(salt) = self.kdf_salt.as_ref() {
+                write_field(&mut fields, FIELD_KDF_SALT, salt)?;
+            }
+        }
+        if let Some(key) = self.signing_key.as_ref() {
+            let signature = match digests {
+                Digests::Zeroed => [0; SIGNATURE_LEN],
+                _ => SigningKey::from_bytes(key.expose())
+                    .sign(payload)
+                    .to_bytes(),
+            };
+            write_field(&mut fields, FIELD_SIGNATURE, &signature)?;
+        }
+        if self.redundant_length {
+            write_field(
+                &mut fields,
+                FIELD_PAYLOAD_LENGTH,
+                &payload_size.to_be_bytes().repeat(PAYLOAD_LENGTH_COPIES),
+            )?;
+        }
+        match self.channel_order {
+            ChannelOrder::Rgba => {}
+            ChannelOrder::Bgra => {
+                write_field(&mut fields, FIELD_CHANNEL_ORDER, &[CHANNEL_ORDER_BGRA])?
+            }
+            ChannelOrder::Argb => {
+                write_field(&mut fields, FIELD_CHANNEL_ORDER, &[CHANNEL_ORDER_ARGB])?
+            }
+        }
+        if let Some(depths) = self.channel_bit_depths {
+            write_field(&mut fields, FIELD_CHANNEL_BIT_DEPTHS, &depths)?;
+        }
+        if let Some(preview) = self.preview.as_ref() {
+            write_field(&mut fields, FIELD_PREVIEW, preview)?;
+        }
+        if let Some(hint) = self.hint.as_ref() {
+            write_field(&mut fields, FIELD_HINT, hint.as_bytes())?;
+        }
+        if self.checkerboard {
+            write_field(&mut fields, FIELD_CHECKERBOARD, &[])?;
+        }
 
-        Self::new_of(buf)
+        Ok((fields, checksum_at))
     }
 
-    fn new_of_v2(r: &mut dyn Read) -> Self {
+    /// the files of content version 6 in the order of the index
+    fn new_of_v6(r: &mut dyn Read, budget: usize) -> Result<Self> {
+        let index = read_file_index(r)?;
+        let end = index.iter().map(IndexEntry::end).max().unwrap_or(0);
+        check_budget(end as usize, budget)?;
+        let buf = read_exact(r, end as usize, "files")?;
+
+        let mut m = Self::new(ContentVersion::V6);
+        m.files = index
+            .into_iter()
+            .map(|entry| {
+                let content = buf[entry.offset as usize..entry.end() as usize].to_vec();
+                (entry.name, content)
+            })
+            .collect();
+
+        Ok(m)
+    }
+
+    fn new_of_v4(r: &mut dyn Read, budget: usize) -> Result<Self> {
+        Self::new_of(Self::read_payload(r, budget)?, budget)
+    }
+
+    /// reads the length prefixed payload of content version 4
+    fn read_payload(r: &mut dyn Read, budget: usize) -> Result<Vec<u8>> {
+        let payload_size = read_payload_size(r)?;
+        Self::read_payload_of_size(r, payload_size, budget)
+    }
+
+    /// reads the payload behind its length
+    fn read_payload_of_size(r: &mut dyn Read, payload_size: u32, budget: usize) -> Result<Vec<u8>> {
+        check_budget(payload_size as usize, budget)?;
+
+        read_exact(r, payload_size as usize, "payload")
+    }
+
+    fn new_of_v2(r: &mut dyn Read, budget: usize) -> Result<Self> {
         const EOF: u8 = 0xff;
         let mut buf = Vec::new();
-        r.read_to_end(&mut buf)
-            .expect("Message read of content version 0x04 failed.");
+        r.take(budget.saturating_add(1) as u64)
+            .read_to_end(&mut buf)?;
+        check_budget(buf.len(), budget)?;
 
         let mut eof = 0;
         for (i, b) in buf.iter().enumerate().rev() {
@@ -133,11 +1097,13 @@ impl Message {
             buf.resize(eof, 0);
         }
 
-        Self::new_of(buf)
+        Self::new_of(buf, budget)
     }
 
-    fn new_of(buf: Vec<u8>) -> Message {
+    fn new_of(buf: Vec<u8>, budget: usize) -> Result<Message> {
         let mut files = Vec::new();
+        let mut unpacked = 0;
+        let mut compression = false;
         let mut buf = Cursor::new(buf);
 
         while let Ok(zip) = zip::read::read_zipfile_from_stream(&mut buf) {
@@ -145,8 +1111,14 @@ impl Message {
                 None => {}
                 Some(mut file) => {
                     let mut writer = Vec::new();
-                    file.read_to_end(&mut writer)
-                        .expect("Failed to read data from inner message structure.");
+                    let remaining = budget.saturating_sub(unpacked);
+                    (&mut file)
+                        .take(remaining.saturating_add(1) as u64)
+                        .read_to_end(&mut writer)
+                        .map_err(|_| FormatError::InvalidPayload)?;
+                    unpacked += writer.len();
+                    check_budget(unpacked, budget)?;
+                    compression |= file.compression() != zip::CompressionMethod::Stored;
 
                     files.push((file.name().to_string(), writer));
                 }
@@ -155,83 +1127,628 @@ impl Message {
 
         let mut m = Message::new(ContentVersion::V4);
         m.files.append(&mut files);
+        m.compression = compression;
 
-        m
+        Ok(m)
     }
 
-    fn new_of_v1(r: &mut dyn Read) -> Self {
-        const EOF: u8 = 0xff;
+    fn new_of_v1(r: &mut dyn Read, budget: usize) -> Result<Self> {
         let mut buf = Vec::new();
 
         while let Ok(b) = r.read_u8() {
-            if b == EOF {
+            if b == V1_TERMINATOR {
                 break;
             }
             buf.push(b);
+            check_budget(buf.len(), budget)?;
         }
 
         // TODO shall we upgrade all v1 to v4, to get rid of the legacy?
         let mut m = Message::new(ContentVersion::V1);
-        m.text = Some(String::from_utf8(buf).expect("Message failed to decode from image"));
+        m.text = Some(String::from_utf8(buf).map_err(|_| FormatError::InvalidText)?);
 
-        m
+        Ok(m)
+    }
+}
+
+/// reads the `u32` payload length of content version 4 and 5
+/// signature of the local header of a file in a zip archive
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+
+/// signature of the end of a zip archive, that is where an empty archive starts
+const ZIP_END_OF_CENTRAL_DIRECTORY: &[u8] = b"PK\x05\x06";
+
+/// length of the local header of a file in a zip archive, without its name
+const ZIP_LOCAL_HEADER_LEN: usize = 30;
+
+/// length of the entry of a file in the central directory of a zip archive, without its name
+const ZIP_CENTRAL_HEADER_LEN: usize = 46;
+
+/// length of the end of central directory record of a zip archive without comment
+const ZIP_END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+
+/// the bytes a file of `len` bytes takes of a zip archive if it is stored without compression
+pub(crate) fn zip_entry_len(name_len: usize, len: usize) -> usize {
+    ZIP_LOCAL_HEADER_LEN + name_len + len + ZIP_CENTRAL_HEADER_LEN + name_len
+}
+
+fn read_payload_size(r: &mut dyn Read) -> Result<u32> {
+    Ok(r.read_u32::<BigEndian>()
+        .map_err(|_| FormatError::Truncated("payload length"))?)
+}
+
+/// a file in the index of content version 6
+struct IndexEntry {
+    name: String,
+    /// from the end of the index
+    offset: u32,
+    size: u32,
+}
+
+impl IndexEntry {
+    /// the offset behind the file
+    fn end(&self) -> u64 {
+        self.offset as u64 + self.size as u64
+    }
+}
+
+/// reads the file index of content version 6
+fn read_file_index(r: &mut dyn Read) -> Result<Vec<IndexEntry>> {
+    let truncated = |_| FormatError::Truncated("file index");
+    let count = r.read_u16::<BigEndian>().map_err(truncated)?;
+
+    (0..count)
+        .map(|_| {
+            let name_len = r.read_u16::<BigEndian>().map_err(truncated)?;
+            let name = String::from_utf8(read_exact(r, name_len as usize, "file index")?)
+                .map_err(|_| FormatError::InvalidText)?;
+
+            Ok(IndexEntry {
+                name,
+                offset: r.read_u32::<BigEndian>().map_err(truncated)?,
+                size: r.read_u32::<BigEndian>().map_err(truncated)?,
+            })
+        })
+        .collect()
+}
+
+/// copies the content of all files of a zip payload into `w`, entry by entry
+fn stream_files(mut r: &mut dyn Read, w: &mut dyn Write) -> Result<u64> {
+    let mut written = 0;
+    while let Some(mut file) =
+        zip::read::read_zipfile_from_stream(&mut r).map_err(|_| FormatError::InvalidPayload)?
+    {
+        written += std::io::copy(&mut file, w)?;
+    }
+
+    Ok(written)
+}
+
+/// the first `max_bytes` of the hidden file `name`, behind the version byte, see
+/// [`Message::extract_file`]
+fn file_prefix(
+    dec: &mut dyn Read,
+    version: ContentVersion,
+    name: &str,
+    max_bytes: usize,
+    budget: usize,
+) -> Result<Vec<u8>> {
+    let not_found = || SteganoError::FileNotFound(name.to_owned());
+
+    match version {
+        ContentVersion::V1 => Err(not_found()),
+        ContentVersion::V2 => find_file(dec, name, max_bytes, budget)?.ok_or_else(not_found),
+        ContentVersion::V4 => {
+            let payload_size = read_payload_size(dec)?;
+            find_file(&mut dec.take(payload_size as u64), name, max_bytes, budget)?
+                .ok_or_else(not_found)
+        }
+        ContentVersion::V5 => {
+            let fields = HeaderFields::read(dec)?;
+            let found = if fields.needs_whole_payload() {
+                let buf = fields.read_payload(dec, budget)?;
+                find_file(&mut buf.as_slice(), name, max_bytes, budget)?
+            } else {
+                let payload_size = fields.read_payload_size(dec)?;
+                find_file(&mut dec.take(payload_size as u64), name, max_bytes, budget)?
+            };
+
+            found.ok_or_else(not_found)
+        }
+        ContentVersion::V6 => {
+            let index = read_file_index(dec)?;
+            let entry = index
+                .iter()
+                .find(|e| e.name == name)
+                .ok_or_else(not_found)?;
+            let len = (entry.size as usize).min(max_bytes);
+            check_budget(len, budget)?;
+            std::io::copy(
+                &mut (&mut *dec).take(entry.offset as u64),
+                &mut std::io::sink(),
+            )?;
+
+            read_exact(dec, len, "file")
+        }
+        ContentVersion::Unsupported(v) => Err(FormatError::UnsupportedVersion(v).into()),
+    }
+}
+
+/// unpacks only the first `max_bytes` of the file `name` of a zip payload, the other entries
+/// are skipped
+fn find_file(
+    mut r: &mut dyn Read,
+    name: &str,
+    max_bytes: usize,
+    budget: usize,
+) -> Result<Option<Vec<u8>>> {
+    while let Some(mut file) =
+        zip::read::read_zipfile_from_stream(&mut r).map_err(|_| FormatError::InvalidPayload)?
+    {
+        if file.name() != name {
+            continue;
+        }
+        let mut buf = Vec::new();
+        (&mut file)
+            .take(max_bytes.min(budget.saturating_add(1)) as u64)
+            .read_to_end(&mut buf)
+            .map_err(|_| FormatError::InvalidPayload)?;
+        check_budget(buf.len(), budget)?;
+
+        return Ok(Some(buf));
+    }
+
+    Ok(None)
+}
+
+/// the header fields of content version 5
+struct HeaderFields {
+    cover_hash: Option<[u8; 32]>,
+    fec_redundancy: Option<u8>,
+    checksum: Option<(ChecksumAlgo, Vec<u8>)>,
+    provenance: Option<Provenance>,
+    comment: Option<String>,
+    mac: Option<[u8; MAC_LEN]>,
+    signature: Option<[u8; SIGNATURE_LEN]>,
+    payload_size: Option<u32>,
+    channel_order: Option<ChannelOrder>,
+    channel_bit_depths: Option<[u8; 3]>,
+    preview: Option<Vec<u8>>,
+    hint: Option<String>,
+    kdf_iterations: Option<u32>,
+    kdf_salt: Option<[u8; KDF_SALT_LEN]>,
+    checkerboard: bool,
+}
+
+impl HeaderFields {
+    fn read(r: &mut dyn Read) -> Result<Self> {
+        let header_size = r
+            .read_u16::<BigEndian>()
+            .map_err(|_| FormatError::Truncated("header length"))?;
+        let header = read_exact(r, header_size as usize, "header fields")?;
+
+        let mut fields = Self {
+            cover_hash: None,
+            fec_redundancy: None,
+            checksum: None,
+            provenance: None,
+            comment: None,
+            mac: None,
+            signature: None,
+            payload_size: None,
+            channel_order: None,
+            channel_bit_depths: None,
+            preview: None,
+            hint: None,
+            kdf_iterations: None,
+            kdf_salt: None,
+            checkerboard: false,
+        };
+        let mut header = Cursor::new(header);
+        while let Ok(tag) = header.read_u8() {
+            let len = header
+                .read_u16::<BigEndian>()
+                .map_err(|_| FormatError::Truncated("header fields"))?;
+            let value = read_exact(&mut header, len as usize, "header fields")?;
+            trace!("header field {tag:#04x} of {len} bytes");
+
+            // unknown fields are skipped, for forward compatibility
+            match tag {
+                FIELD_COVER_HASH => {
+                    if value.len() != COVER_HASH_LEN {
+                        return Err(FormatError::MalformedField(tag).into());
+                    }
+                    fields.cover_hash = value.try_into().ok();
+                }
+                FIELD_FEC_REDUNDANCY => {
+                    let [redundancy] = value[..] else {
+                        return Err(FormatError::MalformedField(tag).into());
+                    };
+                    fields.fec_redundancy = Some(redundancy);
+                }
+                FIELD_CHECKSUM => {
+                    let algo = value
+                        .first()
+                        .and_then(|a| ChecksumAlgo::from_u8(*a))
+                        .ok_or(FormatError::MalformedField(tag))?;
+                    fields.checksum = Some((algo, value[1..].to_vec()));
+                }
+                FIELD_PROVENANCE => {
+                    let malformed = FormatError::MalformedField(tag);
+                    let (created_at, tool_version) =
+                        value.split_at_checked(8).ok_or(malformed.clone())?;
+                    fields.provenance = Some(Provenance {
+                        created_at: u64::from_be_bytes(created_at.try_into().unwrap()),
+                        tool_version: String::from_utf8(tool_version.to_vec())
+                            .map_err(|_| malformed)?,
+                    });
+                }
+                FIELD_COMMENT => {
+                    fields.comment = Some(
+                        String::from_utf8(value).map_err(|_| FormatError::MalformedField(tag))?,
+                    );
+                }
+                FIELD_MAC => {
+                    fields.mac = Some(
+                        value
+                            .try_into()
+                            .map_err(|_| FormatError::MalformedField(tag))?,
+                    );
+                }
+                FIELD_SIGNATURE => {
+                    fields.signature = Some(
+                        value
+                            .try_into()
+                            .map_err(|_| FormatError::MalformedField(tag))?,
+                    );
+                }
+                FIELD_PAYLOAD_LENGTH => {
+                    if value.len() != 4 * PAYLOAD_LENGTH_COPIES {
+                        return Err(FormatError::MalformedField(tag).into());
+                    }
+                    fields.payload_size = Some(majority_of(&value));
+                }
+                FIELD_CHANNEL_ORDER => {
+                    fields.channel_order = match value[..] {
+                        [CHANNEL_ORDER_BGRA] => Some(ChannelOrder::Bgra),
+                        [CHANNEL_ORDER_ARGB] => Some(ChannelOrder::Argb),
+                        _ => return Err(FormatError::MalformedField(tag).into()),
+                    };
+                }
+                FIELD_CHANNEL_BIT_DEPTHS => {
+                    fields.channel_bit_depths = Some(
+                        value
+                            .try_into()
+                            .map_err(|_| FormatError::MalformedField(tag))?,
+                    );
+                }
+                FIELD_PREVIEW => fields.preview = Some(value),
+                FIELD_HINT => {
+                    fields.hint = Some(
+                        String::from_utf8(value).map_err(|_| FormatError::MalformedField(tag))?,
+                    );
+                }
+                FIELD_KDF_ITERATIONS => {
+                    let iterations = value
+                        .try_into()
+                        .map(u32::from_be_bytes)
+                        .map_err(|_| FormatError::MalformedField(tag))?;
+                    // a forged header must not make the decoder stretch the key for hours
+                    if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&iterations) {
+                        return Err(FormatError::MalformedField(tag).into());
+                    }
+                    fields.kdf_iterations = Some(iterations);
+                }
+                FIELD_KDF_SALT => {
+                    fields.kdf_salt = Some(
+                        value
+                            .try_into()
+                            .map_err(|_| FormatError::MalformedField(tag))?,
+                    );
+                }
+                FIELD_CHECKERBOARD => fields.checkerboard = true,
+                _ => {}
+            }
+        }
+        debug!("parsed {header_size} bytes of header fields");
+
+        Ok(fields)
+    }
+
+    /// true if the payload has to be read completely, before it can be used
+    fn needs_whole_payload(&self) -> bool {
+        self.fec_redundancy.is_some() || self.checksum.is_some()
+    }
+
+    /// reads the payload, corrects it and verifies its checksum
+    fn read_payload(&self, r: &mut dyn Read, budget: usize) -> Result<Vec<u8>> {
+        match self.read_payload_unverified(r, budget)? {
+            (buf, true) => Ok(buf),
+            (_, false) => Err(FormatError::ChecksumMismatch.into()),
+        }
+    }
+
+    /// reads the payload length behind the header, the redundant one of the header wins
+    fn read_payload_size(&self, r: &mut dyn Read) -> Result<u32> {
+        let payload_size = read_payload_size(r)?;
+
+        Ok(self.payload_size.unwrap_or(payload_size))
+    }
+
+    /// reads the payload and corrects it, returns it with whether it matches its checksum
+    fn read_payload_unverified(&self, r: &mut dyn Read, budget: usize) -> Result<(Vec<u8>, bool)> {
+        let payload_size = self.read_payload_size(r)?;
+        let mut buf = Message::read_payload_of_size(r, payload_size, budget)?;
+        if let Some(redundancy) = self.fec_redundancy {
+            buf = fec::decode(&buf, redundancy)?;
+        }
+        let checksum_ok = match self.checksum.as_ref() {
+            Some((algo, expected)) => {
+                let ok = algo.digest(&buf) == *expected;
+                debug!(
+                    "{algo:?} checksum of {} payload bytes matches: {ok}",
+                    buf.len()
+                );
+                ok
+            }
+            None => true,
+        };
+
+        Ok((buf, checksum_ok))
+    }
+}
+
+/// the bitwise majority of the three big endian `u32` copies of [`FIELD_PAYLOAD_LENGTH`]
+fn majority_of(copies: &[u8]) -> u32 {
+    let copy = |i: usize| u32::from_be_bytes(copies[i * 4..i * 4 + 4].try_into().unwrap());
+    let (a, b, c) = (copy(0), copy(1), copy(2));
+
+    (a & b) | (a & c) | (b & c)
+}
+
+/// `None` for the errors of a buffer that has no valid header, other errors stay errors
+pub(crate) fn absent_as_none<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(t) => Ok(Some(t)),
+        Err(SteganoError::InvalidFormat(
+            FormatError::Empty
+            | FormatError::UnsupportedVersion(_)
+            | FormatError::Truncated(_)
+            | FormatError::MalformedField(_)
+            | FormatError::InvalidText,
+        )) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// HMAC-SHA256 keyed by `key`, keys of any length are fine
+fn hmac_sha256(key: &[u8]) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
+/// salt of the key derivation of the MAC key for messages without [`FIELD_KDF_SALT`],
+/// it must never change
+const MAC_KDF_SALT: &[u8] = b"stegano-rs mac key";
+
+/// the MAC key stretched by PBKDF2-HMAC-SHA256 with that many iterations, or as it is
+fn stretched_key(key: &[u8], iterations: Option<u32>, salt: Option<[u8; KDF_SALT_LEN]>) -> Vec<u8> {
+    let Some(iterations) = iterations else {
+        return key.to_vec();
+    };
+    let salt = salt.as_ref().map_or(MAC_KDF_SALT, |salt| &salt[..]);
+    let mut stretched = vec![0; MAC_LEN];
+    pbkdf2::pbkdf2_hmac::<Sha256>(key, salt, iterations, &mut stretched);
+
+    stretched
+}
+
+/// the header fields of content version 5, `None` for the other versions that have none
+fn peek_header_fields(dec: &mut dyn Read) -> Result<Option<HeaderFields>> {
+    let version = dec.read_u8().map_err(|_| FormatError::Empty)?;
+
+    match ContentVersion::from_u8(version) {
+        ContentVersion::V5 => Ok(Some(HeaderFields::read(dec)?)),
+        ContentVersion::Unsupported(v) => Err(FormatError::UnsupportedVersion(v).into()),
+        _ => Ok(None),
+    }
+}
+
+/// fails with `SteganoError::MemoryBudgetExceeded` if `needed` bytes do not fit in the budget
+pub(crate) fn check_budget(needed: usize, budget: usize) -> Result<()> {
+    if needed > budget {
+        return Err(SteganoError::MemoryBudgetExceeded { needed, budget });
+    }
+
+    Ok(())
+}
+
+/// reads exactly `len` bytes, fails with `FormatError::Truncated` naming `what` otherwise
+fn read_exact(r: &mut dyn Read, len: usize, what: &'static str) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(FormatError::Truncated(what).into());
     }
+
+    Ok(buf)
 }
 
 impl From<&mut Vec<u8>> for Message {
     fn from(buf: &mut Vec<u8>) -> Self {
         let mut c = Cursor::new(buf);
-        Message::of(&mut c)
+        Message::of(&mut c).expect("Buffer does not contain a valid message")
     }
 }
 
 impl From<&Message> for Vec<u8> {
     fn from(m: &Message) -> Vec<u8> {
-        let mut v = vec![m.header.to_u8()];
+        m.serialize(false)
+            .expect("Failed to serialize the message.")
+            .0
+    }
+}
 
+impl Message {
+    /// the files as zip archive, the container of all content versions but 1 and 6
+    fn zip_archive(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
         {
-            let mut buf = Vec::new();
+            let w = std::io::Cursor::new(&mut buf);
+            let mut zip = zip::ZipWriter::new(w);
+
+            // a fixed time, so that the same message always gives the same bytes
+            let options = zip::write::FileOptions::default()
+                .compression_method(match self.compression {
+                    true => zip::CompressionMethod::Deflated,
+                    false => zip::CompressionMethod::Stored,
+                })
+                .last_modified_time(zip::DateTime::default());
+
+            (self.files)
+                .iter()
+                .map(|(name, buf)| (name, buf))
+                .for_each(|(name, buf)| {
+                    zip.start_file(name, options)
+                        .unwrap_or_else(|_| panic!("processing file '{name}' failed."));
+
+                    let mut r = std::io::Cursor::new(buf);
+                    std::io::copy(&mut r, &mut zip).expect("Failed to copy data to the zip entry.");
+                });
+
+            zip.finish().expect("finish zip failed.");
+        }
 
-            {
-                let w = std::io::Cursor::new(&mut buf);
-                let mut zip = zip::ZipWriter::new(w);
+        buf
+    }
 
-                let options = zip::write::FileOptions::default()
-                    .compression_method(zip::CompressionMethod::Deflated);
+    /// the serialized message, see `Message::to_bytes_deferring_checksum` for `defer_checksum`
+    fn serialize(&self, defer_checksum: bool) -> Result<(Vec<u8>, Option<DeferredChecksum>)> {
+        let m = self;
+        let defer_checksum = defer_checksum
+            && m.header == ContentVersion::V5
+            && m.checksum != ChecksumAlgo::None
+            && m.fec_redundancy.is_none();
+        let mut deferred = None;
+        let mut v = vec![m.header.to_u8()];
 
-                (m.files)
-                    .iter()
-                    .map(|(name, buf)| (name, buf))
-                    .for_each(|(name, buf)| {
-                        zip.start_file(name, options)
-                            .unwrap_or_else(|_| panic!("processing file '{name}' failed."));
-
-                        let mut r = std::io::Cursor::new(buf);
-                        std::io::copy(&mut r, &mut zip)
-                            .expect("Failed to copy data to the zip entry.");
-                    });
+        if m.header == ContentVersion::V1 {
+            if let Some(text) = m.text.as_ref() {
+                v.extend_from_slice(text.as_bytes());
+            }
+            v.push(V1_TERMINATOR);
+
+            return Ok((v, None));
+        }
 
-                zip.finish().expect("finish zip failed.");
+        if m.header == ContentVersion::V6 {
+            v.write_u16::<BigEndian>(length_field(m.files.len(), "number of files")?)
+                .expect("Failed to write the number of files.");
+            let mut offset = 0;
+            for (name, buf) in m.files.iter() {
+                v.write_u16::<BigEndian>(length_field(name.len(), "file name")?)
+                    .expect("Failed to write the file name length.");
+                v.extend_from_slice(name.as_bytes());
+                v.write_u32::<BigEndian>(length_field(offset, "file offset")?)
+                    .expect("Failed to write the file offset.");
+                v.write_u32::<BigEndian>(length_field(buf.len(), "file")?)
+                    .expect("Failed to write the file size.");
+                offset += buf.len();
             }
+            m.files.iter().for_each(|(_, buf)| v.extend_from_slice(buf));
 
-            if m.header == ContentVersion::V4 {
-                v.write_u32::<BigEndian>(buf.len() as u32)
+            return Ok((v, None));
+        }
+
+        {
+            let mut buf = m.zip_archive();
+
+            if m.header == ContentVersion::V5 {
+                let payload = match m.fec_redundancy {
+                    Some(redundancy) => {
+                        fec::encode(&buf, redundancy).expect("Invalid FEC redundancy.")
+                    }
+                    None => buf.clone(),
+                };
+                let payload_size = length_field(payload.len(), "payload")?;
+                let digests = match defer_checksum {
+                    true => Digests::ChecksumDeferred,
+                    false => Digests::Computed,
+                };
+                let (fields, checksum_at) =
+                    m.header_fields_to_bytes(&buf, payload_size, digests)?;
+                v.write_u16::<BigEndian>(length_field(fields.len(), "header")?)
+                    .expect("Failed to write the header fields size.");
+                if let (true, Some(at)) = (defer_checksum, checksum_at) {
+                    // behind the fields and the payload length
+                    let start = v.len() + fields.len() + 4;
+                    deferred = Some(DeferredChecksum {
+                        algo: m.checksum,
+                        at: v.len() + at,
+                        payload: start..start + payload.len(),
+                    });
+                }
+                v.extend_from_slice(&fields);
+                buf = payload;
+            }
+
+            if m.header == ContentVersion::V4 || m.header == ContentVersion::V5 {
+                v.write_u32::<BigEndian>(length_field(buf.len(), "payload")?)
                     .expect("Failed to write the inner message size.");
             }
 
             v.append(&mut buf);
 
             if m.header == ContentVersion::V2 {
-                v.write_u16::<BigEndian>(0xffff)
+                v.write_u16::<BigEndian>(V2_TERMINATOR)
                     .expect("Failed to write content format 2 termination.");
             }
         }
 
-        v
+        Ok((v, deferred))
     }
 }
 
-#[cfg(test)]
+/// the bytes each file takes of the zip archive, its local header, its data and its entry in
+/// the central directory
+fn zip_entry_lens(zip: &[u8]) -> Vec<usize> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(zip)).expect("Failed to read back the zip archive.");
+
+    (0..archive.len())
+        .map(|i| {
+            let entry = archive
+                .by_index_raw(i)
+                .expect("Failed to read back a zip entry.");
+            let local = entry.data_start() - entry.header_start() + entry.compressed_size();
+            let central = ZIP_CENTRAL_HEADER_LEN
+                + entry.name_raw().len()
+                + entry.extra_data().len()
+                + entry.comment().len();
+
+            local as usize + central
+        })
+        .collect()
+}
+
+/// how the checksum, the MAC and the signature of the header fields are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Digests {
+    Computed,
+    /// the checksum is left as zeros, see [`Message::to_bytes_deferring_checksum`]
+    ChecksumDeferred,
+    /// all are left as zeros, when only the length of the fields counts
+    Zeroed,
+}
+
+/// `len` as the integer of its length field, fails with `SteganoError::InvalidParameter`
+/// if it does not fit, instead of writing a truncated length that still parses
+fn length_field<T: TryFrom<usize>>(len: usize, what: &str) -> Result<T> {
+    T::try_from(len).map_err(|_| {
+        SteganoError::InvalidParameter(format!("{what} of {len} bytes is too large to serialize"))
+    })
+}
+
+#[cfg(all(test, feature = "fs"))]
 mod message_tests {
     use super::*;
     use std::io::copy;
@@ -284,7 +1801,7 @@ mod message_tests {
         let mut b: Vec<u8> = (&m).into();
         let mut r = Cursor::new(&mut b);
 
-        let m = Message::of(&mut r);
+        let m = Message::of(&mut r).unwrap();
         assert_eq!(
             m.files.len(),
             1,
@@ -303,11 +1820,239 @@ mod message_tests {
         const BUF: [u8; 6] = [0x1, b'H', b'e', 0xff, 0xff, 0xcd];
 
         let mut r = BufReader::new(&BUF[..]);
-        let m = Message::of(&mut r);
+        let m = Message::of(&mut r).unwrap();
         assert_eq!(m.text.unwrap(), "He", "Message.text was not as expected");
         assert_eq!(m.files.len(), 0, "Message.files were not empty.");
     }
 
+    #[test]
+    fn should_report_an_empty_message() {
+        let m = Message::empty();
+
+        assert!(m.is_empty());
+        assert!(
+            m.payload_len() < 32,
+            "empty message is {} bytes",
+            m.payload_len()
+        );
+    }
+
+    #[test]
+    fn should_know_the_payload_len_without_serializing() {
+        let mut text = Message::new(ContentVersion::V1);
+        text.text = Some("hello".to_string());
+        let mut v5 = Message::new(ContentVersion::V5);
+        v5.fec_redundancy = Some(4);
+        v5.checksum = ChecksumAlgo::Sha256;
+        v5.provenance = Some(Provenance::now());
+        v5.mac_key = Some(Arc::new(Secret::new(b"key".to_vec())));
+        v5.kdf_iterations = Some(crate::format::MIN_KDF_ITERATIONS);
+        v5.signing_key = Some(Arc::new(Secret::new([7; 32])));
+        v5.redundant_length = true;
+        v5.hint = Some("a hint".to_string());
+
+        let mut messages = vec![text, v5];
+        for version in [ContentVersion::V2, ContentVersion::V4, ContentVersion::V6] {
+            messages.push(Message::new(version));
+        }
+        for m in messages.iter_mut() {
+            if m.header != ContentVersion::V1 {
+                m.add_file_data("foo.txt", b"foo".repeat(100))
+                    .add_file_data("bar.bin", (0..=255).collect());
+            }
+
+            assert_eq!(
+                m.payload_len(),
+                m.to_bytes().unwrap().len(),
+                "{:?}",
+                m.header
+            );
+        }
+    }
+
+    #[test]
+    fn should_know_the_payload_len_of_each_prefix_of_the_files() {
+        for version in [ContentVersion::V4, ContentVersion::V5, ContentVersion::V6] {
+            let mut m = Message::new(version);
+            m.fec_redundancy = (version == ContentVersion::V5).then_some(8);
+            m.add_file_data("foo.txt", b"foo".repeat(100))
+                .add_file_data("bar.bin", (0..=255).collect())
+                .add_file_data("baz.txt", b"baz".to_vec());
+
+            let expected: Vec<usize> = (0..=m.files.len())
+                .map(|n| {
+                    let mut prefix = m.clone();
+                    prefix.files.truncate(n);
+                    prefix.payload_len()
+                })
+                .collect();
+            assert_eq!(m.cumulative_payload_lens(), expected, "{version:?}");
+        }
+    }
+
+    #[test]
+    fn should_know_the_uncompressed_payload_len_from_the_file_sizes() {
+        for version in [ContentVersion::V2, ContentVersion::V4, ContentVersion::V5] {
+            let mut m = Message::new(version);
+            m.compression = false;
+            m.add_file_data("foo.txt", b"foo".repeat(100))
+                .add_file_data("bar.bin", (0..=255).collect());
+            assert_eq!(m.uncompressed_payload_len(), m.payload_len(), "{version:?}");
+
+            m.compression = true;
+            assert!(m.payload_len() < m.uncompressed_payload_len());
+        }
+    }
+
+    #[test]
+    fn should_grow_payload_len_by_file_size_and_overhead() {
+        let mut m = Message::empty();
+        let empty_len = m.payload_len();
+        // random data does not compress
+        m.add_file("../resources/secrets/random_1666_byte.bin");
+
+        assert!(!m.is_empty());
+        let grown = m.payload_len() - empty_len;
+        assert!(
+            (1666..1666 + 200).contains(&grown),
+            "payload grew by {grown} bytes"
+        );
+    }
+
+    #[test]
+    fn should_serialize_text_for_content_version_1() {
+        let mut m = Message::new(ContentVersion::V1);
+        m.text = Some("Hello".to_string());
+
+        let mut b = m.to_bytes().expect("V1 should support text");
+        assert_eq!(b, vec![0x01, b'H', b'e', b'l', b'l', b'o', 0xff]);
+
+        let m = Message::from(&mut b);
+        assert_eq!(m.header, ContentVersion::V1);
+        assert_eq!(m.text.unwrap(), "Hello");
+    }
+
+    #[test]
+    fn should_reject_files_for_content_version_1() {
+        let mut m = Message::new(ContentVersion::V1);
+        m.add_file_data("foo.txt", b"foo".to_vec());
+
+        match m.to_bytes() {
+            Err(SteganoError::FeatureNotSupportedInVersion { feature, version }) => {
+                assert_eq!(feature, "files");
+                assert_eq!(version, 0x01);
+            }
+            _ => panic!("files must not be accepted for content version 1"),
+        }
+    }
+
+    #[test]
+    fn should_reject_text_for_content_version_2_and_4() {
+        for version in [ContentVersion::V2, ContentVersion::V4] {
+            let mut m = Message::new(version);
+            m.text = Some("Hello".to_string());
+
+            assert!(matches!(
+                m.to_bytes(),
+                Err(SteganoError::FeatureNotSupportedInVersion {
+                    feature: "text",
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn should_round_trip_files_for_content_version_2() {
+        let mut m = Message::new(ContentVersion::V2);
+        m.add_file_data("foo.txt", b"foo".to_vec());
+
+        let mut b = m.to_bytes().expect("V2 should support files");
+        assert_eq!(b[0], 0x02);
+
+        let m = Message::from(&mut b);
+        assert_eq!(m.files, vec![("foo.txt".to_string(), b"foo".to_vec())]);
+    }
+
+    #[test]
+    fn should_round_trip_header_fields_for_content_version_5() {
+        let mut m = Message::new(ContentVersion::V5);
+        m.add_file_data("foo.txt", b"foo".to_vec());
+        m.cover_hash = Some([0xab; 32]);
+
+        let mut b = m.to_bytes().expect("V5 should support header fields");
+        assert_eq!(b[0], 0x05);
+
+        let m = Message::from(&mut b);
+        assert_eq!(m.header, ContentVersion::V5);
+        assert_eq!(m.cover_hash, Some([0xab; 32]));
+        assert_eq!(m.files, vec![("foo.txt".to_string(), b"foo".to_vec())]);
+    }
+
+    #[test]
+    fn should_skip_unknown_header_fields() {
+        let mut b = vec![0x05, 0x00, 0x05, 0xee, 0x00, 0x02, 0x01, 0x02];
+        let mut payload: Vec<u8> = (&Message::new(ContentVersion::V4)).into();
+        b.extend_from_slice(&payload.split_off(1));
+
+        let m = Message::from(&mut b);
+        assert_eq!(m.header, ContentVersion::V5);
+        assert_eq!(m.cover_hash, None);
+    }
+
+    #[test]
+    fn should_reject_header_fields_before_content_version_5() {
+        let mut m = Message::new(ContentVersion::V4);
+        m.cover_hash = Some([0xab; 32]);
+
+        assert!(matches!(
+            m.to_bytes(),
+            Err(SteganoError::FeatureNotSupportedInVersion {
+                feature: "cover hash",
+                version: 0x04
+            })
+        ));
+    }
+
+    #[test]
+    fn should_reject_a_file_name_too_long_for_the_index() {
+        let mut m = Message::new(ContentVersion::V6);
+        m.add_file_data(&"a".repeat(u16::MAX as usize + 1), b"foo".to_vec());
+
+        assert!(matches!(
+            m.to_bytes(),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            length_field::<u32>(u32::MAX as usize + 1, "file"),
+            Err(SteganoError::InvalidParameter(_))
+        ));
+        assert_eq!(
+            length_field::<u32>(u32::MAX as usize, "file").ok(),
+            Some(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn should_extract_a_file_without_reading_the_ones_behind_it() -> Result<()> {
+        let mut m = Message::new(ContentVersion::V6);
+        m.add_file_data("first.txt", b"first".to_vec())
+            .add_file_data("second.bin", vec![0xab; 100]);
+        let buf = m.to_bytes()?;
+        let m = Message::of(&mut buf.as_slice())?;
+        assert_eq!(m.header, ContentVersion::V6);
+        assert_eq!(m.files[1], ("second.bin".to_string(), vec![0xab; 100]));
+
+        let truncated = &buf[..buf.len() - 100];
+        assert!(Message::of(&mut &truncated[..]).is_err());
+        assert_eq!(
+            Message::extract_file(&mut &truncated[..], "first.txt", usize::MAX)?,
+            b"first"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn should_create_zip_that_is_windows_compatible() -> std::io::Result<()> {
         let mut file = File::open("../resources/with_text/hello_world.png")?;
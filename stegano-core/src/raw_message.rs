@@ -1,5 +1,18 @@
 use std::io::Read;
 
+use crate::{Result, SteganoError};
+
+/// the text encodings raw bytes can be read as, see [`RawMessage::decode_as`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TextEncoding {
+    /// strict, invalid sequences fail
+    Utf8,
+    /// ISO 8859-1, every byte is the unicode code point of its value, so it never fails
+    Latin1,
+    /// strict, bytes above 0x7f fail
+    Ascii,
+}
+
 pub struct RawMessage {
     pub content: Vec<u8>,
 }
@@ -13,6 +26,23 @@ impl RawMessage {
         m
     }
 
+    /// interprets the raw bytes as text in the given encoding. Fails with
+    /// `SteganoError::InvalidText` at the first byte that is invalid in a strict encoding.
+    /// Note: everything behind the hidden text is read as well, e.g. the terminator of
+    /// content version 1, so it might need to be cut off before
+    pub fn decode_as(&self, encoding: TextEncoding) -> Result<String> {
+        let invalid = |position| SteganoError::InvalidText { encoding, position };
+        match encoding {
+            TextEncoding::Utf8 => String::from_utf8(self.content.clone())
+                .map_err(|e| invalid(e.utf8_error().valid_up_to())),
+            TextEncoding::Latin1 => Ok(self.content.iter().map(|b| char::from(*b)).collect()),
+            TextEncoding::Ascii => match self.content.iter().position(|b| !b.is_ascii()) {
+                Some(position) => Err(invalid(position)),
+                None => Ok(self.content.iter().map(|b| char::from(*b)).collect()),
+            },
+        }
+    }
+
     fn new() -> Self {
         RawMessage {
             content: Vec::new(),
@@ -33,4 +63,45 @@ mod raw_message_tests {
         let m = RawMessage::of(&mut r);
         assert_eq!(m.content, BUF, "RawMessage.content was not as expected");
     }
+
+    #[test]
+    fn should_decode_the_raw_bytes_of_hello_world_as_text() {
+        use crate::media::image::{CodecOptions, LsbCodec};
+
+        let image = image::open("../resources/with_text/hello_world.png")
+            .unwrap()
+            .to_rgba8();
+        let raw = RawMessage::of(&mut LsbCodec::decoder(&image, &CodecOptions::default()));
+        assert!(raw
+            .decode_as(TextEncoding::Latin1)
+            .unwrap()
+            .contains("Hello World!"));
+        // the text of content version 1 ends with 0xff, that is neither utf-8 nor ascii
+        for encoding in [TextEncoding::Utf8, TextEncoding::Ascii] {
+            assert!(matches!(
+                raw.decode_as(encoding),
+                Err(SteganoError::InvalidText { position: 13, .. })
+            ));
+        }
+
+        let text = RawMessage {
+            content: raw.content.into_iter().take_while(|b| *b != 0xff).collect(),
+        };
+        for encoding in [TextEncoding::Utf8, TextEncoding::Ascii] {
+            assert_eq!(text.decode_as(encoding).unwrap(), "\x01Hello World!");
+        }
+    }
+
+    #[test]
+    fn should_decode_multi_byte_utf8_but_no_ascii() {
+        let raw = RawMessage {
+            content: "grüße".as_bytes().to_vec(),
+        };
+        assert_eq!(raw.decode_as(TextEncoding::Utf8).unwrap(), "grüße");
+        assert_eq!(raw.decode_as(TextEncoding::Latin1).unwrap(), "grÃ¼Ã\u{9f}e");
+        assert!(matches!(
+            raw.decode_as(TextEncoding::Ascii),
+            Err(SteganoError::InvalidText { position: 2, .. })
+        ));
+    }
 }
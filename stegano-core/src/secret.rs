@@ -0,0 +1,60 @@
+//! Keys and passphrase derived values, kept so that they do not leak.
+//!
+//! A [`Secret`] is not `Clone`, so its value is never copied around. Structs that are `Clone`,
+//! like [`crate::Message`] or [`crate::CodecOptions`], share it in an `Arc` instead, and the
+//! value is wiped from memory once the last of them is dropped.
+
+use std::fmt;
+use zeroize::Zeroize;
+
+/// a key or a value derived from a passphrase. It shows as redacted in `Debug` output and
+/// is zeroized on drop
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// the secret value, it should not be copied out for longer than it is needed
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn should_redact_the_value_in_debug_output() {
+        let secret = Secret::new(b"very secret key".to_vec());
+
+        assert_eq!(format!("{secret:?}"), "Secret(<redacted>)");
+        assert_eq!(
+            format!("{:?}", Some(Arc::new(secret))),
+            "Some(Secret(<redacted>))"
+        );
+    }
+
+    #[test]
+    fn should_share_the_value_instead_of_copying_it() {
+        let secret = Arc::new(Secret::new([7u8; 32]));
+        let shared = Arc::clone(&secret);
+
+        assert!(std::ptr::eq(secret.expose(), shared.expose()));
+    }
+}
@@ -0,0 +1,127 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::Read;
+
+use crate::{Result, SteganoError};
+
+/// One part of a payload that is spread over several carriers,
+/// stored as `u16 index + u16 count + u32 length + bytes`.
+/// The index is what puts the shards back in order, not the name or format of their carriers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u16,
+    pub count: u16,
+    pub data: Vec<u8>,
+}
+
+impl Shard {
+    /// splits the payload into `count` shards of about the same size
+    pub fn split(payload: &[u8], count: u16) -> Result<Vec<Self>> {
+        if count == 0 {
+            return Err(SteganoError::InvalidParameter(
+                "at least one shard is required".to_owned(),
+            ));
+        }
+        let chunk_len = payload.len().div_ceil(count as usize).max(1);
+        let mut chunks = payload.chunks(chunk_len);
+
+        Ok((0..count)
+            .map(|index| Self {
+                index,
+                count,
+                data: chunks.next().unwrap_or_default().to_vec(),
+            })
+            .collect())
+    }
+
+    /// puts the shards back together in the order of their index, no matter in which order they are given
+    pub fn reassemble(mut shards: Vec<Self>) -> Result<Vec<u8>> {
+        shards.sort_by_key(|s| s.index);
+        let count = shards.first().ok_or(SteganoError::NoSecretData)?.count;
+        if shards.iter().any(|s| s.count != count) || shards.len() > count as usize {
+            return Err(SteganoError::MalformedSecretData);
+        }
+        if let Some(missing) =
+            (0..count).find(|i| shards.get(*i as usize).map(|s| s.index) != Some(*i))
+        {
+            return Err(SteganoError::MissingShard(missing));
+        }
+
+        Ok(shards.into_iter().flat_map(|s| s.data).collect())
+    }
+
+    pub fn of(dec: &mut dyn Read) -> Result<Self> {
+        let mut read_u16 = || {
+            dec.read_u16::<BigEndian>()
+                .map_err(|_| SteganoError::MalformedSecretData)
+        };
+        let (index, count) = (read_u16()?, read_u16()?);
+        if index >= count {
+            return Err(SteganoError::MalformedSecretData);
+        }
+        let len = dec
+            .read_u32::<BigEndian>()
+            .map_err(|_| SteganoError::MalformedSecretData)?;
+
+        let mut data = Vec::new();
+        dec.take(len as u64)
+            .read_to_end(&mut data)
+            .map_err(|source| SteganoError::ReadError { source })?;
+        if data.len() != len as usize {
+            return Err(SteganoError::MalformedSecretData);
+        }
+
+        Ok(Self { index, count, data })
+    }
+}
+
+impl From<&Shard> for Vec<u8> {
+    fn from(s: &Shard) -> Vec<u8> {
+        let mut v = Vec::with_capacity(s.data.len() + 8);
+        v.write_u16::<BigEndian>(s.index)
+            .expect("Failed to write the shard index.");
+        v.write_u16::<BigEndian>(s.count)
+            .expect("Failed to write the shard count.");
+        v.write_u32::<BigEndian>(s.data.len() as u32)
+            .expect("Failed to write the shard size.");
+        v.extend_from_slice(&s.data);
+
+        v
+    }
+}
+
+#[cfg(test)]
+mod sharded_message_tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn should_reassemble_shards_in_any_order() {
+        let mut shards = Shard::split(b"Hello World!", 3).unwrap();
+        shards.reverse();
+
+        assert_eq!(Shard::reassemble(shards).unwrap(), b"Hello World!".to_vec());
+    }
+
+    #[test]
+    fn should_fail_for_a_missing_shard() {
+        let mut shards = Shard::split(b"Hello World!", 3).unwrap();
+        shards.remove(1);
+
+        assert!(matches!(
+            Shard::reassemble(shards),
+            Err(SteganoError::MissingShard(1))
+        ));
+    }
+
+    #[test]
+    fn should_convert_into_vec_of_bytes_and_back() {
+        let shard = Shard {
+            index: 1,
+            count: 2,
+            data: b"two".to_vec(),
+        };
+        let b: Vec<u8> = (&shard).into();
+
+        assert_eq!(Shard::of(&mut Cursor::new(b)).unwrap(), shard);
+    }
+}
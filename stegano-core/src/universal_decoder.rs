@@ -73,6 +73,8 @@ impl UnveilAlgorithm for OneBitUnveil {
         match carrier {
             MediaPrimitive::ImageColorChannel(b) => (b & 0x1) > 0,
             MediaPrimitive::AudioSample(b) => (b & 0x1) > 0,
+            #[cfg(feature = "hdr")]
+            MediaPrimitive::ImageFloatChannel(b) => (b & 0x1) > 0,
         }
     }
 }
@@ -1,8 +1,14 @@
 use bitstream_io::{BitRead, BitReader, LittleEndian};
 use enum_dispatch::enum_dispatch;
-use std::io::{Cursor, Result, Write};
+use std::cell::Cell;
+use std::io::{Cursor, Error, Result, Write};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
 
-use crate::{MediaPrimitive, MediaPrimitiveMut};
+use crate::{CancellationToken, MediaPrimitive, MediaPrimitiveMut};
+
+/// number of bytes written between two checks of the cancellation token and the deadline
+pub(crate) const CANCEL_CHECK_INTERVAL: usize = 64;
 
 /// abstracting write back of a carrier item
 pub trait WriteCarrierItem {
@@ -14,6 +20,7 @@ pub trait WriteCarrierItem {
 pub enum HideAlgorithms {
     OneBitHide,
     OneBitInLowFrequencyHide,
+    OneBitMatchingHide,
 }
 
 /// generic hiding algorithm, used for specific ones like LSB
@@ -31,6 +38,8 @@ where
 {
     pub carrier: C,
     pub algorithm: A,
+    pub cancel: Option<CancellationToken>,
+    pub deadline: Option<Instant>,
 }
 
 impl<'c, C, A> Encoder<'c, C, A>
@@ -39,10 +48,37 @@ where
     A: HideAlgorithm,
 {
     pub fn new(carrier: C, algorithm: A) -> Self {
-        Encoder { carrier, algorithm }
+        Encoder {
+            carrier,
+            algorithm,
+            cancel: None,
+            deadline: None,
+        }
+    }
+
+    /// aborts any further writing once the given token is set
+    pub fn with_cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// aborts any further writing once the given instant has passed
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
     }
 }
 
+#[inline]
+pub(crate) fn is_cancelled(token: Option<&CancellationToken>) -> bool {
+    token.map(|t| t.load(Ordering::Relaxed)).unwrap_or(false)
+}
+
+#[inline]
+pub(crate) fn is_past(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|d| Instant::now() >= d)
+}
+
 impl<'c, C, A> Write for Encoder<'c, C, A>
 where
     C: Iterator<Item = MediaPrimitiveMut<'c>>,
@@ -50,6 +86,12 @@ where
 {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if is_cancelled(self.cancel.as_ref()) {
+            return Err(Error::other("encoding was cancelled"));
+        }
+        if is_past(self.deadline) {
+            return Err(Error::other("encoding ran out of time"));
+        }
         // TODO better let the algorithm determine the density of encoding
         let items_to_take = buf.len() << 3; // 1 bit per sample <=> * 8 <=> << 3
         let mut bit_iter = BitReader::endian(Cursor::new(buf), LittleEndian);
@@ -57,6 +99,11 @@ where
         for s in self.carrier.by_ref().take(items_to_take) {
             self.algorithm.encode(s, &bit_iter.read_bit());
             bit_written += 1;
+            if bit_written.is_multiple_of(CANCEL_CHECK_INTERVAL << 3)
+                && (is_cancelled(self.cancel.as_ref()) || is_past(self.deadline))
+            {
+                break;
+            }
         }
 
         Ok(bit_written >> 3)
@@ -81,6 +128,10 @@ impl HideAlgorithm for OneBitHide {
                 MediaPrimitiveMut::AudioSample(b) => {
                     *b = ((*b) & (i16::MAX - 1)) | if *bit { 1 } else { 0 }
                 }
+                #[cfg(feature = "hdr")]
+                MediaPrimitiveMut::ImageFloatChannel(f) => {
+                    *f = f32::from_bits((f.to_bits() & !1) | if *bit { 1 } else { 0 })
+                }
                 _ => {}
             }
         }
@@ -101,12 +152,71 @@ impl HideAlgorithm for OneBitInLowFrequencyHide {
                 MediaPrimitiveMut::AudioSample(b) => {
                     *b = ((*b) & (0b11111111 << 8)) | if *bit { 0b000000011111111 } else { 0 }
                 }
+                #[cfg(feature = "hdr")]
+                MediaPrimitiveMut::ImageFloatChannel(f) => {
+                    *f = f32::from_bits((f.to_bits() & !0b1111) | if *bit { 0b1111 } else { 0 })
+                }
                 _ => {}
             }
         }
     }
 }
 
+/// 1 bit hiding strategy by LSB matching: a value whose LSB differs from the bit is incremented
+/// or decremented by 1 at random, instead of having its LSB replaced. Replacing only ever swaps
+/// `2k` with `2k + 1`, which equalizes their frequencies, matching does not.
+/// Values at the minimum or the maximum go the only way they can. Floats keep the replacement
+#[derive(Debug)]
+pub struct OneBitMatchingHide {
+    state: Cell<u64>,
+}
+
+impl OneBitMatchingHide {
+    /// the directions only need to be independent of the carrier, so any seed does
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Cell::new(seed | 1),
+        }
+    }
+
+    /// the next direction of a xorshift sequence
+    fn up(&self) -> bool {
+        let mut x = self.state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state.set(x);
+
+        x >> 63 == 1
+    }
+}
+
+impl HideAlgorithm for OneBitMatchingHide {
+    #[inline(always)]
+    fn encode(&self, carrier: MediaPrimitiveMut, information: &Result<bool>) {
+        let Ok(bit) = information else {
+            return;
+        };
+        match carrier {
+            MediaPrimitiveMut::ImageColorChannel(b) if (*b & 1 == 1) != *bit => {
+                *b = match (*b, self.up()) {
+                    (u8::MAX, _) | (_, false) if *b != 0 => *b - 1,
+                    _ => *b + 1,
+                }
+            }
+            MediaPrimitiveMut::AudioSample(b) if (*b & 1 == 1) != *bit => {
+                *b = match (*b, self.up()) {
+                    (i16::MAX, _) | (_, false) if *b != i16::MIN => *b - 1,
+                    _ => *b + 1,
+                }
+            }
+            #[cfg(feature = "hdr")]
+            f @ MediaPrimitiveMut::ImageFloatChannel(_) => OneBitHide.encode(f, information),
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,6 +232,28 @@ mod tests {
         assert_eq!(data, 0b11001111);
     }
 
+    #[test]
+    fn should_match_the_lsb_by_one_up_or_down() {
+        let encoder = OneBitMatchingHide::new(42);
+        let mut directions = [0; 2];
+        for _ in 0..100 {
+            let mut data = 0b00001110;
+            encoder.encode(MediaPrimitiveMut::ImageColorChannel(&mut data), &Ok(true));
+            assert!(data == 0b00001101 || data == 0b00001111, "{data:#b}");
+            directions[(data >> 1 & 1) as usize] += 1;
+        }
+        assert!(directions.iter().all(|d| *d > 0), "{directions:?}");
+
+        for (value, bit, expected) in [(0, true, 1), (u8::MAX, false, 254), (6, false, 6)] {
+            let mut data = value;
+            encoder.encode(MediaPrimitiveMut::ImageColorChannel(&mut data), &Ok(bit));
+            assert_eq!(data, expected);
+        }
+        let mut data = i16::MIN;
+        encoder.encode(MediaPrimitiveMut::AudioSample(&mut data), &Ok(true));
+        assert_eq!(data, i16::MIN + 1);
+    }
+
     #[test]
     fn should_not_harm_on_error() {
         let encoder = OneBitHide;
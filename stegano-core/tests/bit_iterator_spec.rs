@@ -9,7 +9,7 @@ speculate! {
         // Hex   : 0x48        0x61        0x6C
         // Binary: 0b01001000  0b01100001  0b01101100
         it "should return the 8 bits of 'H' in LittleEndian byte order" {
-            let b = vec![0b0100_1000, 0b0110_0001, 0b0110_1100];
+            let b = [0b0100_1000, 0b0110_0001, 0b0110_1100];
             let mut it = BitIterator::new(&b[..]);
 
             assert_eq!(it.next().unwrap(), 0, "1st bit not correct");
@@ -26,7 +26,7 @@ speculate! {
         // Hex   : 0x48        0x61        0x6C
         // Binary: 0b01001000  0b01100001  0b01101100
         it "should return 8 bits of 'e' in LittleEndian byte order after skip(8)" {
-            let b = vec![0b0100_1000, 0b0110_0001];
+            let b = [0b0100_1000, 0b0110_0001];
             let mut it = BitIterator::new(&b[..]).skip(8);
 
             assert_eq!(it.next().unwrap(), 1, "1st bit not correct");
@@ -41,7 +41,7 @@ speculate! {
         }
 
         it "should behave as the BitReader" {
-            let b = vec![0b0100_1000, 0b0110_0001];
+            let b = [0b0100_1000, 0b0110_0001];
             let mut it = BitIterator::new(&b[..]);
             let mut reader = BitReader::endian(
                 &b[..],